@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+
+use banana_recovery::{Manifest, NextAction, QrDecoder, ShareSetCollection};
+use clap::Args;
+
+use crate::error::CliError;
+use crate::load::payloads_in;
+use crate::output::SecretOutput;
+use crate::passphrase::PassphraseSource;
+
+/// Arguments of `banana batch`.
+#[derive(Debug, Args)]
+pub struct BatchArgs {
+    /// Directory with photos, scans, or text files of shares; subdirectories are walked too.
+    dir: PathBuf,
+    /// Owner manifest files, for sets of private splits.
+    #[arg(long)]
+    manifest: Vec<PathBuf>,
+    /// Recover the secrets of complete sets, asking for the passphrase of each.
+    #[arg(long)]
+    recover: bool,
+    #[command(flatten)]
+    passphrase: PassphraseSource,
+    #[command(flatten)]
+    output: SecretOutput,
+}
+
+/// Collect the shares of all files in the directory into sets, and report the sets.
+pub fn run(args: BatchArgs, decoder: &dyn QrDecoder) -> Result<(), CliError> {
+    let files = files_in(&args.dir)?;
+    let mut collection = ShareSetCollection::new();
+    let mut skipped = 0;
+    for file in &files {
+        let source = file.display().to_string();
+        let content = std::fs::read(file).map_err(|error| CliError::Io {
+            source_name: source.clone(),
+            error,
+        })?;
+        // unreadable files are expected among the photos, and are only reported
+        let payloads = match payloads_in(&source, content, decoder) {
+            Ok(a) => a,
+            Err(e) => {
+                println!("  skip  {e}");
+                skipped += 1;
+                continue;
+            }
+        };
+        for item in payloads {
+            if let Err(e) = collection.add_sanitized_payload(&item.payload) {
+                println!("  skip  {}: {e}", item.source);
+            }
+        }
+    }
+    for path in &args.manifest {
+        let json = std::fs::read_to_string(path).map_err(|error| CliError::Io {
+            source_name: path.display().to_string(),
+            error,
+        })?;
+        let manifest = Manifest::from_json(&json)?;
+        let index = collection
+            .sets()
+            .iter()
+            .position(|a| a.fingerprint() == manifest.fingerprint);
+        if let Some(set) = index.and_then(|a| collection.set_mut(a)) {
+            set.apply_manifest(manifest)?;
+        }
+    }
+
+    let stats = collection.scan_stats();
+    println!(
+        "Scanned {} files: {} shares accepted, {} repeated, {} files and {} payloads unreadable.",
+        files.len(),
+        stats.accepted,
+        stats.duplicates.values().sum::<usize>(),
+        skipped,
+        stats.not_shares,
+    );
+    let mut complete = 0;
+    for set in collection.sets() {
+        let ids = join(set.collected_ids());
+        let state = match set.next_action() {
+            NextAction::AskUserForPassword => {
+                complete += 1;
+                format!("complete, shares {ids}")
+            }
+            NextAction::MoreShares { have, need, .. } => {
+                format!("{have} of {need} shares, shares {ids}, more needed")
+            }
+            NextAction::AskUserForManifest => format!("shares {ids}, manifest needed"),
+        };
+        println!("Set {} \"{}\": {state}.", set.fingerprint(), set.title());
+    }
+    if complete == 0 {
+        return Err(CliError::NoCompleteSet);
+    }
+
+    if args.recover {
+        for set in collection.sets() {
+            if set.next_action() != NextAction::AskUserForPassword {
+                continue;
+            }
+            let passphrase = args
+                .passphrase
+                .get(&format!("Passphrase for set {}", set.fingerprint()))?;
+            match set.recover_with_passphrase(&passphrase) {
+                Ok(secret) => args.output.show(Some(&set.fingerprint()), &secret)?,
+                Err(e) => println!("Set {}: {e}", set.fingerprint()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Files in the directory and its subdirectories, in the order of their paths.
+pub fn files_in(dir: &Path) -> Result<Vec<PathBuf>, CliError> {
+    let io_error = |error| CliError::Io {
+        source_name: dir.display().to_string(),
+        error,
+    };
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.is_dir() {
+            files.extend(files_in(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn join(ids: &[u32]) -> String {
+    ids.iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::CliError;
+
+/// Name of the configuration file.
+pub const FILE_NAME: &str = "banana.toml";
+
+/// Defaults of the command line tool, from `banana.toml`, so that the operators
+/// of a team split with the same parameters. Command line flags override them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub split: SplitDefaults,
+}
+
+/// Defaults of `banana split`, in `[split]` table.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SplitDefaults {
+    pub shares: Option<usize>,
+    pub threshold: Option<usize>,
+    pub words: Option<usize>,
+    pub wordlist: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+}
+
+impl Config {
+    /// Configuration from the given file, or else from `banana.toml` in the current
+    /// directory, or else from `banana/banana.toml` in the user configuration directory
+    /// (`$XDG_CONFIG_HOME` on Linux); empty if there is none.
+    pub fn load(path: Option<&Path>) -> Result<Self, CliError> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_paths().into_iter().find(|a| a.is_file()) {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+        let text = std::fs::read_to_string(&path).map_err(|error| CliError::Io {
+            source_name: path.display().to_string(),
+            error,
+        })?;
+        Self::parse(&text).map_err(|reason| CliError::Config {
+            path: path.display().to_string(),
+            reason,
+        })
+    }
+    /// Configuration from the file text.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.message().to_owned())
+    }
+    fn default_paths() -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(FILE_NAME)];
+        if let Some(dir) = dirs::config_dir() {
+            paths.push(dir.join("banana").join(FILE_NAME));
+        }
+        paths
+    }
+}
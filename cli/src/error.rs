@@ -0,0 +1,156 @@
+use banana_recovery::Error;
+
+#[allow(missing_docs)]
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    #[cfg(feature = "clipboard")]
+    #[error("Clipboard failed: {0}.")]
+    Clipboard(String),
+
+    #[error("Configuration file {path} is invalid: {reason}.")]
+    Config { path: String, reason: String },
+
+    #[error("File {0} exists; use --force to overwrite it.")]
+    FileExists(String),
+
+    #[error("Unable to read {source_name}: {error}.")]
+    Io {
+        source_name: String,
+        error: std::io::Error,
+    },
+
+    #[cfg(feature = "keyring")]
+    #[error("Keyring failed: {0}.")]
+    Keyring(String),
+
+    #[error(transparent)]
+    Library(#[from] Error),
+
+    #[error("No complete set found.")]
+    NoCompleteSet,
+
+    #[error("Input {0} is neither a file nor a share payload.")]
+    NotFound(String),
+
+    #[error("Passphrases entered do not match.")]
+    PassphraseMismatch,
+
+    #[error("Shares are from {0} different sets; restore one set at a time.")]
+    SeveralSets(usize),
+
+    #[error("Verification failed.")]
+    VerifyFailed,
+
+    #[error("Wordlist {0} has no words.")]
+    WordlistEmpty(String),
+}
+
+/// Kind of the failure, reported as the process exit code, so that scripts
+/// wrapping the tool could branch on it. Codes are stable across releases;
+/// code 2 is left for the command line usage errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// Any other failure, for example a file that could not be read.
+    Other = 1,
+    /// Input is not a share, or the share could not be parsed.
+    Parse = 3,
+    /// Shares do not form a complete set, or do not agree with each other.
+    InconsistentSet = 4,
+    /// Passphrase is wrong; for shares without key commitment,
+    /// the shares could be damaged instead.
+    WrongPassphrase = 5,
+    /// Share is damaged: checksum, set mac, or share text line does not match.
+    Damaged = 6,
+    /// Share version, or its parameters, are not supported.
+    Unsupported = 7,
+}
+
+impl CliError {
+    /// Category of the failure.
+    pub fn category(&self) -> Category {
+        match self {
+            CliError::Config { .. }
+            | CliError::FileExists(_)
+            | CliError::Io { .. }
+            | CliError::NotFound(_)
+            | CliError::PassphraseMismatch
+            | CliError::WordlistEmpty(_) => Category::Other,
+            #[cfg(feature = "clipboard")]
+            CliError::Clipboard(_) => Category::Other,
+            #[cfg(feature = "keyring")]
+            CliError::Keyring(_) => Category::Other,
+            CliError::NoCompleteSet | CliError::SeveralSets(_) | CliError::VerifyFailed => {
+                Category::InconsistentSet
+            }
+            CliError::Library(e) => library_category(e),
+        }
+    }
+    /// Process exit code of the failure.
+    pub fn exit_code(&self) -> u8 {
+        self.category() as u8
+    }
+}
+
+fn library_category(error: &Error) -> Category {
+    match error {
+        Error::BodyNotBase64
+        | Error::DeflateFailed
+        | Error::EmptyShare
+        | Error::ImageInvalid
+        | Error::JsonParsing
+        | Error::KeyCommitmentInvalid
+        | Error::ManifestParsing
+        | Error::NonceLengthInvalid(_)
+        | Error::NonceNotBase64
+        | Error::NoShareInImage
+        | Error::NotFileSecret
+        | Error::NotShareString
+        | Error::ParseBit(_)
+        | Error::QrDecodingFailed(_)
+        | Error::SaltInvalid
+        | Error::SetIdInvalid(_)
+        | Error::TagInvalid(_)
+        | Error::UndefinedBodyNotHex
+        | Error::ValidityInvalid => Category::Parse,
+        Error::ChunkMissing(_)
+        | Error::ManifestMismatch
+        | Error::ManifestNeeded
+        | Error::NoMajority
+        | Error::NotReadyToDecode
+        | Error::ShareAlreadyInSet
+        | Error::ShareBitsDifferent
+        | Error::ShareContentLengthDifferent
+        | Error::ShareFingerprintDifferent
+        | Error::ShareFromDifferentSet
+        | Error::ShareKdfDifferent
+        | Error::ShareKeyCommitmentDifferent
+        | Error::ShareNonceDifferent
+        | Error::ShareNotInSet(_)
+        | Error::ShareRequiredShardsDifferent
+        | Error::ShareSaltDifferent
+        | Error::ShareTitleDifferent
+        | Error::ShareValidityDifferent
+        | Error::ShareVersionDifferent
+        | Error::TooFewShares => Category::InconsistentSet,
+        Error::DecodedSecretNotString | Error::DecodingFailed | Error::WrongPassphrase => {
+            Category::WrongPassphrase
+        }
+        Error::BitsOutOfRange(_)
+        | Error::FileChecksumMismatch
+        | Error::LogOutOfRange(_)
+        | Error::PaperLineDamaged(_)
+        | Error::PaperTextIncomplete
+        | Error::RepairFailed
+        | Error::SetMacMismatch
+        | Error::ShareChecksumMismatch
+        | Error::ShareIdOutOfRange(_)
+        | Error::ShareTooShort => Category::Damaged,
+        Error::DeflateNotSupported
+        | Error::KdfNotSupported(_)
+        | Error::KdfParamsNotSupported(_)
+        | Error::RequiredShardsNotSupported(_)
+        | Error::VersionNotSupported(_) => Category::Unsupported,
+        Error::WrappedShareInvalid(_, reason) => library_category(reason),
+        _ => Category::Other,
+    }
+}
@@ -0,0 +1,120 @@
+use banana_recovery::{QrDecoder, Share, ShareMeta};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use crate::error::CliError;
+use crate::load::{load_all, Loaded};
+
+/// Output format of `banana inspect`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Format {
+    /// Aligned fields, one per line.
+    #[default]
+    Table,
+    /// Json array, one object per share.
+    Json,
+}
+
+/// Arguments of `banana inspect`.
+#[derive(Debug, Args)]
+pub struct InspectArgs {
+    /// Shares to inspect: share payloads, files with shares, one per line,
+    /// images, or `-` for the standard input.
+    #[arg(required = true)]
+    shares: Vec<String>,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+/// Non-sensitive metadata of a share; share content and nonce are never shown.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Inspection {
+    pub source: String,
+    pub version: Option<u32>,
+    pub bits: Option<u32>,
+    pub id: Option<u32>,
+    pub title: Option<String>,
+    pub required_shards: Option<usize>,
+    pub fingerprint: Option<String>,
+    pub kdf: Option<String>,
+    pub tags: Vec<String>,
+    pub compressed: bool,
+    pub payload_size: usize,
+    /// Why the share could not be parsed in full, if it could not.
+    pub error: Option<String>,
+}
+
+impl Inspection {
+    /// Metadata by peeking into the payload, with the fields known only
+    /// after the full parsing added, if the share is supported.
+    pub fn of(loaded: &Loaded) -> Result<Self, CliError> {
+        let meta = ShareMeta::peek(&loaded.payload)?;
+        let share = Share::new(loaded.payload.as_slice());
+        Ok(Self {
+            source: loaded.source.clone(),
+            version: meta.version,
+            bits: share.as_ref().ok().map(|a| a.bits()),
+            id: share.as_ref().ok().map(|a| a.id()),
+            title: meta.title,
+            required_shards: meta.required_shards,
+            fingerprint: share.as_ref().ok().map(|a| a.fingerprint()),
+            kdf: meta.kdf,
+            tags: meta.tags,
+            compressed: meta.compressed,
+            payload_size: loaded.payload.len(),
+            error: share.err().map(|a| a.to_string()),
+        })
+    }
+    fn print_table(&self) {
+        let field = |name: &str, value: Option<String>| {
+            println!("{name:<16}{}", value.unwrap_or_else(|| "-".to_owned()));
+        };
+        field("source", Some(self.source.clone()));
+        field(
+            "version",
+            Some(self.version.map_or("legacy".to_owned(), |a| a.to_string())),
+        );
+        field("bits", self.bits.map(|a| a.to_string()));
+        field("share", self.id.map(|a| a.to_string()));
+        field("title", self.title.clone());
+        field(
+            "required shards",
+            self.required_shards.map(|a| a.to_string()),
+        );
+        field("fingerprint", self.fingerprint.clone());
+        field("kdf", self.kdf.clone());
+        field(
+            "tags",
+            (!self.tags.is_empty()).then(|| self.tags.join(", ")),
+        );
+        field("compressed", Some(self.compressed.to_string()));
+        field("payload size", Some(format!("{} bytes", self.payload_size)));
+        if let Some(ref error) = self.error {
+            field("error", Some(error.clone()));
+        }
+    }
+}
+
+/// Print the metadata of the shares.
+pub fn run(args: InspectArgs, decoder: &dyn QrDecoder) -> Result<(), CliError> {
+    let inspections = load_all(&args.shares, decoder)?
+        .iter()
+        .map(Inspection::of)
+        .collect::<Result<Vec<Inspection>, CliError>>()?;
+    match args.format {
+        Format::Table => {
+            for (i, inspection) in inspections.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                inspection.print_table();
+            }
+        }
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&inspections).expect("inspection is serializable")
+        ),
+    }
+    Ok(())
+}
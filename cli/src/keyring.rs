@@ -0,0 +1,56 @@
+use clap::{Args, Subcommand};
+use keyring::Entry;
+use zeroize::Zeroizing;
+
+use crate::error::CliError;
+use crate::passphrase;
+
+/// Service name of the keyring entries.
+const SERVICE: &str = "banana-recovery";
+
+/// Arguments of `banana keyring`.
+#[derive(Debug, Args)]
+pub struct KeyringArgs {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Debug, Subcommand)]
+enum Action {
+    /// Ask for the passphrase and store it in the keyring entry.
+    Store { entry: String },
+    /// Delete the keyring entry.
+    Forget { entry: String },
+}
+
+/// Store or delete the passphrase entry.
+pub fn run(args: KeyringArgs) -> Result<(), CliError> {
+    match args.action {
+        Action::Store { entry } => {
+            let passphrase = passphrase::ask("Passphrase")?;
+            open(&entry)?
+                .set_password(&passphrase)
+                .map_err(|e| CliError::Keyring(e.to_string()))?;
+            eprintln!("Passphrase stored in keyring entry {entry}.");
+        }
+        Action::Forget { entry } => {
+            open(&entry)?
+                .delete_credential()
+                .map_err(|e| CliError::Keyring(e.to_string()))?;
+            eprintln!("Keyring entry {entry} deleted.");
+        }
+    }
+    Ok(())
+}
+
+/// Passphrase stored in the keyring entry.
+pub fn passphrase(entry: &str) -> Result<Zeroizing<String>, CliError> {
+    open(entry)?
+        .get_password()
+        .map(Zeroizing::new)
+        .map_err(|e| CliError::Keyring(format!("entry {entry}: {e}")))
+}
+
+fn open(entry: &str) -> Result<Entry, CliError> {
+    Entry::new(SERVICE, entry).map_err(|e| CliError::Keyring(e.to_string()))
+}
@@ -0,0 +1,152 @@
+use std::io::Read;
+use std::path::Path;
+
+use banana_recovery::{from_paper_text, Error, QrDecoder};
+
+use crate::error::CliError;
+
+/// Marker at the start of png files.
+const PNG_MAGIC: &[u8] = b"\x89PNG";
+
+/// Markers at the start of other image files: jpeg, gif, bmp, and both tiff byte orders.
+/// Webp is recognized separately, its marker is not at the start.
+const IMAGE_MAGICS: [&[u8]; 5] = [b"\xff\xd8\xff", b"GIF8", b"BM", b"II*\0", b"MM\0*"];
+
+/// Input naming the standard input.
+const STDIN: &str = "-";
+
+/// Share payload, with the name of the place it was loaded from, for the reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loaded {
+    pub source: String,
+    pub payload: Vec<u8>,
+}
+
+/// Payloads from all inputs, in the order given.
+///
+/// Input is `-` for the standard input, a file path, or a share payload as is.
+pub fn load_all(inputs: &[String], decoder: &dyn QrDecoder) -> Result<Vec<Loaded>, CliError> {
+    let mut loaded = Vec::new();
+    for input in inputs {
+        loaded.extend(load(input, decoder)?);
+    }
+    Ok(loaded)
+}
+
+fn load(input: &str, decoder: &dyn QrDecoder) -> Result<Vec<Loaded>, CliError> {
+    let io_error = |error| CliError::Io {
+        source_name: input.to_owned(),
+        error,
+    };
+    if input == STDIN {
+        let mut content = Vec::new();
+        let _ = std::io::stdin()
+            .read_to_end(&mut content)
+            .map_err(io_error)?;
+        payloads_in("stdin", content, decoder)
+    } else if Path::new(input).is_file() {
+        payloads_in(input, std::fs::read(input).map_err(io_error)?, decoder)
+    } else if input.trim_start().starts_with('{') {
+        Ok(vec![Loaded {
+            source: "argument".to_owned(),
+            payload: input.trim().as_bytes().to_vec(),
+        }])
+    } else {
+        Err(CliError::NotFound(input.to_owned()))
+    }
+}
+
+/// Payloads in the file content: one share per line, share text made for paper,
+/// qr codes in image, or, with `stego` feature, share hidden in png image.
+///
+/// Content that is none of these is returned as a single payload, for the share
+/// parsing to report what is wrong with it.
+pub fn payloads_in(
+    source: &str,
+    content: Vec<u8>,
+    decoder: &dyn QrDecoder,
+) -> Result<Vec<Loaded>, CliError> {
+    if is_image(&content) {
+        return from_image(source, &content, decoder);
+    }
+    let loaded = |name: String, payload: Vec<u8>| Loaded {
+        source: name,
+        payload,
+    };
+    let Ok(text) = std::str::from_utf8(&content) else {
+        // compressed payloads are binary
+        return Ok(vec![loaded(source.to_owned(), content)]);
+    };
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, a)| !a.is_empty())
+        .collect();
+    if lines.len() > 1 && lines.iter().all(|(_, a)| a.starts_with('{')) {
+        return Ok(lines
+            .into_iter()
+            .map(|(i, a)| loaded(format!("{source}:{}", i + 1), a.as_bytes().to_vec()))
+            .collect());
+    }
+    match from_paper_text(text) {
+        Ok(payload) => Ok(vec![loaded(source.to_owned(), payload)]),
+        Err(_) => Ok(vec![loaded(
+            source.to_owned(),
+            text.trim().as_bytes().to_vec(),
+        )]),
+    }
+}
+
+fn is_image(content: &[u8]) -> bool {
+    content.starts_with(PNG_MAGIC)
+        || IMAGE_MAGICS.iter().any(|a| content.starts_with(a))
+        || (content.starts_with(b"RIFF") && content.get(8..12) == Some(b"WEBP"))
+}
+
+/// Share hidden in png image, if there is one, or shares in the qr codes of the image.
+fn from_image(
+    source: &str,
+    content: &[u8],
+    decoder: &dyn QrDecoder,
+) -> Result<Vec<Loaded>, CliError> {
+    if let Some(payload) = hidden_share(content) {
+        return Ok(vec![Loaded {
+            source: source.to_owned(),
+            payload,
+        }]);
+    }
+    let payloads = decoder.decode(content)?;
+    match payloads.len() {
+        0 => Err(Error::NoShareInImage.into()),
+        1 => Ok(payloads
+            .into_iter()
+            .map(|payload| Loaded {
+                source: source.to_owned(),
+                payload,
+            })
+            .collect()),
+        _ => Ok(payloads
+            .into_iter()
+            .enumerate()
+            .map(|(i, payload)| Loaded {
+                source: format!("{source}#{}", i + 1),
+                payload,
+            })
+            .collect()),
+    }
+}
+
+#[cfg(feature = "stego")]
+fn hidden_share(content: &[u8]) -> Option<Vec<u8>> {
+    if content.starts_with(PNG_MAGIC) {
+        banana_recovery::extract_from_png(content).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(not(feature = "stego"))]
+fn hidden_share(_content: &[u8]) -> Option<Vec<u8>> {
+    None
+}
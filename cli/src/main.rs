@@ -0,0 +1,110 @@
+//! Banana split command line tool
+//!
+//! Audits printed backups made with the banana [split protocol](https://github.com/paritytech/banana_split).
+
+#![deny(unused_crate_dependencies)]
+#![deny(unused_results)]
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+/// This module contains the `batch` command.
+mod batch;
+
+/// This module contains the configuration file.
+mod config;
+
+/// This module contains the errors of the command line tool.
+mod error;
+
+/// This module contains the `inspect` command.
+mod inspect;
+
+/// This module contains the passphrase storage in the OS keyring.
+#[cfg(feature = "keyring")]
+mod keyring;
+
+/// This module contains loading of share payloads from arguments and files.
+mod load;
+
+/// This module contains the output of the recovered secrets.
+mod output;
+
+/// This module contains the passphrase entry.
+mod passphrase;
+
+/// This module contains the `restore` command.
+mod restore;
+
+/// This module contains the `split` command.
+mod split;
+
+/// This module contains the `verify` command.
+mod verify;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "banana",
+    version,
+    about = "Banana split backups from the command line"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Configuration file [default: `banana.toml` in the current directory,
+    /// or in `banana` directory of the user configuration directory].
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check that the shares of a set agree with each other, and that any
+    /// required number of them recover the secret with the passphrase.
+    Verify(verify::VerifyArgs),
+    /// Show the metadata of shares, without the share content.
+    Inspect(inspect::InspectArgs),
+    /// Collect the shares from a directory of photos, scans, and text files,
+    /// and report which sets are complete.
+    Batch(batch::BatchArgs),
+    /// Split the secret from the standard input into shares, with a generated passphrase.
+    Split(split::SplitArgs),
+    /// Restore the file split with `split --file`, and verify its checksum.
+    Restore(restore::RestoreArgs),
+    /// Keep the passphrase in the OS keyring, for unattended recovery drills.
+    #[cfg(feature = "keyring")]
+    Keyring(keyring::KeyringArgs),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let config = match config::Config::load(cli.config.as_deref()) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::from(e.exit_code());
+        }
+    };
+    let decoder = banana_recovery::RqrrDecoder;
+    let result = match cli.command {
+        Command::Verify(args) => verify::run(args, &decoder),
+        Command::Inspect(args) => inspect::run(args, &decoder),
+        Command::Batch(args) => batch::run(args, &decoder),
+        Command::Split(args) => split::run(args, &config.split),
+        Command::Restore(args) => restore::run(args, &decoder),
+        #[cfg(feature = "keyring")]
+        Command::Keyring(args) => keyring::run(args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(any(feature = "polkadot-js", feature = "ethereum"))]
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+
+use crate::error::CliError;
+
+/// How the recovered secret is shown; printed by default.
+#[derive(Debug, Default, Args)]
+pub struct SecretOutput {
+    /// Copy the recovered secret to the clipboard instead of printing it,
+    /// so that it never lands in the scrollback buffer.
+    #[cfg(feature = "clipboard")]
+    #[arg(long)]
+    clipboard: bool,
+    /// Seconds to keep the secret in the clipboard before clearing it.
+    #[cfg(feature = "clipboard")]
+    #[arg(long, default_value_t = 30, value_name = "SECONDS")]
+    clear_after: u64,
+    /// Show the recovered seed phrase as qr code for importing into Polkadot Vault,
+    /// instead of printing it.
+    #[cfg(feature = "qr")]
+    #[arg(long)]
+    vault_qr: bool,
+    /// Write the account of the recovered seed phrase into polkadot-js json keystore file,
+    /// encrypted with a new password, instead of printing the seed phrase.
+    /// For several sets, the set fingerprint is added to the file name.
+    #[cfg(feature = "polkadot-js")]
+    #[arg(long, value_name = "FILE")]
+    polkadot_js: Option<PathBuf>,
+    /// SS58 address prefix of the keystore account: 0 for Polkadot, 2 for Kusama.
+    #[cfg(feature = "polkadot-js")]
+    #[arg(long, default_value_t = 42, requires = "polkadot_js")]
+    ss58_prefix: u16,
+    /// Account name in the keystore.
+    #[cfg(feature = "polkadot-js")]
+    #[arg(
+        long,
+        default_value = "Recovered from banana split",
+        requires = "polkadot_js"
+    )]
+    account_name: String,
+    /// Write the recovered private key into Ethereum V3 json keystore file,
+    /// encrypted with a new password, instead of printing the key.
+    /// For several sets, the set fingerprint is added to the file name.
+    #[cfg(feature = "ethereum")]
+    #[arg(long, value_name = "FILE")]
+    ethereum_keystore: Option<PathBuf>,
+}
+
+impl SecretOutput {
+    /// Secret is asked to be shown other than by printing, and is to be shown
+    /// even when printing is not asked for.
+    pub fn is_requested(&self) -> bool {
+        #[allow(unused_mut)]
+        let mut requested = false;
+        #[cfg(feature = "clipboard")]
+        {
+            requested |= self.clipboard;
+        }
+        #[cfg(feature = "qr")]
+        {
+            requested |= self.vault_qr;
+        }
+        #[cfg(feature = "polkadot-js")]
+        {
+            requested |= self.polkadot_js.is_some();
+        }
+        #[cfg(feature = "ethereum")]
+        {
+            requested |= self.ethereum_keystore.is_some();
+        }
+        requested
+    }
+    /// Show the secret, labelled with the fingerprint of the set, if there are several.
+    ///
+    /// Secret copied to the clipboard is kept there for the time set, and then
+    /// cleared, unless something else was copied meanwhile; the tool waits till then.
+    pub fn show(&self, set: Option<&str>, secret: &str) -> Result<(), CliError> {
+        let label = set.map(|a| format!("Set {a}"));
+        let label = label.as_deref();
+        #[cfg(feature = "polkadot-js")]
+        if let Some(ref path) = self.polkadot_js {
+            return write_keystore(path, set, |password| {
+                banana_recovery::polkadot_js_keystore(
+                    secret,
+                    password,
+                    &self.account_name,
+                    self.ss58_prefix,
+                )
+            });
+        }
+        #[cfg(feature = "ethereum")]
+        if let Some(ref path) = self.ethereum_keystore {
+            return write_keystore(path, set, |password| {
+                banana_recovery::ethereum_keystore(secret, password)
+            });
+        }
+        #[cfg(feature = "clipboard")]
+        if self.clipboard {
+            return copy_for(label, secret, self.clear_after);
+        }
+        #[cfg(feature = "qr")]
+        if self.vault_qr {
+            if let Some(label) = label {
+                println!("{label}:");
+            }
+            println!("{}", banana_recovery::vault_seed_qr_text(secret)?.as_str());
+            return Ok(());
+        }
+        match label {
+            Some(label) => println!("{label}: {secret}"),
+            None => println!("{secret}"),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "clipboard")]
+fn copy_for(label: Option<&str>, secret: &str, seconds: u64) -> Result<(), CliError> {
+    use zeroize::Zeroizing;
+
+    let clipboard_error = |e: arboard::Error| CliError::Clipboard(e.to_string());
+    let mut clipboard = arboard::Clipboard::new().map_err(clipboard_error)?;
+    clipboard.set_text(secret).map_err(clipboard_error)?;
+    let prefix = label.map(|a| format!("{a}: ")).unwrap_or_default();
+    eprintln!("{prefix}secret copied to the clipboard, to be cleared in {seconds} seconds.");
+    std::thread::sleep(std::time::Duration::from_secs(seconds));
+    let current = clipboard.get_text().map(Zeroizing::new);
+    if matches!(current, Ok(ref a) if a.as_str() == secret) {
+        clipboard.clear().map_err(clipboard_error)?;
+        eprintln!("{prefix}clipboard cleared.");
+    }
+    Ok(())
+}
+
+/// Write the keystore made with the new password, asked twice, into the file.
+#[cfg(any(feature = "polkadot-js", feature = "ethereum"))]
+fn write_keystore(
+    path: &Path,
+    set: Option<&str>,
+    keystore: impl FnOnce(&str) -> Result<String, banana_recovery::Error>,
+) -> Result<(), CliError> {
+    let path = match set {
+        Some(fingerprint) => {
+            eprintln!("Set {fingerprint}:");
+            with_fingerprint(path, fingerprint)
+        }
+        None => path.to_path_buf(),
+    };
+    let password = crate::passphrase::ask_confirmed("Keystore password")?;
+    std::fs::write(&path, keystore(&password)?).map_err(|error| CliError::Io {
+        source_name: path.display().to_string(),
+        error,
+    })?;
+    eprintln!("Keystore written to {}.", path.display());
+    Ok(())
+}
+
+/// Path with the set fingerprint added to the file name, before the extension.
+#[cfg(any(feature = "polkadot-js", feature = "ethereum"))]
+fn with_fingerprint(path: &Path, fingerprint: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(extension) => path.with_file_name(format!(
+            "{stem}-{fingerprint}.{}",
+            extension.to_string_lossy()
+        )),
+        None => path.with_file_name(format!("{stem}-{fingerprint}")),
+    }
+}
@@ -0,0 +1,121 @@
+use std::io::{BufRead, IsTerminal, Read, Write};
+
+use banana_recovery::{Passphrase, PassphraseCheck};
+use clap::Args;
+use zeroize::Zeroizing;
+
+use crate::error::CliError;
+
+/// Number of attempts to enter matching passphrases, see [`ask_confirmed`].
+const CONFIRM_ATTEMPTS: usize = 3;
+
+/// Ask for the passphrase, or other secret input, on the terminal, with the input hidden.
+///
+/// If the standard input is not a terminal, a line is read from it instead, for scripts.
+/// Input is wiped from memory once dropped.
+pub fn ask(prompt: &str) -> Result<Zeroizing<String>, CliError> {
+    let io_error = |error| CliError::Io {
+        source_name: "passphrase".to_owned(),
+        error,
+    };
+    if std::io::stdin().is_terminal() {
+        return rpassword::prompt_password(format!("{prompt}: "))
+            .map(Zeroizing::new)
+            .map_err(io_error);
+    }
+    eprint!("{prompt}: ");
+    std::io::stderr().flush().map_err(io_error)?;
+    let mut passphrase = Zeroizing::new(String::new());
+    let _ = std::io::stdin()
+        .lock()
+        .read_line(&mut passphrase)
+        .map_err(io_error)?;
+    let len = passphrase.trim_end_matches(['\r', '\n']).len();
+    passphrase.truncate(len);
+    Ok(passphrase)
+}
+
+/// Ask for the secret to split on the terminal, with the input hidden.
+///
+/// If the standard input is not a terminal, all of it is read instead, so that
+/// multi-line secrets are kept whole; only the final line break is dropped.
+pub fn ask_secret(prompt: &str) -> Result<Zeroizing<String>, CliError> {
+    if std::io::stdin().is_terminal() {
+        return ask(prompt);
+    }
+    let mut secret = Zeroizing::new(String::new());
+    let _ = std::io::stdin()
+        .lock()
+        .read_to_string(&mut secret)
+        .map_err(|error| CliError::Io {
+            source_name: "secret".to_owned(),
+            error,
+        })?;
+    trim_final_newline(&mut secret);
+    Ok(secret)
+}
+
+/// Drop the line break at the end of the input, if there is one, and nothing else.
+pub fn trim_final_newline(input: &mut String) {
+    if input.ends_with('\n') {
+        let _ = input.pop();
+        if input.ends_with('\r') {
+            let _ = input.pop();
+        }
+    }
+}
+
+/// Ask for the new passphrase twice, so that a typo does not lock the secret away.
+pub fn ask_confirmed(prompt: &str) -> Result<Zeroizing<String>, CliError> {
+    for _ in 0..CONFIRM_ATTEMPTS {
+        let passphrase = ask(prompt)?;
+        if passphrase.is_empty() {
+            eprintln!("Passphrase is empty, try again.");
+            continue;
+        }
+        if ask(&format!("{prompt} again"))? == passphrase {
+            return Ok(passphrase);
+        }
+        eprintln!("Passphrases do not match, try again.");
+    }
+    Err(CliError::PassphraseMismatch)
+}
+
+/// Where the passphrase for recovery comes from; asked on the terminal by default.
+#[derive(Debug, Default, Args)]
+pub struct PassphraseSource {
+    /// Read the passphrase from this entry of the OS keyring, see `banana keyring store`.
+    #[cfg(feature = "keyring")]
+    #[arg(long, value_name = "ENTRY")]
+    keyring: Option<String>,
+}
+
+impl PassphraseSource {
+    /// Passphrase from the source, with the prompt for the terminal.
+    ///
+    /// Passphrase typed in is checked for typos before the slow key derivation,
+    /// and a warning is shown if it looks mistyped.
+    pub fn get(&self, prompt: &str) -> Result<Zeroizing<String>, CliError> {
+        #[cfg(feature = "keyring")]
+        if let Some(ref entry) = self.keyring {
+            return crate::keyring::passphrase(entry);
+        }
+        let passphrase = ask(prompt)?;
+        if let PassphraseCheck::LikelyTypo { unknown_words, .. } = Passphrase::check(&passphrase) {
+            if unknown_words.is_empty() {
+                eprintln!(
+                    "warning: passphrase words are not lowercase and separated by single hyphens, \
+                     recovery will likely fail."
+                );
+            } else {
+                let positions: Vec<String> =
+                    unknown_words.iter().map(|a| (a + 1).to_string()).collect();
+                eprintln!(
+                    "warning: passphrase words {} are not in the wordlist, recovery will likely fail.",
+                    positions.join(", ")
+                );
+            }
+        }
+        Ok(passphrase)
+    }
+}
@@ -0,0 +1,65 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use banana_recovery::{NextAction, QrDecoder, ShareSetCollection};
+use clap::Args;
+
+use crate::error::CliError;
+use crate::load::load_all;
+use crate::passphrase::PassphraseSource;
+
+/// Arguments of `banana restore`.
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    /// Shares of the file: share payloads, files with shares, one per line,
+    /// images, or `-` for the standard input.
+    #[arg(required = true)]
+    shares: Vec<String>,
+    /// Directory to restore the file into.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+    /// Overwrite the file, if it exists.
+    #[arg(long)]
+    force: bool,
+    #[command(flatten)]
+    passphrase: PassphraseSource,
+}
+
+/// Restore the file split with `banana split --file`, with its checksum verified.
+pub fn run(args: RestoreArgs, decoder: &dyn QrDecoder) -> Result<(), CliError> {
+    let mut collection = ShareSetCollection::new();
+    for item in load_all(&args.shares, decoder)? {
+        if let Err(e) = collection.add_payload(item.payload.as_slice()) {
+            println!("  skip  {}: {e}", item.source);
+        }
+    }
+    let set = match collection.sets() {
+        [] => return Err(CliError::NoCompleteSet),
+        [set] => set,
+        sets => return Err(CliError::SeveralSets(sets.len())),
+    };
+    if set.next_action() != NextAction::AskUserForPassword {
+        return Err(CliError::NoCompleteSet);
+    }
+    let passphrase = args.passphrase.get("Passphrase")?;
+    let file = set.recover_file(&passphrase)?;
+
+    let path = args.output_dir.join(&file.name);
+    let io_error = |error| CliError::Io {
+        source_name: path.display().to_string(),
+        error,
+    };
+    if path.exists() && !args.force {
+        return Err(CliError::FileExists(path.display().to_string()));
+    }
+    std::fs::create_dir_all(&args.output_dir).map_err(io_error)?;
+    std::fs::File::create(&path)
+        .and_then(|mut a| a.write_all(&file.content))
+        .map_err(io_error)?;
+    println!(
+        "Restored {} ({} bytes, checksum verified).",
+        path.display(),
+        file.content.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+
+use banana_recovery::{encrypt, encrypt_file, generate, EncryptOptions, Share};
+use clap::Args;
+use rand::Rng;
+use zeroize::Zeroizing;
+
+use crate::config::SplitDefaults;
+use crate::error::CliError;
+use crate::passphrase;
+
+/// Number of shares, if neither given nor configured.
+const DEFAULT_SHARES: usize = 5;
+
+/// Number of passphrase words, if neither given nor configured.
+const DEFAULT_WORDS: usize = 4;
+
+/// Arguments of `banana split`; unset ones are taken from the configuration file.
+#[derive(Debug, Default, Args)]
+pub struct SplitArgs {
+    /// Title of the backup, printed on every share.
+    #[arg(long)]
+    pub title: String,
+    /// Number of shares to make [default: 5].
+    #[arg(long)]
+    pub shares: Option<usize>,
+    /// Number of shares needed for recovery [default: majority of the shares].
+    #[arg(long)]
+    pub threshold: Option<usize>,
+    /// Number of words in the generated passphrase [default: 4].
+    #[arg(long)]
+    pub words: Option<usize>,
+    /// File with the passphrase words, one per line [default: built-in list].
+    #[arg(long)]
+    pub wordlist: Option<PathBuf>,
+    /// Directory to write the shares into, one file per share,
+    /// instead of the standard output.
+    #[arg(long)]
+    pub output_dir: Option<PathBuf>,
+    /// Ask for the passphrase, twice, instead of generating it.
+    #[arg(long)]
+    pub ask_passphrase: bool,
+    /// Ask for the passphrase only once.
+    #[arg(long, requires = "ask_passphrase")]
+    pub no_confirm: bool,
+    /// File to split, for example a wallet keystore, instead of the secret
+    /// from the standard input; restored with `banana restore`.
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+}
+
+/// What is split.
+enum Input {
+    Secret(Zeroizing<String>),
+    File(PathBuf),
+}
+
+/// Parameters of the split, after the configuration defaults are applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub shares: usize,
+    pub threshold: usize,
+    pub words: usize,
+    pub wordlist: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+}
+
+impl SplitArgs {
+    /// Flags given, or else the configured defaults, or else the built-in ones.
+    pub fn settings(&self, defaults: &SplitDefaults) -> Settings {
+        let shares = self.shares.or(defaults.shares).unwrap_or(DEFAULT_SHARES);
+        Settings {
+            shares,
+            threshold: self
+                .threshold
+                .or(defaults.threshold)
+                .unwrap_or(shares / 2 + 1),
+            words: self.words.or(defaults.words).unwrap_or(DEFAULT_WORDS),
+            wordlist: self.wordlist.clone().or(defaults.wordlist.clone()),
+            output_dir: self.output_dir.clone().or(defaults.output_dir.clone()),
+        }
+    }
+}
+
+/// Split the secret read from the terminal or the standard input, or the file.
+pub fn run(args: SplitArgs, defaults: &SplitDefaults) -> Result<(), CliError> {
+    let settings = args.settings(defaults);
+    // passphrase goes first: secret from the standard input takes all of it
+    let passphrase = match (args.ask_passphrase, settings.wordlist) {
+        (true, _) if args.no_confirm => passphrase::ask("Passphrase")?,
+        (true, _) => passphrase::ask_confirmed("Passphrase")?,
+        (false, Some(ref path)) => generate_from(path, settings.words)?,
+        (false, None) => Zeroizing::new(generate(settings.words)?),
+    };
+    let input = match args.file {
+        Some(path) => Input::File(path),
+        None => Input::Secret(passphrase::ask_secret("Secret")?),
+    };
+    let shares = match input {
+        Input::Secret(secret) => encrypt(
+            &secret,
+            &args.title,
+            &passphrase,
+            settings.shares,
+            settings.threshold,
+        )?,
+        Input::File(path) => encrypt_file(
+            path,
+            &args.title,
+            &passphrase,
+            &EncryptOptions::new(settings.shares, settings.threshold),
+        )?,
+    };
+    match settings.output_dir {
+        Some(ref dir) => {
+            let io_error = |error| CliError::Io {
+                source_name: dir.display().to_string(),
+                error,
+            };
+            std::fs::create_dir_all(dir).map_err(io_error)?;
+            for (i, share) in shares.iter().enumerate() {
+                std::fs::write(dir.join(format!("share-{}.txt", i + 1)), share)
+                    .map_err(io_error)?;
+            }
+            eprintln!("{} shares written to {}.", shares.len(), dir.display());
+        }
+        None => {
+            for share in &shares {
+                println!("{share}");
+            }
+        }
+    }
+    eprintln!(
+        "{} of {} shares recover the secret.",
+        settings.threshold, settings.shares
+    );
+    if let Some(share) = shares.first() {
+        eprintln!(
+            "Set fingerprint, to be written on every share: {}",
+            Share::new(share.as_str())?.fingerprint()
+        );
+    }
+    if !args.ask_passphrase {
+        eprintln!(
+            "Passphrase, to be written on every share: {}",
+            passphrase.as_str()
+        );
+    }
+    Ok(())
+}
+
+/// Passphrase of random words from the wordlist file.
+fn generate_from(path: &Path, words: usize) -> Result<Zeroizing<String>, CliError> {
+    let text = std::fs::read_to_string(path).map_err(|error| CliError::Io {
+        source_name: path.display().to_string(),
+        error,
+    })?;
+    let list: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .collect();
+    if list.is_empty() {
+        return Err(CliError::WordlistEmpty(path.display().to_string()));
+    }
+    let mut rng = rand::thread_rng();
+    Ok(Zeroizing::new(
+        (0..words)
+            .map(|_| list[rng.gen_range(0..list.len())])
+            .collect::<Vec<&str>>()
+            .join("-"),
+    ))
+}
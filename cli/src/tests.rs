@@ -0,0 +1,181 @@
+use banana_recovery::Error;
+
+use crate::batch::files_in;
+use crate::config::Config;
+use crate::error::{Category, CliError};
+use crate::inspect::Inspection;
+use crate::load::{payloads_in, Loaded};
+use crate::split::SplitArgs;
+use crate::verify::subsets;
+
+#[test]
+fn verify_subsets() {
+    assert_eq!(
+        subsets(4, 3, 32),
+        vec![vec![0, 1, 2], vec![0, 1, 3], vec![0, 2, 3], vec![1, 2, 3]]
+    );
+    assert_eq!(subsets(3, 3, 32), vec![vec![0, 1, 2]]);
+    assert!(subsets(2, 3, 32).is_empty());
+
+    // too many subsets, each share is checked in k of them
+    let windows = subsets(10, 5, 32);
+    assert_eq!(windows.len(), 10);
+    assert_eq!(windows[8], vec![8, 9, 0, 1, 2]);
+}
+
+fn no_qr(_image: &[u8]) -> Result<Vec<Vec<u8>>, banana_recovery::Error> {
+    panic!("text is not decoded as image")
+}
+
+#[test]
+fn shares_in_text_file() {
+    let content = b"{\"v\":1,\"a\":1}\n\n  {\"v\":1,\"a\":2}  \n".to_vec();
+    let loaded = payloads_in("shares.txt", content, &no_qr).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].source, "shares.txt:1");
+    assert_eq!(loaded[1].source, "shares.txt:3");
+    assert_eq!(loaded[1].payload, b"{\"v\":1,\"a\":2}");
+
+    let paper = banana_recovery::to_paper_text(
+        banana_recovery::encrypt("secret", "title", "pass", 3, 2).unwrap()[0].as_bytes(),
+    )
+    .unwrap();
+    let loaded = payloads_in("paper.txt", paper.into_bytes(), &no_qr).unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert!(loaded[0].payload.starts_with(b"{"));
+}
+
+#[test]
+fn qr_codes_in_image() {
+    let shares = banana_recovery::encrypt("secret", "title", "pass", 3, 2).unwrap();
+    let decoder = |image: &[u8]| {
+        assert!(image.starts_with(b"\xff\xd8\xff"));
+        Ok(vec![
+            shares[0].clone().into_bytes(),
+            shares[2].clone().into_bytes(),
+        ])
+    };
+    let loaded = payloads_in("photo.jpg", b"\xff\xd8\xff\xe0jpeg".to_vec(), &decoder).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[1].source, "photo.jpg#2");
+    assert_eq!(loaded[1].payload, shares[2].as_bytes());
+
+    let blank = |_image: &[u8]| Ok(Vec::new());
+    assert!(payloads_in("blank.gif", b"GIF89a".to_vec(), &blank).is_err());
+}
+
+#[test]
+fn inspect_share() {
+    let payload = banana_recovery::encrypt("secret", "title", "pass", 3, 2).unwrap()[1].clone();
+    let loaded = Loaded {
+        source: "argument".to_owned(),
+        payload: payload.clone().into_bytes(),
+    };
+    let inspection = Inspection::of(&loaded).unwrap();
+    assert_eq!(inspection.version, Some(1));
+    assert_eq!(inspection.bits, Some(8));
+    assert_eq!(inspection.id, Some(2));
+    assert_eq!(inspection.title.as_deref(), Some("title"));
+    assert_eq!(inspection.required_shards, Some(2));
+    assert_eq!(inspection.payload_size, payload.len());
+    assert!(inspection.error.is_none());
+
+    // unsupported version is still inspected
+    let loaded = Loaded {
+        source: "argument".to_owned(),
+        payload: payload.replace(r#""v":1"#, r#""v":7"#).into_bytes(),
+    };
+    let inspection = Inspection::of(&loaded).unwrap();
+    assert_eq!(inspection.version, Some(7));
+    assert_eq!(inspection.id, None);
+    assert!(inspection.error.is_some());
+}
+
+#[test]
+fn batch_walks_subdirectories() {
+    let dir = std::env::temp_dir().join(format!("banana-batch-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("vault")).unwrap();
+    std::fs::write(dir.join("b.txt"), "b").unwrap();
+    std::fs::write(dir.join("vault").join("a.txt"), "a").unwrap();
+    std::fs::write(dir.join("a.txt"), "a").unwrap();
+    let files = files_in(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        files,
+        [
+            dir.join("a.txt"),
+            dir.join("b.txt"),
+            dir.join("vault").join("a.txt")
+        ]
+    );
+}
+
+#[test]
+fn exit_codes() {
+    let code = |e: Error| CliError::from(e).exit_code();
+    assert_eq!(code(Error::JsonParsing), 3);
+    assert_eq!(code(Error::ShareNonceDifferent), 4);
+    assert_eq!(code(Error::WrongPassphrase), 5);
+    assert_eq!(code(Error::ShareChecksumMismatch), 6);
+    assert_eq!(code(Error::VersionNotSupported("7".to_owned())), 7);
+    assert_eq!(code(Error::Cancelled), 1);
+    assert_eq!(
+        code(Error::WrappedShareInvalid(
+            "data uri".to_owned(),
+            Box::new(Error::ShareChecksumMismatch)
+        )),
+        6
+    );
+    assert_eq!(
+        CliError::NoCompleteSet.category(),
+        Category::InconsistentSet
+    );
+    assert_eq!(CliError::NotFound("a".to_owned()).exit_code(), 1);
+}
+
+#[test]
+fn config_defaults_overridden_by_flags() {
+    let config = Config::parse(
+        r#"
+        [split]
+        shares = 7
+        threshold = 4
+        output_dir = "backups"
+        "#,
+    )
+    .unwrap();
+    let args = SplitArgs {
+        threshold: Some(5),
+        ..SplitArgs::default()
+    };
+    let settings = args.settings(&config.split);
+    assert_eq!(settings.shares, 7);
+    assert_eq!(settings.threshold, 5);
+    assert_eq!(settings.words, 4);
+    assert_eq!(settings.output_dir, Some("backups".into()));
+
+    // majority of the shares, unless configured
+    let settings = SplitArgs::default().settings(&Config::default().split);
+    assert_eq!((settings.shares, settings.threshold), (5, 3));
+
+    assert!(Config::parse("[split]\nshards = 3").is_err());
+    // shares are always made with 8 bits
+    assert!(Config::parse("[split]\nbits = 8").is_err());
+}
+
+#[test]
+fn piped_secret_keeps_inner_lines() {
+    use crate::passphrase::trim_final_newline;
+
+    for (input, secret) in [
+        ("line one\nline two\n", "line one\nline two"),
+        ("line one\r\nline two\r\n", "line one\r\nline two"),
+        ("secret\n\n", "secret\n"),
+        (" secret ", " secret "),
+        ("", ""),
+    ] {
+        let mut input = input.to_owned();
+        trim_final_newline(&mut input);
+        assert_eq!(input, secret);
+    }
+}
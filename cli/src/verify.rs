@@ -0,0 +1,193 @@
+use std::path::PathBuf;
+
+use banana_recovery::{Error, Manifest, QrDecoder, Share, ShareSet, ShareSetCollection};
+use clap::Args;
+use zeroize::Zeroizing;
+
+use crate::error::CliError;
+use crate::load::{load_all, Loaded};
+use crate::output::SecretOutput;
+use crate::passphrase::PassphraseSource;
+
+/// Arguments of `banana verify`.
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// Shares to check: share payloads, files with shares, one per line,
+    /// images, or `-` for the standard input.
+    #[arg(required = true)]
+    shares: Vec<String>,
+    /// Owner manifest file, for shares of private split.
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+    /// Largest number of share subsets to recover from; above it, only
+    /// as many subsets as there are shares are checked, each share in several of them.
+    #[arg(long, default_value_t = 32)]
+    max_subsets: usize,
+    /// Print the recovered secret.
+    #[arg(long)]
+    show_secret: bool,
+    #[command(flatten)]
+    passphrase: PassphraseSource,
+    #[command(flatten)]
+    output: SecretOutput,
+}
+
+/// Check the shares of a set, and print the report.
+pub fn run(args: VerifyArgs, decoder: &dyn QrDecoder) -> Result<(), CliError> {
+    let loaded = load_all(&args.shares, decoder)?;
+    let manifest = match args.manifest {
+        Some(path) => {
+            let json = std::fs::read_to_string(&path).map_err(|error| CliError::Io {
+                source_name: path.display().to_string(),
+                error,
+            })?;
+            Some(Manifest::from_json(&json)?)
+        }
+        None => None,
+    };
+
+    // shares must be readable, and from one set
+    let mut passed = true;
+    let mut collection = ShareSetCollection::new();
+    let mut accepted = Vec::new();
+    for item in &loaded {
+        match collection.add_payload(item.payload.as_slice()) {
+            Ok(_) => accepted.push(item),
+            Err(Error::ShareAlreadyInSet) => println!("  skip  {}: repeated share", item.source),
+            Err(e) => {
+                println!("  FAIL  {}: {e}", item.source);
+                passed = false;
+            }
+        }
+    }
+    let set = match collection.sets() {
+        [] => return Err(CliError::VerifyFailed),
+        [set] => set,
+        sets => {
+            println!("FAIL: shares are from {} different sets:", sets.len());
+            for set in sets {
+                println!("  {} \"{}\"", set.fingerprint(), set.title());
+            }
+            return Err(CliError::VerifyFailed);
+        }
+    };
+    for (item, id) in accepted.iter().zip(set.collected_ids()) {
+        println!("  ok    {}: share {id}", item.source);
+    }
+
+    let required = match (set.required_shards(), &manifest) {
+        (0, Some(manifest)) => manifest.required_shards,
+        (0, None) => return Err(Error::ManifestNeeded.into()),
+        (a, _) => a,
+    };
+    println!(
+        "Set {} \"{}\", {required} of {} shares needed.",
+        set.fingerprint(),
+        manifest.as_ref().map_or(set.title(), |a| a.title.clone()),
+        accepted.len()
+    );
+    if accepted.len() < required {
+        println!("FAIL: too few shares to recover the secret.");
+        return Err(CliError::VerifyFailed);
+    }
+
+    // every checked subset must recover the same secret
+    let passphrase = args.passphrase.get("Passphrase")?;
+    let subsets = subsets(accepted.len(), required, args.max_subsets);
+    println!("Checking {} subsets of {required} shares.", subsets.len());
+    let mut secret: Option<Zeroizing<String>> = None;
+    for subset in &subsets {
+        let payloads: Vec<&Loaded> = subset.iter().map(|a| accepted[*a]).collect();
+        let names = payloads
+            .iter()
+            .map(|a| a.source.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ");
+        match recover(&payloads, manifest.as_ref(), &passphrase) {
+            Ok(recovered) => match &secret {
+                Some(a) if a != &recovered => {
+                    println!("  FAIL  {names}: different secret recovered");
+                    passed = false;
+                }
+                _ => {
+                    println!("  ok    {names}");
+                    secret = Some(recovered);
+                }
+            },
+            Err(e) => {
+                println!("  FAIL  {names}: {e}");
+                passed = false;
+            }
+        }
+    }
+
+    if !passed {
+        println!("FAIL");
+        return Err(CliError::VerifyFailed);
+    }
+    println!("PASS");
+    if let Some(secret) = secret {
+        if args.show_secret || args.output.is_requested() {
+            args.output.show(None, &secret)?;
+        }
+    }
+    Ok(())
+}
+
+/// Recover the secret from given payloads only.
+fn recover(
+    payloads: &[&Loaded],
+    manifest: Option<&Manifest>,
+    passphrase: &str,
+) -> Result<Zeroizing<String>, Error> {
+    let (first, rest) = payloads.split_first().ok_or(Error::TooFewShares)?;
+    let mut share_set = ShareSet::init(Share::new(first.payload.as_slice())?);
+    if let Some(manifest) = manifest {
+        share_set.apply_manifest(manifest.clone())?;
+    }
+    for item in rest {
+        share_set.try_add_share(Share::new(item.payload.as_slice())?)?;
+    }
+    Ok(Zeroizing::new(
+        share_set.recover_with_passphrase(passphrase)?,
+    ))
+}
+
+/// Subsets of `k` out of `n` shares to recover from, as share positions.
+///
+/// All subsets, if there are at most `max` of them; otherwise `n` subsets of
+/// consecutive shares, wrapping around, so that each share is checked `k` times.
+pub fn subsets(n: usize, k: usize, max: usize) -> Vec<Vec<usize>> {
+    if k == 0 || k > n {
+        return Vec::new();
+    }
+    if binomial(n, k) > max {
+        return (0..n)
+            .map(|i| (0..k).map(|j| (i + j) % n).collect())
+            .collect();
+    }
+    let mut out = Vec::new();
+    let mut current: Vec<usize> = (0..k).collect();
+    loop {
+        out.push(current.clone());
+        let Some(i) = (0..k).rev().find(|i| current[*i] < n - k + i) else {
+            return out;
+        };
+        current[i] += 1;
+        for j in i + 1..k {
+            current[j] = current[j - 1] + 1;
+        }
+    }
+}
+
+/// Number of subsets of `k` out of `n`, saturating.
+fn binomial(n: usize, k: usize) -> usize {
+    let mut out: usize = 1;
+    for i in 0..k {
+        out = match out.checked_mul(n - i) {
+            Some(a) => a / (i + 1),
+            None => return usize::MAX,
+        };
+    }
+    out
+}
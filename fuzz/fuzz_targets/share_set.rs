@@ -0,0 +1,24 @@
+#![no_main]
+
+use banana_recovery::{Share, ShareSet};
+use libfuzzer_sys::fuzz_target;
+
+// Shares are separated by newlines, as in a file of scanned shares: the first one
+// starts the set, and the rest are added, so that the set gets combined once
+// enough of them agree. Error correction is on, so that surplus shares are
+// combined in the majority vote too.
+fuzz_target!(|data: &[u8]| {
+    let mut shares = data
+        .split(|a| *a == b'\n')
+        .filter_map(|a| Share::new(a).ok());
+    if let Some(first) = shares.next() {
+        let mut share_set = ShareSet::init(first);
+        share_set.set_error_correction(true);
+        for share in shares {
+            let _ = share_set.why_rejected(&share);
+            let _ = share_set.try_add_share(share);
+            let _ = share_set.next_action();
+        }
+        let _ = share_set.summary();
+    }
+});
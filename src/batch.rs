@@ -0,0 +1,171 @@
+use std::fmt;
+
+use zeroize::Zeroizing;
+
+use crate::cancel::CancellationToken;
+use crate::collection::ShareSetCollection;
+use crate::error::Error;
+use crate::kdf::KeyDerivation;
+use crate::shares::NextAction;
+
+/// Outcome of the recovery of a single set, see [`ShareSetCollection::recover_all`].
+#[derive(Debug)]
+pub struct BatchOutcome {
+    /// Fingerprint of the set, see [`ShareSet::fingerprint`](crate::ShareSet::fingerprint).
+    pub fingerprint: String,
+    /// Title of the set; empty for private set without the manifest applied.
+    pub title: String,
+    /// What came out of the recovery.
+    pub result: BatchResult,
+}
+
+/// Result of the recovery of a single set, see [`BatchOutcome`].
+pub enum BatchResult {
+    /// Secret is recovered with the candidate passphrase at given index.
+    Recovered {
+        /// Index of the passphrase in the candidates given.
+        passphrase: usize,
+        /// Recovered secret.
+        secret: Zeroizing<String>,
+    },
+    /// None of the candidate passphrases recovered the secret.
+    NoPassphraseMatched,
+    /// Set has too few shares, or needs the manifest, and was not tried.
+    NotComplete,
+    /// Recovery failed for other reason than the wrong passphrase,
+    /// for example for unsupported key derivation parameters.
+    Failed(Error),
+}
+
+impl fmt::Debug for BatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchResult::Recovered { passphrase, .. } => f
+                .debug_struct("Recovered")
+                .field("passphrase", passphrase)
+                .finish_non_exhaustive(),
+            BatchResult::NoPassphraseMatched => f.write_str("NoPassphraseMatched"),
+            BatchResult::NotComplete => f.write_str("NotComplete"),
+            BatchResult::Failed(e) => f.debug_tuple("Failed").field(e).finish(),
+        }
+    }
+}
+
+/// Sets sharing the key derivation and the salt, for example splits made with
+/// the same title, get the same key from the same passphrase.
+struct KeyGroup {
+    key_derivation: KeyDerivation,
+    salt: Vec<u8>,
+    sets: Vec<usize>,
+}
+
+impl ShareSetCollection {
+    /// Try the candidate passphrases, in order, on every complete set of the collection,
+    /// and report the outcome for each set, in the order of [`ShareSetCollection::sets`],
+    /// for example when an estate executor has found several backups and a few passphrases.
+    ///
+    /// Keys are derived once for the sets sharing the title and the key derivation,
+    /// and the key derivations of different sets run in parallel threads.
+    /// Candidates are tried only until the set is recovered.
+    pub fn recover_all(&self, passphrases: &[&str]) -> Vec<BatchOutcome> {
+        let sets = self.sets();
+        let mut results: Vec<Option<BatchResult>> = Vec::with_capacity(sets.len());
+        let mut groups: Vec<KeyGroup> = Vec::new();
+        for (index, set) in sets.iter().enumerate() {
+            let salt = match set.salt() {
+                Ok(salt) if set.next_action() == NextAction::AskUserForPassword => salt,
+                _ => {
+                    results.push(Some(BatchResult::NotComplete));
+                    continue;
+                }
+            };
+            results.push(None);
+            let key_derivation = set.key_derivation();
+            match groups
+                .iter_mut()
+                .find(|a| a.key_derivation == key_derivation && a.salt == salt)
+            {
+                Some(group) => group.sets.push(index),
+                None => groups.push(KeyGroup {
+                    key_derivation,
+                    salt,
+                    sets: vec![index],
+                }),
+            }
+        }
+
+        for (candidate, passphrase) in passphrases.iter().enumerate() {
+            let pending: Vec<&KeyGroup> = groups
+                .iter()
+                .filter(|a| a.sets.iter().any(|i| results[*i].is_none()))
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+            let keys = derive_in_parallel(&pending, passphrase);
+            for (group, key) in pending.iter().zip(keys) {
+                for index in &group.sets {
+                    if results[*index].is_some() {
+                        continue;
+                    }
+                    let outcome = match key {
+                        Ok(ref key) => sets[*index].decrypt_with_key(key),
+                        Err(ref e) => Err(e.clone()),
+                    };
+                    results[*index] = match outcome {
+                        Ok(secret) => Some(BatchResult::Recovered {
+                            passphrase: candidate,
+                            secret: Zeroizing::new(secret),
+                        }),
+                        Err(Error::DecodingFailed | Error::WrongPassphrase) => None,
+                        Err(e) => Some(BatchResult::Failed(e)),
+                    };
+                }
+            }
+        }
+
+        sets.iter()
+            .zip(results)
+            .map(|(set, result)| BatchOutcome {
+                fingerprint: set.fingerprint(),
+                title: set.title(),
+                result: result.unwrap_or(BatchResult::NoPassphraseMatched),
+            })
+            .collect()
+    }
+}
+
+/// Keys of the groups for the passphrase, in the order of the groups,
+/// derived in as many threads as there are cores, at most one per group.
+fn derive_in_parallel(
+    groups: &[&KeyGroup],
+    passphrase: &str,
+) -> Vec<Result<Zeroizing<Vec<u8>>, Error>> {
+    let threads = std::thread::available_parallelism()
+        .map(|a| a.get())
+        .unwrap_or(1)
+        .min(groups.len());
+    let per_thread = groups.len().div_ceil(threads);
+    let cancel = CancellationToken::default();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = groups
+            .chunks(per_thread)
+            .map(|chunk| {
+                let cancel = &cancel;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|a| {
+                            a.key_derivation
+                                .derive(passphrase.as_bytes(), &a.salt, cancel)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|a| a.join().expect("key derivation does not panic"))
+            .collect()
+    })
+}
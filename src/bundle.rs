@@ -0,0 +1,101 @@
+use std::io::{Cursor, Write};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::encrypt::Distribution;
+use crate::error::Error;
+use crate::paper::to_paper_text;
+use crate::print::PrintLayout;
+
+/// Single artifact to be handed to a custodian, see [`Distribution::bundles`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustodianBundle {
+    /// Custodian label, as given to [`Distribution::custodian`].
+    pub label: String,
+    /// Share numbers in the bundle, as printed, for example `"2 of 5"`.
+    pub shares: Vec<String>,
+    /// Zip archive of the bundle.
+    pub zip: Vec<u8>,
+}
+
+impl Distribution {
+    /// Encrypt the secret same as [`Distribution::encrypt`], and pack the shares
+    /// of each custodian into a zip archive of its own, in the order custodians
+    /// were added, so that every custodian gets exactly one artifact.
+    ///
+    /// Archive has `instructions.txt` with the title, set fingerprint, share numbers,
+    /// and recovery instructions, and for each share `share-N.txt` with the payload
+    /// and `share-N-paper.txt` with the paper text; with `qr` feature, also
+    /// `share-N.svg` with the qr code. The passphrase is never put into the bundles.
+    pub fn bundles(
+        &self,
+        secret: &str,
+        title: &str,
+        passphrase: &str,
+    ) -> Result<Vec<CustodianBundle>, Error> {
+        let custodians = self.encrypt(secret, title, passphrase)?;
+        let all: Vec<String> = custodians
+            .iter()
+            .flat_map(|a| a.shares.iter().cloned())
+            .collect();
+        let mut layouts = PrintLayout::for_shares(&all)?.into_iter();
+        custodians
+            .into_iter()
+            .map(|custodian| {
+                let layouts: Vec<PrintLayout> =
+                    layouts.by_ref().take(custodian.shares.len()).collect();
+                Ok(CustodianBundle {
+                    shares: layouts.iter().map(|a| a.index_text.clone()).collect(),
+                    zip: zip_bundle(&custodian.label, &layouts)?,
+                    label: custodian.label,
+                })
+            })
+            .collect()
+    }
+}
+
+fn zip_bundle(label: &str, layouts: &[PrintLayout]) -> Result<Vec<u8>, Error> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut add = |name: &str, content: &[u8]| -> Result<(), Error> {
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        zip.start_file(name, options).map_err(bundle_error)?;
+        zip.write_all(content).map_err(bundle_error)
+    };
+    add("instructions.txt", instructions(label, layouts).as_bytes())?;
+    for layout in layouts {
+        let index = layout.index;
+        add(&format!("share-{index}.txt"), layout.qr_payload.as_bytes())?;
+        add(
+            &format!("share-{index}-paper.txt"),
+            to_paper_text(layout.qr_payload.as_bytes())?.as_bytes(),
+        )?;
+        #[cfg(feature = "qr")]
+        add(
+            &format!("share-{index}.svg"),
+            crate::share_qr::share_qr_svg(&layout.qr_payload)?.as_bytes(),
+        )?;
+    }
+    Ok(zip.finish().map_err(bundle_error)?.into_inner())
+}
+
+fn instructions(label: &str, layouts: &[PrintLayout]) -> String {
+    let first = &layouts[0];
+    let numbers: Vec<&str> = layouts.iter().map(|a| a.index_text.as_str()).collect();
+    let mut text = format!("Banana split shares for {label}\n\n");
+    if let Some(ref title) = first.title {
+        text.push_str(&format!("Title: {title}\n"));
+    }
+    text.push_str(&format!(
+        "Set fingerprint: {}\nShares: {}\n\n{}\n\nThe passphrase is not in this bundle, \
+         and is given separately by the owner.\n",
+        first.fingerprint,
+        numbers.join(", "),
+        first.instructions
+    ));
+    text
+}
+
+fn bundle_error(error: impl std::fmt::Display) -> Error {
+    Error::BundleFailed(error.to_string())
+}
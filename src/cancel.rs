@@ -0,0 +1,37 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::Error;
+
+/// Token for cooperative cancellation of long-running operations.
+///
+/// Clones share the same state: the application keeps one clone,
+/// and calls [`cancel`](Self::cancel) when the user presses cancel.
+/// The library checks the token between key derivation rounds,
+/// while combining shares, and before each step of
+/// [`ChunkedRecovery`](crate::ChunkedRecovery), and stops with [`Error::Cancelled`].
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// New token, not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Request cancellation of the operations using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+    /// Cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+    /// Error if cancellation was requested.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
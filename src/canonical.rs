@@ -0,0 +1,169 @@
+use crate::error::Error;
+use crate::shares::Share;
+use crate::title::parse_json;
+
+/// Share fields, serialized by [`ShareFields::to_json`] in the canonical form.
+pub(crate) struct ShareFields<'a> {
+    /// Version, `None` for legacy shares.
+    pub(crate) v: Option<u8>,
+    pub(crate) t: Option<&'a str>,
+    pub(crate) r: Option<usize>,
+    pub(crate) d: &'a str,
+    pub(crate) n: &'a str,
+    /// Key derivation header, V2 only.
+    pub(crate) k: Option<&'a serde_json::Value>,
+    /// Share checksum, V2 only.
+    pub(crate) c: Option<&'a str>,
+    /// Key commitment, V2 only.
+    pub(crate) m: Option<&'a str>,
+    /// Application tags, V2 only; left out if empty.
+    pub(crate) g: &'a [String],
+    /// Not before, V2 only.
+    pub(crate) b: Option<u64>,
+    /// Not after, V2 only.
+    pub(crate) e: Option<u64>,
+    /// Set key, V2 only.
+    pub(crate) i: Option<&'a str>,
+    /// Set mac, V2 only.
+    pub(crate) h: Option<&'a str>,
+    /// Set identifier, V2 only.
+    pub(crate) u: Option<&'a str>,
+    /// Salt override, V2 only.
+    pub(crate) s: Option<&'a str>,
+}
+
+impl ShareFields<'_> {
+    /// Minified json, byte-for-byte as `JSON.stringify` in the reference web app makes it.
+    ///
+    /// Fields go in the order of the web app, `v`, `t`, `r`, `d`, `n`,
+    /// followed by V2 fields `k`, `c`, `m`, `g`, `b`, `e`, `i`, `h`, `u`, `s`, that the web app does not have.
+    pub(crate) fn to_json(&self) -> String {
+        let mut out = String::from("{");
+        let mut field = |name: &str, value: &str| {
+            if out.len() > 1 {
+                out.push(',');
+            }
+            push_js_string(&mut out, name);
+            out.push(':');
+            out.push_str(value);
+        };
+        if let Some(v) = self.v {
+            field("v", &v.to_string());
+        }
+        if let Some(t) = self.t {
+            field("t", &js_string(t));
+        }
+        if let Some(r) = self.r {
+            field("r", &r.to_string());
+        }
+        field("d", &js_string(self.d));
+        field("n", &js_string(self.n));
+        if let Some(k) = self.k {
+            // keys of the header object are sorted
+            field("k", &k.to_string());
+        }
+        if let Some(c) = self.c {
+            field("c", &js_string(c));
+        }
+        if let Some(m) = self.m {
+            field("m", &js_string(m));
+        }
+        if !self.g.is_empty() {
+            let tags: Vec<String> = self.g.iter().map(|a| js_string(a)).collect();
+            field("g", &format!("[{}]", tags.join(",")));
+        }
+        if let Some(b) = self.b {
+            field("b", &b.to_string());
+        }
+        if let Some(e) = self.e {
+            field("e", &e.to_string());
+        }
+        if let Some(i) = self.i {
+            field("i", &js_string(i));
+        }
+        if let Some(h) = self.h {
+            field("h", &js_string(h));
+        }
+        if let Some(u) = self.u {
+            field("u", &js_string(u));
+        }
+        if let Some(s) = self.s {
+            field("s", &js_string(s));
+        }
+        out.push('}');
+        out
+    }
+}
+
+/// Re-serialize the share payload into the canonical json, as the reference web app
+/// would print it, for example to compare shares from different implementations byte-for-byte.
+///
+/// Share is checked same way as in [`Share::new`]; compressed payloads are decompressed.
+/// Fields unknown to the protocol are dropped.
+pub fn canonical(payload: &[u8]) -> Result<String, Error> {
+    let _ = Share::new(payload)?;
+    let inflated;
+    let payload = if payload.starts_with(crate::deflate::DEFLATE_MAGIC) {
+        inflated = crate::deflate::inflate(&payload[crate::deflate::DEFLATE_MAGIC.len()..])?;
+        &inflated[..]
+    } else {
+        payload
+    };
+    // share is parsed successfully above, these could not fail
+    let text = std::str::from_utf8(payload).map_err(|_| Error::NotShareString)?;
+    let parsed = parse_json(text)?;
+    let k = match &parsed["k"] {
+        json::JsonValue::Null => None,
+        a => Some(serde_json::from_str(&a.dump()).map_err(|_| Error::JsonParsing)?),
+    };
+    let g: Vec<String> = parsed["g"]
+        .members()
+        .filter_map(|a| a.as_str().map(String::from))
+        .collect();
+    let fields = ShareFields {
+        v: parsed["v"].as_u8(),
+        t: parsed["t"].as_str(),
+        r: parsed["r"].as_usize(),
+        d: parsed["d"].as_str().unwrap_or_default(),
+        n: parsed["n"].as_str().unwrap_or_default(),
+        k: k.as_ref(),
+        c: parsed["c"].as_str(),
+        m: parsed["m"].as_str(),
+        g: &g,
+        b: parsed["b"].as_u64(),
+        e: parsed["e"].as_u64(),
+        i: parsed["i"].as_str(),
+        h: parsed["h"].as_str(),
+        u: parsed["u"].as_str(),
+        s: parsed["s"].as_str(),
+    };
+    Ok(fields.to_json())
+}
+
+/// String in quotes, escaped as by `JSON.stringify`.
+fn js_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    push_js_string(&mut out, value);
+    out
+}
+
+/// `JSON.stringify` escapes only the quote, the backslash, and the control characters;
+/// the usual control characters have short escapes, others are `\u00xx` with lowercase hex.
+/// Non-ascii characters, `/`, and DEL are kept as they are.
+fn push_js_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,76 @@
+use serde::Serialize;
+
+use crate::kdf::{ARGON2ID_NAME, PBKDF2_NAME, SCRYPT_NAME};
+use crate::shares::{MAX_BITS, MIN_BITS};
+
+/// Name of the only supported cipher.
+const CIPHER_NAME: &str = "xsalsa20poly1305";
+
+/// Protocol support of the compiled library, see [`capabilities`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    /// Supported share format versions, as recorded in share `"v"` field.
+    /// Legacy shares without version field are supported as well.
+    pub versions: Vec<u8>,
+    /// Supported ciphers.
+    pub ciphers: Vec<&'static str>,
+    /// Supported key derivation algorithms, as named in V2 share header.
+    pub kdfs: Vec<&'static str>,
+    /// Smallest supported bits value, i.e. n in GF(2^n).
+    pub min_bits: u32,
+    /// Largest supported bits value, i.e. n in GF(2^n).
+    pub max_bits: u32,
+    /// Cargo features the library was compiled with.
+    pub features: Vec<&'static str>,
+}
+
+/// Report what the compiled library supports, so that applications could
+/// adapt the user interface and refuse unsupported shares up front.
+pub fn capabilities() -> Capabilities {
+    let mut features = Vec::new();
+    if cfg!(feature = "argon2") {
+        features.push("argon2");
+    }
+    if cfg!(feature = "bundle") {
+        features.push("bundle");
+    }
+    if cfg!(feature = "deflate") {
+        features.push("deflate");
+    }
+    if cfg!(feature = "ethereum") {
+        features.push("ethereum");
+    }
+    if cfg!(feature = "paranoid-checks") {
+        features.push("paranoid-checks");
+    }
+    if cfg!(feature = "polkadot-js") {
+        features.push("polkadot-js");
+    }
+    if cfg!(feature = "qr") {
+        features.push("qr");
+    }
+    if cfg!(feature = "seal") {
+        features.push("seal");
+    }
+    if cfg!(feature = "stego") {
+        features.push("stego");
+    }
+    if cfg!(feature = "uniffi") {
+        features.push("uniffi");
+    }
+    if cfg!(feature = "wasm") {
+        features.push("wasm");
+    }
+    let mut kdfs = vec![SCRYPT_NAME, PBKDF2_NAME];
+    if cfg!(feature = "argon2") {
+        kdfs.push(ARGON2ID_NAME);
+    }
+    Capabilities {
+        versions: vec![1, 2],
+        ciphers: vec![CIPHER_NAME],
+        kdfs,
+        min_bits: MIN_BITS,
+        max_bits: MAX_BITS,
+        features,
+    }
+}
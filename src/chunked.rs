@@ -0,0 +1,452 @@
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac;
+use salsa20::cipher::{typenum::U4, StreamCipherCore};
+use salsa20::SalsaCore;
+use sha2::{Sha256, Sha512};
+use zeroize::Zeroizing;
+
+use crate::cancel::CancellationToken;
+use crate::error::Error;
+use crate::integrity::{check_key, COMMITMENT_LEN};
+use crate::kdf::{Kdf, KdfAlgorithm, KeyDerivation, KEY_LEN};
+use crate::shares::decrypt;
+
+/// Recovery with the key derivation split into small steps, see
+/// [`ShareSet::start_recovery`](crate::ShareSet::start_recovery).
+///
+/// Intended for platforms where a single key derivation call blocks for too long,
+/// for example browser main thread: the application calls [`step`](Self::step)
+/// repeatedly, yielding to the event loop in between, and updates the progress bar.
+///
+/// Key derivation here is an incremental implementation of the same algorithms,
+/// producing the same key as [`ShareSet::recover_with_passphrase`](crate::ShareSet::recover_with_passphrase).
+#[derive(Debug)]
+pub struct ChunkedRecovery {
+    driver: KdfDriver,
+    data: Vec<u8>,
+    nonce: Vec<u8>,
+    key_commitment: Option<[u8; COMMITMENT_LEN]>,
+    cancellation: CancellationToken,
+}
+
+/// Outcome of [`ChunkedRecovery::step`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum RecoveryStep {
+    /// Key derivation is still running.
+    Pending {
+        /// Work units done so far.
+        done: u64,
+        /// Total work units.
+        total: u64,
+    },
+    /// Secret is recovered.
+    Done(String),
+}
+
+impl ChunkedRecovery {
+    pub(crate) fn new(
+        key_derivation: KeyDerivation,
+        passphrase: &[u8],
+        salt: &[u8],
+        data: Vec<u8>,
+        nonce: Vec<u8>,
+        key_commitment: Option<[u8; COMMITMENT_LEN]>,
+        cancellation: CancellationToken,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            driver: KdfDriver::new(key_derivation, passphrase, salt)?,
+            data,
+            nonce,
+            key_commitment,
+            cancellation,
+        })
+    }
+    /// Do up to `units` units of key derivation work, and decrypt the secret once the key is ready.
+    ///
+    /// One unit is one scrypt `BlockMix` or one PBKDF2 iteration; with default scrypt
+    /// parameters a unit takes a few microseconds natively. Argon2id has no incremental
+    /// implementation, and a whole round of it is a single unit.
+    /// Cancellation is checked before each step.
+    pub fn step(&mut self, units: u64) -> Result<RecoveryStep, Error> {
+        self.cancellation.check()?;
+        match self.driver.step(units) {
+            Some(key) => {
+                check_key(key, self.key_commitment.as_ref())?;
+                decrypt(key, &self.nonce, &self.data).map(RecoveryStep::Done)
+            }
+            None => {
+                let (done, total) = self.driver.progress();
+                Ok(RecoveryStep::Pending { done, total })
+            }
+        }
+    }
+}
+
+/// Key derivation state machine, running all rounds of the key derivation step by step.
+#[derive(Debug)]
+pub(crate) struct KdfDriver {
+    key_derivation: KeyDerivation,
+    salt: Vec<u8>,
+    round: u32,
+    stage: Stage,
+    key: Option<Zeroizing<Vec<u8>>>,
+}
+
+#[derive(Debug)]
+enum Stage {
+    Scrypt(ScryptState),
+    Pbkdf2(Box<Pbkdf2State>),
+    OneShot(OneShotState),
+}
+
+impl KdfDriver {
+    pub(crate) fn new(
+        key_derivation: KeyDerivation,
+        passphrase: &[u8],
+        salt: &[u8],
+    ) -> Result<Self, Error> {
+        key_derivation.check()?;
+        Ok(Self {
+            stage: Stage::new(key_derivation.algorithm, passphrase, salt)?,
+            key_derivation,
+            salt: salt.to_vec(),
+            round: 0,
+            key: None,
+        })
+    }
+    /// Do up to `units` of work; key, once all rounds are done.
+    pub(crate) fn step(&mut self, mut units: u64) -> Option<&[u8]> {
+        while self.key.is_none() && units > 0 {
+            let (used, key) = match self.stage {
+                Stage::Scrypt(ref mut state) => state.step(units),
+                Stage::Pbkdf2(ref mut state) => state.step(units),
+                Stage::OneShot(ref mut state) => state.step(units),
+            };
+            units -= used;
+            if let Some(key) = key {
+                self.round += 1;
+                if self.round == self.key_derivation.rounds {
+                    self.key = Some(key);
+                } else {
+                    // parameters were checked at start, next round could not fail
+                    self.stage = Stage::new(self.key_derivation.algorithm, &key, &self.salt)
+                        .expect("checked parameters");
+                }
+            }
+        }
+        self.key.as_deref().map(|key| &key[..])
+    }
+    /// Work units done, and total work units.
+    pub(crate) fn progress(&self) -> (u64, u64) {
+        let (done, per_round) = match self.stage {
+            Stage::Scrypt(ref state) => (state.done, state.total()),
+            Stage::Pbkdf2(ref state) => (state.done, state.total),
+            Stage::OneShot(ref state) => (state.done, 1),
+        };
+        let total = per_round * self.key_derivation.rounds as u64;
+        if self.key.is_some() {
+            (total, total)
+        } else {
+            (per_round * self.round as u64 + done, total)
+        }
+    }
+}
+
+impl Stage {
+    fn new(algorithm: KdfAlgorithm, passphrase: &[u8], salt: &[u8]) -> Result<Self, Error> {
+        match algorithm {
+            KdfAlgorithm::Scrypt { log_n, r, p } => Ok(Stage::Scrypt(ScryptState::new(
+                passphrase, salt, log_n, r, p,
+            )?)),
+            KdfAlgorithm::Pbkdf2 { iterations } => Ok(Stage::Pbkdf2(Box::new(Pbkdf2State::new(
+                passphrase, salt, iterations,
+            )))),
+            KdfAlgorithm::Argon2id { .. } => Ok(Stage::OneShot(OneShotState {
+                algorithm,
+                passphrase: Zeroizing::new(passphrase.to_vec()),
+                salt: salt.to_vec(),
+                done: 0,
+            })),
+        }
+    }
+}
+
+/// Salsa20/8 core, as used by `scrypt` crate.
+type Salsa20_8 = SalsaCore<U4>;
+
+/// Incremental scrypt, RFC 7914, with the Salsa20/8 core of `salsa20` crate.
+///
+/// Work unit is one `BlockMix`; each of `p` lanes takes `2N` of them in `ROMix`.
+struct ScryptState {
+    passphrase: Zeroizing<Vec<u8>>,
+    /// `p` lanes of `32r` words each, after the initial PBKDF2
+    b: Zeroizing<Vec<u32>>,
+    /// `ROMix` table, `N` entries of `32r` words each
+    v: Zeroizing<Vec<u32>>,
+    /// `ROMix` working block
+    x: Zeroizing<Vec<u32>>,
+    /// `BlockMix` output buffer
+    y: Zeroizing<Vec<u32>>,
+    n: u64,
+    r: usize,
+    p: usize,
+    done: u64,
+}
+
+impl std::fmt::Debug for ScryptState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScryptState")
+            .field("n", &self.n)
+            .field("r", &self.r)
+            .field("p", &self.p)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl ScryptState {
+    fn new(passphrase: &[u8], salt: &[u8], log_n: u8, r: u32, p: u32) -> Result<Self, Error> {
+        let too_large = || Error::KdfParamsNotSupported(format!("{log_n} {r} {p}"));
+        let n = 1u64.checked_shl(log_n as u32).ok_or_else(too_large)?;
+        let r = r as usize;
+        let p = p as usize;
+        let block_words = 32 * r;
+        let v_words = usize::try_from(n)
+            .ok()
+            .and_then(|n| n.checked_mul(block_words))
+            .ok_or_else(too_large)?;
+
+        let mut bytes = Zeroizing::new(vec![0u8; p * block_words * 4]);
+        pbkdf2_hmac::<Sha256>(passphrase, salt, 1, &mut bytes);
+        let b: Vec<u32> = bytes
+            .chunks_exact(4)
+            .map(|a| u32::from_le_bytes([a[0], a[1], a[2], a[3]]))
+            .collect();
+        let x = b[..block_words].to_vec();
+        Ok(Self {
+            passphrase: Zeroizing::new(passphrase.to_vec()),
+            b: Zeroizing::new(b),
+            v: Zeroizing::new(vec![0; v_words]),
+            x: Zeroizing::new(x),
+            y: Zeroizing::new(vec![0; block_words]),
+            n,
+            r,
+            p,
+            done: 0,
+        })
+    }
+    fn total(&self) -> u64 {
+        2 * self.n * self.p as u64
+    }
+    /// Do up to `units` of work; units used, and key if the round is complete.
+    fn step(&mut self, units: u64) -> (u64, Option<Zeroizing<Vec<u8>>>) {
+        let block_words = 32 * self.r;
+        let mut used = 0;
+        while used < units && self.done < self.total() {
+            let lane = (self.done / (2 * self.n)) as usize;
+            let j = self.done % (2 * self.n);
+            if j < self.n {
+                // first loop of ROMix, fill the table
+                let start = j as usize * block_words;
+                self.v[start..start + block_words].copy_from_slice(&self.x);
+            } else {
+                // second loop of ROMix, mix in table entry picked by the block
+                let last = (2 * self.r - 1) * 16;
+                let integer = self.x[last] as u64 | (self.x[last + 1] as u64) << 32;
+                let start = (integer & (self.n - 1)) as usize * block_words;
+                for (x, v) in self.x.iter_mut().zip(&self.v[start..start + block_words]) {
+                    *x ^= v;
+                }
+            }
+            block_mix(&mut self.x, &mut self.y, self.r);
+            self.done += 1;
+            used += 1;
+
+            // lane complete, store it and start the next one
+            if j + 1 == 2 * self.n {
+                let start = lane * block_words;
+                self.b[start..start + block_words].copy_from_slice(&self.x);
+                if lane + 1 < self.p {
+                    self.x
+                        .copy_from_slice(&self.b[start + block_words..start + 2 * block_words]);
+                }
+            }
+        }
+        if self.done < self.total() {
+            return (used, None);
+        }
+        let mut bytes = Zeroizing::new(Vec::with_capacity(self.b.len() * 4));
+        for word in self.b.iter() {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        let mut key = Zeroizing::new(vec![0; KEY_LEN]);
+        pbkdf2_hmac::<Sha256>(&self.passphrase, &bytes, 1, &mut key);
+        (used, Some(key))
+    }
+}
+
+/// `BlockMix` with Salsa20/8, `b` is replaced with the output; `y` is scratch space.
+fn block_mix(b: &mut [u32], y: &mut [u32], r: usize) {
+    let mut x = [0u32; 16];
+    x.copy_from_slice(&b[(2 * r - 1) * 16..]);
+    let mut keystream = [0u8; 64];
+    for i in 0..2 * r {
+        for (x, b) in x.iter_mut().zip(&b[i * 16..(i + 1) * 16]) {
+            *x ^= b;
+        }
+        Salsa20_8::from_raw_state(x).write_keystream_block((&mut keystream).into());
+        for (x, a) in x.iter_mut().zip(keystream.chunks_exact(4)) {
+            *x = u32::from_le_bytes([a[0], a[1], a[2], a[3]]);
+        }
+        // even blocks go to the first half of output, odd blocks to the second
+        let out = (i / 2 + (i % 2) * r) * 16;
+        y[out..out + 16].copy_from_slice(&x);
+    }
+    b.copy_from_slice(y);
+}
+
+/// Key derivation without incremental implementation, Argon2id;
+/// the whole round is a single work unit.
+struct OneShotState {
+    algorithm: KdfAlgorithm,
+    passphrase: Zeroizing<Vec<u8>>,
+    salt: Vec<u8>,
+    done: u64,
+}
+
+impl std::fmt::Debug for OneShotState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OneShotState")
+            .field("algorithm", &self.algorithm)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl OneShotState {
+    /// Do up to `units` of work; units used, and key if the round is complete.
+    fn step(&mut self, units: u64) -> (u64, Option<Zeroizing<Vec<u8>>>) {
+        if units == 0 || self.done == 1 {
+            return (0, None);
+        }
+        let mut key = Zeroizing::new(vec![0; KEY_LEN]);
+        // parameters were checked at start
+        self.algorithm
+            .derive_key(&self.passphrase, &self.salt, &mut key)
+            .expect("checked parameters");
+        self.done = 1;
+        (1, Some(key))
+    }
+}
+
+/// Incremental PBKDF2-HMAC-SHA512; key fits into a single hash output block.
+///
+/// Work unit is one iteration.
+struct Pbkdf2State {
+    mac: Hmac<Sha512>,
+    u: Zeroizing<Vec<u8>>,
+    t: Zeroizing<Vec<u8>>,
+    total: u64,
+    done: u64,
+}
+
+impl std::fmt::Debug for Pbkdf2State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pbkdf2State")
+            .field("total", &self.total)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl Pbkdf2State {
+    fn new(passphrase: &[u8], salt: &[u8], iterations: u32) -> Self {
+        let mac = <Hmac<Sha512>>::new_from_slice(passphrase).expect("any key length is fine");
+        Self {
+            mac,
+            u: Zeroizing::new(salt.iter().chain(&1u32.to_be_bytes()).copied().collect()),
+            t: Zeroizing::new(vec![0; 64]),
+            total: iterations as u64,
+            done: 0,
+        }
+    }
+    /// Do up to `units` of work; units used, and key if the round is complete.
+    fn step(&mut self, units: u64) -> (u64, Option<Zeroizing<Vec<u8>>>) {
+        let mut used = 0;
+        while used < units && self.done < self.total {
+            let mut mac = self.mac.clone();
+            mac.update(&self.u);
+            self.u = Zeroizing::new(mac.finalize().into_bytes().to_vec());
+            for (t, u) in self.t.iter_mut().zip(self.u.iter()) {
+                *t ^= u;
+            }
+            self.done += 1;
+            used += 1;
+        }
+        if self.done < self.total {
+            (used, None)
+        } else {
+            (used, Some(Zeroizing::new(self.t[..KEY_LEN].to_vec())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunked(key_derivation: KeyDerivation, chunk: u64) -> Vec<u8> {
+        let mut driver = KdfDriver::new(key_derivation, b"password", b"salt").unwrap();
+        loop {
+            if let Some(key) = driver.step(chunk) {
+                return key.to_vec();
+            }
+        }
+    }
+
+    #[test]
+    fn chunked_kdf_matches_one_shot() {
+        let cancel = CancellationToken::default();
+        for (algorithm, rounds) in [
+            (
+                KdfAlgorithm::Scrypt {
+                    log_n: 10,
+                    r: 8,
+                    p: 1,
+                },
+                1,
+            ),
+            (
+                KdfAlgorithm::Scrypt {
+                    log_n: 11,
+                    r: 4,
+                    p: 2,
+                },
+                2,
+            ),
+            (KdfAlgorithm::Pbkdf2 { iterations: 10_000 }, 2),
+        ] {
+            let key_derivation = KeyDerivation { algorithm, rounds };
+            let expected = key_derivation
+                .derive(b"password", b"salt", &cancel)
+                .unwrap();
+            for chunk in [1, 1000, u64::MAX] {
+                assert_eq!(chunked(key_derivation, chunk), expected.to_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn progress_is_reported() {
+        let key_derivation = KeyDerivation {
+            algorithm: KdfAlgorithm::Pbkdf2 { iterations: 10_000 },
+            rounds: 2,
+        };
+        let mut driver = KdfDriver::new(key_derivation, b"password", b"salt").unwrap();
+        assert_eq!(driver.progress(), (0, 20_000));
+        assert!(driver.step(15_000).is_none());
+        assert_eq!(driver.progress(), (15_000, 20_000));
+        assert!(driver.step(5_000).is_some());
+        assert_eq!(driver.progress(), (20_000, 20_000));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::error::Error;
+use crate::input::ShareInput;
+use crate::shares::{NextAction, Share, ShareSet};
+use crate::stats::ScanStats;
+
+/// Shares of several splits, scanned together and grouped into sets,
+/// for example when the user made several backups with the same title over the years.
+///
+/// Shares recording the set identifier, see [`EncryptOptions::set_id`](crate::EncryptOptions::set_id),
+/// are grouped by it. Other shares are grouped by the set fingerprint,
+/// see [`ShareSet::fingerprint`].
+#[derive(Debug, Default)]
+pub struct ShareSetCollection {
+    sets: Vec<ShareSet>,
+    not_shares: usize,
+}
+
+/// Progress of a set of the collection, see [`ShareSetCollection::progress`].
+#[derive(Debug, PartialEq)]
+pub struct SetProgress {
+    /// Index of the set in [`ShareSetCollection::sets`].
+    pub index: usize,
+    /// Fingerprint of the set, see [`ShareSet::fingerprint`].
+    pub fingerprint: String,
+    /// Title of the set; empty for private split without the manifest applied.
+    pub title: String,
+    /// What the set needs next.
+    pub next_action: NextAction,
+}
+
+impl ShareSetCollection {
+    /// Empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Add the share to the set it belongs to, or start a new set with it;
+    /// returns the index of the set in [`ShareSetCollection::sets`].
+    ///
+    /// Share is added to the set same way as with [`ShareSet::try_add_share`],
+    /// and the errors of it are passed through, for example for a repeated share.
+    pub fn add_share(&mut self, share: Share) -> Result<usize, Error> {
+        let fingerprint = share.fingerprint();
+        let found = self.sets.iter().position(|set| match share.set_id() {
+            Some(set_id) => set.set_id() == Some(set_id),
+            None => set.set_id().is_none() && set.fingerprint() == fingerprint,
+        });
+        match found {
+            Some(index) => {
+                self.sets[index].try_add_share(share)?;
+                Ok(index)
+            }
+            None => {
+                self.sets.push(ShareSet::init(share));
+                Ok(self.sets.len() - 1)
+            }
+        }
+    }
+    /// Add the shares of any number of splits, for example all the qr prints
+    /// found in a shoebox, same as [`ShareSetCollection::add_share`];
+    /// returns the outcome for each share, in the order given.
+    pub fn add_shares(
+        &mut self,
+        shares: impl IntoIterator<Item = Share>,
+    ) -> Vec<Result<usize, Error>> {
+        shares.into_iter().map(|a| self.add_share(a)).collect()
+    }
+    /// Parse the scanned payload and add the share, same as [`ShareSetCollection::add_share`];
+    /// payloads that are not shares are counted in [`ScanStats::not_shares`].
+    pub fn add_payload<'a>(&mut self, payload: impl ShareInput<'a>) -> Result<usize, Error> {
+        match Share::new(payload) {
+            Ok(share) => self.add_share(share),
+            Err(e) => {
+                self.not_shares += 1;
+                Err(e)
+            }
+        }
+    }
+    /// Parse the scanned payload with the wrappers stripped, see [`Share::new_sanitized`],
+    /// and add the share, same as [`ShareSetCollection::add_payload`].
+    pub fn add_sanitized_payload(&mut self, payload: &[u8]) -> Result<usize, Error> {
+        match Share::new_sanitized(payload) {
+            Ok(share) => self.add_share(share),
+            Err(e) => {
+                self.not_shares += 1;
+                Err(e)
+            }
+        }
+    }
+    /// Statistics of the scanning session, added up over all sets.
+    ///
+    /// Repeated scans are counted by share number regardless of the set,
+    /// see [`ShareSet::scan_stats`] of the particular set for details.
+    pub fn scan_stats(&self) -> ScanStats {
+        let mut stats = ScanStats::default();
+        for set in &self.sets {
+            stats.merge(set.scan_stats());
+        }
+        stats.not_shares = self.not_shares;
+        stats
+    }
+    /// Progress of each set collected so far, in the order of [`ShareSetCollection::sets`],
+    /// for the user interface to show which backups are complete and which need more shares.
+    pub fn progress(&self) -> Vec<SetProgress> {
+        self.sets
+            .iter()
+            .enumerate()
+            .map(|(index, set)| SetProgress {
+                index,
+                fingerprint: set.fingerprint(),
+                title: set.title(),
+                next_action: set.next_action(),
+            })
+            .collect()
+    }
+    /// Sets collected so far, in the order of their first shares.
+    pub fn sets(&self) -> &[ShareSet] {
+        &self.sets
+    }
+    /// Set by its index, for example to apply the manifest or to recover the secret.
+    pub fn set_mut(&mut self, index: usize) -> Option<&mut ShareSet> {
+        self.sets.get_mut(index)
+    }
+    /// Set with given set identifier.
+    pub fn by_set_id(&self, set_id: &str) -> Option<&ShareSet> {
+        self.sets.iter().find(|a| a.set_id() == Some(set_id))
+    }
+    /// Take the collected sets.
+    pub fn into_sets(self) -> Vec<ShareSet> {
+        self.sets
+    }
+}
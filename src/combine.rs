@@ -0,0 +1,223 @@
+use crate::cancel::CancellationToken;
+use crate::error::Error;
+use crate::paranoid;
+use crate::shares::{fill_logs_and_exps, gf_mul, lagrange, lagrange_coefficients, BIT_RANGE};
+
+/// Memory budget of [`GfStrategy::default`], enough for the tables of all supported bits.
+pub const DEFAULT_GF_MEMORY_BUDGET: usize = 8 * 1024 * 1024;
+
+/// How the arithmetic in GF(2^n) is done when the shares are combined.
+///
+/// Logarithm and exponent tables take `2^(n + 3)` bytes, 8 MiB for 20 bits, which is
+/// prohibitive on small devices. Table-free arithmetic is slower, and takes constant memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GfStrategy {
+    /// Logarithm and exponent tables, always.
+    Tables,
+    /// Table-free multiplication, always.
+    TableFree,
+    /// Tables if they fit into the memory budget, in bytes, and table-free otherwise.
+    Auto {
+        /// Largest memory the tables could take.
+        memory_budget: usize,
+    },
+}
+
+impl Default for GfStrategy {
+    fn default() -> Self {
+        GfStrategy::Auto {
+            memory_budget: DEFAULT_GF_MEMORY_BUDGET,
+        }
+    }
+}
+
+impl GfStrategy {
+    /// Whether the tables are used for given bits.
+    pub fn uses_tables(&self, bits: u32) -> bool {
+        match *self {
+            GfStrategy::Tables => true,
+            GfStrategy::TableFree => false,
+            GfStrategy::Auto { memory_budget } => {
+                2usize.pow(bits) * 2 * std::mem::size_of::<u32>() <= memory_budget
+            }
+        }
+    }
+}
+
+/// Length of each of the two scratch tables for [`combine_into`], for given bits.
+pub fn combine_table_len(bits: u32) -> Result<usize, Error> {
+    if BIT_RANGE.contains(&bits) {
+        Ok(2usize.pow(bits))
+    } else {
+        Err(Error::BitsOutOfRange(bits))
+    }
+}
+
+/// Largest possible length of the [`combine_into`] output,
+/// for given bits and share content length.
+pub fn combine_output_len(bits: u32, content_len: usize) -> usize {
+    (content_len * bits as usize).div_ceil(8)
+}
+
+/// Combine share contents into the encrypted secret, without any allocations.
+///
+/// Intended for memory-constrained devices: all memory is provided by the caller.
+/// `ids` and `contents` are share ids and share contents, as in the parsed shares,
+/// in the same order; all contents must have the same length.
+/// `logs` and `exps` are scratch tables, at least [`combine_table_len`] long each,
+/// and `out` is the output buffer, at least [`combine_output_len`] long.
+///
+/// Returns the length of the encrypted secret written into the beginning of `out`.
+pub fn combine_into(
+    bits: u32,
+    ids: &[u32],
+    contents: &[&[u8]],
+    logs: &mut [u32],
+    exps: &mut [u32],
+    out: &mut [u8],
+) -> Result<usize, Error> {
+    combine_bounded(bits, ids, contents, logs, exps, out, None)
+}
+
+/// Same as [`combine_into`], with cancellation checked for each content element.
+pub(crate) fn combine_bounded(
+    bits: u32,
+    ids: &[u32],
+    contents: &[&[u8]],
+    logs: &mut [u32],
+    exps: &mut [u32],
+    out: &mut [u8],
+    cancel: Option<&CancellationToken>,
+) -> Result<usize, Error> {
+    let table_len = combine_table_len(bits)?;
+    let (logs, exps) = match (logs.get_mut(..table_len), exps.get_mut(..table_len)) {
+        (Some(logs), Some(exps)) => (logs, exps),
+        _ => return Err(Error::BufferTooSmall(table_len)),
+    };
+
+    // calculate logarithms and exponents in GF(2^n) for n = bits
+    fill_logs_and_exps(bits, logs, exps);
+    paranoid::gf_tables(bits, paranoid::filled_log(logs), exps)?;
+
+    combine_elements(bits, ids, contents, out, cancel, |i| {
+        lagrange(
+            ids,
+            contents.iter().map(|content| content[i] as u32),
+            logs,
+            exps,
+            bits,
+        )
+    })
+}
+
+/// Same as [`combine_into`], without the logarithm and exponent tables,
+/// for devices that could not spare the memory for them; slower.
+pub fn combine_into_table_free(
+    bits: u32,
+    ids: &[u32],
+    contents: &[&[u8]],
+    out: &mut [u8],
+) -> Result<usize, Error> {
+    combine_table_free_bounded(bits, ids, contents, out, None)
+}
+
+/// Same as [`combine_into_table_free`], with cancellation checked for each content element.
+pub(crate) fn combine_table_free_bounded(
+    bits: u32,
+    ids: &[u32],
+    contents: &[&[u8]],
+    out: &mut [u8],
+    cancel: Option<&CancellationToken>,
+) -> Result<usize, Error> {
+    let size = combine_table_len(bits)? as u32;
+    let coefficients = lagrange_coefficients(ids, bits)?;
+    combine_elements(bits, ids, contents, out, cancel, |i| {
+        let mut sum = 0;
+        for (content, coefficient) in contents.iter().zip(&coefficients) {
+            // values of y elements are u8 by decoding, and could exceed the field
+            let y = content[i] as u32;
+            if y >= size {
+                return Err(Error::LogOutOfRange(y));
+            }
+            sum ^= gf_mul(y, *coefficient, bits);
+        }
+        Ok(sum)
+    })
+}
+
+/// Checks the contents and the output buffer, and writes the elements calculated
+/// by `element` from the i-th component of each share content.
+fn combine_elements(
+    bits: u32,
+    ids: &[u32],
+    contents: &[&[u8]],
+    out: &mut [u8],
+    cancel: Option<&CancellationToken>,
+    mut element: impl FnMut(usize) -> Result<u32, Error>,
+) -> Result<usize, Error> {
+    if ids.len() != contents.len() {
+        return Err(Error::ShareContentLengthDifferent);
+    }
+    let content_len = match contents.first() {
+        Some(a) => a.len(),
+        None => return Err(Error::TooFewShares),
+    };
+    if contents.iter().any(|a| a.len() != content_len) {
+        return Err(Error::ShareContentLengthDifferent);
+    }
+    let out_len = combine_output_len(bits, content_len);
+    let out = match out.get_mut(..out_len) {
+        Some(a) => a,
+        None => return Err(Error::BufferTooSmall(out_len)),
+    };
+    out.fill(0);
+    paranoid::ids(bits, ids)?;
+
+    let mut writer = BitWriter {
+        out,
+        marker_seen: false,
+        written: 0,
+    };
+    for i in 0..content_len {
+        if let Some(cancel) = cancel {
+            cancel.check()?;
+        }
+        // new element, from i-th component of each share content;
+        // is calculated as u32, its value is always below 2^bits;
+        let new = element(i)?;
+
+        // in js code this crate follows, the bits string representation of new element (i.e. without leading zeroes)
+        // was padded from left with zeroes so that the string length became multiple of (bits) number;
+        // since the new element value is always below 2^bits, this procedure effectively means keeping only
+        // (bits) amount of lower bits from the element
+        for shift in (0..bits).rev() {
+            writer.push(new >> shift & 1 == 1);
+        }
+    }
+    Ok(writer.written.div_ceil(8))
+}
+
+/// Collects the bits into bytes, most significant bit first.
+///
+/// The js code this crate follows cuts all leading false bits up until the first true,
+/// which serves as a padding marker, and cuts the padding marker as well;
+/// the last byte is padded with zeroes on the right, if necessary.
+struct BitWriter<'a> {
+    out: &'a mut [u8],
+    marker_seen: bool,
+    written: usize,
+}
+
+impl BitWriter<'_> {
+    fn push(&mut self, bit: bool) {
+        if !self.marker_seen {
+            self.marker_seen = bit;
+            return;
+        }
+        if bit {
+            // output length is always sufficient for all the element bits
+            self.out[self.written / 8] |= 0x80 >> (self.written % 8);
+        }
+        self.written += 1;
+    }
+}
@@ -0,0 +1,35 @@
+use crate::error::Error;
+
+/// Marker at the start of deflate-compressed share payload.
+/// Uncompressed payload is json object and always starts with `{`.
+pub(crate) const DEFLATE_MAGIC: &[u8] = b"BSZ1";
+
+/// Limit on decompressed payload size, in bytes. Largest qr codes carry
+/// below 3 kB, so anything much larger is damaged or malicious.
+#[cfg(feature = "deflate")]
+const MAX_INFLATED_LEN: usize = 1 << 16;
+
+/// Compress share json for printing into a smaller qr code.
+///
+/// Output is deflated json prefixed with a marker; [`Share::new`](crate::Share::new)
+/// detects the marker and decompresses the payload. Compressed payloads
+/// could not be read by the upstream javascript code.
+#[cfg(feature = "deflate")]
+pub fn compress(share: &str) -> Vec<u8> {
+    let mut out = DEFLATE_MAGIC.to_vec();
+    out.extend(miniz_oxide::deflate::compress_to_vec(share.as_bytes(), 10));
+    out
+}
+
+/// Decompress share payload, with the marker already removed.
+#[cfg(feature = "deflate")]
+pub(crate) fn inflate(data: &[u8]) -> Result<Vec<u8>, Error> {
+    miniz_oxide::inflate::decompress_to_vec_with_limit(data, MAX_INFLATED_LEN)
+        .map_err(|_| Error::DeflateFailed)
+}
+
+/// Compressed payloads are recognized, but could not be read without `deflate` feature.
+#[cfg(not(feature = "deflate"))]
+pub(crate) fn inflate(_data: &[u8]) -> Result<Vec<u8>, Error> {
+    Err(Error::DeflateNotSupported)
+}
@@ -0,0 +1,46 @@
+use serde::Serialize;
+
+/// Byte mode capacity of qr codes with low error correction, versions 1 to 40.
+const BYTE_CAPACITY_L: [usize; 40] = [
+    17, 32, 53, 78, 106, 134, 154, 192, 230, 271, 321, 367, 425, 458, 520, 586, 644, 718, 792, 858,
+    929, 1003, 1091, 1171, 1273, 1367, 1465, 1528, 1628, 1732, 1840, 1952, 2068, 2188, 2303, 2431,
+    2563, 2699, 2809, 2953,
+];
+
+/// Highest qr version still reliably scanned from print by phone cameras.
+const MAX_COMFORTABLE_VERSION: u8 = 20;
+
+/// Size of an encoded share, and the qr code it needs, see [`ShareDensity::of`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ShareDensity {
+    /// Payload length in bytes, as encoded into qr code.
+    pub payload_len: usize,
+    /// Smallest qr version fitting the payload in byte mode with low error correction;
+    /// `None` if the payload does not fit into any qr code.
+    pub qr_version: Option<u8>,
+    /// Qr code would be too dense to scan reliably from print,
+    /// and the share is better split into several codes, or compressed.
+    pub chunking_advised: bool,
+}
+
+impl ShareDensity {
+    /// Report for the share, as produced by [`encrypt`](crate::encrypt) and friends,
+    /// so that applications could warn before printing unscannable codes.
+    pub fn of(share: &str) -> Self {
+        Self::of_payload(share.as_bytes())
+    }
+    /// Report for the raw share payload, for example compressed one.
+    pub fn of_payload(payload: &[u8]) -> Self {
+        let payload_len = payload.len();
+        let qr_version = BYTE_CAPACITY_L
+            .iter()
+            .position(|capacity| *capacity >= payload_len)
+            .map(|index| index as u8 + 1);
+        let chunking_advised = qr_version.is_none_or(|a| a > MAX_COMFORTABLE_VERSION);
+        Self {
+            payload_len,
+            qr_version,
+            chunking_advised,
+        }
+    }
+}
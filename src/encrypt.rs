@@ -1,22 +1,372 @@
-use crate::shares::generate_logs_and_exps;
+use crate::cancel::CancellationToken;
+use crate::canonical::ShareFields;
+use crate::integrity::{
+    key_commitment, random_set_id, set_mac, share_checksum, SetParams, CHECKSUM_LEN, SET_KEY_LEN,
+};
+use crate::kdf::{KdfAlgorithm, KeyDerivation};
+use crate::manifest::Manifest;
+use crate::metrics::{Metric, MetricsHook};
+use crate::paranoid;
+use crate::shares::{
+    check_tag_values, generate_logs_and_exps, max_shares, ShareSet, MAX_TAGS, NONCE_LEN, SALT_LEN,
+};
+use crate::title::derivation_salt;
+use crate::trace::trace_span;
+use crate::validity::Validity;
 use crate::Error;
 use base64::engine::general_purpose::STANDARD as BASE64;
 use base64::Engine;
-use bitvec::macros::internal::funty::Fundamental;
 use crypto_secretbox::aead::{generic_array::GenericArray, Aead, KeyInit};
 use crypto_secretbox::XSalsa20Poly1305;
-use rand::RngCore;
-use scrypt::{scrypt, Params};
-use serde::Serialize;
+use rand::{CryptoRng, RngCore};
 use sha2::{Digest, Sha512};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Serialize)]
-struct Share {
-    v: u8,
-    t: String,
-    r: usize,
-    d: String,
-    n: String,
+/// Bits value of produced shares, i.e. n in GF(2^n).
+const SHARE_BITS: u8 = 8;
+
+/// Counter of nonces made by this process, for [`NonceStrategy::Hybrid`].
+static NONCE_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// How the 24 bytes nonce of the cipher is made, see [`EncryptOptions::nonce_strategy`].
+///
+/// Nonce is recorded in the shares as is, so the strategy does not affect
+/// the format, and the recovery does not need to know it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonceStrategy {
+    /// All 24 bytes are random, as in the reference web app.
+    Random,
+    /// First 24 bytes of SHA-512 over 24 random bytes, the system time in nanoseconds,
+    /// and a process-wide counter.
+    ///
+    /// Nonces stay unique even if the random generator repeats itself,
+    /// as it could in cloned virtual machines or restored snapshots.
+    /// The time is hashed, and could not be read from the shares.
+    #[default]
+    Hybrid,
+}
+
+impl NonceStrategy {
+    /// Make a new nonce.
+    pub(crate) fn nonce(&self, rng: &mut impl RngCore) -> Result<[u8; NONCE_LEN], Error> {
+        let mut nonce = [0; NONCE_LEN];
+        match self {
+            NonceStrategy::Random => rng
+                .try_fill_bytes(&mut nonce)
+                .map_err(|_| Error::RandomnessUnavailable)?,
+            NonceStrategy::Hybrid => {
+                let mut random = [0; NONCE_LEN];
+                rng.try_fill_bytes(&mut random)
+                    .map_err(|_| Error::RandomnessUnavailable)?;
+                // clock before the epoch still differs between the runs
+                let nanos = match SystemTime::now().duration_since(UNIX_EPOCH) {
+                    Ok(a) => a.as_nanos() as u64,
+                    Err(e) => e.duration().as_nanos() as u64,
+                };
+                let counter = NONCE_COUNTER.fetch_add(1, Ordering::Relaxed);
+                let hash = Sha512::new()
+                    .chain_update(random)
+                    .chain_update(nanos.to_be_bytes())
+                    .chain_update(counter.to_be_bytes())
+                    .finalize();
+                nonce.copy_from_slice(&hash[..NONCE_LEN]);
+            }
+        }
+        Ok(nonce)
+    }
+}
+
+/// How the secret is padded before the splitting, see [`EncryptOptions::padding`].
+///
+/// Padding hides the exact secret length in the share length. Padding is
+/// cut off on recovery without knowing the policy, so the recovery does not need it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Padding {
+    /// Pad to multiples of 128 bits, same as the upstream javascript code;
+    /// shares are of the same length as made by the reference web app.
+    #[default]
+    Upstream,
+    /// Pad to multiples of 56 bits, as did earlier versions of this crate;
+    /// shares are shorter, but reveal more of the secret length.
+    Compact,
+    /// Pad to multiples of the given number of bytes, from 1 to 16;
+    /// `Block(16)` is same as [`Padding::Upstream`], and `Block(1)` adds only the marker.
+    Block(usize),
+}
+
+impl Padding {
+    /// Number of zero bytes before the marker byte, for the secret of given length.
+    fn zero_bytes(&self, secret_len: usize) -> usize {
+        match self {
+            Padding::Upstream => Padding::Block(16).zero_bytes(secret_len),
+            Padding::Compact => 7 - (secret_len + 1) % 7,
+            Padding::Block(block) => (secret_len + 1).div_ceil(*block) * block - secret_len - 1,
+        }
+    }
+    /// Padding must fit within the padding recognized on recovery.
+    fn check(&self) -> Result<(), Error> {
+        match self {
+            Padding::Block(block) if !(1..=MAX_PADDING_BLOCK).contains(block) => {
+                Err(Error::PaddingInvalid(*block))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Length of the salt generated with [`EncryptOptions::random_salt`].
+const RANDOM_SALT_LEN: usize = 32;
+
+/// Largest padding block, in bytes; the secret length estimate before the recovery
+/// relies on the padding being at most 128 bits.
+const MAX_PADDING_BLOCK: usize = 16;
+
+/// Options for [`encrypt_with_options`].
+///
+/// Shares are produced in V1 format, unless some option needs V2 format to be recorded.
+#[derive(Debug, Clone)]
+pub struct EncryptOptions {
+    total_shards: usize,
+    required_shards: usize,
+    key_derivation: KeyDerivation,
+    allow_weak_kdf: bool,
+    random_ids: bool,
+    hide_title: bool,
+    hide_threshold: bool,
+    compact: bool,
+    checksums: bool,
+    key_commitment: bool,
+    force_v2: bool,
+    tags: Vec<Vec<String>>,
+    validity: Validity,
+    set_mac: bool,
+    set_id: bool,
+    nonce_strategy: NonceStrategy,
+    padding: Padding,
+    salt: Option<Vec<u8>>,
+    random_salt: bool,
+    metrics: Option<MetricsHook>,
+    cancellation: CancellationToken,
+}
+
+impl EncryptOptions {
+    /// Options with default settings, for `total_shards` shares with
+    /// `required_shards` of them needed for recovery.
+    pub fn new(total_shards: usize, required_shards: usize) -> Self {
+        Self {
+            total_shards,
+            required_shards,
+            key_derivation: KeyDerivation::default(),
+            allow_weak_kdf: false,
+            random_ids: false,
+            hide_title: false,
+            hide_threshold: false,
+            compact: false,
+            checksums: false,
+            key_commitment: false,
+            force_v2: false,
+            tags: Vec::new(),
+            validity: Validity::default(),
+            set_mac: false,
+            set_id: false,
+            nonce_strategy: NonceStrategy::default(),
+            padding: Padding::default(),
+            salt: None,
+            random_salt: false,
+            metrics: None,
+            cancellation: CancellationToken::default(),
+        }
+    }
+    /// Chain the key derivation sequentially `rounds` times, so that the recovery
+    /// takes at least `rounds` times longer than usual on any hardware.
+    ///
+    /// This is a time-lock for inheritance scenarios; with default scrypt parameters
+    /// each round takes roughly a second on a desktop machine. Requires V2 format.
+    pub fn time_lock(mut self, rounds: u32) -> Self {
+        self.key_derivation.rounds = rounds;
+        self
+    }
+    /// Use given key derivation algorithm instead of the default scrypt.
+    /// Non-default algorithm requires V2 format.
+    pub fn kdf(mut self, algorithm: KdfAlgorithm) -> Self {
+        self.key_derivation.algorithm = algorithm;
+        self
+    }
+    /// Allow key derivation parameters below the recommended minimums.
+    /// Without this, [`encrypt_with_options`] refuses weak parameters.
+    /// Parameters below the absolute minimums are refused in any case.
+    pub fn allow_weak_kdf(mut self, allow: bool) -> Self {
+        self.allow_weak_kdf = allow;
+        self
+    }
+    /// Draw share ids randomly from the whole id space instead of numbering
+    /// shares sequentially from 1.
+    ///
+    /// With sequential ids, a single found share hints at how many shares
+    /// exist in total. Randomized ids are read by upstream recovery code as is,
+    /// so the shares remain in V1 format.
+    pub fn random_ids(mut self, random: bool) -> Self {
+        self.random_ids = random;
+        self
+    }
+    /// Privacy mode: omit the title from the shares, so that a lost share
+    /// does not reveal what it is for. Requires V2 format.
+    ///
+    /// The title is then recorded only in the [`Manifest`] kept by the owner,
+    /// and the shares are produced with [`encrypt_with_manifest`].
+    pub fn private(mut self, private: bool) -> Self {
+        self.hide_title = private;
+        self
+    }
+    /// Omit also the number of required shards from the shares, so that a lost share
+    /// does not reveal how many others are needed. Requires V2 format.
+    ///
+    /// The number is then recorded only in the [`Manifest`].
+    pub fn hide_threshold(mut self, hide: bool) -> Self {
+        self.hide_threshold = hide;
+        self
+    }
+    /// Leave out the V2 header fields that have default values,
+    /// for smaller qr codes. Recovery fills in the defaults for the missing fields.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+    /// Report key derivation metrics to the hook.
+    pub fn metrics(mut self, hook: MetricsHook) -> Self {
+        self.metrics = Some(hook);
+        self
+    }
+    /// Stop the key derivation with [`Error::Cancelled`] once the token is cancelled.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+    /// Record a checksum of the content in each share, so that a damaged share
+    /// is detected on scanning, before it spoils the recovery. Requires V2 format.
+    pub fn checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+    /// Record a commitment to the derived key in the shares, so that the wrong
+    /// passphrase is told apart from damaged shares on recovery. Requires V2 format.
+    pub fn key_commitment(mut self, key_commitment: bool) -> Self {
+        self.key_commitment = key_commitment;
+        self
+    }
+    /// Produce shares in V2 format even if no option needs it,
+    /// for example to record the default key derivation explicitly.
+    pub fn force_v2(mut self, force_v2: bool) -> Self {
+        self.force_v2 = force_v2;
+        self
+    }
+    /// Attach application tags to the share with given index, counting from 0,
+    /// for example its storage location, such as `"safe-deposit-box"`.
+    /// Requires V2 format.
+    ///
+    /// Tags are public, and are read from the share without the passphrase,
+    /// so that inventory tooling could reconcile the physical storage against the records.
+    /// Each share takes up to 8 tags, of up to 32 bytes each.
+    pub fn share_tags(mut self, share: usize, tags: &[&str]) -> Self {
+        if self.tags.len() <= share {
+            self.tags.resize(share + 1, Vec::new());
+        }
+        self.tags[share] = tags.iter().map(|a| a.to_string()).collect();
+        self
+    }
+    /// Record the validity period of the set, for policies where backups
+    /// must be rotated, for example annually. Requires V2 format.
+    ///
+    /// Recovery outside of the period is not prevented, but warned about,
+    /// see [`ShareSet::warnings`].
+    pub fn validity(mut self, validity: Validity) -> Self {
+        self.validity = validity;
+        self
+    }
+    /// Record a random set key and a mac over the set parameters in the shares,
+    /// so that mixing shares of sibling backups, with the same title and threshold,
+    /// is reported precisely, and altered set parameters are detected. Requires V2 format.
+    pub fn set_mac(mut self, set_mac: bool) -> Self {
+        self.set_mac = set_mac;
+        self
+    }
+    /// Record a random set identifier, a uuid, in every share, so that shares
+    /// of several backups made with identical titles over the years are grouped
+    /// without ambiguity, see [`ShareSetCollection`](crate::ShareSetCollection).
+    /// Requires V2 format.
+    pub fn set_id(mut self, set_id: bool) -> Self {
+        self.set_id = set_id;
+        self
+    }
+    /// Choose how the nonce is made, [`NonceStrategy::Hybrid`] by default.
+    /// Shares remain in V1 format with any strategy.
+    pub fn nonce_strategy(mut self, strategy: NonceStrategy) -> Self {
+        self.nonce_strategy = strategy;
+        self
+    }
+    /// Choose how the secret is padded, [`Padding::Upstream`] by default.
+    /// Shares remain in V1 format with any padding.
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+    /// Use the caller-supplied key derivation salt instead of the title salt,
+    /// for integrations that manage their own salt registry, or need domain separation
+    /// beyond the title. Salt must be 16 to 64 bytes long. Requires V2 format.
+    ///
+    /// Salt is recorded in the shares, and used on recovery instead of the title salt.
+    pub fn salt_override(mut self, salt: &[u8]) -> Self {
+        self.salt = Some(salt.to_vec());
+        self
+    }
+    /// Generate a random key derivation salt for the split, instead of the title salt,
+    /// so that sets with repeating titles get unrelated keys. Requires V2 format.
+    ///
+    /// Salt is recorded in the shares, same as with [`EncryptOptions::salt_override`],
+    /// which takes precedence if given.
+    pub fn random_salt(mut self, random_salt: bool) -> Self {
+        self.random_salt = random_salt;
+        self
+    }
+    /// Shares could be produced in V1 format only with default settings.
+    fn needs_v2(&self) -> bool {
+        self.key_derivation != KeyDerivation::default()
+            || self.is_private()
+            || self.checksums
+            || self.key_commitment
+            || self.force_v2
+            || self.tags.iter().any(|a| !a.is_empty())
+            || !self.validity.is_unlimited()
+            || self.set_mac
+            || self.set_id
+            || self.salt.is_some()
+            || self.random_salt
+    }
+    /// Tags must fit the share count and limits.
+    fn check_tags(&self) -> Result<(), Error> {
+        if self.tags.len() > self.total_shards {
+            return Err(Error::TagInvalid(format!(
+                "tags given for share {}, but there are {} shares",
+                self.tags.len() - 1,
+                self.total_shards
+            )));
+        }
+        for tags in &self.tags {
+            if tags.len() > MAX_TAGS {
+                return Err(Error::TagInvalid(format!(
+                    "{} tags given, up to {MAX_TAGS} are allowed",
+                    tags.len()
+                )));
+            }
+            check_tag_values(tags)?;
+        }
+        Ok(())
+    }
+    /// Some metadata is recorded only in the manifest.
+    fn is_private(&self) -> bool {
+        self.hide_title || self.hide_threshold
+    }
 }
 
 /// Encrypts a secret and returns a set of shares.
@@ -27,44 +377,386 @@ pub fn encrypt(
     total_shards: usize,
     required_shards: usize,
 ) -> Result<Vec<String>, Error> {
-    // hash title into salt
-    let salt = hash_string(title);
+    encrypt_with_options(
+        secret,
+        title,
+        passphrase,
+        &EncryptOptions::new(total_shards, required_shards),
+    )
+}
+
+/// Renames the backup, and returns the new set of shares, one for each of given `shares`.
+///
+/// Title is the key derivation salt, so the secret is recovered from the `shares`
+/// and encrypted again, see [`ShareSet::retitle`]; other parameters of the set are kept.
+/// Shares of private split are refused, as the manifest is needed for their recovery.
+pub fn retitle<T: AsRef<[u8]>>(
+    shares: &[T],
+    passphrase: &str,
+    new_title: &str,
+) -> Result<Vec<String>, Error> {
+    share_set_of(shares)?.retitle(passphrase, new_title, shares.len())
+}
+
+/// Upgrades the complete legacy set, i.e. with hexadecimal shares without version,
+/// into V1 format, and returns the new set of shares, one for each of given `shares`.
+///
+/// See [`ShareSet::upgrade_legacy`].
+pub fn upgrade_legacy<T: AsRef<[u8]>>(
+    shares: &[T],
+    passphrase: &str,
+) -> Result<Vec<String>, Error> {
+    share_set_of(shares)?.upgrade_legacy(passphrase, shares.len())
+}
+
+/// Migrates the complete V1 or legacy set into V2 format, with the V2 features
+/// chosen in `options`, and returns the new set of shares.
+///
+/// See [`ShareSet::migrate_to_v2`].
+pub fn migrate_v1_to_v2<T: AsRef<[u8]>>(
+    shares: &[T],
+    passphrase: &str,
+    options: &EncryptOptions,
+) -> Result<Vec<String>, Error> {
+    share_set_of(shares)?.migrate_to_v2(passphrase, options)
+}
+
+/// Share set from the share payloads, all of which must be accepted.
+fn share_set_of<T: AsRef<[u8]>>(shares: &[T]) -> Result<ShareSet, Error> {
+    let mut payloads = shares.iter().map(|a| crate::Share::new(a.as_ref()));
+    let mut share_set = match payloads.next() {
+        Some(share) => ShareSet::init(share?),
+        None => return Err(Error::TooFewShares),
+    };
+    for share in payloads {
+        share_set.try_add_share(share?)?;
+    }
+    Ok(share_set)
+}
+
+/// Encrypts a secret with given options and returns a set of shares.
+///
+/// Private splits are refused, as the shares are useless without the manifest;
+/// use [`encrypt_with_manifest`] for these.
+pub fn encrypt_with_options(
+    secret: &str,
+    title: &str,
+    passphrase: &str,
+    options: &EncryptOptions,
+) -> Result<Vec<String>, Error> {
+    if options.is_private() {
+        return Err(Error::ManifestNeeded);
+    }
+    encrypt_with_manifest(secret, title, passphrase, options).map(|(shares, _)| shares)
+}
+
+/// Encrypts a binary secret, for example a raw seed, a DER key, or a small archive,
+/// and returns a set of shares.
+///
+/// Secret is recovered with [`ShareSet::recover_bytes_with_passphrase`].
+pub fn encrypt_bytes(
+    secret: &[u8],
+    title: &str,
+    passphrase: &str,
+    total_shards: usize,
+    required_shards: usize,
+) -> Result<Vec<String>, Error> {
+    encrypt_bytes_with_options(
+        secret,
+        title,
+        passphrase,
+        &EncryptOptions::new(total_shards, required_shards),
+    )
+}
+
+/// Encrypts a binary secret with given options and returns a set of shares,
+/// same as [`encrypt_with_options`].
+pub fn encrypt_bytes_with_options(
+    secret: &[u8],
+    title: &str,
+    passphrase: &str,
+    options: &EncryptOptions,
+) -> Result<Vec<String>, Error> {
+    if options.is_private() {
+        return Err(Error::ManifestNeeded);
+    }
+    encrypt_secret(secret, title, passphrase, options, &mut rand::thread_rng())
+        .map(|(shares, _)| shares)
+}
+
+/// Encrypts a secret with given options, same as [`encrypt_with_options`], with all
+/// the randomness (nonce, shamir coefficients, share ids, salt) drawn from `rng`,
+/// for hardware wallet firmware with its own entropy source, or deterministic tests.
+///
+/// Default [`NonceStrategy::Hybrid`] mixes the system time and a counter into the nonce;
+/// shares are fully determined by `rng` with [`NonceStrategy::Random`].
+pub fn encrypt_with_rng<R: RngCore + CryptoRng>(
+    secret: &str,
+    title: &str,
+    passphrase: &str,
+    options: &EncryptOptions,
+    rng: &mut R,
+) -> Result<Vec<String>, Error> {
+    if options.is_private() {
+        return Err(Error::ManifestNeeded);
+    }
+    encrypt_secret(secret.as_bytes(), title, passphrase, options, rng).map(|(shares, _)| shares)
+}
+
+/// Encrypts a secret with given options and returns a set of shares
+/// together with the [`Manifest`] to be kept by the owner.
+///
+/// Manifest is required for recovery only if the split is private,
+/// see [`EncryptOptions::private`].
+pub fn encrypt_with_manifest(
+    secret: &str,
+    title: &str,
+    passphrase: &str,
+    options: &EncryptOptions,
+) -> Result<(Vec<String>, Manifest), Error> {
+    encrypt_secret(
+        secret.as_bytes(),
+        title,
+        passphrase,
+        options,
+        &mut rand::thread_rng(),
+    )
+}
 
-    // set up the parameters for scrypt
-    let params = Params::new(15, 8, 1, 32).expect("static checked params"); // default ones are used
+fn encrypt_secret(
+    secret: &[u8],
+    title: &str,
+    passphrase: &str,
+    options: &EncryptOptions,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> Result<(Vec<String>, Manifest), Error> {
+    trace_span!(
+        "encrypt",
+        total_shards = options.total_shards,
+        required_shards = options.required_shards
+    );
+    let total_shards = options.total_shards;
+    let required_shards = options.required_shards;
+    options.key_derivation.check()?;
+    options.check_tags()?;
+    options.validity.check()?;
+    options.padding.check()?;
+    if options.key_derivation.is_weak() && !options.allow_weak_kdf {
+        return Err(Error::KdfParamsTooWeak(format!(
+            "{:?}",
+            options.key_derivation.algorithm
+        )));
+    }
 
-    // set up output buffer for scrypt
-    let mut key: Vec<u8> = [0; 32].to_vec(); // allocate here, empty output buffer is rejected
+    // hash title into salt, unless the salt is given
+    if options
+        .salt
+        .as_ref()
+        .is_some_and(|a| !SALT_LEN.contains(&a.len()))
+    {
+        return Err(Error::SaltInvalid);
+    }
+    let salt_recorded = match options.salt {
+        Some(ref a) => Some(a.clone()),
+        None if options.random_salt => {
+            let mut salt = vec![0; RANDOM_SALT_LEN];
+            rng.try_fill_bytes(&mut salt)
+                .map_err(|_| Error::RandomnessUnavailable)?;
+            Some(salt)
+        }
+        None => None,
+    };
+    let salt = derivation_salt(salt_recorded.as_deref(), title);
 
-    // ... and scrypt them
-    scrypt(passphrase.as_bytes(), &salt, &params, &mut key).map_err(Error::ScryptFailed)?;
+    // derive the key
+    let timer = MetricsHook::start(options.metrics.as_ref());
+    let key = options
+        .key_derivation
+        .derive(passphrase.as_bytes(), &salt, &options.cancellation)?;
+    if let Some(timer) = timer {
+        timer.finish(|duration| Metric::KeyDerived {
+            duration,
+            rounds: options.key_derivation.rounds,
+        });
+    }
 
-    let mut nonce = [0; 24].to_vec(); // allocate here, empty output buffer is rejected
-    let mut rng = rand::thread_rng();
-    rng.fill_bytes(&mut nonce);
+    let nonce = options.nonce_strategy.nonce(rng)?;
 
     // set up cipher with key and decrypt secret using nonce
+    paranoid::cipher_input(&key[..], &nonce)?;
     let cipher = XSalsa20Poly1305::new(GenericArray::from_slice(&key[..]));
     let encrypted = cipher
-        .encrypt(GenericArray::from_slice(&nonce), secret.as_bytes())
+        .encrypt(GenericArray::from_slice(&nonce), secret)
         .map_err(|_| Error::EncryptionFailed)?;
 
-    let shares = share(&encrypted, total_shards, required_shards)?;
+    let shares = share(
+        &encrypted,
+        total_shards,
+        required_shards,
+        options.random_ids,
+        options.padding,
+        rng,
+    )?;
     let nonce = BASE64.encode(nonce);
+    let commitment = options.key_commitment.then(|| key_commitment(&key[..]));
+    let key_commitment = commitment.map(|a| BASE64.encode(a));
+    let (version, key_derivation) = if options.needs_v2() {
+        if options.compact {
+            // compact header with all the default values is left out completely
+            let header = options.key_derivation.to_compact_header();
+            let is_empty = header.as_object().is_some_and(|a| a.is_empty());
+            (2, (!is_empty).then_some(header))
+        } else {
+            (2, Some(options.key_derivation.to_header()))
+        }
+    } else {
+        (1, None)
+    };
+    let salt_field = salt_recorded.as_ref().map(|a| BASE64.encode(a));
+    let set_id = match options.set_id {
+        true => Some(random_set_id(rng)?),
+        false => None,
+    };
+    let set_mac = if options.set_mac {
+        let mut set_key = [0; SET_KEY_LEN];
+        rng.try_fill_bytes(&mut set_key)
+            .map_err(|_| Error::RandomnessUnavailable)?;
+        let params = SetParams {
+            version,
+            title: (!options.hide_title).then_some(title),
+            required_shards: (!options.hide_threshold).then_some(required_shards),
+            nonce: &nonce,
+            key_derivation: options.key_derivation,
+            key_commitment: commitment.as_ref(),
+            validity: options.validity,
+            bits: SHARE_BITS as u32,
+            set_id: set_id.as_deref(),
+            salt: salt_recorded.as_deref(),
+        };
+        Some((
+            BASE64.encode(set_key),
+            BASE64.encode(set_mac(&set_key, &params)),
+        ))
+    } else {
+        None
+    };
 
-    Ok(shares
+    let shares: Vec<String> = shares
         .into_iter()
-        .map(|share| {
-            let share = Share {
-                v: 1,
-                t: title.to_string(),
-                r: required_shards,
-                d: share,
-                n: nonce.clone(),
-            };
-            serde_json::to_string(&share).expect("share is serializable")
+        .enumerate()
+        .map(|(i, (share, checksum))| {
+            let checksum = options.checksums.then(|| BASE64.encode(checksum));
+            ShareFields {
+                v: Some(version),
+                t: (!options.hide_title).then_some(title),
+                r: (!options.hide_threshold).then_some(required_shards),
+                d: &share,
+                n: &nonce,
+                k: key_derivation.as_ref(),
+                c: checksum.as_deref(),
+                m: key_commitment.as_deref(),
+                g: options.tags.get(i).map(|a| &a[..]).unwrap_or_default(),
+                b: options.validity.not_before,
+                e: options.validity.not_after,
+                i: set_mac.as_ref().map(|(key, _)| key.as_str()),
+                h: set_mac.as_ref().map(|(_, mac)| mac.as_str()),
+                u: set_id.as_deref(),
+                s: salt_field.as_deref(),
+            }
+            .to_json()
         })
-        .collect())
+        .collect();
+
+    // fingerprint is calculated the same way the recovering side does it
+    let fingerprint = match shares.first() {
+        Some(share) => crate::Share::new(share.as_str())?.fingerprint(),
+        None => return Err(Error::TooFewShares),
+    };
+    let manifest = Manifest {
+        title: title.to_string(),
+        required_shards,
+        fingerprint,
+    };
+    Ok((shares, manifest))
+}
+
+/// Plan for handing shares out to custodians.
+///
+/// Custodian with weight `w` receives `w` shares of the same split,
+/// and so has more voting power when the secret gets recovered.
+/// Total number of shares is the sum of all weights.
+#[derive(Debug, Clone)]
+pub struct Distribution {
+    required_shards: usize,
+    custodians: Vec<(String, usize)>,
+}
+
+/// Shares allocated to a single custodian by [`Distribution::encrypt`].
+#[derive(Debug, Clone)]
+pub struct CustodianShares {
+    /// Custodian label, as given to [`Distribution::custodian`].
+    pub label: String,
+    /// Shares of the custodian.
+    pub shares: Vec<String>,
+}
+
+impl Distribution {
+    /// Start a distribution plan, with `required_shards` shares needed for recovery.
+    pub fn new(required_shards: usize) -> Self {
+        Self {
+            required_shards,
+            custodians: Vec::new(),
+        }
+    }
+    /// Add custodian with given label and number of shares.
+    pub fn custodian(mut self, label: &str, weight: usize) -> Self {
+        self.custodians.push((label.to_string(), weight));
+        self
+    }
+    /// Total number of shares to be generated.
+    ///
+    /// Fails with [`Error::TooManyShares`] if the weights add up to more shares
+    /// than a split could have.
+    pub fn total_shards(&self) -> Result<usize, Error> {
+        let bits = SHARE_BITS as u32;
+        let max_shares = max_shares(bits).ok_or(Error::BitsOutOfRange(bits))?;
+        self.custodians
+            .iter()
+            .try_fold(0usize, |total, (_, weight)| {
+                total
+                    .checked_add(*weight)
+                    .filter(|a| *a <= max_shares as usize)
+                    .ok_or(Error::TooManyShares(max_shares))
+            })
+    }
+    /// Encrypt the secret and split the shares among the custodians, in the order
+    /// custodians were added.
+    pub fn encrypt(
+        &self,
+        secret: &str,
+        title: &str,
+        passphrase: &str,
+    ) -> Result<Vec<CustodianShares>, Error> {
+        if let Some((label, _)) = self.custodians.iter().find(|(_, weight)| *weight == 0) {
+            return Err(Error::CustodianWithoutShares(label.to_owned()));
+        }
+        let mut shares = encrypt(
+            secret,
+            title,
+            passphrase,
+            self.total_shards()?,
+            self.required_shards,
+        )?
+        .into_iter();
+        Ok(self
+            .custodians
+            .iter()
+            .map(|(label, weight)| CustodianShares {
+                label: label.to_owned(),
+                shares: shares.by_ref().take(*weight).collect(),
+            })
+            .collect())
+    }
 }
 
 pub(crate) fn hash_string(s: &str) -> [u8; 64] {
@@ -73,35 +765,52 @@ pub(crate) fn hash_string(s: &str) -> [u8; 64] {
     hasher.finalize().into()
 }
 
-fn share(secret: &[u8], num_shares: usize, required_shards: usize) -> Result<Vec<String>, Error> {
+fn share(
+    secret: &[u8],
+    num_shares: usize,
+    required_shards: usize,
+    random_ids: bool,
+    padding: Padding,
+    rng: &mut impl RngCore,
+) -> Result<Vec<(String, [u8; CHECKSUM_LEN])>, Error> {
     if num_shares < 2 {
         return Err(Error::TooFewShares);
     }
-    if num_shares < required_shards {
+    if num_shares < required_shards || required_shards < 2 {
         return Err(Error::TooFewShares);
     }
-    let bits = 8u8;
-    let max_shares = 2u32.pow(bits as u32) - 1; // do not allow bits exceed 20; 2^n with n 20 or below always fits in u32 limits
+    let bits = SHARE_BITS;
+    let max_shares = max_shares(bits as u32).ok_or(Error::BitsOutOfRange(bits as u32))?;
     if num_shares > max_shares as usize {
         return Err(Error::TooManyShares(max_shares));
     }
 
+    // share numbering starts from 1
+    let ids: Vec<u8> = if random_ids {
+        random_share_ids(num_shares, rng)?
+    } else {
+        (1..=num_shares)
+            .map(|id| u8::try_from(id).map_err(|_| Error::TooManyShares(max_shares)))
+            .collect::<Result<_, _>>()?
+    };
+    let ids_checked: Vec<u32> = ids.iter().map(|a| *a as u32).collect();
+    paranoid::ids(bits as u32, &ids_checked)?;
+
     // Security:
     // For additional security, pad in multiples of 128 bits by default.
     // A small trade-off in larger share size to help prevent leakage of information
     // about small-ish secrets and increase the difficulty of attacking them.
-    let pad_length = 7;
-    let left_pad = pad_length - (secret.len() + 1) % pad_length;
-
-    let mut to_split = vec![0u8; left_pad];
+    // The upstream code marks the secret start with a single `1` bit, here it is
+    // the marker byte `1` with 7 more zero bits of padding, same bits in total.
+    let mut to_split = vec![0u8; padding.zero_bytes(secret.len())];
     to_split.extend(vec![1u8]);
     to_split.extend(secret);
 
     // Vec[[share1[1], share2[1] ... shareM[1]], [share1[2], share2[2] ... shareM[2]] ... [share1[N], share2[N] ... shareM[N]]]
     let splits: Vec<Vec<u8>> = to_split
         .into_iter()
-        .map(|x| get_shares(x, num_shares, required_shards, bits))
-        .collect();
+        .map(|x| get_shares(x, &ids, required_shards, bits, rng))
+        .collect::<Result<_, _>>()?;
 
     // to Vec[[share1[1], share1[2] ... share1[N]], [share2[1], share2[2] ... share2[N]] ... [shareM[1], shareM[2] ... shareM[N]]]
     let mut x = Vec::with_capacity(num_shares);
@@ -113,22 +822,50 @@ fn share(secret: &[u8], num_shares: usize, required_shards: usize) -> Result<Vec
         x.push(y);
     }
 
-    Ok(x.iter()
-        .enumerate()
-        .map(|(idx, data)| construct_public_share_string(bits, idx.as_u8() + 1, data))
-        .collect())
+    x.iter()
+        .zip(ids.iter())
+        .map(|(data, id)| {
+            let share = construct_public_share_string(bits, *id, data)?;
+            Ok((share, share_checksum(*id as u32, data)))
+        })
+        .collect()
 }
 
-// Generates a random shamir pool for a given secret, returns share points.
-fn get_shares(secret: u8, num_shares: usize, threshold: usize, bits: u8) -> Vec<u8> {
+// Draws `num_shares` distinct non-zero share ids, in random order.
+// Rejection sampling keeps every id equally likely.
+fn random_share_ids(num_shares: usize, rng: &mut impl RngCore) -> Result<Vec<u8>, Error> {
+    let mut ids = Vec::with_capacity(num_shares);
+    let mut candidate = [0u8; 1];
+    while ids.len() < num_shares {
+        rng.try_fill_bytes(&mut candidate)
+            .map_err(|_| Error::RandomnessUnavailable)?;
+        if candidate[0] != 0 && !ids.contains(&candidate[0]) {
+            ids.push(candidate[0]);
+        }
+    }
+    Ok(ids)
+}
+
+// Generates a random shamir pool for a given secret, returns share points at given ids.
+fn get_shares(
+    secret: u8,
+    ids: &[u8],
+    threshold: usize,
+    bits: u8,
+    rng: &mut impl RngCore,
+) -> Result<Vec<u8>, Error> {
     let mut coeffs = vec![0; threshold - 1];
-    let mut rng = rand::thread_rng();
-    rng.fill_bytes(&mut coeffs);
+    rng.try_fill_bytes(&mut coeffs)
+        .map_err(|_| Error::RandomnessUnavailable)?;
     let mut poly = vec![secret];
     poly.extend(coeffs);
     let (logs, exps) = generate_logs_and_exps(bits as u32);
-    (1..num_shares + 1)
-        .map(|x| horner(x as u8, &poly, &logs, &exps, bits as u32))
+    paranoid::gf_tables(bits as u32, |x| logs.get(x).copied().flatten(), &exps)?;
+    ids.iter()
+        .map(|x| {
+            let y = horner(*x as u32, &poly, &logs, &exps, bits as u32)?;
+            u8::try_from(y).map_err(|_| Error::ElementOutOfRange(y))
+        })
         .collect()
 }
 
@@ -136,42 +873,63 @@ fn get_shares(secret: u8, num_shares: usize, threshold: usize, bits: u8) -> Vec<
 // NOTE: fx=fx * x + coeff[i] ->  exp(log(fx) + log(x)) + coeff[i],
 //       so if fx===0, just set fx to coeff[i] because
 //       using the exp/log form will result in incorrect value
-fn horner(x: u8, coeffs: &[u8], logs: &[Option<u32>], exps: &[u32], n: u32) -> u8 {
-    let logx = logs[x as usize]
-        .expect("logs[x] is never zero, it is share number, numbering starts from 1");
+//       x is share number, numbering starts from 1, so log(x) is always defined within the field;
+//       coefficients are u8 and could exceed the field for n < 8, this is reported as error
+pub(crate) fn horner(
+    x: u32,
+    coeffs: &[u8],
+    logs: &[Option<u32>],
+    exps: &[u32],
+    n: u32,
+) -> Result<u32, Error> {
+    let logx = match logs.get(x as usize) {
+        Some(Some(a)) => *a,
+        _ => return Err(Error::LogOutOfRange(x)),
+    };
     let mut fx = 0;
     let max_shares = 2u32.pow(n) - 1;
     for i in coeffs.iter().rev() {
         if fx != 0 {
-            let exp = (logx + logs[fx as usize].expect("log(x) is not defined")) % max_shares;
-            fx = exps[exp as usize] ^ *i as u32;
+            let logfx = match logs.get(fx as usize) {
+                Some(Some(a)) => *a,
+                _ => return Err(Error::LogOutOfRange(fx)),
+            };
+            let exp = (logx + logfx) % max_shares;
+            fx = match exps.get(exp as usize) {
+                Some(a) => a ^ *i as u32,
+                None => return Err(Error::LogOutOfRange(exp)),
+            };
         } else {
             fx = *i as u32;
         }
     }
-    fx.try_into().expect("failed to convert result to u8")
+    Ok(fx)
 }
 
-fn construct_public_share_string(bits: u8, id: u8, data: &[u8]) -> String {
+fn construct_public_share_string(bits: u8, id: u8, data: &[u8]) -> Result<String, Error> {
     let mut combined = vec![id];
     combined.extend_from_slice(data);
-    format!(
+    Ok(format!(
         "{}{}",
-        format_radix(bits as u32, 36),
+        format_radix(bits as u32)?,
         BASE64.encode(combined),
-    )
+    ))
 }
 
-fn format_radix(mut x: u32, radix: u32) -> String {
+/// Format number in radix36, as used for bits in share data.
+fn format_radix(mut x: u32) -> Result<String, Error> {
     let mut result = vec![];
     loop {
-        let m = x % radix;
-        x /= radix;
+        let m = x % 36;
+        x /= 36;
 
-        result.push(std::char::from_digit(m, radix).expect("bad radix (< 2 or > 36)"));
+        match std::char::from_digit(m, 36) {
+            Some(a) => result.push(a),
+            None => return Err(Error::ElementOutOfRange(m)),
+        }
         if x == 0 {
             break;
         }
     }
-    result.into_iter().rev().collect()
+    Ok(result.into_iter().rev().collect())
 }
@@ -1,57 +1,213 @@
+use crate::kdf::KdfAlgorithm;
 use crate::shares::BIT_RANGE;
 
 #[allow(missing_docs)]
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 #[non_exhaustive]
 pub enum Error {
     #[error("Bits in share data {0} are outside of expected range [{range:?}]. Likely the share is damaged.", range=BIT_RANGE)]
     BitsOutOfRange(u32),
 
+    #[error("Provided buffer is too small, {0} elements are needed.")]
+    BufferTooSmall(usize),
+
+    #[error("Unable to pack the custodian bundle: {0}.")]
+    BundleFailed(String),
+
+    #[error("Operation was cancelled.")]
+    Cancelled,
+
+    #[error("Shares of the file chunk {0} are missing.")]
+    ChunkMissing(usize),
+
+    #[error("File chunk size must not be zero.")]
+    ChunkSizeZero,
+
+    #[error("Compressed share could not be parsed without copying.")]
+    CompressedShareBorrowed,
+
+    #[error("Custodian {0} was given no shares.")]
+    CustodianWithoutShares(String),
+
+    #[error("Compressed share is not supported, the crate is built without `deflate` feature.")]
+    DeflateNotSupported,
+
+    #[error("Unable to decompress the share.")]
+    DeflateFailed,
+
     #[error("Decoded secret could not be displayed as a string.")]
     DecodedSecretNotString,
 
     #[error("Unable to decode the secret.")]
     DecodingFailed,
 
+    #[error("Value {0} does not fit into the share element.")]
+    ElementOutOfRange(u32),
+
     #[error("Encryption failed.")]
     EncryptionFailed,
 
+    #[error("Passphrase entropy is expected to be 4 bytes per word, got {0} bytes.")]
+    EntropyLengthInvalid(usize),
+
     #[error("Share contains no data.")]
     EmptyShare,
 
+    #[error("Unable to read or write the png image.")]
+    ImageInvalid,
+
+    #[error("Image is too small to carry the share, {0} color samples are needed.")]
+    ImageTooSmall(usize),
+
+    #[error("Runtime invariant violated: {0}.")]
+    InvariantViolated(String),
+
     #[error("Unable to parse the input as a json object.")]
     JsonParsing,
 
+    #[error("Key commitment is not a valid base64 value of expected length.")]
+    KeyCommitmentInvalid,
+
+    #[error("Key derivation {0} is not supported.")]
+    KdfNotSupported(String),
+
+    #[error("Key derivation parameters {0} are not supported.")]
+    KdfParamsNotSupported(String),
+
+    #[error("Key derivation parameters {0} are too weak.")]
+    KdfParamsTooWeak(String),
+
     #[error("While processing, tried addressing log[{0}] out of expected range. Likely the share is damaged.")]
     LogOutOfRange(u32),
 
+    #[error("Manifest does not belong to this share set.")]
+    ManifestMismatch,
+
+    #[error("Share set is private, the owner manifest is needed.")]
+    ManifestNeeded,
+
+    #[error("Unable to parse the manifest.")]
+    ManifestParsing,
+
+    #[error("Nonce is expected to be 24 bytes long, got {0} bytes.")]
+    NonceLengthInvalid(usize),
+
     #[error("Nonce is not in base64 format")]
     NonceNotBase64,
 
+    #[error("File checksum does not match the restored content.")]
+    FileChecksumMismatch,
+
+    #[error("Unable to read the file {0}.")]
+    FileIo(String),
+
+    #[error("Shares disagree, and no result has the majority; another share is needed.")]
+    NoMajority,
+
+    #[error("Image carries no embedded share or qr code.")]
+    NoShareInImage,
+
+    #[error("Share set is already in the current format, and needs no upgrade.")]
+    NotLegacySet,
+
     #[error("ShareSet was not ready to decode. Should not ba here.")]
     NotReadyToDecode,
 
+    #[error("Recovered secret is not a file split with encrypt_file.")]
+    NotFileSecret,
+
     #[error("Received qr code could not be read as a string.")]
     NotShareString,
 
+    #[error("Line {0} of the share text is damaged, and has to be typed again.")]
+    PaperLineDamaged(usize),
+
+    #[error(
+        "Share text is incomplete or damaged: lines are missing, or the final line does not match."
+    )]
+    PaperTextIncomplete,
+
     #[error("Unable to parse first data char '{0}' as a number in radix36 format")]
     ParseBit(char),
 
+    #[error("Qr code decoding failed: {0}.")]
+    QrDecodingFailed(String),
+
+    #[error("Qr code encoding failed: {0}.")]
+    QrEncodingFailed(String),
+
+    #[error("Random number generator is not available.")]
+    RandomnessUnavailable,
+
+    #[error("Response does not answer the request.")]
+    ResponseIdMismatch,
+
+    #[error("Damaged share could not be repaired within the given number of bit flips.")]
+    RepairFailed,
+
     #[error("Required shards value {0} has unsupported format.")]
     RequiredShardsNotSupported(String),
 
+    #[error("Salt override is not a valid base64 value of 16 to 64 bytes.")]
+    SaltInvalid,
+
+    #[error("Recovery session does not allow this step in its current state.")]
+    SessionStepInvalid,
+
     #[error("Scrypt calculation failed.")]
     ScryptFailed(#[from] scrypt::errors::InvalidOutputLen),
 
+    #[error("Sealing the share failed.")]
+    SealingFailed,
+
+    #[error("Sealed share is not in base64 format.")]
+    SealedShareNotBase64,
+
+    #[error("Self-test failed: {0}.")]
+    SelfTestFailed(String),
+
+    #[error("Secret is not a seed phrase of 12, 15, 18, 21, or 24 words.")]
+    SecretNotSeedPhrase,
+
+    #[error("Secret is not a private key of 32 bytes in hexadecimal.")]
+    SecretNotPrivateKey,
+
+    #[error("Set identifier {0} is not a valid uuid.")]
+    SetIdInvalid(String),
+
+    #[error("Set parameters do not match the set mac. Likely the share is damaged or altered.")]
+    SetMacMismatch,
+
     #[error("Share is already in the set.")]
     ShareAlreadyInSet,
 
+    #[error("Share {0} is not in the set.")]
+    ShareNotInSet(u32),
+
+    #[error("SS58 address prefix {0} is outside of the range 0 to 16383.")]
+    Ss58PrefixInvalid(u16),
+
     #[error("Share could not be added to the set, because its bits setting is different.")]
     ShareBitsDifferent,
 
+    #[error("Share checksum does not match its content. The share is likely damaged.")]
+    ShareChecksumMismatch,
+
     #[error("Share could not be added to the set, because its content length is different.")]
     ShareContentLengthDifferent,
 
+    #[error("Share does not match the fingerprint of the set.")]
+    ShareFingerprintDifferent,
+
+    #[error("Share is from a different split, likely a sibling backup with the same title.")]
+    ShareFromDifferentSet,
+
+    #[error("Share could not be added to the set, because its key commitment is different.")]
+    ShareKeyCommitmentDifferent,
+
+    #[error("Share could not be added to the set, because its key derivation is different.")]
+    ShareKdfDifferent,
+
     #[error("Share could not be added to the set, because its nonce is different.")]
     ShareNonceDifferent,
 
@@ -60,26 +216,62 @@ pub enum Error {
     )]
     ShareRequiredShardsDifferent,
 
+    #[error("Share has different salt than the set.")]
+    ShareSaltDifferent,
+
     #[error("Share could not be added to the set, because its title is different.")]
     ShareTitleDifferent,
 
+    #[error("Share number {0} is outside of the range allowed by share bits. Likely the share is damaged.")]
+    ShareIdOutOfRange(u32),
+
     #[error(
         "Share content is too short to separate share id properly. Likely the share is damaged."
     )]
     ShareTooShort,
 
+    #[error("Share has different validity period than the set.")]
+    ShareValidityDifferent,
+
     #[error("Share could not be added to the set, because its version is different.")]
     ShareVersionDifferent,
 
+    #[error("Unable to open the sealed share. Likely it was sealed for a different key.")]
+    UnsealingFailed,
+
     #[error("Share with undefined version was expected to have hexadecimal content.")]
     UndefinedBodyNotHex,
 
+    #[error("Validity period is invalid.")]
+    ValidityInvalid,
+
     #[error("Version {0} is not supported.")]
     VersionNotSupported(String),
 
     #[error("Share with version V1 was expected to have content in base64 format.")]
     BodyNotBase64,
 
+    #[error("Wordlist has no words.")]
+    WordlistEmpty,
+
+    #[error("Padding block of {0} bytes is not supported, blocks of 1 to 16 bytes are.")]
+    PaddingInvalid(usize),
+
+    #[error("Saved share set could not be read, it is damaged or altered.")]
+    SetSnapshotInvalid,
+
+    #[error("Passphrase words at positions {0:?} are not in the wordlist.")]
+    PassphraseWordsInvalid(Vec<usize>),
+
+    #[error("Share unwrapped from {0} could not be parsed: {1}")]
+    WrappedShareInvalid(String, Box<Error>),
+
+    #[error("Passphrase does not match the key commitment of the set.")]
+    WrongPassphrase,
+
+    #[error("Share tags are invalid: {0}.")]
+    TagInvalid(String),
+
     #[error("Too few shares.")]
     TooFewShares,
 
@@ -88,3 +280,21 @@ pub enum Error {
     )]
     TooManyShares(u32),
 }
+
+/// Non-fatal problems found in shares or share sets.
+///
+/// Warnings do not prevent recovery, but should be shown to the user.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum Warning {
+    /// Key derivation parameters are accepted, but are below the recommended minimums.
+    /// Either the set was made for a constrained device, or the parameters were downgraded.
+    #[error("Key derivation parameters {0:?} are below the recommended minimums.")]
+    WeakKdf(KdfAlgorithm),
+    /// Set is past its validity period, at given unix time, and is due for rotation.
+    #[error("Share set expired at {0} (unix time), and should have been rotated.")]
+    Expired(u64),
+    /// Set is recovered before its validity period starts, at given unix time.
+    #[error("Share set is not valid before {0} (unix time).")]
+    NotYetValid(u64),
+}
@@ -0,0 +1,81 @@
+use aes::cipher::{KeyIvInit, StreamCipher};
+use k256::ecdsa::SigningKey;
+use rand::RngCore;
+use scrypt::{scrypt, Params};
+use serde_json::json;
+use sha3::{Digest, Keccak256};
+use zeroize::Zeroizing;
+
+use crate::error::Error;
+use crate::integrity::random_set_id;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// Scrypt parameters of V3 keystore, standard ones of geth: log2 of N, r, p.
+const SCRYPT_LOG_N: u8 = 18;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// Length of scrypt salt and output; first half of the output is aes key,
+/// second half is the mac key.
+const SCRYPT_SALT_LEN: usize = 32;
+const SCRYPT_OUTPUT_LEN: usize = 32;
+
+/// Length of aes-128-ctr iv.
+const IV_LEN: usize = 16;
+
+/// Encrypted Ethereum V3 json keystore of the recovered private key,
+/// for importing into MetaMask, geth, or other wallets.
+///
+/// Secret is the 32 byte private key in hexadecimal, with or without `0x`.
+/// The keystore is encrypted with the new `password`, with scrypt
+/// parameters of geth, and has the account address.
+pub fn ethereum_keystore(secret: &str, password: &str) -> Result<String, Error> {
+    let secret = secret.trim();
+    let key_hex = secret.strip_prefix("0x").unwrap_or(secret);
+    let private_key = Zeroizing::new(hex::decode(key_hex).map_err(|_| Error::SecretNotPrivateKey)?);
+    let signing_key =
+        SigningKey::from_slice(&private_key).map_err(|_| Error::SecretNotPrivateKey)?;
+    let public = signing_key.verifying_key().to_encoded_point(false);
+    let address = &Keccak256::digest(&public.as_bytes()[1..])[12..];
+
+    let mut rng = rand::thread_rng();
+    let mut salt = [0u8; SCRYPT_SALT_LEN];
+    let mut iv = [0u8; IV_LEN];
+    rng.try_fill_bytes(&mut salt)
+        .map_err(|_| Error::RandomnessUnavailable)?;
+    rng.try_fill_bytes(&mut iv)
+        .map_err(|_| Error::RandomnessUnavailable)?;
+    let mut key = Zeroizing::new([0u8; SCRYPT_OUTPUT_LEN]);
+    let params = Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, SCRYPT_OUTPUT_LEN)
+        .map_err(|_| Error::KdfParamsNotSupported("ethereum scrypt".to_owned()))?;
+    scrypt(password.as_bytes(), &salt, &params, key.as_mut_slice()).map_err(Error::ScryptFailed)?;
+
+    let mut ciphertext = private_key.to_vec();
+    Aes128Ctr::new(key[..16].into(), &iv.into()).apply_keystream(&mut ciphertext);
+    let mac = Keccak256::new()
+        .chain_update(&key[16..])
+        .chain_update(&ciphertext)
+        .finalize();
+
+    Ok(json!({
+        "version": 3,
+        "id": random_set_id(&mut rng)?,
+        "address": hex::encode(address),
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": hex::encode(iv) },
+            "ciphertext": hex::encode(ciphertext),
+            "kdf": "scrypt",
+            "kdfparams": {
+                "dklen": SCRYPT_OUTPUT_LEN,
+                "n": 1u32 << SCRYPT_LOG_N,
+                "p": SCRYPT_P,
+                "r": SCRYPT_R,
+                "salt": hex::encode(salt),
+            },
+            "mac": hex::encode(mac),
+        },
+    })
+    .to_string())
+}
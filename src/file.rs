@@ -0,0 +1,267 @@
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::collection::ShareSetCollection;
+use crate::encrypt::{encrypt_with_options, EncryptOptions};
+use crate::error::Error;
+use crate::input::ShareInput;
+use crate::shares::{Share, ShareSet};
+
+/// Marker at the start of the secret carrying a file, followed by the file json.
+const FILE_MARKER: &str = "banana-file/1\n";
+
+/// File restored from the shares by [`ShareSet::recover_file`].
+pub struct RecoveredFile {
+    /// File name, without directories, as it was when split.
+    pub name: String,
+    /// File content, verified against the checksum recorded at split time.
+    pub content: Zeroizing<Vec<u8>>,
+}
+
+impl std::fmt::Debug for RecoveredFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecoveredFile")
+            .field("name", &self.name)
+            .field("content", &format_args!("{} bytes", self.content.len()))
+            .finish()
+    }
+}
+
+/// Reads the file, for example a wallet keystore, and splits its exact bytes
+/// into shares, same as [`encrypt_with_options`].
+///
+/// The secret carries the file name, the content in base64, and its sha256
+/// checksum, and is restored with [`ShareSet::recover_file`]. Shares grow with
+/// the file, and are meant to be kept as files rather than printed.
+pub fn encrypt_file(
+    path: impl AsRef<Path>,
+    title: &str,
+    passphrase: &str,
+    options: &EncryptOptions,
+) -> Result<Vec<String>, Error> {
+    let (name, content) = read_file(path.as_ref())?;
+    let secret = Zeroizing::new(file_secret(&name, &content));
+    encrypt_with_options(&secret, title, passphrase, options)
+}
+
+/// File name, without directories, and content.
+fn read_file(path: &Path) -> Result<(String, Zeroizing<Vec<u8>>), Error> {
+    let content = Zeroizing::new(
+        std::fs::read(path).map_err(|e| Error::FileIo(format!("{}: {e}", path.display())))?,
+    );
+    let name = path
+        .file_name()
+        .map(|a| a.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok((name, content))
+}
+
+/// Secret carrying the file.
+pub(crate) fn file_secret(name: &str, content: &[u8]) -> String {
+    let json = serde_json::json!({
+        "name": name,
+        "sha256": hex::encode(Sha256::digest(content)),
+        "content": BASE64.encode(content),
+    });
+    format!("{FILE_MARKER}{json}")
+}
+
+impl ShareSet {
+    /// Recover the file split with [`encrypt_file`], and verify its checksum.
+    ///
+    /// Secret not made from a file is refused with [`Error::NotFileSecret`].
+    pub fn recover_file(&self, passphrase: &str) -> Result<RecoveredFile, Error> {
+        let secret = Zeroizing::new(self.recover_with_passphrase(passphrase)?);
+        let json = secret
+            .strip_prefix(FILE_MARKER)
+            .ok_or(Error::NotFileSecret)?;
+        let parsed: serde_json::Value =
+            serde_json::from_str(json).map_err(|_| Error::NotFileSecret)?;
+        let field = |name: &str| parsed[name].as_str().ok_or(Error::NotFileSecret);
+        let content = Zeroizing::new(
+            BASE64
+                .decode(field("content")?)
+                .map_err(|_| Error::NotFileSecret)?,
+        );
+        if hex::encode(Sha256::digest(&content[..])) != field("sha256")? {
+            return Err(Error::FileChecksumMismatch);
+        }
+        // only the file name is kept, so that the file could not be restored
+        // outside of the directory chosen
+        let name = Path::new(field("name")?)
+            .file_name()
+            .map(|a| a.to_string_lossy().into_owned())
+            .ok_or(Error::NotFileSecret)?;
+        Ok(RecoveredFile { name, content })
+    }
+}
+
+/// Layout of the files split by [`encrypt_files`], kept alongside the shares:
+/// file names, sizes and checksums, and the chunks each file is cut into.
+///
+/// The manifest holds no secret content, and is needed for [`reassemble`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileManifest {
+    /// Files, in the order given for the split.
+    pub files: Vec<ManifestFile>,
+    /// Chunks of all files; each is split as a share set of its own.
+    pub chunks: Vec<ManifestChunk>,
+}
+
+/// File entry of [`FileManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// File name, without directories.
+    pub name: String,
+    /// File size in bytes.
+    pub size: u64,
+    /// Sha256 checksum of the file content, hex encoded.
+    pub sha256: String,
+    /// Indices of the file chunks in [`FileManifest::chunks`], in content order.
+    pub chunks: Vec<usize>,
+}
+
+/// Chunk entry of [`FileManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestChunk {
+    /// Fingerprint of the share set carrying the chunk,
+    /// see [`ShareSet::fingerprint`].
+    pub fingerprint: String,
+    /// Chunk size in bytes.
+    pub size: u64,
+    /// Sha256 checksum of the chunk content, hex encoded.
+    pub sha256: String,
+}
+
+impl FileManifest {
+    /// Manifest as json string, to be stored alongside the shares.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("manifest is serializable")
+    }
+    /// Read the manifest from json string.
+    pub fn from_json(manifest: &str) -> Result<Self, Error> {
+        serde_json::from_str(manifest).map_err(|_| Error::ManifestParsing)
+    }
+}
+
+/// Shares of the files split by [`encrypt_files`], with their manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitFiles {
+    /// Layout of the files, for [`reassemble`].
+    pub manifest: FileManifest,
+    /// Shares of each chunk, in the order of [`FileManifest::chunks`].
+    /// Share `n` of every chunk goes to the same custodian.
+    pub shares: Vec<Vec<String>>,
+}
+
+/// Reads the files, cuts them into chunks of at most `chunk_size` bytes,
+/// and splits each chunk as a share set of its own, same as
+/// [`encrypt_with_options`], for multi-file or large backups.
+///
+/// Key derivation runs for every chunk, so chunks are better kept large.
+/// Files are restored with [`reassemble`].
+pub fn encrypt_files(
+    paths: &[impl AsRef<Path>],
+    chunk_size: usize,
+    title: &str,
+    passphrase: &str,
+    options: &EncryptOptions,
+) -> Result<SplitFiles, Error> {
+    if chunk_size == 0 {
+        return Err(Error::ChunkSizeZero);
+    }
+    let mut manifest = FileManifest {
+        files: Vec::new(),
+        chunks: Vec::new(),
+    };
+    let mut shares = Vec::new();
+    for path in paths {
+        let (name, content) = read_file(path.as_ref())?;
+        let mut file = ManifestFile {
+            name,
+            size: content.len() as u64,
+            sha256: hex::encode(Sha256::digest(&content[..])),
+            chunks: Vec::new(),
+        };
+        for chunk in content.chunks(chunk_size) {
+            let secret = Zeroizing::new(BASE64.encode(chunk));
+            let chunk_shares = encrypt_with_options(&secret, title, passphrase, options)?;
+            file.chunks.push(manifest.chunks.len());
+            manifest.chunks.push(ManifestChunk {
+                fingerprint: Share::new(chunk_shares[0].as_str())?.fingerprint(),
+                size: chunk.len() as u64,
+                sha256: hex::encode(Sha256::digest(chunk)),
+            });
+            shares.push(chunk_shares);
+        }
+        manifest.files.push(file);
+    }
+    Ok(SplitFiles { manifest, shares })
+}
+
+/// Restore the files split with [`encrypt_files`] from the shares of their
+/// chunks, in any order, and verify every chunk and file against the manifest.
+///
+/// Shares not listed in the manifest are ignored. Chunk without a share set
+/// is reported with [`Error::ChunkMissing`], content not matching the manifest
+/// with [`Error::FileChecksumMismatch`].
+pub fn reassemble<'a, I>(
+    manifest: &FileManifest,
+    shares: I,
+    passphrase: &str,
+) -> Result<Vec<RecoveredFile>, Error>
+where
+    I: IntoIterator,
+    I::Item: ShareInput<'a>,
+{
+    let mut collection = ShareSetCollection::new();
+    for share in shares {
+        match collection.add_payload(share) {
+            Ok(_) | Err(Error::ShareAlreadyInSet) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let mut files = Vec::with_capacity(manifest.files.len());
+    for file in &manifest.files {
+        let mut content = Zeroizing::new(Vec::with_capacity(file.size as usize));
+        for index in &file.chunks {
+            let entry = manifest
+                .chunks
+                .get(*index)
+                .ok_or(Error::ChunkMissing(*index))?;
+            let set = collection
+                .sets()
+                .iter()
+                .find(|a| a.fingerprint() == entry.fingerprint)
+                .ok_or(Error::ChunkMissing(*index))?;
+            let secret = Zeroizing::new(set.recover_with_passphrase(passphrase)?);
+            let chunk = Zeroizing::new(
+                BASE64
+                    .decode(secret.as_bytes())
+                    .map_err(|_| Error::FileChecksumMismatch)?,
+            );
+            if chunk.len() as u64 != entry.size
+                || hex::encode(Sha256::digest(&chunk[..])) != entry.sha256
+            {
+                return Err(Error::FileChecksumMismatch);
+            }
+            content.extend_from_slice(&chunk);
+        }
+        if content.len() as u64 != file.size
+            || hex::encode(Sha256::digest(&content[..])) != file.sha256
+        {
+            return Err(Error::FileChecksumMismatch);
+        }
+        let name = Path::new(&file.name)
+            .file_name()
+            .map(|a| a.to_string_lossy().into_owned())
+            .ok_or(Error::ManifestParsing)?;
+        files.push(RecoveredFile { name, content });
+    }
+    Ok(files)
+}
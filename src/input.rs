@@ -0,0 +1,62 @@
+use std::borrow::Cow;
+use std::ops::Deref;
+
+use zeroize::Zeroize;
+
+/// Share payload as decoded from qr code, accepted by [`Share::new`](crate::Share::new)
+/// as is: `&str`, `String`, `&[u8]`, or `Vec<u8>`.
+///
+/// Borrowed payloads are parsed in place, without copying. Owned payloads
+/// are taken over, and wiped once the share is parsed.
+pub trait ShareInput<'a> {
+    /// Payload bytes, borrowed or owned.
+    fn into_payload(self) -> Cow<'a, [u8]>;
+}
+
+impl<'a> ShareInput<'a> for &'a str {
+    fn into_payload(self) -> Cow<'a, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+}
+
+impl<'a> ShareInput<'a> for String {
+    fn into_payload(self) -> Cow<'a, [u8]> {
+        Cow::Owned(self.into_bytes())
+    }
+}
+
+impl<'a> ShareInput<'a> for &'a [u8] {
+    fn into_payload(self) -> Cow<'a, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<'a> ShareInput<'a> for Vec<u8> {
+    fn into_payload(self) -> Cow<'a, [u8]> {
+        Cow::Owned(self)
+    }
+}
+
+/// Payload being parsed, wiped on drop if owned.
+pub(crate) struct Payload<'a>(Cow<'a, [u8]>);
+
+impl<'a> Payload<'a> {
+    pub(crate) fn new(input: impl ShareInput<'a>) -> Self {
+        Self(input.into_payload())
+    }
+}
+
+impl Deref for Payload<'_> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for Payload<'_> {
+    fn drop(&mut self) {
+        if let Cow::Owned(a) = &mut self.0 {
+            a.zeroize();
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha512};
+
+use crate::error::Error;
+use crate::kdf::KeyDerivation;
+use crate::validity::Validity;
+
+/// Length of the key commitment, in bytes.
+pub(crate) const COMMITMENT_LEN: usize = 16;
+
+/// Length of the share checksum, in bytes.
+pub(crate) const CHECKSUM_LEN: usize = 4;
+
+/// Length of the set key, in bytes.
+pub(crate) const SET_KEY_LEN: usize = 16;
+
+/// Length of the set mac, in bytes.
+pub(crate) const SET_MAC_LEN: usize = 16;
+
+/// Domain separation for the set mac.
+const SET_MAC_CONTEXT: &[u8] = b"banana split set mac";
+
+/// Domain separation for the key commitment.
+const COMMITMENT_CONTEXT: &[u8] = b"banana split key commitment";
+
+/// Commitment to the derived key, recorded in V2 shares `"m"` field.
+///
+/// With the commitment, the wrong passphrase is told apart from the damaged shares:
+/// the key derived from the wrong passphrase does not match the commitment,
+/// while the key matching the commitment could fail to decrypt only the damaged secret.
+pub(crate) fn key_commitment(key: &[u8]) -> [u8; COMMITMENT_LEN] {
+    let mut commitment = [0; COMMITMENT_LEN];
+    commitment.copy_from_slice(&commitment_mac(key).finalize().into_bytes()[..COMMITMENT_LEN]);
+    commitment
+}
+
+/// Check the derived key against the commitment, if the set has one;
+/// the comparison is constant-time.
+pub(crate) fn check_key(
+    key: &[u8],
+    commitment: Option<&[u8; COMMITMENT_LEN]>,
+) -> Result<(), Error> {
+    match commitment {
+        Some(a) => commitment_mac(key)
+            .verify_truncated_left(a)
+            .map_err(|_| Error::WrongPassphrase),
+        None => Ok(()),
+    }
+}
+
+fn commitment_mac(key: &[u8]) -> Hmac<Sha512> {
+    let mut mac =
+        <Hmac<Sha512> as Mac>::new_from_slice(key).expect("hmac takes keys of any length");
+    mac.update(COMMITMENT_CONTEXT);
+    mac
+}
+
+/// Set-level parameters of the split, authenticated by the set mac.
+pub(crate) struct SetParams<'a> {
+    pub(crate) version: u8,
+    pub(crate) title: Option<&'a str>,
+    pub(crate) required_shards: Option<usize>,
+    pub(crate) nonce: &'a str,
+    pub(crate) key_derivation: KeyDerivation,
+    pub(crate) key_commitment: Option<&'a [u8; COMMITMENT_LEN]>,
+    pub(crate) validity: Validity,
+    pub(crate) bits: u32,
+    pub(crate) set_id: Option<&'a str>,
+    pub(crate) salt: Option<&'a [u8]>,
+}
+
+/// Mac over the set parameters, recorded in V2 shares `"h"` field,
+/// keyed with the random set key from `"i"` field.
+///
+/// Sibling backups, i.e. splits with the same title and threshold, have different
+/// set keys, so that mixing their shares is detected precisely, and the mac
+/// detects shares with the set parameters damaged or altered.
+pub(crate) fn set_mac(set_key: &[u8; SET_KEY_LEN], params: &SetParams) -> [u8; SET_MAC_LEN] {
+    let mut mac =
+        <Hmac<Sha512> as Mac>::new_from_slice(set_key).expect("hmac takes keys of any length");
+    mac.update(SET_MAC_CONTEXT);
+    mac.update(&[params.version]);
+    let mut field = |value: &[u8]| {
+        mac.update(&(value.len() as u64).to_be_bytes());
+        mac.update(value);
+    };
+    field(params.title.unwrap_or_default().as_bytes());
+    field(&params.required_shards.map_or(0, |a| a as u64).to_be_bytes());
+    field(params.nonce.as_bytes());
+    field(params.key_derivation.to_header().to_string().as_bytes());
+    field(params.key_commitment.map_or(&[][..], |a| &a[..]));
+    field(&params.validity.not_before.unwrap_or_default().to_be_bytes());
+    field(&params.validity.not_after.unwrap_or(u64::MAX).to_be_bytes());
+    field(&params.bits.to_be_bytes());
+    field(params.set_id.unwrap_or_default().as_bytes());
+    field(params.salt.unwrap_or_default());
+    let mut out = [0; SET_MAC_LEN];
+    out.copy_from_slice(&mac.finalize().into_bytes()[..SET_MAC_LEN]);
+    out
+}
+
+/// Random set identifier, formatted as version 4 uuid, recorded in V2 shares `"u"` field.
+pub(crate) fn random_set_id(rng: &mut impl rand::RngCore) -> Result<String, Error> {
+    let mut bytes = [0u8; 16];
+    rng.try_fill_bytes(&mut bytes)
+        .map_err(|_| Error::RandomnessUnavailable)?;
+    // version 4, variant 1
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+/// Set identifier must be a uuid in its canonical lowercase form.
+pub(crate) fn check_set_id(set_id: &str) -> Result<(), Error> {
+    let is_valid = set_id.len() == 36
+        && set_id.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => matches!(c, '0'..='9' | 'a'..='f'),
+        });
+    if is_valid {
+        Ok(())
+    } else {
+        Err(Error::SetIdInvalid(set_id.to_string()))
+    }
+}
+
+/// Checksum of the share id and content, recorded in V2 shares `"c"` field.
+///
+/// Damaged share is detected on parsing, before it spoils the whole set.
+pub(crate) fn share_checksum(id: u32, content: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let mut hasher = Sha512::new();
+    hasher.update(id.to_be_bytes());
+    hasher.update(content);
+    let mut checksum = [0; CHECKSUM_LEN];
+    checksum.copy_from_slice(&hasher.finalize()[..CHECKSUM_LEN]);
+    checksum
+}
+
+/// Decode base64 field of fixed length.
+pub(crate) fn decode_field<const N: usize>(value: &str, error: Error) -> Result<[u8; N], Error> {
+    // room for the base64 decoding slack
+    let mut buffer = [0u8; 64];
+    match BASE64.decode_slice(value, &mut buffer) {
+        Ok(len) if len == N => {
+            let mut out = [0; N];
+            out.copy_from_slice(&buffer[..N]);
+            Ok(out)
+        }
+        _ => Err(error),
+    }
+}
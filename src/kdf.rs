@@ -0,0 +1,416 @@
+use pbkdf2::pbkdf2_hmac;
+use scrypt::{scrypt, Params};
+use sha2::Sha512;
+use zeroize::Zeroizing;
+
+use crate::cancel::CancellationToken;
+use crate::error::Error;
+use crate::trace::{trace_event, trace_span};
+
+/// Length of the symmetric key, in bytes.
+pub(crate) const KEY_LEN: usize = 32;
+
+/// Name of scrypt algorithm in V2 share header.
+pub(crate) const SCRYPT_NAME: &str = "scrypt";
+
+/// Name of PBKDF2-HMAC-SHA512 algorithm in V2 share header.
+pub(crate) const PBKDF2_NAME: &str = "pbkdf2-sha512";
+
+/// Name of Argon2id algorithm in V2 share header.
+pub(crate) const ARGON2ID_NAME: &str = "argon2id";
+
+/// Smallest scrypt memory cost (128 * r * (2^log_n + p) bytes) accepted at all.
+const MIN_SCRYPT_MEMORY: u64 = 1 << 20;
+
+/// Recommended minimal scrypt memory cost, same as for default parameters.
+const RECOMMENDED_SCRYPT_MEMORY: u64 = 1 << 25;
+
+/// Smallest number of PBKDF2 iterations accepted at all.
+const MIN_PBKDF2_ITERATIONS: u32 = 10_000;
+
+/// Recommended minimal number of PBKDF2-HMAC-SHA512 iterations.
+const RECOMMENDED_PBKDF2_ITERATIONS: u32 = 210_000;
+
+/// Largest number of PBKDF2 iterations accepted from a share.
+/// A single derivation could not be cancelled, longer delays are for the time-lock.
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+/// Largest scrypt memory cost (128 * r * (2^log_n + p) bytes) accepted from a share.
+/// Shares are untrusted input, and should not be able to exhaust the device memory.
+const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+
+/// Largest scrypt parallelism accepted from a share.
+const MAX_SCRYPT_PARALLELISM: u32 = 16;
+
+/// Largest number of sequential key derivation rounds accepted from a share,
+/// roughly 18 hours with default scrypt parameters.
+const MAX_TIME_LOCK_ROUNDS: u32 = 1 << 16;
+
+/// Smallest Argon2id memory cost accepted at all, in KiB.
+const MIN_ARGON2_MEMORY_KIB: u32 = 1 << 10;
+
+/// Recommended minimal Argon2id memory cost, in KiB.
+const RECOMMENDED_ARGON2_MEMORY_KIB: u32 = 19 << 10;
+
+/// Largest Argon2id memory cost accepted from a share, in KiB, same as for scrypt.
+const MAX_ARGON2_MEMORY_KIB: u32 = 1 << 20;
+
+/// Largest Argon2id parallelism accepted from a share.
+const MAX_ARGON2_PARALLELISM: u32 = 16;
+
+/// Largest number of Argon2id passes accepted from a share.
+/// A single derivation could not be cancelled, longer delays are for the time-lock.
+const MAX_ARGON2_ITERATIONS: u32 = 64;
+
+/// Key derivation algorithm with its parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum KdfAlgorithm {
+    /// scrypt, with cost `2^log_n`, block size `r`, and parallelism `p`.
+    /// Default for all versions, needs `128 * r * (2^log_n + p)` bytes of memory (32 MiB by default).
+    Scrypt {
+        /// Logarithm of the cost parameter.
+        log_n: u8,
+        /// Block size.
+        r: u32,
+        /// Parallelism.
+        p: u32,
+    },
+    /// PBKDF2-HMAC-SHA512 with given number of iterations.
+    ///
+    /// **Weaker than scrypt**: it is not memory-hard, and is much cheaper to attack
+    /// with specialized hardware. Intended only for constrained devices that
+    /// do not have enough memory for scrypt.
+    Pbkdf2 {
+        /// Number of iterations.
+        iterations: u32,
+    },
+    /// Argon2id, version 0x13, with memory cost in KiB, number of passes, and parallelism.
+    ///
+    /// Memory-hard as scrypt, and more resistant to GPU attacks. Needs `argon2` feature;
+    /// without it, splits and shares with Argon2id are refused.
+    Argon2id {
+        /// Memory cost, in KiB.
+        memory_kib: u32,
+        /// Number of passes over the memory.
+        iterations: u32,
+        /// Degree of parallelism.
+        parallelism: u32,
+    },
+}
+
+impl KdfAlgorithm {
+    /// Default scrypt parameters, same as used in V1.
+    pub const SCRYPT_DEFAULT: Self = Self::Scrypt {
+        log_n: 15,
+        r: 8,
+        p: 1,
+    };
+    /// Default PBKDF2-HMAC-SHA512 parameters.
+    pub const PBKDF2_DEFAULT: Self = Self::Pbkdf2 {
+        iterations: 210_000,
+    };
+    /// Default Argon2id parameters, 64 MiB of memory in 3 passes, as recommended in RFC 9106.
+    pub const ARGON2ID_DEFAULT: Self = Self::Argon2id {
+        memory_kib: 1 << 16,
+        iterations: 3,
+        parallelism: 1,
+    };
+}
+
+/// Key derivation function, deriving the symmetric key from the passphrase and the salt.
+///
+/// Built-in algorithms are [`KdfAlgorithm`] variants, chosen for the split with
+/// [`EncryptOptions::kdf`](crate::EncryptOptions::kdf) and recorded in V2 share header,
+/// so that the recovering side runs the same derivation.
+pub trait Kdf {
+    /// Name of the algorithm, as recorded in V2 share header.
+    fn name(&self) -> &'static str;
+    /// Fill `key` with the key derived from `passphrase` and `salt`.
+    fn derive_key(&self, passphrase: &[u8], salt: &[u8], key: &mut [u8]) -> Result<(), Error>;
+}
+
+/// Key derivation used for the set.
+///
+/// Shares before V2 always use scrypt with `log_n = 15`, `r = 8`, `p = 1`, in a single round.
+/// V2 shares record the key derivation in `"k"` field, for example
+/// `{"a":"scrypt","n":15,"r":8,"p":1,"c":1}`, `{"a":"pbkdf2-sha512","i":210000,"c":1}`,
+/// or `{"a":"argon2id","m":65536,"t":3,"p":1,"c":1}`.
+///
+/// With `rounds` above 1 the derivation is chained sequentially,
+/// the output of each round being the input passphrase for the next one.
+/// This is an opt-in time-lock: each round takes the full KDF time and the rounds
+/// could not be calculated in parallel, so the recovery takes at least `rounds` times
+/// longer on any hardware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct KeyDerivation {
+    pub(crate) algorithm: KdfAlgorithm,
+    pub(crate) rounds: u32,
+}
+
+impl Default for KeyDerivation {
+    fn default() -> Self {
+        Self {
+            algorithm: KdfAlgorithm::SCRYPT_DEFAULT,
+            rounds: 1,
+        }
+    }
+}
+
+impl Kdf for KdfAlgorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            KdfAlgorithm::Scrypt { .. } => SCRYPT_NAME,
+            KdfAlgorithm::Pbkdf2 { .. } => PBKDF2_NAME,
+            KdfAlgorithm::Argon2id { .. } => ARGON2ID_NAME,
+        }
+    }
+    fn derive_key(&self, passphrase: &[u8], salt: &[u8], key: &mut [u8]) -> Result<(), Error> {
+        match *self {
+            KdfAlgorithm::Scrypt { log_n, r, p } => {
+                let params = Params::new(log_n, r, p, KEY_LEN)
+                    .map_err(|_| Error::KdfParamsNotSupported(format!("{self:?}")))?;
+                scrypt(passphrase, salt, &params, key).map_err(Error::ScryptFailed)
+            }
+            KdfAlgorithm::Pbkdf2 { iterations } => {
+                pbkdf2_hmac::<Sha512>(passphrase, salt, iterations, key);
+                Ok(())
+            }
+            #[cfg(feature = "argon2")]
+            KdfAlgorithm::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let not_supported = |_| Error::KdfParamsNotSupported(format!("{self:?}"));
+                let params = argon2::Params::new(memory_kib, iterations, parallelism, None)
+                    .map_err(not_supported)?;
+                argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password_into(passphrase, salt, key)
+                    .map_err(not_supported)
+            }
+            #[cfg(not(feature = "argon2"))]
+            KdfAlgorithm::Argon2id { .. } => Err(Error::KdfNotSupported(ARGON2ID_NAME.to_owned())),
+        }
+    }
+}
+
+impl KeyDerivation {
+    /// Derive the key from passphrase and salt.
+    /// Cancellation is checked before each round.
+    pub(crate) fn derive(
+        &self,
+        passphrase: &[u8],
+        salt: &[u8],
+        cancel: &CancellationToken,
+    ) -> Result<Zeroizing<Vec<u8>>, Error> {
+        trace_span!("kdf", algorithm = ?self.algorithm, rounds = self.rounds);
+        // allocate here, empty output buffer is rejected
+        let mut key = Zeroizing::new(vec![0; KEY_LEN]);
+        cancel.check()?;
+        self.algorithm.derive_key(passphrase, salt, &mut key)?;
+        for _round in 1..self.rounds {
+            trace_event!(round = _round, "kdf round done");
+            cancel.check()?;
+            let previous = key.clone();
+            self.algorithm.derive_key(&previous, salt, &mut key)?;
+        }
+        trace_event!("kdf done");
+        Ok(key)
+    }
+    /// Check that the parameters are usable, both at split time and when received in a share.
+    ///
+    /// Parameters below the absolute minimums are rejected, so that a share with
+    /// downgraded parameters could not be used to make the passphrase easy to brute-force.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        if !(1..=MAX_TIME_LOCK_ROUNDS).contains(&self.rounds) {
+            return Err(Error::KdfParamsNotSupported(format!("{self:?}")));
+        }
+        match self.algorithm {
+            KdfAlgorithm::Scrypt { log_n, r, p } => {
+                if p > MAX_SCRYPT_PARALLELISM {
+                    return Err(Error::KdfParamsNotSupported(format!("{self:?}")));
+                }
+                match scrypt_memory(log_n, r, p) {
+                    Some(a) if a <= MAX_SCRYPT_MEMORY => {
+                        if a < MIN_SCRYPT_MEMORY {
+                            return Err(Error::KdfParamsTooWeak(format!("{self:?}")));
+                        }
+                    }
+                    _ => return Err(Error::KdfParamsNotSupported(format!("{self:?}"))),
+                }
+                if Params::new(log_n, r, p, KEY_LEN).is_err() {
+                    return Err(Error::KdfParamsNotSupported(format!("{self:?}")));
+                }
+            }
+            KdfAlgorithm::Pbkdf2 { iterations } => {
+                if iterations > MAX_PBKDF2_ITERATIONS {
+                    return Err(Error::KdfParamsNotSupported(format!("{self:?}")));
+                }
+                if iterations < MIN_PBKDF2_ITERATIONS {
+                    return Err(Error::KdfParamsTooWeak(format!("{self:?}")));
+                }
+            }
+            KdfAlgorithm::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                if !cfg!(feature = "argon2") {
+                    return Err(Error::KdfNotSupported(ARGON2ID_NAME.to_owned()));
+                }
+                if memory_kib > MAX_ARGON2_MEMORY_KIB
+                    || iterations > MAX_ARGON2_ITERATIONS
+                    || !(1..=MAX_ARGON2_PARALLELISM).contains(&parallelism)
+                {
+                    return Err(Error::KdfParamsNotSupported(format!("{self:?}")));
+                }
+                if memory_kib < MIN_ARGON2_MEMORY_KIB || iterations == 0 {
+                    return Err(Error::KdfParamsTooWeak(format!("{self:?}")));
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Parameters are accepted, but are below the recommended minimums.
+    pub(crate) fn is_weak(&self) -> bool {
+        match self.algorithm {
+            KdfAlgorithm::Scrypt { log_n, r, p } => {
+                scrypt_memory(log_n, r, p).unwrap_or(u64::MAX) < RECOMMENDED_SCRYPT_MEMORY
+            }
+            KdfAlgorithm::Pbkdf2 { iterations } => iterations < RECOMMENDED_PBKDF2_ITERATIONS,
+            KdfAlgorithm::Argon2id {
+                memory_kib,
+                iterations,
+                ..
+            } => memory_kib < RECOMMENDED_ARGON2_MEMORY_KIB || iterations < 2,
+        }
+    }
+    /// Key derivation header, as recorded in V2 share json.
+    pub(crate) fn to_header(self) -> serde_json::Value {
+        match self.algorithm {
+            KdfAlgorithm::Scrypt { log_n, r, p } => serde_json::json!({
+                "a": SCRYPT_NAME,
+                "n": log_n,
+                "r": r,
+                "p": p,
+                "c": self.rounds,
+            }),
+            KdfAlgorithm::Pbkdf2 { iterations } => serde_json::json!({
+                "a": PBKDF2_NAME,
+                "i": iterations,
+                "c": self.rounds,
+            }),
+            KdfAlgorithm::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => serde_json::json!({
+                "a": ARGON2ID_NAME,
+                "m": memory_kib,
+                "t": iterations,
+                "p": parallelism,
+                "c": self.rounds,
+            }),
+        }
+    }
+    /// Same as [`to_header`](Self::to_header), but with the fields
+    /// that have default values left out, for smaller qr codes.
+    pub(crate) fn to_compact_header(self) -> serde_json::Value {
+        let default = Self::default();
+        let mut header = serde_json::Map::new();
+        match self.algorithm {
+            KdfAlgorithm::Scrypt { log_n, r, p } => {
+                if log_n != 15 {
+                    let _ = header.insert("n".to_string(), log_n.into());
+                }
+                if r != 8 {
+                    let _ = header.insert("r".to_string(), r.into());
+                }
+                if p != 1 {
+                    let _ = header.insert("p".to_string(), p.into());
+                }
+            }
+            KdfAlgorithm::Pbkdf2 { iterations } => {
+                let _ = header.insert("a".to_string(), PBKDF2_NAME.into());
+                let _ = header.insert("i".to_string(), iterations.into());
+            }
+            KdfAlgorithm::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let _ = header.insert("a".to_string(), ARGON2ID_NAME.into());
+                let _ = header.insert("m".to_string(), memory_kib.into());
+                let _ = header.insert("t".to_string(), iterations.into());
+                let _ = header.insert("p".to_string(), parallelism.into());
+            }
+        }
+        if self.rounds != default.rounds {
+            let _ = header.insert("c".to_string(), self.rounds.into());
+        }
+        serde_json::Value::Object(header)
+    }
+    /// Read key derivation from V2 share json header.
+    /// Missing header or missing fields are filled with default values.
+    pub(crate) fn from_header(header: &json::JsonValue) -> Result<Self, Error> {
+        let default = Self::default();
+        if header.is_null() {
+            return Ok(default);
+        }
+        let rounds = header_number(header, "c")?.unwrap_or(default.rounds);
+        let algorithm = match &header["a"] {
+            // scrypt is the default algorithm, and could be omitted in compact header
+            a if a.is_null() || a.as_str() == Some(SCRYPT_NAME) => {
+                let log_n = header_number(header, "n")?.unwrap_or(15);
+                KdfAlgorithm::Scrypt {
+                    log_n: u8::try_from(log_n)
+                        .map_err(|_| Error::KdfParamsNotSupported(header.dump()))?,
+                    r: header_number(header, "r")?.unwrap_or(8),
+                    p: header_number(header, "p")?.unwrap_or(1),
+                }
+            }
+            a if a.as_str() == Some(PBKDF2_NAME) => KdfAlgorithm::Pbkdf2 {
+                iterations: header_number(header, "i")?
+                    .ok_or_else(|| Error::KdfParamsNotSupported(header.dump()))?,
+            },
+            a if a.as_str() == Some(ARGON2ID_NAME) => {
+                let field = |name| {
+                    header_number(header, name)?
+                        .ok_or_else(|| Error::KdfParamsNotSupported(header.dump()))
+                };
+                KdfAlgorithm::Argon2id {
+                    memory_kib: field("m")?,
+                    iterations: field("t")?,
+                    parallelism: field("p")?,
+                }
+            }
+            a => return Err(Error::KdfNotSupported(a.to_string())),
+        };
+        let key_derivation = Self { algorithm, rounds };
+        key_derivation.check()?;
+        Ok(key_derivation)
+    }
+}
+
+/// Memory needed for scrypt with given parameters, in bytes:
+/// `ROMix` table of `2^log_n` blocks and `p` lanes of one block each.
+fn scrypt_memory(log_n: u8, r: u32, p: u32) -> Option<u64> {
+    if log_n >= 64 {
+        return None;
+    }
+    (1u64 << log_n)
+        .checked_add(p as u64)
+        .and_then(|a| a.checked_mul(128 * r as u64))
+}
+
+/// Read optional non-negative integer field from json header.
+fn header_number(header: &json::JsonValue, field: &str) -> Result<Option<u32>, Error> {
+    match &header[field] {
+        json::JsonValue::Null => Ok(None),
+        a => match a.as_u32() {
+            Some(b) => Ok(Some(b)),
+            None => Err(Error::KdfParamsNotSupported(header.dump())),
+        },
+    }
+}
@@ -0,0 +1,133 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose, Engine};
+use blake2::{Blake2b512, Digest};
+use crypto_secretbox::aead::{generic_array::GenericArray, Aead, KeyInit};
+use crypto_secretbox::XSalsa20Poly1305;
+use rand::RngCore;
+use schnorrkel::{ExpansionMode, MiniSecretKey};
+use scrypt::{scrypt, Params};
+use serde_json::json;
+use zeroize::Zeroizing;
+
+use crate::error::Error;
+use crate::shares::NONCE_LEN;
+
+/// Scrypt parameters of polkadot-js keystore: log2 of N, r, p.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// Length of scrypt salt and output in polkadot-js keystore;
+/// first half of the output is the key.
+const SCRYPT_SALT_LEN: usize = 32;
+const SCRYPT_OUTPUT_LEN: usize = 64;
+
+/// PKCS8 framing of sr25519 keypair, as in polkadot-js: header, 64 byte secret key
+/// in ed25519 form, divider, and 32 byte public key.
+const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+const PKCS8_DIVIDER: [u8; 5] = [161, 35, 3, 33, 0];
+
+/// Largest SS58 address prefix.
+const MAX_SS58_PREFIX: u16 = 16383;
+
+/// Encrypted polkadot-js json keystore of sr25519 account of the recovered
+/// seed phrase, without derivation path, for importing into polkadot-js
+/// apps or extension without showing the seed phrase.
+///
+/// The keystore is encrypted with the new `password`, and has the account
+/// address for the network of `ss58_prefix` (0 for Polkadot, 2 for Kusama,
+/// 42 for generic Substrate), and `name` as the account name.
+pub fn polkadot_js_keystore(
+    secret: &str,
+    password: &str,
+    name: &str,
+    ss58_prefix: u16,
+) -> Result<String, Error> {
+    if ss58_prefix > MAX_SS58_PREFIX {
+        return Err(Error::Ss58PrefixInvalid(ss58_prefix));
+    }
+    let mnemonic = bip39::Mnemonic::parse_in_normalized(
+        bip39::Language::English,
+        &secret.split_whitespace().collect::<Vec<&str>>().join(" "),
+    )
+    .map_err(|_| Error::SecretNotSeedPhrase)?;
+    let entropy = Zeroizing::new(mnemonic.to_entropy());
+    let mini_secret = Zeroizing::new(
+        substrate_bip39::mini_secret_from_entropy(&entropy, "")
+            .map_err(|_| Error::SecretNotSeedPhrase)?
+            .to_bytes(),
+    );
+    let keypair = MiniSecretKey::from_bytes(mini_secret.as_slice())
+        .map_err(|_| Error::SecretNotSeedPhrase)?
+        .expand_to_keypair(ExpansionMode::Ed25519);
+    let public = keypair.public.to_bytes();
+
+    let mut pkcs8 = Zeroizing::new(PKCS8_HEADER.to_vec());
+    pkcs8.extend_from_slice(&keypair.secret.to_ed25519_bytes());
+    pkcs8.extend_from_slice(&PKCS8_DIVIDER);
+    pkcs8.extend_from_slice(&public);
+
+    let mut rng = rand::thread_rng();
+    let mut salt = [0u8; SCRYPT_SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.try_fill_bytes(&mut salt)
+        .map_err(|_| Error::RandomnessUnavailable)?;
+    rng.try_fill_bytes(&mut nonce)
+        .map_err(|_| Error::RandomnessUnavailable)?;
+    let mut key = Zeroizing::new([0u8; SCRYPT_OUTPUT_LEN]);
+    let params = Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, SCRYPT_OUTPUT_LEN)
+        .map_err(|_| Error::KdfParamsNotSupported("polkadot-js scrypt".to_owned()))?;
+    scrypt(password.as_bytes(), &salt, &params, key.as_mut_slice()).map_err(Error::ScryptFailed)?;
+    let cipher = XSalsa20Poly1305::new(GenericArray::from_slice(&key[..32]));
+    let encrypted = cipher
+        .encrypt(GenericArray::from_slice(&nonce), pkcs8.as_slice())
+        .map_err(|_| Error::EncryptionFailed)?;
+
+    // salt, scrypt N, p, r as little endian u32, nonce, encrypted keypair
+    let mut encoded = salt.to_vec();
+    encoded.extend_from_slice(&(1u32 << SCRYPT_LOG_N).to_le_bytes());
+    encoded.extend_from_slice(&SCRYPT_P.to_le_bytes());
+    encoded.extend_from_slice(&SCRYPT_R.to_le_bytes());
+    encoded.extend_from_slice(&nonce);
+    encoded.extend_from_slice(&encrypted);
+
+    let when_created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|a| a.as_millis() as u64)
+        .unwrap_or_default();
+    Ok(json!({
+        "encoded": general_purpose::STANDARD.encode(encoded),
+        "encoding": {
+            "content": ["pkcs8", "sr25519"],
+            "type": ["scrypt", "xsalsa20-poly1305"],
+            "version": "3",
+        },
+        "address": ss58(&public, ss58_prefix),
+        "meta": {
+            "genesisHash": "",
+            "name": name,
+            "whenCreated": when_created,
+        },
+    })
+    .to_string())
+}
+
+/// SS58 address of the public key, for the network of `prefix`.
+pub(crate) fn ss58(public: &[u8], prefix: u16) -> String {
+    let mut data = if prefix < 64 {
+        vec![prefix as u8]
+    } else {
+        vec![
+            ((prefix & 0b1111_1100) >> 2) as u8 | 0b0100_0000,
+            ((prefix >> 8) as u8) | (((prefix & 0b11) as u8) << 6),
+        ]
+    };
+    data.extend_from_slice(public);
+    let checksum = Blake2b512::new()
+        .chain_update(b"SS58PRE")
+        .chain_update(&data)
+        .finalize();
+    data.extend_from_slice(&checksum[..2]);
+    bs58::encode(data).into_string()
+}
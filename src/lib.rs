@@ -1,6 +1,9 @@
 //! Bananasplit
 //!
 //! Recovers secrets from split chunks according to the banana [split protocol](https://github.com/paritytech/banana_split).
+//!
+//! Reports for the user interface, such as [`Capabilities`] or [`ShareSetSummary`],
+//! serialize into json, for host applications using the library over FFI or WASM.
 
 #![deny(missing_docs)]
 #![deny(unused_crate_dependencies)]
@@ -9,15 +12,213 @@
 
 mod error;
 mod shares;
+mod trace;
+
+/// This module contains share payload forms accepted for parsing.
+mod input;
+pub use input::ShareInput;
+
+/// This module contains stripping of scanner wrappers around share payloads.
+mod sanitize;
+pub use sanitize::{sanitize_payload, PayloadWrapper, SanitizedPayload};
+
+/// This module contains share parsing without copying.
+mod share_ref;
+pub use share_ref::ShareRef;
+
+/// This module contains the canonical share json, same as in the reference web app.
+mod canonical;
+pub use canonical::canonical;
+
+/// This module contains the title unescaping and salt, same as in the reference web app.
+mod title;
+pub use title::{title_salt, unescape_title};
+
+/// This module contains share checksums and the key commitment.
+mod integrity;
+
+/// This module contains the repair of a damaged share by bit flip search.
+mod repair;
+pub use repair::{repair, Repaired};
+
+/// This module contains key derivation from the passphrase.
+mod kdf;
+pub use kdf::{Kdf, KdfAlgorithm};
 
 /// This module contains all the crypto related functions.
 mod encrypt;
-pub use encrypt::encrypt;
+pub use encrypt::{
+    encrypt, encrypt_bytes, encrypt_bytes_with_options, encrypt_with_manifest,
+    encrypt_with_options, encrypt_with_rng, migrate_v1_to_v2, retitle, upgrade_legacy,
+    CustodianShares, Distribution, EncryptOptions, NonceStrategy, Padding,
+};
+
+/// This module contains splitting of files, such as wallet keystores.
+mod file;
+pub use file::{
+    encrypt_file, encrypt_files, reassemble, FileManifest, ManifestChunk, ManifestFile,
+    RecoveredFile, SplitFiles,
+};
+
+/// This module contains grouping of shares of several splits into sets.
+mod collection;
+pub use collection::{SetProgress, ShareSetCollection};
+
+/// This module contains recovery of many sets with candidate passphrases.
+mod batch;
+pub use batch::{BatchOutcome, BatchResult};
+
+/// This module contains the pluggable qr decoding of share images.
+mod qr;
+pub use qr::QrDecoder;
+#[cfg(feature = "qr-decode")]
+pub use qr::RqrrDecoder;
+
+/// This module contains statistics of the scanning session.
+mod stats;
+pub use stats::ScanStats;
+
+/// This module contains the validity period of the split.
+mod validity;
+pub use validity::Validity;
+
+/// This module contains the share size and qr density report.
+mod density;
+pub use density::ShareDensity;
+
+/// This module contains the layout of printed shares.
+mod print;
+pub use print::PrintLayout;
+
+/// This module contains the per-custodian share bundles.
+#[cfg(feature = "bundle")]
+mod bundle;
+#[cfg(feature = "bundle")]
+pub use bundle::CustodianBundle;
+
+/// This module contains the share text for typing in when the qr code is unscannable.
+mod paper;
+pub use paper::{from_paper_text, to_paper_text};
+
+/// This module contains the owner manifest for private splits.
+mod manifest;
+pub use manifest::Manifest;
+
+/// This module contains the recovery session state machine for user interfaces.
+mod session;
+pub use session::{RecoverySession, SessionState};
+
+/// This module contains saving of share sets in progress, encrypted with a password.
+mod saved;
+
+/// This module contains messages for collecting shares from remote custodians.
+mod protocol;
+pub use protocol::{RecoveryStatus, ShareRequest, ShareResponse};
+
+/// This module contains sealing of shares to custodian keys for transport.
+#[cfg(feature = "seal")]
+mod seal;
+#[cfg(feature = "seal")]
+pub use seal::{custodian_keypair, SealedShare};
+
+/// This module contains rendering of shares into qr code images.
+#[cfg(feature = "qr")]
+mod share_qr;
+#[cfg(feature = "qr")]
+pub use share_qr::{encrypt_to_qr_png, encrypt_to_qr_svg, share_qr_png, share_qr_svg};
+
+/// This module contains the export of recovered seed phrases for Polkadot Vault.
+mod vault;
+pub use vault::vault_seed_payload;
+#[cfg(feature = "qr")]
+pub use vault::{vault_seed_qr_svg, vault_seed_qr_text};
+
+/// This module contains the export of recovered private keys as Ethereum keystores.
+#[cfg(feature = "ethereum")]
+mod ethereum;
+#[cfg(feature = "ethereum")]
+pub use ethereum::ethereum_keystore;
+
+/// This module contains the export of recovered seed phrases as polkadot-js keystores.
+#[cfg(feature = "polkadot-js")]
+mod keystore;
+#[cfg(feature = "polkadot-js")]
+pub use keystore::polkadot_js_keystore;
+
+/// This module contains hiding of shares in png images.
+#[cfg(feature = "stego")]
+mod stego;
+#[cfg(feature = "stego")]
+pub use stego::{embed_in_png, extract_from_png};
+
+/// This module contains deflate compression of share payloads.
+mod deflate;
+#[cfg(feature = "deflate")]
+pub use deflate::compress;
 
 mod passphrase;
-pub use passphrase::generate;
+pub use passphrase::{
+    generate, generate_with_entropy, generate_with_rng, Passphrase, PassphraseCheck,
+};
+
+/// This module contains cancellation of long-running operations.
+mod cancel;
+pub use cancel::CancellationToken;
+
+/// This module contains key derivation and recovery in small steps.
+mod chunked;
+pub use chunked::{ChunkedRecovery, RecoveryStep};
+
+/// This module contains combining of shares without allocations.
+mod combine;
+pub use combine::{
+    combine_into, combine_into_table_free, combine_output_len, combine_table_len, GfStrategy,
+    DEFAULT_GF_MEMORY_BUDGET,
+};
+
+/// This module contains the metrics callback for progress and telemetry.
+mod metrics;
+pub use metrics::{Metric, MetricsHook};
+
+mod paranoid;
+
+/// This module contains the report on supported protocol features.
+mod capabilities;
+pub use capabilities::{capabilities, Capabilities};
+
+/// This module contains share metadata and version negotiation.
+mod meta;
+pub use meta::{negotiate, Negotiation, Requirement, ShareMeta};
+
+/// This module contains structured error reports for FFI and WASM consumers.
+mod report;
+pub use report::ErrorReport;
+
+/// This module contains the bindings for JavaScript, for recovery in the browser.
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wasm")]
+pub use wasm::{wasm_encrypt, wasm_generate, WasmShare, WasmShareSet};
+
+/// This module contains the bindings for Kotlin and Swift, generated with UniFFI.
+#[cfg(feature = "uniffi")]
+mod mobile;
+#[cfg(feature = "uniffi")]
+pub use mobile::{
+    mobile_encrypt, mobile_generate, MobileError, MobileNextAction, MobileShare, MobileShareSet,
+};
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+/// This module contains the self-test to be run before trusting the library output.
+mod selftest;
+pub use selftest::selftest;
 #[cfg(test)]
 mod tests;
 
-pub use error::Error;
-pub use shares::{NextAction, Share, ShareSet};
+pub use error::{Error, Warning};
+pub use shares::{
+    max_shares, supported_bits, CollectedShare, Diagnosis, MajorityVote, NextAction,
+    RejectionReason, Share, ShareDiff, ShareSet, ShareSetSummary, MAX_BITS, MAX_VOTE_SUBSETS,
+    MIN_BITS,
+};
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Metadata of a private split, kept by the owner separately from the shares.
+///
+/// Shares of a private split do not record the title, and possibly the number
+/// of required shards, so that a lost share reveals as little as possible.
+/// The title is needed for the key derivation, therefore the recovery of
+/// a private set is not possible without the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Title of the split.
+    pub title: String,
+    /// Number of shares needed for recovery.
+    pub required_shards: usize,
+    /// Fingerprint of the shares, see [`Share::fingerprint`](crate::Share::fingerprint).
+    pub fingerprint: String,
+}
+
+impl Manifest {
+    /// Manifest as json string, to be stored by the owner.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("manifest is serializable")
+    }
+    /// Read the manifest from json string.
+    pub fn from_json(manifest: &str) -> Result<Self, Error> {
+        serde_json::from_str(manifest).map_err(|_| Error::ManifestParsing)
+    }
+}
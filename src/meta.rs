@@ -0,0 +1,151 @@
+use crate::capabilities::capabilities;
+use crate::deflate::{inflate, DEFLATE_MAGIC};
+use crate::error::Error;
+use crate::title::parse_json;
+
+/// Public metadata of a share, read without full share parsing.
+///
+/// Unlike [`Share::new`](crate::Share::new), peeking does not reject shares
+/// of unknown versions or with unknown key derivation, so that the application
+/// could tell the user what exactly is not supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareMeta {
+    /// Format version from `"v"` field, `None` for legacy shares without version.
+    pub version: Option<u32>,
+    /// Title, `None` if the share does not record it.
+    pub title: Option<String>,
+    /// Number of required shards, `None` if the share does not record it.
+    pub required_shards: Option<usize>,
+    /// Key derivation algorithm from V2 header, `None` for the default one.
+    pub kdf: Option<String>,
+    /// Application tags, for example the storage location; empty if there are none.
+    pub tags: Vec<String>,
+    /// Payload is deflate-compressed.
+    pub compressed: bool,
+}
+
+/// What the current build lacks to recover the set, see [`negotiate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Requirement {
+    /// Share format version is not supported.
+    Version(u32),
+    /// Key derivation algorithm is not supported.
+    Kdf(String),
+    /// Library is compiled without the needed cargo feature.
+    Feature(&'static str),
+}
+
+/// Result of [`negotiate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Negotiation {
+    /// Current build could recover the set, which uses given format version.
+    Supported {
+        /// Format version of the set, `None` for legacy shares.
+        version: Option<u32>,
+    },
+    /// Current build could not recover the set, and needs an upgrade.
+    UpgradeRequired(Vec<Requirement>),
+    /// Shares disagree on format version, and could not be from the same set.
+    Inconsistent,
+}
+
+impl ShareMeta {
+    /// Read public metadata from the share payload, as scanned from the qr code.
+    pub fn peek(payload: &[u8]) -> Result<Self, Error> {
+        let compressed = payload.starts_with(DEFLATE_MAGIC);
+        let inflated;
+        let payload = if compressed {
+            match inflate(&payload[DEFLATE_MAGIC.len()..]) {
+                Ok(a) => {
+                    inflated = a;
+                    &inflated[..]
+                }
+                // nothing else could be learned without decompression
+                Err(Error::DeflateNotSupported) => {
+                    return Ok(Self {
+                        version: None,
+                        title: None,
+                        required_shards: None,
+                        kdf: None,
+                        tags: Vec::new(),
+                        compressed,
+                    })
+                }
+                Err(e) => return Err(e),
+            }
+        } else {
+            payload
+        };
+        let share_string = std::str::from_utf8(payload).map_err(|_| Error::NotShareString)?;
+        let parsed = parse_json(share_string)?;
+        let version = match &parsed["v"] {
+            json::JsonValue::Null => None,
+            a => match a.as_u32() {
+                Some(b) => Some(b),
+                None => return Err(Error::VersionNotSupported(a.to_string())),
+            },
+        };
+        Ok(Self {
+            version,
+            title: parsed["t"].as_str().map(|a| a.to_string()),
+            required_shards: parsed["r"].as_usize(),
+            kdf: parsed["k"]["a"].as_str().map(|a| a.to_string()),
+            tags: parsed["g"]
+                .members()
+                .filter_map(|a| a.as_str().map(|b| b.to_string()))
+                .collect(),
+            compressed,
+        })
+    }
+    /// Compressed share that could not be decompressed in the current build.
+    fn is_opaque(&self) -> bool {
+        self.compressed && self.version.is_none() && self.title.is_none()
+    }
+}
+
+/// Determine which format version the recovery of the set must use,
+/// and whether the current build supports everything the shares need.
+///
+/// Intended to be called on the scanned shares before the recovery starts,
+/// so that the user gets "upgrade required" message up front instead of
+/// a share parsing error midway.
+pub fn negotiate(metas: &[ShareMeta]) -> Negotiation {
+    let capabilities = capabilities();
+    let mut requirements = Vec::new();
+    let mut require = |requirement: Requirement| {
+        if !requirements.contains(&requirement) {
+            requirements.push(requirement)
+        }
+    };
+
+    // shares that could not be looked into are not counted against the others
+    let mut versions = metas
+        .iter()
+        .filter(|meta| !meta.is_opaque())
+        .map(|meta| meta.version);
+    let version = versions.next().flatten();
+    if versions.any(|a| a != version) {
+        return Negotiation::Inconsistent;
+    }
+
+    for meta in metas {
+        if meta.compressed && !capabilities.features.contains(&"deflate") {
+            require(Requirement::Feature("deflate"));
+        }
+        if let Some(version) = meta.version {
+            if !capabilities.versions.iter().any(|a| *a as u32 == version) {
+                require(Requirement::Version(version));
+            }
+        }
+        if let Some(ref kdf) = meta.kdf {
+            if !capabilities.kdfs.contains(&kdf.as_str()) {
+                require(Requirement::Kdf(kdf.to_owned()));
+            }
+        }
+    }
+    if requirements.is_empty() {
+        Negotiation::Supported { version }
+    } else {
+        Negotiation::UpgradeRequired(requirements)
+    }
+}
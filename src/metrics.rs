@@ -0,0 +1,72 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Measurement reported to the [`MetricsHook`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Metric {
+    /// Share was accepted into the set.
+    ShareProcessed {
+        /// Number of distinct shares in the set.
+        have: usize,
+    },
+    /// Shares were combined into encrypted secret.
+    Combined {
+        /// Time spent combining.
+        duration: Duration,
+        /// Number of combined shares.
+        shares: usize,
+    },
+    /// Key was derived from the passphrase.
+    KeyDerived {
+        /// Time spent in key derivation, all rounds included.
+        duration: Duration,
+        /// Number of key derivation rounds.
+        rounds: u32,
+    },
+}
+
+/// Callback receiving [`Metric`]s, for progress bars and telemetry.
+///
+/// Time is measured only if the hook is set, so platforms without
+/// a clock are not affected unless the hook is used there.
+#[derive(Clone)]
+pub struct MetricsHook(Arc<dyn Fn(Metric) + Send + Sync>);
+
+impl MetricsHook {
+    /// Hook calling given function on every measurement.
+    pub fn new(hook: impl Fn(Metric) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+    /// Report the measurement.
+    pub(crate) fn report(&self, metric: Metric) {
+        (self.0)(metric)
+    }
+    /// Start timing an operation, if the hook is set.
+    pub(crate) fn start(hook: Option<&MetricsHook>) -> Option<Timer<'_>> {
+        hook.map(|hook| Timer {
+            hook,
+            started: Instant::now(),
+        })
+    }
+}
+
+impl fmt::Debug for MetricsHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MetricsHook")
+    }
+}
+
+/// Running measurement, see [`MetricsHook::start`].
+pub(crate) struct Timer<'a> {
+    hook: &'a MetricsHook,
+    started: Instant,
+}
+
+impl Timer<'_> {
+    /// Report the measurement made from elapsed time.
+    pub(crate) fn finish(self, metric: impl FnOnce(Duration) -> Metric) {
+        self.hook.report(metric(self.started.elapsed()))
+    }
+}
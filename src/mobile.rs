@@ -0,0 +1,183 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use zeroize::Zeroizing;
+
+use crate::error::Error;
+use crate::shares::{NextAction, Share, ShareSet};
+
+/// [`Error`] for Kotlin and Swift, with the fields of [`Error::report`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, uniffi::Error)]
+pub enum MobileError {
+    /// Library error.
+    #[error("{message}")]
+    Failed {
+        /// Name of the [`Error`] variant.
+        name: String,
+        /// Stable numeric code of the [`Error`] variant.
+        code: u16,
+        /// Human-readable message.
+        message: String,
+    },
+}
+
+impl From<Error> for MobileError {
+    fn from(error: Error) -> Self {
+        let report = error.report();
+        MobileError::Failed {
+            name: report.name.to_owned(),
+            code: report.code,
+            message: report.message,
+        }
+    }
+}
+
+/// [`NextAction`] for Kotlin and Swift.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Enum)]
+pub enum MobileNextAction {
+    /// More shares are required for reconstruction.
+    MoreShares {
+        /// The current number of shares available.
+        have: u32,
+        /// Number of shares needed for recovery.
+        need: u32,
+        /// Numbers of the shares already scanned, in the order they were added.
+        collected: Vec<u32>,
+    },
+    /// The user password is needed.
+    AskUserForPassword,
+    /// The set is private, and the owner manifest is needed.
+    AskUserForManifest,
+}
+
+impl From<NextAction> for MobileNextAction {
+    fn from(action: NextAction) -> Self {
+        match action {
+            NextAction::MoreShares {
+                have,
+                need,
+                collected,
+            } => MobileNextAction::MoreShares {
+                have: have as u32,
+                need: need as u32,
+                collected,
+            },
+            NextAction::AskUserForPassword => MobileNextAction::AskUserForPassword,
+            NextAction::AskUserForManifest => MobileNextAction::AskUserForManifest,
+        }
+    }
+}
+
+/// [`crate::encrypt`] for Kotlin and Swift, exported as `encrypt`.
+#[uniffi::export(name = "encrypt")]
+pub fn mobile_encrypt(
+    secret: String,
+    title: String,
+    passphrase: String,
+    total_shards: u32,
+    required_shards: u32,
+) -> Result<Vec<String>, MobileError> {
+    let secret = Zeroizing::new(secret);
+    let passphrase = Zeroizing::new(passphrase);
+    Ok(crate::encrypt(
+        &secret,
+        &title,
+        &passphrase,
+        total_shards as usize,
+        required_shards as usize,
+    )?)
+}
+
+/// [`crate::generate`] for Kotlin and Swift, exported as `generate`.
+#[uniffi::export(name = "generate")]
+pub fn mobile_generate(amount: u32) -> Result<String, MobileError> {
+    Ok(crate::generate(amount as usize)?)
+}
+
+/// [`Share`] for Kotlin and Swift.
+///
+/// Share objects are shared, so the set gets its own copy of the share,
+/// parsed again from the payload.
+#[derive(uniffi::Object)]
+pub struct MobileShare {
+    share: Share,
+    payload: Zeroizing<Vec<u8>>,
+}
+
+#[uniffi::export]
+impl MobileShare {
+    /// Parse the scanned qr code payload, see [`Share::new`].
+    #[uniffi::constructor]
+    pub fn new(payload: Vec<u8>) -> Result<Arc<Self>, MobileError> {
+        let payload = Zeroizing::new(payload);
+        Ok(Arc::new(Self {
+            share: Share::new(&payload[..])?,
+            payload,
+        }))
+    }
+    /// Share number, see [`Share::id`].
+    pub fn id(&self) -> u32 {
+        self.share.id()
+    }
+    /// Title of the set, see [`Share::title`].
+    pub fn title(&self) -> String {
+        self.share.title()
+    }
+    /// Number of shares needed for recovery, see [`Share::required_shards`].
+    pub fn required_shards(&self) -> u32 {
+        self.share.required_shards() as u32
+    }
+    /// Fingerprint of the set, see [`Share::fingerprint`].
+    pub fn fingerprint(&self) -> String {
+        self.share.fingerprint()
+    }
+}
+
+impl MobileShare {
+    /// Own copy of the share.
+    fn to_share(&self) -> Result<Share, Error> {
+        Share::new(&self.payload[..])
+    }
+}
+
+/// [`ShareSet`] for Kotlin and Swift.
+#[derive(Debug, uniffi::Object)]
+pub struct MobileShareSet(Mutex<ShareSet>);
+
+#[uniffi::export]
+impl MobileShareSet {
+    /// Start the set with its first share.
+    #[uniffi::constructor]
+    pub fn new(share: Arc<MobileShare>) -> Result<Arc<Self>, MobileError> {
+        Ok(Arc::new(Self(Mutex::new(ShareSet::init(
+            share.to_share()?,
+        )))))
+    }
+    /// Add the share to the set, see [`ShareSet::try_add_share`].
+    pub fn add_share(&self, share: Arc<MobileShare>) -> Result<(), MobileError> {
+        Ok(self.lock().try_add_share(share.to_share()?)?)
+    }
+    /// What is needed to proceed, see [`ShareSet::next_action`].
+    pub fn next_action(&self) -> MobileNextAction {
+        self.lock().next_action().into()
+    }
+    /// Title of the set, see [`ShareSet::title`].
+    pub fn title(&self) -> String {
+        self.lock().title()
+    }
+    /// Number of shares needed for recovery, see [`ShareSet::required_shards`].
+    pub fn required_shards(&self) -> u32 {
+        self.lock().required_shards() as u32
+    }
+    /// Recover the secret, see [`ShareSet::recover_with_passphrase`].
+    pub fn recover_with_passphrase(&self, passphrase: String) -> Result<String, MobileError> {
+        let passphrase = Zeroizing::new(passphrase);
+        Ok(self.lock().recover_with_passphrase(&passphrase)?)
+    }
+}
+
+impl MobileShareSet {
+    fn lock(&self) -> MutexGuard<'_, ShareSet> {
+        // set is never left half-updated, so the poisoned set is still usable
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,213 @@
+use sha2::{Digest, Sha512};
+
+use crate::error::Error;
+use crate::shares::Share;
+
+/// Crockford base32 alphabet: digits and capital letters without `I`, `L`, `O`, `U`,
+/// that are too easily confused when typing or recognizing text.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Characters in a group, groups are separated by spaces for easier reading.
+const GROUP_LEN: usize = 5;
+
+/// Groups in a line.
+const GROUPS_PER_LINE: usize = 4;
+
+/// Characters of the line checksum.
+const LINE_CHECKSUM_LEN: usize = 2;
+
+/// Characters of the payload checksum, on the final line.
+const PAYLOAD_CHECKSUM_LEN: usize = 8;
+
+/// Marker of the final line.
+const END_MARKER: &str = "END";
+
+/// Render the share payload as text to be printed next to the qr code,
+/// and typed back in, or recognized, with [`from_paper_text`] if the qr code
+/// could not be scanned.
+///
+/// Payload is checked to be a share, and encoded with Crockford base32 into
+/// numbered lines of 20 characters, each line followed by its two character checksum,
+/// so that a mistyped line is pointed out. The final line records the payload length
+/// and the payload checksum:
+///
+/// ```text
+/// 01 FCH7C 8HT64 P24X1 278H7 JX
+/// 02 8TBMD HJJ4B 12E8H 3MCHC TE
+/// ...
+/// 09 EHB5E H9J9S 0NADV S49YG YK
+/// END 112 NW2Y1P8Z
+/// ```
+pub fn to_paper_text(share: &[u8]) -> Result<String, Error> {
+    let _ = Share::new(share)?;
+    let encoded = encode(share);
+    let mut out = String::new();
+    for (i, line) in encoded.chunks(GROUP_LEN * GROUPS_PER_LINE).enumerate() {
+        let number = i + 1;
+        out.push_str(&format!("{number:02}"));
+        for group in line.chunks(GROUP_LEN) {
+            out.push(' ');
+            out.push_str(&String::from_utf8_lossy(group));
+        }
+        out.push(' ');
+        out.push_str(&line_checksum(number, line));
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "{END_MARKER} {} {}\n",
+        share.len(),
+        payload_checksum(share)
+    ));
+    Ok(out)
+}
+
+/// Share payload from the text made by [`to_paper_text`].
+///
+/// Parsing is tolerant to what typing and text recognition usually do: letter case,
+/// spacing, dashes, blank lines and headings are ignored, lines could go in any order,
+/// and `O`, `I`, `L` are read as `0`, `1`, `1`. Damaged line is reported by its number.
+pub fn from_paper_text(text: &str) -> Result<Vec<u8>, Error> {
+    let mut lines: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut end = None;
+    for line in text.lines() {
+        let line = line.trim().to_ascii_uppercase();
+        if let Some(rest) = line.strip_prefix(END_MARKER) {
+            end = Some(parse_end(rest)?);
+            continue;
+        }
+        // headings and anything else not starting with the line number are skipped
+        let Some((number, rest)) = split_number(&line) else {
+            continue;
+        };
+        let chars = normalize(rest).ok_or(Error::PaperLineDamaged(number))?;
+        if chars.len() <= LINE_CHECKSUM_LEN {
+            return Err(Error::PaperLineDamaged(number));
+        }
+        let (data, checksum) = chars.split_at(chars.len() - LINE_CHECKSUM_LEN);
+        if line_checksum(number, data).as_bytes() != checksum {
+            return Err(Error::PaperLineDamaged(number));
+        }
+        if lines.len() < number {
+            lines.resize(number, None);
+        }
+        match &lines[number - 1] {
+            Some(a) if a != data => return Err(Error::PaperLineDamaged(number)),
+            _ => lines[number - 1] = Some(data.to_vec()),
+        }
+    }
+    let (len, checksum) = end.ok_or(Error::PaperTextIncomplete)?;
+    let encoded = lines
+        .into_iter()
+        .collect::<Option<Vec<Vec<u8>>>>()
+        .ok_or(Error::PaperTextIncomplete)?
+        .concat();
+    let payload = decode(&encoded).ok_or(Error::PaperTextIncomplete)?;
+    if payload.len() != len || payload_checksum(&payload) != checksum {
+        return Err(Error::PaperTextIncomplete);
+    }
+    Ok(payload)
+}
+
+/// Line number at the line start, and the rest of the line.
+fn split_number(line: &str) -> Option<(usize, &str)> {
+    let end = line.find(is_separator).unwrap_or(line.len());
+    let digits = line[..end].chars().map(read_as_digit).collect::<String>();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    match digits.parse::<usize>() {
+        Ok(number) if number > 0 => Some((number, &line[end..])),
+        _ => None,
+    }
+}
+
+/// Payload length and checksum from the final line, after the marker.
+fn parse_end(rest: &str) -> Result<(usize, String), Error> {
+    let mut parts = rest.split(is_separator).filter(|a| !a.is_empty());
+    let len = parts
+        .next()
+        .map(|a| a.chars().map(read_as_digit).collect::<String>())
+        .and_then(|a| a.parse::<usize>().ok())
+        .ok_or(Error::PaperTextIncomplete)?;
+    let checksum = normalize(&parts.collect::<String>())
+        .filter(|a| a.len() == PAYLOAD_CHECKSUM_LEN)
+        .ok_or(Error::PaperTextIncomplete)?;
+    Ok((len, String::from_utf8_lossy(&checksum).into_owned()))
+}
+
+/// Characters of the alphabet, with separators removed and look-alikes replaced;
+/// `None` if there are characters outside of the alphabet.
+fn normalize(text: &str) -> Option<Vec<u8>> {
+    text.chars()
+        .filter(|c| !is_separator(*c))
+        .map(|c| {
+            let c = read_as_digit(c);
+            c.is_ascii()
+                .then_some(c as u8)
+                .filter(|a| ALPHABET.contains(a))
+        })
+        .collect()
+}
+
+/// Separators people put between the characters when typing.
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '-' | ':' | '.')
+}
+
+fn read_as_digit(c: char) -> char {
+    match c {
+        'O' => '0',
+        'I' | 'L' => '1',
+        c => c,
+    }
+}
+
+/// Base32 encoding, most significant bits first, without padding.
+fn encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(buffer >> bits) as usize & 0x1f]);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[(buffer << (5 - bits)) as usize & 0x1f]);
+    }
+    out
+}
+
+/// Base32 decoding of [`encode`] output; `None` on characters outside of the alphabet.
+fn decode(encoded: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in encoded {
+        let value = ALPHABET.iter().position(|a| a == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// Checksum of the line with its number, so that swapped lines are detected too.
+fn line_checksum(number: usize, data: &[u8]) -> String {
+    let mut hasher = Sha512::new();
+    hasher.update(format!("{number}:"));
+    hasher.update(data);
+    let encoded = encode(&hasher.finalize()[..2]);
+    String::from_utf8_lossy(&encoded[..LINE_CHECKSUM_LEN]).into_owned()
+}
+
+fn payload_checksum(payload: &[u8]) -> String {
+    let encoded = encode(&Sha512::digest(payload)[..5]);
+    String::from_utf8_lossy(&encoded[..PAYLOAD_CHECKSUM_LEN]).into_owned()
+}
@@ -0,0 +1,89 @@
+//! Checks of the runtime invariants, for audit builds.
+//!
+//! All checks pass without doing anything unless the crate is compiled
+//! with `paranoid-checks` feature.
+
+use crate::error::Error;
+use crate::kdf::KEY_LEN;
+use crate::shares::{NONCE_LEN, UNDEFINED_LOG};
+
+/// Shortest content checked for entropy, shorter contents could be uniform by chance.
+const MIN_ENTROPY_CHECKED_LEN: usize = 16;
+
+/// Logarithms and exponents tables are inverse of each other in GF(2^n),
+/// and exponents cycle through all non-zero elements.
+///
+/// Logarithm lookup `log` gives `None` for undefined logarithm.
+pub(crate) fn gf_tables(
+    bits: u32,
+    log: impl Fn(usize) -> Option<u32>,
+    exps: &[u32],
+) -> Result<(), Error> {
+    if !cfg!(feature = "paranoid-checks") {
+        return Ok(());
+    }
+    if log(0).is_some() {
+        return Err(violated("gf log of zero is defined"));
+    }
+    for x in 1..2usize.pow(bits) {
+        match log(x).and_then(|a| exps.get(a as usize)) {
+            Some(a) if *a as usize == x => {}
+            _ => return Err(violated("gf exponent is not inverse of logarithm")),
+        }
+    }
+    Ok(())
+}
+
+/// Logarithm lookup for the table filled by [`fill_logs_and_exps`](crate::shares::fill_logs_and_exps).
+pub(crate) fn filled_log(logs: &[u32]) -> impl Fn(usize) -> Option<u32> + '_ {
+    |x| logs.get(x).copied().filter(|a| *a != UNDEFINED_LOG)
+}
+
+/// Share ids are within the field, non-zero, and distinct.
+pub(crate) fn ids(bits: u32, ids: &[u32]) -> Result<(), Error> {
+    if !cfg!(feature = "paranoid-checks") {
+        return Ok(());
+    }
+    let max = 2u32.pow(bits) - 1;
+    for (i, id) in ids.iter().enumerate() {
+        if *id == 0 || *id > max {
+            return Err(violated("share id is out of range"));
+        }
+        if ids[..i].contains(id) {
+            return Err(violated("share id is repeated"));
+        }
+    }
+    Ok(())
+}
+
+/// Share content looks random: it is not a single byte repeated.
+///
+/// Genuine share content is indistinguishable from random, and a uniform one
+/// means a broken generator or a blanked scan.
+pub(crate) fn content(content: &[u8]) -> Result<(), Error> {
+    if !cfg!(feature = "paranoid-checks") {
+        return Ok(());
+    }
+    if content.len() >= MIN_ENTROPY_CHECKED_LEN && content.iter().all(|a| *a == content[0]) {
+        return Err(violated("share content is uniform"));
+    }
+    Ok(())
+}
+
+/// Nonce and key are of the lengths the cipher expects.
+pub(crate) fn cipher_input(key: &[u8], nonce: &[u8]) -> Result<(), Error> {
+    if !cfg!(feature = "paranoid-checks") {
+        return Ok(());
+    }
+    if key.len() != KEY_LEN {
+        return Err(violated("key length is invalid"));
+    }
+    if nonce.len() != NONCE_LEN {
+        return Err(violated("nonce length is invalid"));
+    }
+    Ok(())
+}
+
+fn violated(what: &str) -> Error {
+    Error::InvariantViolated(what.to_string())
+}
@@ -1,5 +1,9 @@
-use rand::Rng;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
 
+use crate::error::Error;
+
+#[allow(clippy::large_const_arrays)]
 const WORDS: [&str; 7776] = [
     "abacus",
     "abdomen",
@@ -7779,13 +7783,223 @@ const WORDS: [&str; 7776] = [
     "zoom",
 ];
 
+/// Bytes of entropy behind each word, see [`generate_with_entropy`].
+const ENTROPY_PER_WORD: usize = 4;
+
+/// Passphrase of hyphen-separated words, as generated by [`generate_with_entropy`].
+///
+/// Passphrase is wiped from memory when dropped, and is not shown by `Debug`.
+#[derive(PartialEq, Eq)]
+pub struct Passphrase(Zeroizing<String>);
+
+/// Outcome of [`Passphrase::check`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum PassphraseCheck {
+    /// Passphrase is shaped as the generated ones: lowercase words of the built-in
+    /// wordlist, separated by hyphens.
+    Valid,
+    /// Passphrase looks like a generated one, typed in with mistakes;
+    /// the key derivation would most likely fail with it.
+    LikelyTypo {
+        /// Positions of the words not in the wordlist, counted from 0.
+        unknown_words: Vec<usize>,
+        /// Words are separated by other than single hyphens, are not lowercase,
+        /// or have spaces around them.
+        format: bool,
+        /// Corrected passphrase, if every unknown word is one edit away
+        /// from a single word of the wordlist.
+        suggestion: Option<Passphrase>,
+    },
+    /// Passphrase does not look like a generated one, for example chosen by the user;
+    /// only the key derivation could tell if it is right.
+    Custom,
+}
+
+impl Passphrase {
+    /// Cheap sanity check of the passphrase entered for recovery, to be run before
+    /// the key derivation, so that obviously mistyped passphrases do not waste
+    /// a second of scrypt. Generated passphrases have no checksum word, so only
+    /// the wordlist membership and the format are checked.
+    ///
+    /// Words separated by spaces, underscores, dots, or commas are recognized too,
+    /// and reported as format mistakes.
+    pub fn check(passphrase: &str) -> PassphraseCheck {
+        let words: Vec<String> = passphrase
+            .split(['-', ' ', '_', '.', ','])
+            .filter(|a| !a.is_empty())
+            .map(|a| a.to_lowercase())
+            .collect();
+        let mut unknown_words = Vec::new();
+        let mut corrected = Vec::with_capacity(words.len());
+        for (i, word) in words.iter().enumerate() {
+            if WORDS.binary_search(&word.as_str()).is_ok() {
+                corrected.push(Some(word.as_str()));
+            } else {
+                unknown_words.push(i);
+                corrected.push(closest_word(word));
+            }
+        }
+        let joined = Zeroizing::new(words.join("-"));
+        let format = *joined != passphrase;
+        if !words.is_empty() && unknown_words.is_empty() && !format {
+            return PassphraseCheck::Valid;
+        }
+        // most of the words must be from the wordlist, or close to them
+        let likely = corrected.iter().filter(|a| a.is_some()).count();
+        if likely * 2 <= words.len() {
+            return PassphraseCheck::Custom;
+        }
+        let suggestion = corrected
+            .into_iter()
+            .collect::<Option<Vec<&str>>>()
+            .map(|a| Passphrase(Zeroizing::new(a.join("-"))));
+        PassphraseCheck::LikelyTypo {
+            unknown_words,
+            format,
+            suggestion,
+        }
+    }
+    /// Check the passphrase typed in by the user against the structure of
+    /// the generated ones, hyphen-separated words of the built-in wordlist,
+    /// for instant feedback before the slow key derivation.
+    ///
+    /// Case and spaces around the words are ignored. Empty words and words not
+    /// in the wordlist are reported by their positions, counted from 0, with
+    /// [`Error::PassphraseWordsInvalid`]. Passphrases chosen by the user do not
+    /// pass, and are to be used as is.
+    pub fn parse(passphrase: &str) -> Result<Self, Error> {
+        let words: Vec<String> = passphrase
+            .split('-')
+            .map(|a| a.trim().to_lowercase())
+            .collect();
+        let invalid: Vec<usize> = words
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| WORDS.binary_search(&word.as_str()).is_err())
+            .map(|(i, _)| i)
+            .collect();
+        if !invalid.is_empty() {
+            return Err(Error::PassphraseWordsInvalid(invalid));
+        }
+        Ok(Self(Zeroizing::new(words.join("-"))))
+    }
+    /// Re-render the passphrase from the entropy of [`generate_with_entropy`],
+    /// with the built-in wordlist.
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, Error> {
+        Self::from_entropy_with_wordlist(entropy, &WORDS)
+    }
+    /// Render the entropy of [`generate_with_entropy`] with another wordlist,
+    /// one word for every 4 bytes of entropy.
+    ///
+    /// Words are picked as the big endian number of each 4 bytes modulo the
+    /// wordlist length; for wordlists much shorter than 2^32 words the bias is negligible.
+    pub fn from_entropy_with_wordlist(entropy: &[u8], wordlist: &[&str]) -> Result<Self, Error> {
+        if !entropy.len().is_multiple_of(ENTROPY_PER_WORD) {
+            return Err(Error::EntropyLengthInvalid(entropy.len()));
+        }
+        if wordlist.is_empty() {
+            return Err(Error::WordlistEmpty);
+        }
+        let words: Vec<&str> = entropy
+            .chunks(ENTROPY_PER_WORD)
+            .map(|a| {
+                let draw = u32::from_be_bytes(a.try_into().expect("chunks are 4 bytes long"));
+                wordlist[draw as usize % wordlist.len()]
+            })
+            .collect();
+        Ok(Self(Zeroizing::new(words.join("-"))))
+    }
+    /// Passphrase text, words joined with hyphens.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    /// Words of the passphrase, in order.
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.0.split('-')
+    }
+}
+
+impl std::fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Passphrase({} words)", self.words().count())
+    }
+}
+
+/// The only word of the wordlist one edit away from the given word,
+/// by replaced, added, removed, or swapped adjacent letter.
+fn closest_word(word: &str) -> Option<&'static str> {
+    let mut found = WORDS.iter().filter(|a| one_edit_apart(word, a));
+    match (found.next(), found.next()) {
+        (Some(a), None) => Some(a),
+        _ => None,
+    }
+}
+
+fn one_edit_apart(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    match (a.len(), b.len()) {
+        (0, 0) => false,
+        (x, y) if x == y => {
+            a[1..] == b[1..] || (x >= 2 && a[0] == b[1] && a[1] == b[0] && a[2..] == b[2..])
+        }
+        (x, y) if x + 1 == y => a == &b[1..],
+        (x, y) if x == y + 1 => &a[1..] == b,
+        _ => false,
+    }
+}
+
 /// Generate a passphrase with a given amount of words
-pub fn generate(amount: usize) -> String {
-    let mut rng = rand::thread_rng();
-    (0..amount)
-        .map(|_| WORDS[rng.gen_range(0..WORDS.len())].to_string())
-        .collect::<Vec<_>>()
-        .join("-")
+pub fn generate(amount: usize) -> Result<String, Error> {
+    generate_with_rng(amount, &mut rand::thread_rng())
+}
+
+/// Generate a passphrase with a given amount of words, same as [`generate`],
+/// with the words drawn from `rng`, for hardware wallet firmware with its own
+/// entropy source, or deterministic tests.
+pub fn generate_with_rng<R: RngCore + CryptoRng>(
+    amount: usize,
+    rng: &mut R,
+) -> Result<String, Error> {
+    let entropy = draw_entropy(amount, rng)?;
+    Ok(Passphrase::from_entropy(&entropy)?.as_str().to_owned())
+}
+
+/// Generate a passphrase with a given amount of words, same as [`generate`],
+/// together with the entropy the words were picked by, 4 bytes per word.
+///
+/// Applications could archive the entropy, or derive other material from it,
+/// and re-render the passphrase later with [`Passphrase::from_entropy`], or with
+/// another wordlist by [`Passphrase::from_entropy_with_wordlist`].
+pub fn generate_with_entropy(amount: usize) -> Result<(Passphrase, Zeroizing<Vec<u8>>), Error> {
+    let entropy = draw_entropy(amount, &mut rand::thread_rng())?;
+    Ok((Passphrase::from_entropy(&entropy)?, entropy))
+}
+
+/// Entropy for `amount` words, 4 bytes per word.
+fn draw_entropy<R: RngCore>(amount: usize, rng: &mut R) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let mut entropy = Zeroizing::new(Vec::with_capacity(amount * ENTROPY_PER_WORD));
+    for _ in 0..amount {
+        entropy.extend_from_slice(&random_draw(rng, WORDS.len())?.to_be_bytes());
+    }
+    Ok(entropy)
+}
+
+/// Random number, uniformly distributed modulo `len`.
+/// Rejection sampling is used, so that all words are equally likely.
+fn random_draw<R: RngCore>(rng: &mut R, len: usize) -> Result<u32, Error> {
+    let len = len as u32;
+    let zone = u32::MAX - u32::MAX % len;
+    loop {
+        let mut bytes = [0u8; 4];
+        rng.try_fill_bytes(&mut bytes)
+            .map_err(|_| Error::RandomnessUnavailable)?;
+        let x = u32::from_be_bytes(bytes);
+        if x < zone {
+            return Ok(x);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -7794,14 +8008,109 @@ mod tests {
 
     #[test]
     fn test_generate_password_length() {
-        let password = generate(5);
+        let password = generate(5).unwrap();
         assert_eq!(password.split('-').collect::<Vec<_>>().len(), 5);
     }
 
+    /// Generator without entropy: fallible draws fail, infallible ones give zeros.
+    struct FailingRng;
+
+    impl RngCore for FailingRng {
+        fn next_u32(&mut self) -> u32 {
+            0
+        }
+        fn next_u64(&mut self) -> u64 {
+            0
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(0)
+        }
+        fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), rand::Error> {
+            Err(rand::Error::new("no entropy"))
+        }
+    }
+
+    #[test]
+    fn test_failing_rng_is_reported() {
+        assert!(matches!(
+            random_draw(&mut FailingRng, WORDS.len()),
+            Err(Error::RandomnessUnavailable)
+        ));
+    }
+
+    #[test]
+    fn test_passphrase_from_entropy() {
+        let (passphrase, entropy) = generate_with_entropy(4).unwrap();
+        assert_eq!(entropy.len(), 16);
+        assert_eq!(
+            Passphrase::from_entropy(&entropy).unwrap().as_str(),
+            passphrase.as_str()
+        );
+
+        let entropy = [0, 0, 0, 1, 0, 0, 30, 96];
+        assert_eq!(
+            Passphrase::from_entropy(&entropy).unwrap().as_str(),
+            "abdomen-abacus"
+        );
+        let other = Passphrase::from_entropy_with_wordlist(&entropy, &["a", "b", "c"]).unwrap();
+        assert_eq!(other.words().collect::<Vec<_>>(), ["b", "a"]);
+        assert!(matches!(
+            Passphrase::from_entropy(&entropy[..5]),
+            Err(Error::EntropyLengthInvalid(5))
+        ));
+    }
+
+    #[test]
+    fn test_passphrase_parse() {
+        assert!(WORDS.windows(2).all(|a| a[0] < a[1]));
+        let passphrase = Passphrase::parse(" Zoom - abacus-zoology ").unwrap();
+        assert_eq!(passphrase.as_str(), "zoom-abacus-zoology");
+        assert_eq!(
+            Passphrase::parse("zoom-abacsu--zoology-").unwrap_err(),
+            Error::PassphraseWordsInvalid(vec![1, 2, 4])
+        );
+        assert_eq!(
+            Passphrase::parse("").unwrap_err(),
+            Error::PassphraseWordsInvalid(vec![0])
+        );
+    }
+
+    #[test]
+    fn test_passphrase_check() {
+        assert_eq!(Passphrase::check("zoom-abacus"), PassphraseCheck::Valid);
+        assert_eq!(
+            Passphrase::check("Zoom abacsu"),
+            PassphraseCheck::LikelyTypo {
+                unknown_words: vec![1],
+                format: true,
+                suggestion: Passphrase::parse("zoom-abacus").ok(),
+            }
+        );
+        assert_eq!(
+            Passphrase::check("zoom-qqqqq-zoology"),
+            PassphraseCheck::LikelyTypo {
+                unknown_words: vec![1],
+                format: false,
+                suggestion: None,
+            }
+        );
+        assert_eq!(
+            Passphrase::check("my own secret phrase!"),
+            PassphraseCheck::Custom
+        );
+        assert_eq!(Passphrase::check(""), PassphraseCheck::Custom);
+
+        assert!(one_edit_apart("zom", "zoom"));
+        assert!(one_edit_apart("zoon", "zoom"));
+        assert!(one_edit_apart("ozom", "zoom"));
+        assert!(!one_edit_apart("zoom", "zoom"));
+        assert!(!one_edit_apart("zo", "zoom"));
+    }
+
     #[test]
     fn test_generate_password_uniqueness() {
-        let password1 = generate(5);
-        let password2 = generate(5);
+        let password1 = generate(5).unwrap();
+        let password2 = generate(5).unwrap();
         assert_ne!(password1, password2);
     }
 }
@@ -0,0 +1,83 @@
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::shares::Share;
+
+/// Line for the passphrase to be handwritten on the printed share.
+const PASSPHRASE_PLACEHOLDER: &str = "Passphrase: ____________________________";
+
+/// Everything that belongs on a printed share, see [`PrintLayout::for_shares`],
+/// so that applications could render their own paper or pdf templates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PrintLayout {
+    /// Title of the secret; `None` for shares of private split, that do not reveal it.
+    pub title: Option<String>,
+    /// Share number, starting from 1.
+    pub index: usize,
+    /// Total number of shares printed.
+    pub total: usize,
+    /// Share number as printed, for example `"2 of 5"`.
+    pub index_text: String,
+    /// Number of shares needed for the recovery; `None` if the share does not record it.
+    pub required_shards: Option<usize>,
+    /// Share payload to be encoded into qr code, as is.
+    pub qr_payload: String,
+    /// Set fingerprint, see [`Share::fingerprint`], for custodians to tell sets apart.
+    pub fingerprint: String,
+    /// Recovery instructions for the custodian.
+    pub instructions: String,
+    /// Passphrase line, left blank for the owner to fill in by hand.
+    pub passphrase_placeholder: String,
+}
+
+impl PrintLayout {
+    /// Layouts for all shares of a set, as produced by [`encrypt`](crate::encrypt)
+    /// and friends, in the order given.
+    ///
+    /// Shares are parsed and checked to be from the same set.
+    pub fn for_shares(shares: &[String]) -> Result<Vec<Self>, Error> {
+        let parsed = shares
+            .iter()
+            .map(|a| Share::new(a.as_str()))
+            .collect::<Result<Vec<Share>, Error>>()?;
+        let first = parsed.first().ok_or(Error::TooFewShares)?;
+        let fingerprint = first.fingerprint();
+        if parsed.iter().any(|a| a.fingerprint() != fingerprint) {
+            return Err(Error::ShareFingerprintDifferent);
+        }
+        let title = first.recorded_title().map(String::from);
+        let total = shares.len();
+        let required_shards = match first.required_shards() {
+            0 => None,
+            a => Some(a),
+        };
+        let instructions = match required_shards {
+            Some(required) => format!(
+                "This is one of {total} shares of a secret. Scan any {required} of them \
+                 with banana split, and enter the passphrase, to recover the secret."
+            ),
+            None => format!(
+                "This is one of {total} shares of a secret. Scan the shares with banana split, \
+                 together with the owner manifest, and enter the passphrase, to recover the secret."
+            ),
+        };
+        Ok(shares
+            .iter()
+            .enumerate()
+            .map(|(i, payload)| {
+                let index = i + 1;
+                Self {
+                    title: title.clone(),
+                    index,
+                    total,
+                    index_text: format!("{index} of {total}"),
+                    required_shards,
+                    qr_payload: payload.clone(),
+                    fingerprint: fingerprint.clone(),
+                    instructions: instructions.clone(),
+                    passphrase_placeholder: PASSPHRASE_PLACEHOLDER.to_string(),
+                }
+            })
+            .collect())
+    }
+}
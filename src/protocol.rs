@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::shares::{Share, ShareSet};
+
+/// Request sent by the application to a custodian, asking for their share.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareRequest {
+    /// Identifier chosen by the application, echoed back in the response.
+    pub request_id: String,
+    /// Title of the backup being recovered.
+    pub title: String,
+    /// Fingerprint of the set being recovered, if already known.
+    pub fingerprint: Option<String>,
+}
+
+/// Response of a custodian, carrying the share payload as it is printed in the qr code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareResponse {
+    /// Identifier of the request this response answers.
+    pub request_id: String,
+    /// Share payload, i.e. json string from the qr code.
+    pub payload: String,
+}
+
+/// Current recovery status, to be reported to custodians or remote user interface.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryStatus {
+    /// Title of the set.
+    pub title: String,
+    /// Fingerprint of the set.
+    pub fingerprint: String,
+    /// Number of shares collected so far.
+    pub have: usize,
+    /// Number of shares needed for recovery.
+    pub need: usize,
+}
+
+impl ShareRequest {
+    /// New request for a share of the backup with given title.
+    pub fn new(request_id: &str, title: &str) -> Self {
+        Self {
+            request_id: request_id.to_string(),
+            title: title.to_string(),
+            fingerprint: None,
+        }
+    }
+    /// New request for one more share of the set already in progress.
+    /// Responses are checked against the set fingerprint.
+    pub fn for_set(request_id: &str, share_set: &ShareSet) -> Self {
+        Self {
+            request_id: request_id.to_string(),
+            title: share_set.title(),
+            fingerprint: Some(share_set.fingerprint()),
+        }
+    }
+}
+
+impl ShareResponse {
+    /// Check that the response answers the request and parse the share.
+    pub fn into_share(self, request: &ShareRequest) -> Result<Share, Error> {
+        if self.request_id != request.request_id {
+            return Err(Error::ResponseIdMismatch);
+        }
+        let share = Share::new(self.payload.into_bytes())?;
+        // private shares do not record the title, fingerprint is checked instead
+        if !share.is_private() && share.title() != request.title {
+            return Err(Error::ShareTitleDifferent);
+        }
+        if let Some(ref fingerprint) = request.fingerprint {
+            if &share.fingerprint() != fingerprint {
+                return Err(Error::ShareFingerprintDifferent);
+            }
+        }
+        Ok(share)
+    }
+    /// Check the response, and add its share into the set.
+    pub fn ingest(
+        self,
+        request: &ShareRequest,
+        share_set: &mut ShareSet,
+    ) -> Result<RecoveryStatus, Error> {
+        let share = self.into_share(request)?;
+        share_set.try_add_share(share)?;
+        Ok(RecoveryStatus::of(share_set))
+    }
+}
+
+impl RecoveryStatus {
+    /// Status of the given set.
+    pub fn of(share_set: &ShareSet) -> Self {
+        Self {
+            title: share_set.title(),
+            fingerprint: share_set.fingerprint(),
+            have: share_set.collected_shards(),
+            need: share_set.required_shards(),
+        }
+    }
+    /// All required shares are collected.
+    /// Never complete while the number of required shares is unknown.
+    pub fn is_complete(&self) -> bool {
+        self.need != 0 && self.have >= self.need
+    }
+}
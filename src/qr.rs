@@ -0,0 +1,108 @@
+use crate::collection::ShareSetCollection;
+use crate::error::Error;
+use crate::shares::Share;
+
+/// Image to payload step of the share scanning, so that applications could plug in
+/// the qr decoder of their platform, for example iOS Vision or ML Kit,
+/// and reuse the share parsing, see [`ShareSetCollection::add_image`].
+///
+/// Closures taking the image and returning the payloads are decoders too.
+pub trait QrDecoder {
+    /// Payloads of all qr codes found in the image, as is, in any order;
+    /// empty if there are none. Image formats supported are up to the decoder.
+    ///
+    /// Decoder failures are reported with [`Error::QrDecodingFailed`].
+    fn decode(&self, image: &[u8]) -> Result<Vec<Vec<u8>>, Error>;
+}
+
+impl<F> QrDecoder for F
+where
+    F: Fn(&[u8]) -> Result<Vec<Vec<u8>>, Error>,
+{
+    fn decode(&self, image: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        self(image)
+    }
+}
+
+/// Built-in qr decoder, `rqrr` over png or jpeg images, see [`Share::from_qr_image`].
+///
+/// Qr codes that are found but could not be read are skipped; if none could be read,
+/// the failure of the first one is reported with [`Error::QrDecodingFailed`].
+#[cfg(feature = "qr-decode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RqrrDecoder;
+
+#[cfg(feature = "qr-decode")]
+impl QrDecoder for RqrrDecoder {
+    fn decode(&self, image: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        let image = image::load_from_memory(image)
+            .map_err(|e| Error::QrDecodingFailed(e.to_string()))?
+            .into_luma8();
+        let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+            image.width() as usize,
+            image.height() as usize,
+            |x, y| image.get_pixel(x as u32, y as u32)[0],
+        );
+        let mut payloads = Vec::new();
+        let mut first_error = None;
+        for grid in prepared.detect_grids() {
+            let mut payload = Vec::new();
+            match grid.decode_to(&mut payload) {
+                Ok(_) => payloads.push(payload),
+                Err(e) => {
+                    let _ = first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) if payloads.is_empty() => Err(Error::QrDecodingFailed(e.to_string())),
+            _ => Ok(payloads),
+        }
+    }
+}
+
+impl ShareSetCollection {
+    /// Decode the qr codes in the image, for example a photo of several printed shares,
+    /// and add each share found, same as [`ShareSetCollection::add_payload`].
+    ///
+    /// Outcomes are reported for each qr code. Image without qr codes
+    /// is reported with [`Error::NoShareInImage`].
+    pub fn add_image(
+        &mut self,
+        decoder: &dyn QrDecoder,
+        image: &[u8],
+    ) -> Result<Vec<Result<usize, Error>>, Error> {
+        let payloads = decoder.decode(image)?;
+        if payloads.is_empty() {
+            return Err(Error::NoShareInImage);
+        }
+        Ok(payloads.into_iter().map(|a| self.add_payload(a)).collect())
+    }
+}
+
+impl Share {
+    /// Decode the qr codes in png or jpeg image, for example a scanned photo
+    /// of a printed share, with the built-in [`RqrrDecoder`], and parse the first
+    /// share found, same as [`Share::new`].
+    ///
+    /// Image without qr codes is reported with [`Error::NoShareInImage`]; if none of
+    /// the qr codes is a share, the parsing error of the first one is reported.
+    #[cfg(feature = "qr-decode")]
+    pub fn from_qr_image(image: &[u8]) -> Result<Self, Error> {
+        Self::from_qr_image_with(&RqrrDecoder, image)
+    }
+    /// Same as [`Share::from_qr_image`], with the qr decoder of the application,
+    /// for example of the platform.
+    pub fn from_qr_image_with(decoder: &dyn QrDecoder, image: &[u8]) -> Result<Self, Error> {
+        let mut first_error = None;
+        for payload in decoder.decode(image)? {
+            match Share::new(payload) {
+                Ok(share) => return Ok(share),
+                Err(e) => {
+                    let _ = first_error.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_error.unwrap_or(Error::NoShareInImage))
+    }
+}
@@ -0,0 +1,202 @@
+use zeroize::Zeroizing;
+
+use crate::cancel::CancellationToken;
+use crate::combine::{combine_into, combine_output_len, combine_table_len};
+use crate::error::Error;
+use crate::integrity::{check_key, share_checksum};
+use crate::shares::{decode_nonce, decrypt, Share, ShareSet};
+use crate::title::derivation_salt;
+
+/// Outcome of [`repair`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct Repaired {
+    /// Recovered secret.
+    pub secret: String,
+    /// Index of the repaired share in the given shares, `None` if no repair was needed.
+    pub share: Option<usize>,
+    /// Flipped bits of the repaired share content, as bit positions from the content start.
+    pub flipped_bits: Vec<usize>,
+}
+
+/// Recover the secret from the shares, one of which is damaged, by searching
+/// the content bit flips of up to `max_flips` bits in a single share.
+///
+/// Intended for scans of creased or faded prints, when exactly the threshold
+/// number of shares is available and the decryption fails. The search is expensive:
+/// the number of tried candidates grows as content bits to the power of `max_flips`,
+/// so `max_flips` above 2 is rarely practical.
+///
+/// Shares with V2 checksums guide the search: the share failing the checksum
+/// is the only one perturbed, and candidates are checked against the checksum
+/// before the combining. Key derivation is done only once; with key commitment
+/// the wrong passphrase is reported right away.
+pub fn repair<T: AsRef<[u8]>>(
+    payloads: &[T],
+    passphrase: &str,
+    max_flips: usize,
+) -> Result<Repaired, Error> {
+    let shares = payloads
+        .iter()
+        .map(|a| Share::parse_unverified(a.as_ref()))
+        .collect::<Result<Vec<Share>, Error>>()?;
+    let first = shares.first().ok_or(Error::TooFewShares)?;
+
+    // shares must be from the same set, checked same way as when collecting them
+    let reference = ShareSet::init(Share::parse_unverified(payloads[0].as_ref())?);
+    for (i, share) in shares.iter().enumerate().skip(1) {
+        if let Some(reason) = reference.why_rejected(share) {
+            return Err(reason.into());
+        }
+        if shares[..i].iter().any(|a| a.id() == share.id()) {
+            return Err(Error::ShareAlreadyInSet);
+        }
+    }
+    if first.is_private() {
+        return Err(Error::ManifestNeeded);
+    }
+    if shares.len() < first.required_shards() {
+        return Err(Error::TooFewShares);
+    }
+
+    let (key_derivation, key_commitment) = first.key();
+    let salt = derivation_salt(first.salt_override(), &first.title());
+    let key = key_derivation.derive(passphrase.as_bytes(), &salt, &CancellationToken::default())?;
+    check_key(&key, key_commitment)?;
+    let nonce = decode_nonce(first.nonce())?;
+
+    let mut search = Search::new(&shares, &key, &nonce)?;
+    if let Some(secret) = search.attempt()? {
+        return Ok(Repaired {
+            secret,
+            share: None,
+            flipped_bits: Vec::new(),
+        });
+    }
+
+    // shares failing the checksum are the only suspects, if there are any
+    let failing: Vec<usize> = shares
+        .iter()
+        .enumerate()
+        .filter(|(_, share)| {
+            share
+                .checksum()
+                .is_some_and(|a| share_checksum(share.id(), share.content()) != *a)
+        })
+        .map(|(i, _)| i)
+        .collect();
+    let suspects: Vec<usize> = match failing.len() {
+        0 => (0..shares.len()).collect(),
+        1 => failing,
+        // single share repair could not help
+        _ => return Err(Error::RepairFailed),
+    };
+
+    // element values must stay within the field
+    let bits_per_element = first.bits().min(8) as usize;
+    let positions = first.content().len() * bits_per_element;
+    for flips in 1..=max_flips.min(positions) {
+        for &suspect in &suspects {
+            let checksum = shares[suspect].checksum();
+            let id = shares[suspect].id();
+            let mut combination: Vec<usize> = (0..flips).collect();
+            loop {
+                let flipped: Vec<(usize, u8)> = combination
+                    .iter()
+                    .map(|p| (p / bits_per_element, 1 << (p % bits_per_element)))
+                    .collect();
+                search.flip(suspect, &flipped);
+                let passes_checksum =
+                    checksum.is_none_or(|a| share_checksum(id, &search.contents[suspect]) == *a);
+                let found = if passes_checksum {
+                    search.attempt()?
+                } else {
+                    None
+                };
+                search.flip(suspect, &flipped);
+                if let Some(secret) = found {
+                    return Ok(Repaired {
+                        secret,
+                        share: Some(suspect),
+                        flipped_bits: combination,
+                    });
+                }
+                if !next_combination(&mut combination, positions) {
+                    break;
+                }
+            }
+        }
+    }
+    Err(Error::RepairFailed)
+}
+
+/// Combining and decryption with reused buffers.
+struct Search<'a> {
+    bits: u32,
+    ids: Vec<u32>,
+    contents: Vec<Zeroizing<Vec<u8>>>,
+    logs: Vec<u32>,
+    exps: Vec<u32>,
+    out: Vec<u8>,
+    key: &'a [u8],
+    nonce: &'a [u8],
+}
+
+impl<'a> Search<'a> {
+    fn new(shares: &[Share], key: &'a [u8], nonce: &'a [u8]) -> Result<Self, Error> {
+        let bits = shares[0].bits();
+        let table_len = combine_table_len(bits)?;
+        let content_len = shares[0].content().len();
+        Ok(Self {
+            bits,
+            ids: shares.iter().map(|a| a.id()).collect(),
+            contents: shares
+                .iter()
+                .map(|a| Zeroizing::new(a.content().to_vec()))
+                .collect(),
+            logs: vec![0; table_len],
+            exps: vec![0; table_len],
+            out: vec![0; combine_output_len(bits, content_len)],
+            key,
+            nonce,
+        })
+    }
+    /// Flip the bits of the share content; flipping twice restores the content.
+    fn flip(&mut self, share: usize, flipped: &[(usize, u8)]) {
+        for (byte, mask) in flipped {
+            self.contents[share][*byte] ^= mask;
+        }
+    }
+    /// Combine the current contents and try to decrypt; `None` if the decryption fails.
+    fn attempt(&mut self) -> Result<Option<String>, Error> {
+        let contents: Vec<&[u8]> = self.contents.iter().map(|a| a.as_slice()).collect();
+        let len = combine_into(
+            self.bits,
+            &self.ids,
+            &contents,
+            &mut self.logs,
+            &mut self.exps,
+            &mut self.out,
+        )?;
+        match decrypt(self.key, self.nonce, &self.out[..len]) {
+            Ok(secret) => Ok(Some(secret)),
+            Err(Error::DecodingFailed) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Advance to the next combination of `k` positions out of `n`, in lexicographic order;
+/// `false` once all combinations are done.
+fn next_combination(combination: &mut [usize], n: usize) -> bool {
+    let k = combination.len();
+    for i in (0..k).rev() {
+        if combination[i] < n - k + i {
+            combination[i] += 1;
+            for j in i + 1..k {
+                combination[j] = combination[j - 1] + 1;
+            }
+            return true;
+        }
+    }
+    false
+}
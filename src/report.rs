@@ -0,0 +1,182 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+
+/// Structured form of [`Error`], see [`Error::report`], for host applications
+/// to branch on error kinds same way native callers match on [`Error`] variants.
+///
+/// With `wasm` feature, [`Error`] converts into JS `Error` object carrying the same fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorReport {
+    /// Name of the [`Error`] variant, for example `"WrongPassphrase"`.
+    pub name: &'static str,
+    /// Numeric code of the [`Error`] variant. Codes are stable across releases,
+    /// codes of removed variants are not reused.
+    pub code: u16,
+    /// Human-readable message, same as [`Error`] displays.
+    pub message: String,
+    /// Values carried by the variant, by field name; empty for variants without values.
+    pub context: Map<String, Value>,
+}
+
+impl Error {
+    /// Structured report of the error.
+    pub fn report(&self) -> ErrorReport {
+        let (name, code) = self.name_and_code();
+        ErrorReport {
+            name,
+            code,
+            message: self.to_string(),
+            context: self.context(),
+        }
+    }
+    fn name_and_code(&self) -> (&'static str, u16) {
+        match self {
+            Error::BitsOutOfRange(_) => ("BitsOutOfRange", 1),
+            Error::BufferTooSmall(_) => ("BufferTooSmall", 2),
+            Error::Cancelled => ("Cancelled", 3),
+            Error::CompressedShareBorrowed => ("CompressedShareBorrowed", 4),
+            Error::CustodianWithoutShares(_) => ("CustodianWithoutShares", 5),
+            Error::DeflateNotSupported => ("DeflateNotSupported", 6),
+            Error::DeflateFailed => ("DeflateFailed", 7),
+            Error::DecodedSecretNotString => ("DecodedSecretNotString", 8),
+            Error::DecodingFailed => ("DecodingFailed", 9),
+            Error::ElementOutOfRange(_) => ("ElementOutOfRange", 10),
+            Error::EncryptionFailed => ("EncryptionFailed", 11),
+            Error::EmptyShare => ("EmptyShare", 12),
+            Error::ImageInvalid => ("ImageInvalid", 13),
+            Error::ImageTooSmall(_) => ("ImageTooSmall", 14),
+            Error::InvariantViolated(_) => ("InvariantViolated", 15),
+            Error::JsonParsing => ("JsonParsing", 16),
+            Error::KeyCommitmentInvalid => ("KeyCommitmentInvalid", 17),
+            Error::KdfNotSupported(_) => ("KdfNotSupported", 18),
+            Error::KdfParamsNotSupported(_) => ("KdfParamsNotSupported", 19),
+            Error::KdfParamsTooWeak(_) => ("KdfParamsTooWeak", 20),
+            Error::LogOutOfRange(_) => ("LogOutOfRange", 21),
+            Error::ManifestMismatch => ("ManifestMismatch", 22),
+            Error::ManifestNeeded => ("ManifestNeeded", 23),
+            Error::ManifestParsing => ("ManifestParsing", 24),
+            Error::NonceLengthInvalid(_) => ("NonceLengthInvalid", 25),
+            Error::NonceNotBase64 => ("NonceNotBase64", 26),
+            Error::NoShareInImage => ("NoShareInImage", 27),
+            Error::NotLegacySet => ("NotLegacySet", 28),
+            Error::NotReadyToDecode => ("NotReadyToDecode", 29),
+            Error::NotShareString => ("NotShareString", 30),
+            Error::PaperLineDamaged(_) => ("PaperLineDamaged", 31),
+            Error::PaperTextIncomplete => ("PaperTextIncomplete", 32),
+            Error::ParseBit(_) => ("ParseBit", 33),
+            Error::RandomnessUnavailable => ("RandomnessUnavailable", 34),
+            Error::ResponseIdMismatch => ("ResponseIdMismatch", 35),
+            Error::RepairFailed => ("RepairFailed", 36),
+            Error::RequiredShardsNotSupported(_) => ("RequiredShardsNotSupported", 37),
+            Error::SaltInvalid => ("SaltInvalid", 38),
+            Error::ScryptFailed(_) => ("ScryptFailed", 39),
+            Error::SealingFailed => ("SealingFailed", 40),
+            Error::SealedShareNotBase64 => ("SealedShareNotBase64", 41),
+            Error::SelfTestFailed(_) => ("SelfTestFailed", 42),
+            Error::SetIdInvalid(_) => ("SetIdInvalid", 43),
+            Error::SetMacMismatch => ("SetMacMismatch", 44),
+            Error::ShareAlreadyInSet => ("ShareAlreadyInSet", 45),
+            Error::ShareBitsDifferent => ("ShareBitsDifferent", 46),
+            Error::ShareChecksumMismatch => ("ShareChecksumMismatch", 47),
+            Error::ShareContentLengthDifferent => ("ShareContentLengthDifferent", 48),
+            Error::ShareFingerprintDifferent => ("ShareFingerprintDifferent", 49),
+            Error::ShareFromDifferentSet => ("ShareFromDifferentSet", 50),
+            Error::ShareKeyCommitmentDifferent => ("ShareKeyCommitmentDifferent", 51),
+            Error::ShareKdfDifferent => ("ShareKdfDifferent", 52),
+            Error::ShareNonceDifferent => ("ShareNonceDifferent", 53),
+            Error::ShareRequiredShardsDifferent => ("ShareRequiredShardsDifferent", 54),
+            Error::ShareSaltDifferent => ("ShareSaltDifferent", 55),
+            Error::ShareTitleDifferent => ("ShareTitleDifferent", 56),
+            Error::ShareIdOutOfRange(_) => ("ShareIdOutOfRange", 57),
+            Error::ShareTooShort => ("ShareTooShort", 58),
+            Error::ShareValidityDifferent => ("ShareValidityDifferent", 59),
+            Error::ShareVersionDifferent => ("ShareVersionDifferent", 60),
+            Error::UnsealingFailed => ("UnsealingFailed", 61),
+            Error::UndefinedBodyNotHex => ("UndefinedBodyNotHex", 62),
+            Error::ValidityInvalid => ("ValidityInvalid", 63),
+            Error::VersionNotSupported(_) => ("VersionNotSupported", 64),
+            Error::BodyNotBase64 => ("BodyNotBase64", 65),
+            Error::WrongPassphrase => ("WrongPassphrase", 66),
+            Error::TagInvalid(_) => ("TagInvalid", 67),
+            Error::TooFewShares => ("TooFewShares", 68),
+            Error::TooManyShares(_) => ("TooManyShares", 69),
+            Error::QrDecodingFailed(_) => ("QrDecodingFailed", 70),
+            Error::QrEncodingFailed(_) => ("QrEncodingFailed", 71),
+            Error::SecretNotSeedPhrase => ("SecretNotSeedPhrase", 72),
+            Error::Ss58PrefixInvalid(_) => ("Ss58PrefixInvalid", 73),
+            Error::SecretNotPrivateKey => ("SecretNotPrivateKey", 74),
+            Error::FileChecksumMismatch => ("FileChecksumMismatch", 75),
+            Error::FileIo(_) => ("FileIo", 76),
+            Error::NotFileSecret => ("NotFileSecret", 77),
+            Error::ChunkMissing(_) => ("ChunkMissing", 78),
+            Error::ChunkSizeZero => ("ChunkSizeZero", 79),
+            Error::BundleFailed(_) => ("BundleFailed", 80),
+            Error::EntropyLengthInvalid(_) => ("EntropyLengthInvalid", 81),
+            Error::WordlistEmpty => ("WordlistEmpty", 82),
+            Error::PassphraseWordsInvalid(_) => ("PassphraseWordsInvalid", 83),
+            Error::SessionStepInvalid => ("SessionStepInvalid", 84),
+            Error::WrappedShareInvalid(..) => ("WrappedShareInvalid", 85),
+            Error::PaddingInvalid(_) => ("PaddingInvalid", 86),
+            Error::SetSnapshotInvalid => ("SetSnapshotInvalid", 87),
+            Error::ShareNotInSet(_) => ("ShareNotInSet", 88),
+            Error::NoMajority => ("NoMajority", 89),
+        }
+    }
+    fn context(&self) -> Map<String, Value> {
+        let (field, value) = match self {
+            Error::BitsOutOfRange(a) => ("bits", Value::from(*a)),
+            Error::BufferTooSmall(a) => ("needed", Value::from(*a)),
+            Error::BundleFailed(a) => ("reason", Value::from(a.as_str())),
+            Error::ChunkMissing(a) => ("chunk", Value::from(*a)),
+            Error::CustodianWithoutShares(a) => ("custodian", Value::from(a.as_str())),
+            Error::ElementOutOfRange(a) => ("value", Value::from(*a)),
+            Error::EntropyLengthInvalid(a) => ("length", Value::from(*a)),
+            Error::FileIo(a) => ("reason", Value::from(a.as_str())),
+            Error::ImageTooSmall(a) => ("needed", Value::from(*a)),
+            Error::InvariantViolated(a) => ("invariant", Value::from(a.as_str())),
+            Error::KdfNotSupported(a) => ("kdf", Value::from(a.as_str())),
+            Error::KdfParamsNotSupported(a) => ("params", Value::from(a.as_str())),
+            Error::KdfParamsTooWeak(a) => ("params", Value::from(a.as_str())),
+            Error::LogOutOfRange(a) => ("index", Value::from(*a)),
+            Error::NonceLengthInvalid(a) => ("length", Value::from(*a)),
+            Error::PaddingInvalid(a) => ("block", Value::from(*a)),
+            Error::PaperLineDamaged(a) => ("line", Value::from(*a)),
+            Error::ParseBit(a) => ("char", Value::from(a.to_string())),
+            Error::PassphraseWordsInvalid(a) => ("positions", Value::from(a.clone())),
+            Error::QrDecodingFailed(a) => ("reason", Value::from(a.as_str())),
+            Error::QrEncodingFailed(a) => ("reason", Value::from(a.as_str())),
+            Error::RequiredShardsNotSupported(a) => ("value", Value::from(a.as_str())),
+            Error::SelfTestFailed(a) => ("check", Value::from(a.as_str())),
+            Error::SetIdInvalid(a) => ("set_id", Value::from(a.as_str())),
+            Error::ShareIdOutOfRange(a) => ("id", Value::from(*a)),
+            Error::ShareNotInSet(a) => ("id", Value::from(*a)),
+            Error::Ss58PrefixInvalid(a) => ("prefix", Value::from(*a)),
+            Error::TagInvalid(a) => ("reason", Value::from(a.as_str())),
+            Error::TooManyShares(a) => ("max", Value::from(*a)),
+            Error::WrappedShareInvalid(a, _) => ("wrappers", Value::from(a.as_str())),
+            Error::VersionNotSupported(a) => ("version", Value::from(a.as_str())),
+            _ => return Map::new(),
+        };
+        let mut context = Map::new();
+        let _ = context.insert(field.to_string(), value);
+        context
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl From<Error> for wasm_bindgen::JsValue {
+    /// JS `Error` object with `name`, `message`, `code`, and `context` properties
+    /// of [`ErrorReport`], to be thrown from the bindings instead of a string.
+    fn from(error: Error) -> Self {
+        let report = error.report();
+        let js_error = js_sys::Error::new(&report.message);
+        js_error.set_name(report.name);
+        let context = js_sys::JSON::parse(&Value::Object(report.context).to_string())
+            .unwrap_or_else(|_| js_sys::Object::new().into());
+        let _ = js_sys::Reflect::set(&js_error, &"code".into(), &report.code.into());
+        let _ = js_sys::Reflect::set(&js_error, &"context".into(), &context);
+        js_error.into()
+    }
+}
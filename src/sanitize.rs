@@ -0,0 +1,166 @@
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use zeroize::Zeroizing;
+
+use crate::error::Error;
+use crate::shares::Share;
+
+/// Longest app-specific prefix recognized before the share json.
+const MAX_PREFIX_LEN: usize = 64;
+
+/// Wrapper stripped from the scanned payload by [`sanitize_payload`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayloadWrapper {
+    /// `data:` uri, with the payload in base64 or percent-encoded.
+    DataUri,
+    /// Percent-encoded payload, as in urls.
+    PercentEncoding,
+    /// App-specific prefix before the share json, for example `bananasplit:`
+    /// or an url ending with `#`.
+    Prefix(String),
+}
+
+impl fmt::Display for PayloadWrapper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadWrapper::DataUri => write!(f, "data uri"),
+            PayloadWrapper::PercentEncoding => write!(f, "percent encoding"),
+            PayloadWrapper::Prefix(a) => write!(f, "prefix {a:?}"),
+        }
+    }
+}
+
+/// Payload with the wrappers stripped, see [`sanitize_payload`].
+pub struct SanitizedPayload {
+    /// Payload to be parsed as a share; wiped when dropped.
+    pub payload: Zeroizing<Vec<u8>>,
+    /// Wrappers stripped, outermost first; empty if the payload was not wrapped.
+    pub wrappers: Vec<PayloadWrapper>,
+}
+
+impl fmt::Debug for SanitizedPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SanitizedPayload")
+            .field("payload", &format_args!("{} bytes", self.payload.len()))
+            .field("wrappers", &self.wrappers)
+            .finish()
+    }
+}
+
+/// Strip the wrappers some scanners put around the share payload: `data:` uris,
+/// percent encoding, and app-specific prefixes, along with the surrounding whitespace.
+///
+/// Payloads that are not text, for example compressed ones, are left as they are.
+pub fn sanitize_payload(payload: &[u8]) -> SanitizedPayload {
+    let mut wrappers = Vec::new();
+    let Ok(text) = std::str::from_utf8(payload) else {
+        return SanitizedPayload {
+            payload: Zeroizing::new(payload.to_vec()),
+            wrappers,
+        };
+    };
+    let mut current = Zeroizing::new(text.trim().to_owned());
+    loop {
+        let next = if let Some(data) = strip_prefix_ignore_case(&current, "data:") {
+            let Some((meta, data)) = data.split_once(',') else {
+                break;
+            };
+            let data = percent_decode(data);
+            let decoded = if meta.to_ascii_lowercase().ends_with(";base64") {
+                BASE64.decode(data.as_bytes()).ok().map(Zeroizing::new)
+            } else {
+                Some(Zeroizing::new(data.as_bytes().to_vec()))
+            };
+            match decoded.and_then(|a| String::from_utf8(a.to_vec()).ok()) {
+                Some(a) => (PayloadWrapper::DataUri, Zeroizing::new(a)),
+                None => break,
+            }
+        } else if !current.contains('{') && current.to_ascii_lowercase().contains("%7b") {
+            (PayloadWrapper::PercentEncoding, percent_decode(&current))
+        } else if let Some(start) = prefix_len(&current) {
+            (
+                PayloadWrapper::Prefix(current[..start].to_owned()),
+                Zeroizing::new(current[start..].to_owned()),
+            )
+        } else {
+            break;
+        };
+        wrappers.push(next.0);
+        current = Zeroizing::new(next.1.trim().to_owned());
+    }
+    SanitizedPayload {
+        payload: Zeroizing::new(current.as_bytes().to_vec()),
+        wrappers,
+    }
+}
+
+impl Share {
+    /// Parse the share, same as [`Share::new`], after stripping the wrappers
+    /// with [`sanitize_payload`].
+    ///
+    /// If the unwrapped payload is still not a share, the error is reported with
+    /// the wrappers found, in [`Error::WrappedShareInvalid`].
+    pub fn new_sanitized(payload: &[u8]) -> Result<Self, Error> {
+        let sanitized = sanitize_payload(payload);
+        if sanitized.wrappers.is_empty() {
+            return Share::new(payload);
+        }
+        Share::new(&sanitized.payload[..]).map_err(|e| {
+            let wrappers = sanitized
+                .wrappers
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            Error::WrappedShareInvalid(wrappers, Box::new(e))
+        })
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    match text.get(..prefix.len()) {
+        Some(a) if a.eq_ignore_ascii_case(prefix) => Some(&text[prefix.len()..]),
+        _ => None,
+    }
+}
+
+/// Length of the app-specific prefix before the share json: short, without
+/// whitespace, and ending as a scheme, an url fragment, or a query parameter.
+fn prefix_len(text: &str) -> Option<usize> {
+    let start = text.find('{')?;
+    let prefix = &text[..start];
+    let recognized = start > 0
+        && start <= MAX_PREFIX_LEN
+        && text.ends_with('}')
+        && !prefix.contains(|a: char| a.is_whitespace() || a == '"')
+        && prefix.ends_with([':', '/', '#', '=']);
+    recognized.then_some(start)
+}
+
+/// Percent-decode the text; `+` is kept, as it is a valid base64 character.
+/// Invalid escapes are kept as they are.
+fn percent_decode(text: &str) -> Zeroizing<String> {
+    let bytes = text.as_bytes();
+    let mut out = Zeroizing::new(Vec::with_capacity(bytes.len()));
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|a| std::str::from_utf8(a).ok())
+            .and_then(|a| u8::from_str_radix(a, 16).ok());
+        match escaped {
+            Some(a) => {
+                out.push(a);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    Zeroizing::new(String::from_utf8_lossy(&out).into_owned())
+}
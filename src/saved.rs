@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use crypto_secretbox::aead::{generic_array::GenericArray, Aead, KeyInit};
+use crypto_secretbox::XSalsa20Poly1305;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::error::Error;
+use crate::kdf::KeyDerivation;
+use crate::paranoid;
+use crate::shares::{decode_nonce, decrypt_bytes, ShareSet, NONCE_LEN};
+
+/// Format of the encrypted saved set.
+const SAVED_FORMAT: u8 = 1;
+
+/// Length of the random key derivation salt of the encrypted saved set, in bytes.
+const SAVED_SALT_LEN: usize = 32;
+
+/// Encrypted saved set, see [`ShareSet::to_encrypted_bytes`]:
+/// key derivation header as in V2 shares, hex encoded salt,
+/// base64 encoded nonce and encrypted [`ShareSet::to_bytes`] output.
+#[derive(Serialize, Deserialize)]
+struct EncryptedSet {
+    format: u8,
+    kdf: serde_json::Value,
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+impl ShareSet {
+    /// Save the set into the file, encrypted with the password,
+    /// so that the collected shares are not left in the clear; see [`ShareSet::load_encrypted`].
+    pub fn save_encrypted(&self, path: impl AsRef<Path>, password: &str) -> Result<(), Error> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_encrypted_bytes(password)?)
+            .map_err(|e| Error::FileIo(format!("{}: {e}", path.display())))
+    }
+    /// Restore the set saved with [`ShareSet::save_encrypted`].
+    ///
+    /// Wrong password is reported with [`Error::DecodingFailed`], same as the damaged
+    /// encrypted data.
+    pub fn load_encrypted(path: impl AsRef<Path>, password: &str) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let saved =
+            std::fs::read(path).map_err(|e| Error::FileIo(format!("{}: {e}", path.display())))?;
+        Self::from_encrypted_bytes(&saved, password)
+    }
+    /// Same as [`ShareSet::save_encrypted`], but in memory, for the platforms
+    /// without files, for example browser storage.
+    ///
+    /// The key is derived from the password with scrypt of the default parameters,
+    /// and a random salt; the set is encrypted with XSalsa20Poly1305, same as the secret.
+    pub fn to_encrypted_bytes(&self, password: &str) -> Result<Vec<u8>, Error> {
+        let key_derivation = KeyDerivation::default();
+        let mut rng = rand::thread_rng();
+        let mut salt = [0u8; SAVED_SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.try_fill_bytes(&mut salt)
+            .map_err(|_| Error::RandomnessUnavailable)?;
+        rng.try_fill_bytes(&mut nonce)
+            .map_err(|_| Error::RandomnessUnavailable)?;
+        let key =
+            key_derivation.derive(password.as_bytes(), &salt, &CancellationToken::default())?;
+        paranoid::cipher_input(&key[..], &nonce)?;
+        let cipher = XSalsa20Poly1305::new(GenericArray::from_slice(&key[..]));
+        let data = cipher
+            .encrypt(GenericArray::from_slice(&nonce), self.to_bytes().as_slice())
+            .map_err(|_| Error::EncryptionFailed)?;
+        let saved = EncryptedSet {
+            format: SAVED_FORMAT,
+            kdf: key_derivation.to_header(),
+            salt: hex::encode(salt),
+            nonce: BASE64.encode(nonce),
+            data: BASE64.encode(data),
+        };
+        Ok(serde_json::to_vec(&saved).expect("saved set is serializable"))
+    }
+    /// Restore the set saved with [`ShareSet::to_encrypted_bytes`].
+    pub fn from_encrypted_bytes(saved: &[u8], password: &str) -> Result<Self, Error> {
+        let saved: EncryptedSet =
+            serde_json::from_slice(saved).map_err(|_| Error::SetSnapshotInvalid)?;
+        if saved.format != SAVED_FORMAT {
+            return Err(Error::SetSnapshotInvalid);
+        }
+        let kdf = json::parse(&saved.kdf.to_string()).map_err(|_| Error::SetSnapshotInvalid)?;
+        let key_derivation = KeyDerivation::from_header(&kdf)?;
+        let salt = hex::decode(&saved.salt).map_err(|_| Error::SetSnapshotInvalid)?;
+        let nonce = decode_nonce(&saved.nonce)?;
+        let data = BASE64
+            .decode(&saved.data)
+            .map_err(|_| Error::SetSnapshotInvalid)?;
+        let key =
+            key_derivation.derive(password.as_bytes(), &salt, &CancellationToken::default())?;
+        let snapshot = decrypt_bytes(&key, &nonce, &data)?;
+        Self::from_bytes(&snapshot)
+    }
+}
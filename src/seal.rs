@@ -0,0 +1,65 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use crypto_box::{PublicKey, SecretKey};
+use rand::RngCore;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::error::Error;
+
+/// Share payload sealed to the x25519 public key of a custodian
+/// (libsodium-compatible sealed box).
+///
+/// Sealed share could be sent by email or messenger without exposing
+/// the share payload in transit. After opening with the custodian secret key,
+/// the original payload is restored and could be printed as usual.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedShare(Vec<u8>);
+
+impl SealedShare {
+    /// Seal the share payload (json string from the qr code) to the recipient public key.
+    pub fn seal(share: &str, recipient_public_key: &[u8; 32]) -> Result<Self, Error> {
+        let public_key = PublicKey::from(*recipient_public_key);
+        let mut rng = rand::thread_rng();
+        public_key
+            .seal(&mut rng, share.as_bytes())
+            .map(Self)
+            .map_err(|_| Error::SealingFailed)
+    }
+    /// Open the sealed share with the recipient secret key, and get the original share payload.
+    pub fn open(&self, secret_key: &[u8; 32]) -> Result<String, Error> {
+        let secret_key = SecretKey::from(*secret_key);
+        let opened = secret_key
+            .unseal(&self.0)
+            .map_err(|_| Error::UnsealingFailed)?;
+        // opened bytes are wiped, if they are not returned
+        String::from_utf8(opened).map_err(|e| {
+            e.into_bytes().zeroize();
+            Error::NotShareString
+        })
+    }
+    /// Sealed share as raw bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+    /// Sealed share in base64 format, for text-only transports.
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(&self.0)
+    }
+    /// Read sealed share from base64 format.
+    pub fn from_base64(sealed: &str) -> Result<Self, Error> {
+        BASE64
+            .decode(sealed.trim())
+            .map(Self)
+            .map_err(|_| Error::SealedShareNotBase64)
+    }
+}
+
+/// Generate a new custodian key pair for sealing shares, as `(secret_key, public_key)`.
+pub fn custodian_keypair() -> Result<(Zeroizing<[u8; 32]>, [u8; 32]), Error> {
+    let mut secret_key = Zeroizing::new([0u8; 32]);
+    rand::thread_rng()
+        .try_fill_bytes(secret_key.as_mut())
+        .map_err(|_| Error::RandomnessUnavailable)?;
+    let public_key = SecretKey::from(*secret_key).public_key().to_bytes();
+    Ok((secret_key, public_key))
+}
@@ -0,0 +1,78 @@
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::encrypt::encrypt;
+use crate::error::Error;
+use crate::passphrase::generate;
+use crate::shares::{Share, ShareSet};
+
+// known-answer shares, made by the upstream javascript banana split
+const KNOWN_SHARE_1: &str = r#"{"v":1,"t":"Alice tries BananaSplit again","r":2,"d":"8AfltRMFZBBY02k6uErb6Ntz7XU6IWygGvFIDLBGVagT/nzSePzU0NzCnquyYu67eff4gTbgEdDUBxvqYOM2UpH2lgXTLg6gX47TliIXUMf1ub2/vurltyrwiQkVNV6PQXg==","n":"o9DbpBi9r7UWJHOriuDArR4Vrc0VOo3l"}"#;
+const KNOWN_SHARE_2: &str = r#"{"v":1,"t":"Alice tries BananaSplit again","r":2,"d":"8Au/aiJ+yCCxoqZxCCMn21/BcXgZKI51kUt+dJmox/rUEl44HQITzCpUAJ8Qh5cZ0+qUqpgUMviqawr8v6qxm9YTONcnffyBwBIi0gcKWowdcwo1fBpEkQv5wWiCXeO8Hjw==","n":"o9DbpBi9r7UWJHOriuDArR4Vrc0VOo3l"}"#;
+const KNOWN_PASSPHRASE: &str = "blighted-comprised-bucktooth-disjoin";
+const KNOWN_SECRET: &str = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+
+/// Length of random samples taken from the RNG.
+const SAMPLE_LEN: usize = 32;
+
+/// Check that the library works correctly on the current machine.
+///
+/// Intended to be run by applications at startup, especially on air-gapped machines,
+/// before trusting a freshly split backup. The checks are:
+///
+/// - random number generators (both thread-local and operating system one) are available,
+///   and produce non-constant output,
+/// - known-answer recovery of a set made by the upstream javascript banana split,
+///   exercising the key derivation, share combining, and decryption,
+/// - round trip of a random secret through [`encrypt`] and recovery.
+///
+/// The check runs the key derivation three times, and takes a few seconds.
+pub fn selftest() -> Result<(), Error> {
+    check_rng(&mut rand::thread_rng(), "thread rng")?;
+    check_rng(&mut OsRng, "os rng")?;
+    check_known_answer()?;
+    check_round_trip()
+}
+
+/// Basic sanity of the RNG output: two samples should be different,
+/// and neither should consist of a single repeated byte.
+fn check_rng<R: RngCore>(rng: &mut R, name: &str) -> Result<(), Error> {
+    let mut first = [0u8; SAMPLE_LEN];
+    let mut second = [0u8; SAMPLE_LEN];
+    for sample in [&mut first, &mut second] {
+        rng.try_fill_bytes(sample)
+            .map_err(|e| Error::SelfTestFailed(format!("{name} unavailable: {e}")))?;
+        if sample.iter().all(|x| *x == sample[0]) {
+            return Err(Error::SelfTestFailed(format!("{name} output is constant")));
+        }
+    }
+    if first == second {
+        return Err(Error::SelfTestFailed(format!("{name} output repeats")));
+    }
+    Ok(())
+}
+
+fn check_known_answer() -> Result<(), Error> {
+    let mut share_set = ShareSet::init(Share::new(KNOWN_SHARE_1)?);
+    share_set.try_add_share(Share::new(KNOWN_SHARE_2)?)?;
+    if share_set.recover_with_passphrase(KNOWN_PASSPHRASE)? != KNOWN_SECRET {
+        return Err(Error::SelfTestFailed(
+            "known answer recovery produced unexpected secret".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn check_round_trip() -> Result<(), Error> {
+    let secret = generate(8)?;
+    let passphrase = generate(4)?;
+    let shares = encrypt(&secret, "selftest", &passphrase, 3, 2)?;
+    let mut share_set = ShareSet::init(Share::new(shares[2].as_str())?);
+    share_set.try_add_share(Share::new(shares[0].as_str())?)?;
+    if share_set.recover_with_passphrase(&passphrase)? != secret {
+        return Err(Error::SelfTestFailed(
+            "round trip produced unexpected secret".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::error::Error;
+use crate::input::ShareInput;
+use crate::manifest::Manifest;
+use crate::shares::{NextAction, Share, ShareSet};
+
+/// Step of the [`RecoverySession`], for the user interface to show.
+///
+/// Serializes into json tagged by `"state"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SessionState {
+    /// Shares are being scanned; `need` is `None` until the first share is scanned.
+    Scanning {
+        /// Number of shares collected so far.
+        have: usize,
+        /// Number of shares needed for recovery.
+        need: Option<usize>,
+    },
+    /// Shares of a private split are scanned, the owner manifest is needed.
+    NeedsManifest,
+    /// Enough shares are collected, the passphrase is to be entered.
+    Ready {
+        /// Passphrase attempts left.
+        attempts_left: u32,
+    },
+    /// Secret is recovered, see [`RecoverySession::secret`].
+    Done,
+    /// All passphrase attempts failed.
+    Failed,
+}
+
+/// Full recovery flow, from scanning the shares to the recovered secret, so that
+/// all user interfaces drive the same workflow:
+/// [`SessionState::Scanning`] (or [`SessionState::NeedsManifest`]) →
+/// [`SessionState::Ready`] → [`SessionState::Done`] or [`SessionState::Failed`].
+///
+/// Each step returns the new state. Steps not allowed in the current state
+/// are refused with [`Error::SessionStepInvalid`], and failed steps leave
+/// the session as it was.
+#[derive(Debug)]
+pub struct RecoverySession {
+    set: Option<ShareSet>,
+    attempts_left: u32,
+    secret: Option<Zeroizing<String>>,
+}
+
+impl RecoverySession {
+    /// New session, allowing given number of passphrase attempts.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            set: None,
+            attempts_left: max_attempts,
+            secret: None,
+        }
+    }
+    /// Current state of the session.
+    pub fn state(&self) -> SessionState {
+        if self.secret.is_some() {
+            return SessionState::Done;
+        }
+        let Some(ref set) = self.set else {
+            return SessionState::Scanning {
+                have: 0,
+                need: None,
+            };
+        };
+        match set.next_action() {
+            NextAction::MoreShares { have, need, .. } => SessionState::Scanning {
+                have,
+                need: Some(need),
+            },
+            NextAction::AskUserForManifest => SessionState::NeedsManifest,
+            NextAction::AskUserForPassword if self.attempts_left == 0 => SessionState::Failed,
+            NextAction::AskUserForPassword => SessionState::Ready {
+                attempts_left: self.attempts_left,
+            },
+        }
+    }
+    /// Parse the scanned payload and add the share, same as [`ShareSet::try_add_share`].
+    /// Allowed while scanning, or while waiting for the manifest.
+    pub fn add_payload<'a>(&mut self, payload: impl ShareInput<'a>) -> Result<SessionState, Error> {
+        if !matches!(
+            self.state(),
+            SessionState::Scanning { .. } | SessionState::NeedsManifest
+        ) {
+            return Err(Error::SessionStepInvalid);
+        }
+        let share = Share::new(payload)?;
+        match self.set {
+            Some(ref mut set) => set.try_add_share(share)?,
+            None => self.set = Some(ShareSet::init(share)),
+        }
+        Ok(self.state())
+    }
+    /// Apply the owner manifest, same as [`ShareSet::apply_manifest`].
+    /// Allowed only while waiting for the manifest.
+    pub fn apply_manifest(&mut self, manifest: Manifest) -> Result<SessionState, Error> {
+        match (self.state(), &mut self.set) {
+            (SessionState::NeedsManifest, Some(set)) => set.apply_manifest(manifest)?,
+            _ => return Err(Error::SessionStepInvalid),
+        }
+        Ok(self.state())
+    }
+    /// Try to recover the secret with the passphrase. Allowed only when ready.
+    ///
+    /// Wrong passphrase uses up an attempt, and is reported with the new state,
+    /// [`SessionState::Ready`] with fewer attempts left, or [`SessionState::Failed`].
+    /// Other errors are passed through without using up an attempt.
+    pub fn try_passphrase(&mut self, passphrase: &str) -> Result<SessionState, Error> {
+        let (SessionState::Ready { .. }, Some(set)) = (self.state(), &self.set) else {
+            return Err(Error::SessionStepInvalid);
+        };
+        match set.recover_with_passphrase(passphrase) {
+            Ok(secret) => self.secret = Some(Zeroizing::new(secret)),
+            Err(Error::DecodingFailed | Error::WrongPassphrase) => self.attempts_left -= 1,
+            Err(e) => return Err(e),
+        }
+        Ok(self.state())
+    }
+    /// Recovered secret, once the session is done.
+    pub fn secret(&self) -> Option<&str> {
+        self.secret.as_deref().map(String::as_str)
+    }
+    /// Shares collected so far, `None` until the first share is scanned.
+    pub fn share_set(&self) -> Option<&ShareSet> {
+        self.set.as_ref()
+    }
+}
@@ -0,0 +1,84 @@
+use png::{BitDepth, ColorType, Encoder};
+use qrcode::render::svg;
+use qrcode::{Color, QrCode};
+
+use crate::encrypt::encrypt;
+use crate::error::Error;
+
+/// Size of a qr code module in png images, in pixels.
+const MODULE_PIXELS: usize = 8;
+
+/// Quiet zone around the qr code in png images, in modules.
+const QUIET_ZONE: usize = 4;
+
+/// Qr code of the share payload, as svg image.
+pub fn share_qr_svg(share: &str) -> Result<String, Error> {
+    Ok(share_qr(share)?
+        .render::<svg::Color>()
+        .min_dimensions(256, 256)
+        .build())
+}
+
+/// Qr code of the share payload, as grayscale png image, with 8 pixels per module
+/// and the quiet zone of 4 modules.
+pub fn share_qr_png(share: &str) -> Result<Vec<u8>, Error> {
+    let code = share_qr(share)?;
+    let modules = code.width();
+    let side = (modules + 2 * QUIET_ZONE) * MODULE_PIXELS;
+    let mut pixels = vec![u8::MAX; side * side];
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Light {
+            continue;
+        }
+        let top = (i / modules + QUIET_ZONE) * MODULE_PIXELS;
+        let left = (i % modules + QUIET_ZONE) * MODULE_PIXELS;
+        for row in top..top + MODULE_PIXELS {
+            pixels[row * side + left..row * side + left + MODULE_PIXELS].fill(0);
+        }
+    }
+
+    let encoding_failed = |e: png::EncodingError| Error::QrEncodingFailed(e.to_string());
+    let side = u32::try_from(side).map_err(|e| Error::QrEncodingFailed(e.to_string()))?;
+    let mut out = Vec::new();
+    let mut encoder = Encoder::new(&mut out, side, side);
+    encoder.set_color(ColorType::Grayscale);
+    encoder.set_depth(BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(encoding_failed)?;
+    writer.write_image_data(&pixels).map_err(encoding_failed)?;
+    writer.finish().map_err(encoding_failed)?;
+    Ok(out)
+}
+
+/// Encrypts a secret, same as [`encrypt`], and returns the qr codes of the shares,
+/// as svg images.
+pub fn encrypt_to_qr_svg(
+    secret: &str,
+    title: &str,
+    passphrase: &str,
+    total_shards: usize,
+    required_shards: usize,
+) -> Result<Vec<String>, Error> {
+    encrypt(secret, title, passphrase, total_shards, required_shards)?
+        .iter()
+        .map(|a| share_qr_svg(a))
+        .collect()
+}
+
+/// Encrypts a secret, same as [`encrypt`], and returns the qr codes of the shares,
+/// as png images, see [`share_qr_png`].
+pub fn encrypt_to_qr_png(
+    secret: &str,
+    title: &str,
+    passphrase: &str,
+    total_shards: usize,
+    required_shards: usize,
+) -> Result<Vec<Vec<u8>>, Error> {
+    encrypt(secret, title, passphrase, total_shards, required_shards)?
+        .iter()
+        .map(|a| share_qr_png(a))
+        .collect()
+}
+
+fn share_qr(share: &str) -> Result<QrCode, Error> {
+    QrCode::new(share.as_bytes()).map_err(|e| Error::QrEncodingFailed(e.to_string()))
+}
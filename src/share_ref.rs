@@ -0,0 +1,362 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::borrow::Cow;
+
+use crate::deflate::DEFLATE_MAGIC;
+use crate::error::Error;
+use crate::kdf::KeyDerivation;
+use crate::shares::{Share, BIT_RANGE};
+use crate::title::unescape_title;
+
+/// Share borrowing its data from the scanned payload, see [`ShareRef::parse`].
+///
+/// Title, nonce, and share data are slices of the caller's payload buffer,
+/// nothing is allocated for them. Share content is decoded only on request,
+/// into a buffer provided by the caller, see [`ShareRef::content_into`].
+/// Intended for high-throughput scanners and memory-constrained devices;
+/// [`ShareRef::to_share`] makes an owned [`Share`] when needed.
+#[derive(Debug, Clone, Copy)]
+pub struct ShareRef<'a> {
+    payload: &'a [u8],
+    version: Option<u32>,
+    title: Option<Field<'a>>,
+    required_shards: Option<usize>,
+    nonce: &'a str,
+    bits: u32,
+    id: u32,
+    body: &'a str,
+}
+
+/// Raw json value, as it is in the payload.
+#[derive(Debug, Clone, Copy)]
+enum Field<'a> {
+    /// Contents of a string, between the quotes, possibly with escapes.
+    Str(&'a str),
+    /// Any other value: number, literal, object, or array.
+    Raw(&'a str),
+}
+
+impl<'a> Field<'a> {
+    /// Value as a string; same as `json` crate would display it, except for objects and arrays.
+    fn text(&self) -> Cow<'a, str> {
+        match *self {
+            Field::Str(a) if a.contains('\\') => match unescape_title(a) {
+                Ok(b) => Cow::Owned(b),
+                Err(_) => Cow::Borrowed(a),
+            },
+            Field::Str(a) | Field::Raw(a) => Cow::Borrowed(a),
+        }
+    }
+    fn raw(&self) -> &'a str {
+        match *self {
+            Field::Str(a) | Field::Raw(a) => a,
+        }
+    }
+}
+
+impl<'a> ShareRef<'a> {
+    /// Parse the share payload, as scanned from the qr code, without copying.
+    ///
+    /// Checks are the same as in [`Share::new`], except that the share content is not decoded.
+    /// Compressed payloads could not be borrowed from, and are rejected.
+    pub fn parse(payload: &'a [u8]) -> Result<Self, Error> {
+        if payload.starts_with(DEFLATE_MAGIC) {
+            return Err(Error::CompressedShareBorrowed);
+        }
+        let text = std::str::from_utf8(payload).map_err(|_| Error::NotShareString)?;
+        let mut fields = Fields::default();
+        Scanner { text, pos: 0 }.object(&mut fields)?;
+
+        let version = match fields.v {
+            None => None,
+            Some(Field::Raw("1")) => Some(1),
+            Some(Field::Raw("2")) => Some(2),
+            Some(a) => return Err(Error::VersionNotSupported(a.text().into_owned())),
+        };
+        let v2 = version == Some(2);
+        // only V2 shares could omit title and number of required shards
+        let title = match fields.t {
+            None if v2 => None,
+            None => Some(Field::Raw("null")),
+            a => a,
+        };
+        let required_shards = match fields.r {
+            Some(Field::Raw(a)) => match a.parse::<usize>() {
+                Ok(b) => Some(b),
+                Err(_) => return Err(Error::RequiredShardsNotSupported(a.to_string())),
+            },
+            None if v2 => None,
+            a => {
+                let value = a.map_or(Cow::Borrowed("null"), |b| b.text());
+                return Err(Error::RequiredShardsNotSupported(value.into_owned()));
+            }
+        };
+        if v2 {
+            if let Some(k) = fields.k {
+                let _ = KeyDerivation::from_header(
+                    &json::parse(k.raw()).map_err(|_| Error::JsonParsing)?,
+                )?;
+            }
+        }
+        let nonce = fields.n.map_or("null", |a| a.raw());
+        let data = fields.d.map_or("null", |a| a.raw());
+
+        // first share char is bits info in radix36 format
+        let mut chars = data.chars();
+        let bits = match chars.next() {
+            Some(a) => match a.to_digit(36) {
+                Some(b) if BIT_RANGE.contains(&b) => b,
+                Some(b) => return Err(Error::BitsOutOfRange(b)),
+                None => return Err(Error::ParseBit(a)),
+            },
+            None => return Err(Error::EmptyShare),
+        };
+        let body = chars.as_str();
+
+        // share id is read from the start of the body, up to 3 bytes
+        let max = 2u32.pow(bits) - 1;
+        let id_length = max.to_be_bytes().iter().skip_while(|x| x == &&0).count();
+        let mut id_bytes = [0u8; 3];
+        match version {
+            None => {
+                let id_hex = body.get(..2 * id_length).ok_or(Error::ShareTooShort)?;
+                hex::decode_to_slice(id_hex, &mut id_bytes[..id_length])
+                    .map_err(|_| Error::UndefinedBodyNotHex)?;
+            }
+            Some(_) => {
+                // 4 base64 chars always carry 3 whole bytes
+                let prefix = body.get(..4).ok_or(Error::ShareTooShort)?;
+                let _ = BASE64
+                    .decode_slice(prefix, &mut id_bytes)
+                    .map_err(|_| Error::BodyNotBase64)?;
+            }
+        }
+        let id = id_bytes[..id_length]
+            .iter()
+            .fold(0u32, |id, byte| (id << 8) | *byte as u32);
+        if id == 0 || id > max {
+            return Err(Error::ShareIdOutOfRange(id));
+        }
+
+        Ok(Self {
+            payload,
+            version,
+            title,
+            required_shards,
+            nonce,
+            bits,
+            id,
+            body,
+        })
+    }
+    /// Share title; empty for shares of private split.
+    /// Borrowed from the payload, unless the title has json escapes.
+    pub fn title(&self) -> Cow<'a, str> {
+        self.title.map_or(Cow::Borrowed(""), |a| a.text())
+    }
+    /// Number of required shards; 0 if the share does not record it.
+    pub fn required_shards(&self) -> usize {
+        self.required_shards.unwrap_or_default()
+    }
+    /// Share is from a private split, see [`Share::is_private`].
+    pub fn is_private(&self) -> bool {
+        self.title.is_none() || self.required_shards.is_none()
+    }
+    /// Nonce, as recorded in the share.
+    pub fn nonce(&self) -> &'a str {
+        self.nonce
+    }
+    /// Bits value, i.e. n in GF(2^n).
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+    /// Share id.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    /// Length of the buffer that always fits the decoded share body,
+    /// see [`ShareRef::content_into`].
+    pub fn body_len(&self) -> usize {
+        match self.version {
+            None => self.body.len() / 2,
+            Some(_) => base64::decoded_len_estimate(self.body.len()),
+        }
+    }
+    /// Decode the share content, without id, into the beginning of `out`, and return its length.
+    ///
+    /// Buffer must fit the whole decoded share body, i.e. content and id, see [`ShareRef::body_len`].
+    /// Nothing is allocated, and the same buffer could be reused for many shares;
+    /// wiping it afterwards is up to the caller, for example with `zeroize::Zeroizing`.
+    pub fn content_into(&self, out: &mut [u8]) -> Result<usize, Error> {
+        let id_length = (2u32.pow(self.bits) - 1)
+            .to_be_bytes()
+            .iter()
+            .skip_while(|x| x == &&0)
+            .count();
+        let len = match self.version {
+            None => {
+                let len = self.body.len() / 2;
+                let out = out.get_mut(..len).ok_or(Error::BufferTooSmall(len))?;
+                hex::decode_to_slice(self.body, out).map_err(|_| Error::UndefinedBodyNotHex)?;
+                len
+            }
+            Some(_) => {
+                let needed = self.body_len();
+                BASE64.decode_slice(self.body, out).map_err(|e| match e {
+                    base64::DecodeSliceError::OutputSliceTooSmall => Error::BufferTooSmall(needed),
+                    base64::DecodeSliceError::DecodeError(_) => Error::BodyNotBase64,
+                })?
+            }
+        };
+        if len < id_length {
+            return Err(Error::ShareTooShort);
+        }
+        out.copy_within(id_length..len, 0);
+        Ok(len - id_length)
+    }
+    /// Make an owned [`Share`], with the full checks of [`Share::new`].
+    pub fn to_share(&self) -> Result<Share, Error> {
+        Share::new(self.payload)
+    }
+}
+
+/// Share fields found in the payload object.
+#[derive(Default)]
+struct Fields<'a> {
+    v: Option<Field<'a>>,
+    t: Option<Field<'a>>,
+    r: Option<Field<'a>>,
+    d: Option<Field<'a>>,
+    n: Option<Field<'a>>,
+    k: Option<Field<'a>>,
+}
+
+/// Minimal json scanner, finding the values of the top-level object without copying.
+/// Nested values are only skipped over; they are checked with `json` crate if needed.
+struct Scanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        self.skip_whitespace();
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(Error::JsonParsing)
+        }
+    }
+    /// Top-level object; the rest of the text must be whitespace only.
+    fn object(&mut self, fields: &mut Fields<'a>) -> Result<(), Error> {
+        self.expect(b'{')?;
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+        } else {
+            loop {
+                self.skip_whitespace();
+                let key = self.string()?;
+                self.expect(b':')?;
+                let value = self.value()?;
+                let slot = match key {
+                    "v" => &mut fields.v,
+                    "t" => &mut fields.t,
+                    "r" => &mut fields.r,
+                    "d" => &mut fields.d,
+                    "n" => &mut fields.n,
+                    "k" => &mut fields.k,
+                    _ => &mut None,
+                };
+                // null is the same as missing field
+                if !matches!(value, Field::Raw("null")) {
+                    *slot = Some(value);
+                }
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(b',') => self.pos += 1,
+                    Some(b'}') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return Err(Error::JsonParsing),
+                }
+            }
+        }
+        self.skip_whitespace();
+        if self.pos == self.text.len() {
+            Ok(())
+        } else {
+            Err(Error::JsonParsing)
+        }
+    }
+    /// String contents between the quotes, escapes are kept as is.
+    fn string(&mut self) -> Result<&'a str, Error> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 2,
+                Some(_) => self.pos += 1,
+                None => return Err(Error::JsonParsing),
+            }
+        }
+        let string = self.text.get(start..self.pos).ok_or(Error::JsonParsing)?;
+        self.pos += 1;
+        Ok(string)
+    }
+    /// Any value; nested objects and arrays are skipped over by bracket depth.
+    fn value(&mut self) -> Result<Field<'a>, Error> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.peek() {
+            Some(b'"') => return self.string().map(Field::Str),
+            Some(b'{' | b'[') => {
+                let mut depth = 0usize;
+                loop {
+                    match self.peek() {
+                        Some(b'{' | b'[') => {
+                            depth += 1;
+                            self.pos += 1
+                        }
+                        Some(b'}' | b']') => {
+                            depth -= 1;
+                            self.pos += 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        Some(b'"') => {
+                            let _ = self.string()?;
+                        }
+                        Some(_) => self.pos += 1,
+                        None => return Err(Error::JsonParsing),
+                    }
+                }
+            }
+            Some(_) => {
+                while let Some(a) = self.peek() {
+                    if matches!(a, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r') {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+            }
+            None => return Err(Error::JsonParsing),
+        }
+        match self.text.get(start..self.pos) {
+            Some(a) if !a.is_empty() => Ok(Field::Raw(a)),
+            _ => Err(Error::JsonParsing),
+        }
+    }
+}
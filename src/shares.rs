@@ -1,19 +1,91 @@
 use base64::Engine;
-use bitvec::prelude::*;
 use crypto_secretbox::aead::{generic_array::GenericArray, Aead, KeyInit};
 use crypto_secretbox::XSalsa20Poly1305;
-use scrypt::{scrypt, Params};
-use std::convert::TryInto;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use std::fmt;
 use std::ops::RangeInclusive;
-use zeroize::Zeroize;
+use std::str::FromStr;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
-use crate::encrypt::hash_string;
+use crate::encrypt::{encrypt_with_options, EncryptOptions};
 use base64::engine::general_purpose::STANDARD as BASE64;
 
-use crate::error::Error;
+use crate::cancel::CancellationToken;
+use crate::chunked::ChunkedRecovery;
+use crate::combine::{
+    combine_bounded, combine_output_len, combine_table_free_bounded, combine_table_len, GfStrategy,
+};
+use crate::deflate::{inflate, DEFLATE_MAGIC};
+use crate::error::{Error, Warning};
+use crate::input::{Payload, ShareInput};
+use crate::integrity::{
+    check_key, check_set_id, decode_field, set_mac, share_checksum, SetParams, CHECKSUM_LEN,
+    COMMITMENT_LEN, SET_KEY_LEN, SET_MAC_LEN,
+};
+use crate::kdf::KeyDerivation;
+use crate::manifest::Manifest;
+use crate::metrics::{Metric, MetricsHook};
+use crate::paranoid;
+use crate::stats::ScanStats;
+use crate::title::{derivation_salt, parse_json};
+use crate::trace::{trace_event, trace_span};
+use crate::validity::Validity;
+
+/// Smallest supported bits value, i.e. n in GF(2^n).
+pub const MIN_BITS: u32 = 3;
+
+/// Largest supported bits value, i.e. n in GF(2^n).
+pub const MAX_BITS: u32 = 20;
 
 /// To be valid character, the bits must be within certain bounds.
-pub(crate) const BIT_RANGE: RangeInclusive<u32> = 3..=20;
+pub(crate) const BIT_RANGE: RangeInclusive<u32> = MIN_BITS..=MAX_BITS;
+
+/// Supported bits values, i.e. n in GF(2^n), for shares to be parsed and combined.
+///
+/// Shares made by this crate always have bits 8, see [`max_shares`].
+pub fn supported_bits() -> RangeInclusive<u32> {
+    BIT_RANGE
+}
+
+/// Largest number of shares in a set with given bits, `2^bits - 1`;
+/// `None` for bits outside of [`supported_bits`].
+///
+/// Shares made by this crate have bits 8, so at most `max_shares(8)`, i.e. 255,
+/// shares could be made at once.
+pub fn max_shares(bits: u32) -> Option<u32> {
+    BIT_RANGE.contains(&bits).then(|| (1 << bits) - 1)
+}
+
+/// Length of XSalsa20Poly1305 nonce, in bytes.
+pub(crate) const NONCE_LEN: usize = 24;
+
+/// Length of Poly1305 authentication tag, added to the secret by encryption.
+pub(crate) const TAG_LEN: usize = 16;
+
+/// Largest number of padding bits, marker included, in front of the encrypted secret:
+/// js code and this crate by default pad to a multiple of 128 bits,
+/// compact padding of this crate stays within 64 bits.
+const MAX_PADDING_BITS: usize = 128;
+
+/// Placeholder for the redacted fields in `Debug` output.
+const REDACTED: &str = "<redacted>";
+
+/// Number of hash bytes kept in the set fingerprint.
+const FINGERPRINT_LEN: usize = 4;
+
+/// Allowed lengths of the salt override, in bytes.
+pub(crate) const SALT_LEN: RangeInclusive<usize> = 16..=64;
+
+/// Largest number of share subsets combined for the majority vote,
+/// see [`ShareSet::set_error_correction`].
+pub const MAX_VOTE_SUBSETS: usize = 32;
+
+/// Largest number of tags per share.
+pub(crate) const MAX_TAGS: usize = 8;
+
+/// Largest tag length, in bytes.
+pub(crate) const MAX_TAG_LEN: usize = 32;
 
 /// Struct to store information about individual share.
 /// `Share` information is decoded from the incoming share only.
@@ -21,48 +93,170 @@ pub(crate) const BIT_RANGE: RangeInclusive<u32> = 3..=20;
 /// this is always checked during share generation.
 /// Share contains certain things that should better remain secret,
 /// specifically content, nonce, and title, however nothing could be done with them unless
-/// the passphrase is also known
-#[derive(Debug)]
+/// the passphrase is also known.
+/// Shares of a private split (V2 only) have title and possibly
+/// the number of required shards omitted, these are then known only from the [`Manifest`].
+///
+/// Neither `Debug` nor `Display` print the title, nonce, or content,
+/// so that the shares could be safely logged.
 pub struct Share {
     version: Version,
-    title: String,
-    required_shards: usize,
+    title: Option<String>,
+    required_shards: Option<usize>,
     nonce: String,
     bits: u32,
     id: u32,
-    content: Vec<u8>,
+    content: Zeroizing<Vec<u8>>,
+    key_derivation: KeyDerivation,
+    key_commitment: Option<[u8; COMMITMENT_LEN]>,
+    checksum: Option<[u8; CHECKSUM_LEN]>,
+    tags: Vec<String>,
+    validity: Validity,
+    set_key: Option<[u8; SET_KEY_LEN]>,
+    set_id: Option<String>,
+    salt: Option<Vec<u8>>,
+}
+
+/// Fields that differ between two shares, see [`Share::diff`].
+///
+/// Only tells which fields differ, and never the values, so that it could be
+/// shown by support tooling. Different title means a different backup;
+/// same title with different nonce means a different generation of the same backup;
+/// same id with everything but the content same means a damaged copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ShareDiff {
+    /// Version differs.
+    pub version: bool,
+    /// Title differs.
+    pub title: bool,
+    /// Number of required shards differs.
+    pub required_shards: bool,
+    /// Key derivation parameters differ.
+    pub key_derivation: bool,
+    /// Key commitment differs.
+    pub key_commitment: bool,
+    /// Nonce differs.
+    pub nonce: bool,
+    /// Bits setting differs.
+    pub bits: bool,
+    /// Share id differs.
+    pub id: bool,
+    /// Content length differs.
+    pub content_length: bool,
+    /// Content differs.
+    pub content: bool,
+    /// Application tags differ; tags are per share, and do not tell the splits apart.
+    pub tags: bool,
+    /// Validity period differs.
+    pub validity: bool,
+    /// Set key differs, i.e. shares are from different splits.
+    pub set_key: bool,
+    /// Set identifier differs, i.e. shares are from different splits.
+    pub set_id: bool,
+    /// Salt override differs.
+    pub salt: bool,
+}
+
+impl ShareDiff {
+    /// Shares are the same in all fields.
+    pub fn is_identical(&self) -> bool {
+        *self == ShareDiff::default()
+    }
+    /// Shares could be from the same split, i.e. all set-level fields are the same.
+    pub fn is_same_split(&self) -> bool {
+        !(self.version
+            || self.title
+            || self.required_shards
+            || self.key_derivation
+            || self.key_commitment
+            || self.nonce
+            || self.bits
+            || self.content_length
+            || self.validity
+            || self.set_key
+            || self.set_id
+            || self.salt)
+    }
+    /// Shares have the same id in the same split, but different content,
+    /// i.e. one of them is likely damaged.
+    pub fn is_damaged_copy(&self) -> bool {
+        self.is_same_split() && !self.id && self.content
+    }
 }
 
 /// Version of banana split
-/// currently only V1 exists, no version in json results in Undefined variant;
+/// currently V1 and V2 exist, no version in json results in Undefined variant;
+/// V2 is same as V1, but additionally records key derivation parameters;
 /// other versions are not supported and rejected;
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[non_exhaustive]
 pub enum Version {
     Undefined,
     V1,
+    V2,
+}
+
+impl Version {
+    /// Numeric tag of the version, as it is (or would be) recorded in share json.
+    fn tag(&self) -> u8 {
+        match self {
+            Version::Undefined => 0,
+            Version::V1 => 1,
+            Version::V2 => 2,
+        }
+    }
+}
+
+impl FromStr for Share {
+    type Err = Error;
+
+    fn from_str(share: &str) -> Result<Self, Error> {
+        Self::from_json_str(share)
+    }
 }
 
 impl Share {
-    /// Incoming new share is received as decoded qr code, in Vec<u8> format
-    /// without QR header and padding
-    pub fn new(share_vec: Vec<u8>) -> Result<Self, Error> {
-        // transforming into String
-        let share_string = match String::from_utf8(share_vec) {
-            Ok(a) => a,
-            Err(_) => return Err(Error::NotShareString),
+    /// Incoming new share is received as decoded qr code, without QR header and padding,
+    /// in any of [`ShareInput`] forms.
+    pub fn new<'a>(share: impl ShareInput<'a>) -> Result<Self, Error> {
+        let share = Self::parse_unverified(share)?;
+        match share.checksum {
+            Some(checksum) if share_checksum(share.id, &share.content) != checksum => {
+                Err(Error::ShareChecksumMismatch)
+            }
+            _ => Ok(share),
+        }
+    }
+    /// Share from json text already at hand, for example pasted from a file
+    /// or clipboard; same as [`Share::new`] with `&str`, and as [`str::parse`].
+    pub fn from_json_str(share: &str) -> Result<Self, Error> {
+        Self::new(share)
+    }
+    /// Parse the share without checking the content against the checksum,
+    /// for repair of damaged shares.
+    pub(crate) fn parse_unverified<'a>(share: impl ShareInput<'a>) -> Result<Self, Error> {
+        let payload = Payload::new(share);
+        trace_span!("share_parse", len = payload.len());
+        // compressed payloads are detected by the marker, and decompressed first
+        let inflated;
+        let share_bytes = if payload.starts_with(DEFLATE_MAGIC) {
+            inflated = Zeroizing::new(inflate(&payload[DEFLATE_MAGIC.len()..])?);
+            &inflated[..]
+        } else {
+            &payload[..]
         };
 
-        // parsing the string with json
-        let share_string_parsed = match json::parse(&share_string) {
-            Ok(a) => a,
-            Err(_) => return Err(Error::JsonParsing),
-        };
+        let share_string = std::str::from_utf8(share_bytes).map_err(|_| Error::NotShareString)?;
+
+        // parsing the string with json, same way as the web app does
+        let share_string_parsed = parse_json(share_string)?;
 
         let version = match &share_string_parsed["v"] {
             json::JsonValue::Number(a) => {
                 if a == &json::number::Number::from(1u32) {
                     Version::V1
+                } else if a == &json::number::Number::from(2u32) {
+                    Version::V2
                 } else {
                     return Err(Error::VersionNotSupported(a.to_string()));
                 }
@@ -70,21 +264,32 @@ impl Share {
             json::JsonValue::Null => Version::Undefined,
             a => return Err(Error::VersionNotSupported(a.to_string())),
         };
-        let title = share_string_parsed["t"].to_string();
-        let required_shards = match &share_string_parsed["r"] {
-            json::JsonValue::Number(a) => match a.to_string().parse::<usize>() {
-                Ok(b) => b,
+        // only V2 shares could omit title and number of required shards
+        let title = match (&version, &share_string_parsed["t"]) {
+            (Version::V2, json::JsonValue::Null) => None,
+            (_, a) => Some(a.to_string()),
+        };
+        let required_shards = match (&version, &share_string_parsed["r"]) {
+            (_, json::JsonValue::Number(a)) => match a.to_string().parse::<usize>() {
+                Ok(b) => Some(b),
                 Err(_) => return Err(Error::RequiredShardsNotSupported(a.to_string())),
             },
-            a => return Err(Error::RequiredShardsNotSupported(a.to_string())),
+            (Version::V2, json::JsonValue::Null) => None,
+            (_, a) => return Err(Error::RequiredShardsNotSupported(a.to_string())),
         };
         let nonce = share_string_parsed["n"].to_string();
-        let data = share_string_parsed["d"].to_string();
+        let data = Zeroizing::new(share_string_parsed["d"].to_string());
+
+        // only V2 records key derivation, earlier versions use the default one
+        let key_derivation = match version {
+            Version::V2 => KeyDerivation::from_header(&share_string_parsed["k"])?,
+            _ => KeyDerivation::default(),
+        };
 
         // process the share data
-        let share_chars: Vec<char> = data.chars().collect();
+        let mut share_chars = data.chars();
         // first share char is bits info in radix36 format
-        let bits = match share_chars.first() {
+        let bits = match share_chars.next() {
             Some(a) => match a.to_digit(36) {
                 Some(b) => {
                     // checking if bits value is within allowed limits
@@ -94,22 +299,29 @@ impl Share {
                         return Err(Error::BitsOutOfRange(b));
                     }
                 }
-                None => return Err(Error::ParseBit(*a)),
+                None => return Err(Error::ParseBit(a)),
             },
             None => return Err(Error::EmptyShare),
         };
         // remaining piece is the share body;
         // is treated depending on the version;
-        let share_body = match version {
-            Version::Undefined => match hex::decode(String::from_iter(&share_chars[1..])) {
-                Ok(a) => a,
-                Err(_) => return Err(Error::UndefinedBodyNotHex),
-            },
-            Version::V1 => match BASE64.decode(String::from_iter(&share_chars[1..]).into_bytes()) {
-                Ok(a) => a,
-                Err(_) => return Err(Error::BodyNotBase64),
-            },
-        };
+        // decoded directly into the buffer that becomes the share content,
+        // so that no unwiped copies of the body are left behind
+        let body = share_chars.as_str();
+        let mut share_body = Zeroizing::new(Vec::new());
+        match version {
+            Version::Undefined => {
+                share_body.resize(body.len() / 2, 0);
+                if hex::decode_to_slice(body, &mut share_body).is_err() {
+                    return Err(Error::UndefinedBodyNotHex);
+                }
+            }
+            Version::V1 | Version::V2 => {
+                if BASE64.decode_vec(body, &mut share_body).is_err() {
+                    return Err(Error::BodyNotBase64);
+                }
+            }
+        }
 
         // maximum possible number of shares, u32
         let max = 2u32.pow(bits) - 1; // do not allow bits exceed 20; 2^n with n 20 or below always fits in u32 limits
@@ -119,24 +331,95 @@ impl Share {
         // starting zeroes are removed in length calculation
         let id_length = max.to_be_bytes().iter().skip_while(|x| x == &&0).count();
 
-        // identifier piece (short Vec<u8>) and share content (Vec<u8>) separated
-        let (identifier_piece, content) = match share_body.get(..id_length) {
-            Some(a) => (a.to_vec(), share_body[id_length..].to_vec()),
+        // current share id, u32, from the identifier piece at the start of the body;
+        // identifier piece is padded from the left with zeroes to 4 bytes
+        let id = match share_body.get(..id_length) {
+            Some(a) => a.iter().fold(0u32, |id, byte| (id << 8) | *byte as u32),
             None => return Err(Error::ShareTooShort),
         };
+        // the rest of the body is the share content, shifted in place
+        let content_len = share_body.len() - id_length;
+        share_body.copy_within(id_length.., 0);
+        share_body.truncate(content_len);
+        let content = share_body;
+
+        // share numbering starts from 1, and could not exceed the number of field elements
+        if id == 0 || id > max {
+            return Err(Error::ShareIdOutOfRange(id));
+        }
+        paranoid::content(&content)?;
 
-        // current share id, u32
-        let id = u32::from_be_bytes(
-            [
-                max.to_be_bytes()[..4 - id_length].to_vec(),
-                identifier_piece,
-            ]
-            .concat()
-            .try_into()
-            .expect("fixed length of 4"),
+        // only V2 shares could record the checksum and the key commitment
+        let key_commitment = match (&version, &share_string_parsed["m"]) {
+            (Version::V2, json::JsonValue::Null) | (Version::Undefined | Version::V1, _) => None,
+            (Version::V2, a) => Some(decode_field(&a.to_string(), Error::KeyCommitmentInvalid)?),
+        };
+        let checksum = match (&version, &share_string_parsed["c"]) {
+            (Version::V2, json::JsonValue::Null) | (Version::Undefined | Version::V1, _) => None,
+            (Version::V2, a) => Some(decode_field(&a.to_string(), Error::ShareChecksumMismatch)?),
+        };
+        // as well as the application tags
+        let tags = match (&version, &share_string_parsed["g"]) {
+            (Version::V2, json::JsonValue::Null) | (Version::Undefined | Version::V1, _) => {
+                Vec::new()
+            }
+            (Version::V2, json::JsonValue::Array(a)) if a.len() <= MAX_TAGS => {
+                let tags = a
+                    .iter()
+                    .map(|b| b.as_str().map(String::from))
+                    .collect::<Option<Vec<String>>>()
+                    .ok_or(Error::TagInvalid("tag is not a string".to_string()))?;
+                check_tag_values(&tags)?;
+                tags
+            }
+            (Version::V2, a) => return Err(Error::TagInvalid(a.to_string())),
+        };
+        // and the validity period
+        let validity = match version {
+            Version::V2 => {
+                Validity::from_fields(&share_string_parsed["b"], &share_string_parsed["e"])?
+            }
+            _ => Validity::default(),
+        };
+        // and the set identifier
+        let set_id = match (&version, &share_string_parsed["u"]) {
+            (Version::V2, json::JsonValue::Null) | (Version::Undefined | Version::V1, _) => None,
+            (Version::V2, a) => {
+                let set_id = a.to_string();
+                check_set_id(&set_id)?;
+                Some(set_id)
+            }
+        };
+        // and the salt override
+        let salt = match (&version, &share_string_parsed["s"]) {
+            (Version::V2, json::JsonValue::Null) | (Version::Undefined | Version::V1, _) => None,
+            (Version::V2, a) => match BASE64.decode(a.to_string()) {
+                Ok(salt) if SALT_LEN.contains(&salt.len()) => Some(salt),
+                _ => return Err(Error::SaltInvalid),
+            },
+        };
+        // and the set key, with the set mac
+        let set_key = match (
+            &version,
+            &share_string_parsed["i"],
+            &share_string_parsed["h"],
+        ) {
+            (Version::V2, json::JsonValue::Null, json::JsonValue::Null)
+            | (Version::Undefined | Version::V1, _, _) => None,
+            (Version::V2, key, mac) => Some((
+                decode_field::<SET_KEY_LEN>(&key.to_string(), Error::SetMacMismatch)?,
+                decode_field::<SET_MAC_LEN>(&mac.to_string(), Error::SetMacMismatch)?,
+            )),
+        };
+        trace_event!(
+            version = version.tag(),
+            bits,
+            id,
+            content_len = content.len(),
+            "share parsed"
         );
 
-        Ok(Share {
+        let share = Share {
             version,
             title,
             required_shards,
@@ -144,15 +427,188 @@ impl Share {
             bits,
             id,
             content,
-        })
+            key_derivation,
+            key_commitment,
+            checksum,
+            tags,
+            validity,
+            set_key: set_key.map(|(key, _)| key),
+            set_id,
+            salt,
+        };
+        // set parameters must be the ones the set mac was made for
+        if let Some((key, mac)) = set_key {
+            if set_mac(&key, &share.set_params()) != mac {
+                return Err(Error::SetMacMismatch);
+            }
+        }
+        Ok(share)
+    }
+    /// Set-level parameters, as authenticated by the set mac.
+    fn set_params(&self) -> SetParams<'_> {
+        SetParams {
+            version: self.version.tag(),
+            title: self.title.as_deref(),
+            required_shards: self.required_shards,
+            nonce: &self.nonce,
+            key_derivation: self.key_derivation,
+            key_commitment: self.key_commitment.as_ref(),
+            validity: self.validity,
+            bits: self.bits,
+            set_id: self.set_id.as_deref(),
+            salt: self.salt.as_deref(),
+        }
+    }
+    /// Key derivation salt recorded in the share, instead of the title salt.
+    pub(crate) fn salt_override(&self) -> Option<&[u8]> {
+        self.salt.as_deref()
+    }
+    /// Checksum recorded in the share, if any.
+    pub(crate) fn checksum(&self) -> Option<&[u8; CHECKSUM_LEN]> {
+        self.checksum.as_ref()
     }
-    /// Function to print share title into user interface
+    /// Share content, without id.
+    pub(crate) fn content(&self) -> &[u8] {
+        &self.content
+    }
+    /// Share number, as printed on the share, starting from 1.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    /// Bits value, i.e. n in GF(2^n).
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+    /// Format version of the share, as recorded in share `"v"` field;
+    /// `None` for legacy share without version.
+    pub fn version(&self) -> Option<u32> {
+        match self.version.tag() {
+            0 => None,
+            a => Some(a as u32),
+        }
+    }
+    /// Length of the share content, in bytes, without id.
+    /// All shares of the same split have the same content length.
+    pub fn content_len(&self) -> usize {
+        self.content.len()
+    }
+    /// Nonce, as recorded in the share.
+    pub(crate) fn nonce(&self) -> &str {
+        &self.nonce
+    }
+    /// Title, if the share records it.
+    pub(crate) fn recorded_title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+    /// Key derivation and key commitment of the share.
+    pub(crate) fn key(&self) -> (&KeyDerivation, Option<&[u8; COMMITMENT_LEN]>) {
+        (&self.key_derivation, self.key_commitment.as_ref())
+    }
+    /// Function to print share title into user interface;
+    /// empty for shares of private split
     pub fn title(&self) -> String {
-        self.title.to_owned()
+        self.title.to_owned().unwrap_or_default()
     }
-    /// Get the number of required shards
+    /// Get the number of required shards;
+    /// 0 if the share does not record it
     pub fn required_shards(&self) -> usize {
-        self.required_shards
+        self.required_shards.unwrap_or_default()
+    }
+    /// Application tags of the share, for example its storage location,
+    /// see [`EncryptOptions::share_tags`]; empty if there are none.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+    /// Validity period of the set, see [`Validity`].
+    pub fn validity(&self) -> Validity {
+        self.validity
+    }
+    /// Random identifier of the split, a uuid, if the share records it,
+    /// see [`EncryptOptions::set_id`].
+    pub fn set_id(&self) -> Option<&str> {
+        self.set_id.as_deref()
+    }
+    /// Share is from a private split, and the recovery needs the [`Manifest`].
+    pub fn is_private(&self) -> bool {
+        self.title.is_none() || self.required_shards.is_none()
+    }
+    /// Non-fatal problems with the share, to be shown to the user.
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = key_derivation_warnings(&self.key_derivation);
+        warnings.extend(self.validity.warnings());
+        warnings
+    }
+    /// Which fields differ between the two shares, see [`ShareDiff`].
+    pub fn diff(&self, other: &Share) -> ShareDiff {
+        ShareDiff {
+            version: self.version != other.version,
+            title: self.title != other.title,
+            required_shards: self.required_shards != other.required_shards,
+            key_derivation: self.key_derivation != other.key_derivation,
+            key_commitment: self.key_commitment != other.key_commitment,
+            nonce: self.nonce != other.nonce,
+            bits: self.bits != other.bits,
+            id: self.id != other.id,
+            content_length: self.content.len() != other.content.len(),
+            content: self.content != other.content,
+            tags: self.tags != other.tags,
+            validity: self.validity != other.validity,
+            set_key: self.set_key != other.set_key,
+            set_id: self.set_id != other.set_id,
+            salt: self.salt != other.salt,
+        }
+    }
+    /// Set-level fingerprint of the share, see [`ShareSet::fingerprint`].
+    /// All shares of the same split have the same fingerprint.
+    pub fn fingerprint(&self) -> String {
+        fingerprint(
+            &self.version,
+            self.title.as_deref().unwrap_or_default(),
+            self.required_shards.unwrap_or_default(),
+            self.bits,
+            &self.nonce,
+        )
+    }
+}
+
+impl fmt::Debug for Share {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Share")
+            .field("version", &self.version)
+            .field("title", &self.title.as_ref().map(|_| REDACTED))
+            .field("required_shards", &self.required_shards)
+            .field("nonce", &REDACTED)
+            .field("bits", &self.bits)
+            .field("id", &self.id)
+            .field("content_len", &self.content.len())
+            .field("key_derivation", &self.key_derivation)
+            .field("key_commitment", &self.key_commitment.is_some())
+            .field("tags", &self.tags)
+            .field("validity", &self.validity)
+            .field("set_key", &self.set_key.is_some())
+            .field("set_id", &self.set_id)
+            .field("salt", &self.salt.is_some())
+            .finish()
+    }
+}
+
+/// Safe one-line summary, for logs and user interface,
+/// for example `V1 share 2, bits 8, set 3fa0-91c2, content 96 bytes`.
+impl fmt::Display for Share {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let version = match self.version {
+            Version::Undefined => "Legacy",
+            Version::V1 => "V1",
+            Version::V2 => "V2",
+        };
+        write!(
+            f,
+            "{version} share {}, bits {}, set {}, content {} bytes",
+            self.id,
+            self.bits,
+            self.fingerprint(),
+            self.content.len()
+        )
     }
 }
 
@@ -161,11 +617,27 @@ impl Share {
 /// (1) its bits number same as in set,
 /// (2) its share number is not yet encountered,
 /// (3) its content length is same as the length of other contents in the set.
+/// Title and number of required shards omitted in the shares of private split
+/// are taken from the [`Manifest`], once it is applied.
 #[derive(Debug)]
 pub struct ShareSet {
     version: Version,
-    title: String,
-    required_shards: usize,
+    title: Option<String>,
+    required_shards: Option<usize>,
+    manifest: Option<Manifest>,
+    metrics: Option<MetricsHook>,
+    cancellation: CancellationToken,
+    gf_strategy: GfStrategy,
+    error_correction: bool,
+    fingerprint: String,
+    key_derivation: KeyDerivation,
+    key_commitment: Option<[u8; COMMITMENT_LEN]>,
+    validity: Validity,
+    set_key: Option<[u8; SET_KEY_LEN]>,
+    set_id: Option<String>,
+    salt: Option<Vec<u8>>,
+    stats: ScanStats,
+    extend_errors: Vec<(u32, Error)>,
     state: ShareSetState,
 }
 
@@ -175,19 +647,78 @@ pub enum ShareSetState {
     SetCombined(SetCombined),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct SetInProgress {
     bits: u32,
     id_set: Vec<u32>,
     content_length: usize,
-    content_set: Vec<Vec<u8>>,
+    content_set: Vec<Zeroizing<Vec<u8>>>,
     nonce: String,
 }
 
 #[derive(Debug)]
 pub struct SetCombined {
     data: Vec<u8>,
-    nonce: Vec<u8>,
+    nonce: [u8; NONCE_LEN],
+    /// Shares the set was combined from, kept so that the set could be
+    /// taken back in progress, see [`ShareSet::retain_shares`].
+    /// With error correction, surplus shares are kept here too.
+    shares: SetInProgress,
+    /// Outcome of the majority vote, if the set was combined by it.
+    vote: Option<MajorityVote>,
+}
+
+/// Share collected into the set, see [`ShareSet::collected`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollectedShare {
+    /// Share number.
+    pub id: u32,
+    /// Bits setting of the share.
+    pub bits: u32,
+    /// Times the share was scanned, repeated scans included.
+    pub scans: usize,
+}
+
+/// Progress of the share set, see [`ShareSet::summary`], for the user interface
+/// to render the progress screen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShareSetSummary {
+    /// Title of the set; empty for private set until the manifest is applied.
+    pub title: String,
+    /// Number of shares needed for recovery; `None` for private set
+    /// until the manifest is applied.
+    pub required: Option<usize>,
+    /// Number of distinct shares collected so far.
+    pub have: usize,
+    /// Bits setting of the set shares, i.e. n in GF(2^n).
+    pub bits: u32,
+    /// Format version of the set shares; `None` for legacy shares.
+    pub version: Option<u32>,
+}
+
+/// Outcome of the majority vote of the share subsets, see [`ShareSet::majority_vote`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MajorityVote {
+    /// Number of share subsets combined.
+    pub subsets: usize,
+    /// Number of subsets agreeing on the result; all of them, if no share is damaged.
+    pub agreeing: usize,
+    /// Numbers of the shares left out of all the agreeing subsets, likely damaged;
+    /// empty if all subsets agree.
+    pub suspects: Vec<u32>,
+}
+
+/// Outcome of [`ShareSet::diagnose`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnosis {
+    /// Number of share subsets combined and decrypted.
+    pub subsets: usize,
+    /// Number of subsets decrypted with the passphrase.
+    pub decrypted: usize,
+    /// Numbers of the shares left out of all the decrypted subsets, likely damaged;
+    /// empty if no subset is decrypted, i.e. the passphrase is wrong,
+    /// or too many shares are damaged.
+    pub suspects: Vec<u32>,
 }
 
 /// The next action to do for the share set at hand.
@@ -199,9 +730,77 @@ pub enum NextAction {
         have: usize,
         /// Number of shares needed for recovery.
         need: usize,
+        /// Numbers of the shares already scanned, in the order they were added,
+        /// for the user interface to tell which shares are still missing.
+        collected: Vec<u32>,
     },
     /// The user password is needed.
     AskUserForPassword,
+    /// The set is private, and the owner manifest is needed
+    /// to learn the title or the number of required shards.
+    AskUserForManifest,
+}
+
+/// Share field preventing the share from joining the set, see [`ShareSet::why_rejected`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RejectionReason {
+    /// Share is of different version.
+    Version,
+    /// Share has different set identifier, i.e. is from a different split.
+    SetId,
+    /// Share has different set key, i.e. is from a different split,
+    /// possibly a sibling backup with the same title and threshold.
+    SetKey,
+    /// Share has different title.
+    Title,
+    /// Share has different number of required shards.
+    RequiredShards,
+    /// Share has different key derivation parameters.
+    KeyDerivation,
+    /// Share has different salt override.
+    Salt,
+    /// Share has different key commitment.
+    KeyCommitment,
+    /// Share has different validity period.
+    Validity,
+    /// Share has different nonce, i.e. is from a different split.
+    Nonce,
+    /// Share has different bits setting.
+    Bits {
+        /// Bits of the set.
+        set: u32,
+        /// Bits of the share.
+        share: u32,
+    },
+    /// Share with this id is already in the set.
+    AlreadyInSet(u32),
+    /// Share content length is different.
+    ContentLength {
+        /// Content length of the set shares.
+        set: usize,
+        /// Content length of the share.
+        share: usize,
+    },
+}
+
+impl From<RejectionReason> for Error {
+    fn from(reason: RejectionReason) -> Self {
+        match reason {
+            RejectionReason::Version => Error::ShareVersionDifferent,
+            RejectionReason::SetId | RejectionReason::SetKey => Error::ShareFromDifferentSet,
+            RejectionReason::Title => Error::ShareTitleDifferent,
+            RejectionReason::RequiredShards => Error::ShareRequiredShardsDifferent,
+            RejectionReason::KeyDerivation => Error::ShareKdfDifferent,
+            RejectionReason::Salt => Error::ShareSaltDifferent,
+            RejectionReason::KeyCommitment => Error::ShareKeyCommitmentDifferent,
+            RejectionReason::Validity => Error::ShareValidityDifferent,
+            RejectionReason::Nonce => Error::ShareNonceDifferent,
+            RejectionReason::Bits { .. } => Error::ShareBitsDifferent,
+            RejectionReason::AlreadyInSet(_) => Error::ShareAlreadyInSet,
+            RejectionReason::ContentLength { .. } => Error::ShareContentLengthDifferent,
+        }
+    }
 }
 
 impl SetInProgress {
@@ -209,74 +808,135 @@ impl SetInProgress {
     /// To be called only on checked and ready set of shares,
     /// in other words does not check itself if the processing
     /// shares will produce a valid result.
-    fn combine(&self) -> Result<SetCombined, Error> {
-        // transpose content set
-        // from
-        // Vec[[share1[1], share1[2] ... share1[N]], [share2[1], share2[2] ... share2[N]] ... [shareM[1], shareM[2] ... shareM[N]]]
-        // into
-        // Vec[[share1[1], share2[1] ... shareM[1]], [share1[2], share2[2] ... shareM[2]] ... [share1[N], share2[N] ... shareM[N]]]
-        let mut content_zipped: Vec<Vec<u32>> = Vec::with_capacity(self.content_length);
-        for i in 0..self.content_length {
-            let mut new: Vec<u32> = Vec::new();
-            for j in 0..self.id_set.len() {
-                new.push(self.content_set[j][i] as u32)
-            }
-            content_zipped.push(new);
-        }
-
-        // calculate logarithms and exponents in GF(2^n) for n = self.bits
-        let (logs, exps) = generate_logs_and_exps(self.bits);
-
-        // process and collect bit sequence from each element of content_zipped
-        let mut result: BitVec<u32, Msb0> = BitVec::new();
-        for content_zipped_element in content_zipped.iter() {
-            // new element that will be processed; is calculated as u32, its value is always below 2^(self.bits);
-            let new = lagrange(
-                &self.id_set,
-                content_zipped_element,
-                &logs,
-                &exps,
+    fn combine(
+        &self,
+        cancel: &CancellationToken,
+        strategy: GfStrategy,
+    ) -> Result<(Vec<u8>, [u8; NONCE_LEN]), Error> {
+        trace_span!(
+            "combine",
+            bits = self.bits,
+            shares = self.id_set.len(),
+            content_len = self.content_length
+        );
+        // output buffer, and scratch tables if used, for the bounded combining
+        let mut data = vec![0; combine_output_len(self.bits, self.content_length)];
+        let contents: Vec<&[u8]> = self.content_set.iter().map(|a| a.as_slice()).collect();
+        let data_len = if strategy.uses_tables(self.bits) {
+            let table_len = combine_table_len(self.bits)?;
+            let mut logs = vec![0; table_len];
+            let mut exps = vec![0; table_len];
+            combine_bounded(
                 self.bits,
-            )?;
-
-            // transform new element into new bitvec to operate on bits individually
-            let new_bitvec: BitVec<u32, Msb0> = BitVec::from_vec(vec![new]);
-
-            // in js code this crate follows, the bits string representation of new element (i.e. without leading zeroes)
-            // was padded from left with zeroes so that the string length became multiple of (self.bits) number;
-            // since the new element value is always below 2^(self.bits), this procedure effectively means keeping only
-            // (self.bits) amount of bits from the element;
-            // cut is the starting point after which the bits are retained;
-            let cut = (32 - self.bits) as usize;
-
-            // resulting bits are added into collection;
-            result.extend_from_bitslice(&new_bitvec[cut..]);
-        }
-        // the js code this crate follows then calls for cutting all leading false bits
-        // up until the first true, which serves as a padding marker,
-        // cut padding marker as well, and then collect bytes with some padding on the left if necessary
-        let result: BitVec<u8, Msb0> = result.into_iter().skip_while(|x| !*x).skip(1).collect();
-
-        // transform result in its final form, Vec<u8>
-        let data = result.into_vec();
+                &self.id_set,
+                &contents,
+                &mut logs,
+                &mut exps,
+                &mut data,
+                Some(cancel),
+            )?
+        } else {
+            combine_table_free_bounded(self.bits, &self.id_set, &contents, &mut data, Some(cancel))?
+        };
+        data.truncate(data_len);
 
         // process nonce, so that it is done before asking for a password
-        let nonce = match BASE64.decode(self.nonce.as_bytes()) {
-            Ok(a) => a,
-            Err(_) => return Err(Error::NonceNotBase64),
-        };
+        let nonce = decode_nonce(&self.nonce)?;
         // now the set is ready
-        Ok(SetCombined { data, nonce })
+        trace_event!(data_len = data.len(), "set combined");
+        Ok((data, nonce))
+    }
+    /// Combine the subsets of `required_shards` shares, and take the result
+    /// most of the subsets agree on, see [`ShareSet::set_error_correction`].
+    ///
+    /// If no result has the majority, fails with [`Error::NoMajority`].
+    fn combine_by_majority(
+        &self,
+        required_shards: usize,
+        cancel: &CancellationToken,
+        strategy: GfStrategy,
+    ) -> Result<(Vec<u8>, [u8; NONCE_LEN], MajorityVote), Error> {
+        let subsets = share_subsets(self.id_set.len(), required_shards, MAX_VOTE_SUBSETS);
+        // distinct results, with the subsets resulting in them
+        let mut results: Vec<(Vec<u8>, Vec<&[usize]>)> = Vec::new();
+        for subset in &subsets {
+            let (data, _) = self.subset(subset).combine(cancel, strategy)?;
+            match results.iter_mut().find(|(a, _)| a == &data) {
+                Some((_, agreeing)) => agreeing.push(subset),
+                None => results.push((data, vec![subset])),
+            }
+        }
+        let most = results
+            .iter()
+            .map(|(_, a)| a.len())
+            .max()
+            .unwrap_or_default();
+        let mut leading = (0..results.len()).filter(|a| results[*a].1.len() == most);
+        let majority = match (leading.next(), leading.next()) {
+            (Some(i), None) => i,
+            // tied results could not tell which of them is right
+            _ => return Err(Error::NoMajority),
+        };
+        let (data, agreeing) = results.swap_remove(majority);
+        let vote = MajorityVote {
+            subsets: subsets.len(),
+            agreeing: agreeing.len(),
+            // shares not in any of the agreeing subsets could not be trusted
+            suspects: self.left_out(&agreeing),
+        };
+        trace_event!(
+            agreeing = vote.agreeing,
+            subsets = vote.subsets,
+            "majority vote"
+        );
+        Ok((data, decode_nonce(&self.nonce)?, vote))
+    }
+    /// Shares at given positions.
+    fn subset(&self, positions: &[usize]) -> SetInProgress {
+        SetInProgress {
+            bits: self.bits,
+            id_set: positions.iter().map(|a| self.id_set[*a]).collect(),
+            content_length: self.content_length,
+            content_set: positions
+                .iter()
+                .map(|a| self.content_set[*a].clone())
+                .collect(),
+            nonce: self.nonce.to_owned(),
+        }
+    }
+    /// Numbers of the shares not in any of the subsets.
+    fn left_out(&self, subsets: &[&[usize]]) -> Vec<u32> {
+        (0..self.id_set.len())
+            .filter(|a| !subsets.iter().any(|subset| subset.contains(a)))
+            .map(|a| self.id_set[a])
+            .collect()
     }
 }
 
 impl ShareSet {
     /// Initiating share set with first incoming share
     pub fn init(share: Share) -> Self {
+        let fingerprint = share.fingerprint();
+        let mut stats = ScanStats::default();
+        stats.record_accepted();
         Self {
+            fingerprint,
             version: share.version,
             title: share.title,
             required_shards: share.required_shards,
+            manifest: None,
+            metrics: None,
+            cancellation: CancellationToken::default(),
+            gf_strategy: GfStrategy::default(),
+            error_correction: false,
+            key_derivation: share.key_derivation,
+            key_commitment: share.key_commitment,
+            validity: share.validity,
+            set_key: share.set_key,
+            set_id: share.set_id,
+            salt: share.salt,
+            stats,
+            extend_errors: Vec::new(),
             state: ShareSetState::SetInProgress(SetInProgress {
                 bits: share.bits,
                 id_set: vec![share.id],
@@ -286,101 +946,1015 @@ impl ShareSet {
             }),
         }
     }
+    /// Make the set of all the shares at hand at once, for example of a directory of scans:
+    /// the set is initiated with the first share, and the rest are added one by one,
+    /// same as with [`Extend::extend`].
+    ///
+    /// Shares that could not join the set are reported by [`ShareSet::take_extend_errors`],
+    /// with the share numbers and the reasons. Empty list is refused with [`Error::TooFewShares`].
+    pub fn from_shares(shares: Vec<Share>) -> Result<Self, Error> {
+        let mut shares = shares.into_iter();
+        let mut set = Self::init(shares.next().ok_or(Error::TooFewShares)?);
+        set.extend(shares);
+        Ok(set)
+    }
+    /// Explain why the share could not join the set, without modifying the set;
+    /// `None` if the share would be accepted by [`ShareSet::try_add_share`].
+    ///
+    /// Fields are checked in the same order as when adding the share.
+    /// Combined set takes no more shares, and any share is ignored without error,
+    /// unless error correction is on, see [`ShareSet::set_error_correction`].
+    pub fn why_rejected(&self, share: &Share) -> Option<RejectionReason> {
+        let set_in_progress = match &self.state {
+            ShareSetState::SetInProgress(a) => a,
+            ShareSetState::SetCombined(a) if self.error_correction => &a.shares,
+            ShareSetState::SetCombined(_) => return None,
+        };
+        if share.set_id != self.set_id {
+            Some(RejectionReason::SetId)
+        } else if share.version != self.version {
+            Some(RejectionReason::Version)
+        } else if share.set_key != self.set_key {
+            Some(RejectionReason::SetKey)
+        } else if share.title != self.title {
+            Some(RejectionReason::Title)
+        } else if share.required_shards != self.required_shards {
+            Some(RejectionReason::RequiredShards)
+        } else if share.key_derivation != self.key_derivation {
+            Some(RejectionReason::KeyDerivation)
+        } else if share.salt != self.salt {
+            Some(RejectionReason::Salt)
+        } else if share.key_commitment != self.key_commitment {
+            Some(RejectionReason::KeyCommitment)
+        } else if share.validity != self.validity {
+            Some(RejectionReason::Validity)
+        } else if share.nonce != set_in_progress.nonce {
+            Some(RejectionReason::Nonce)
+        } else if share.bits != set_in_progress.bits {
+            Some(RejectionReason::Bits {
+                set: set_in_progress.bits,
+                share: share.bits,
+            })
+        } else if set_in_progress.id_set.contains(&share.id) {
+            Some(RejectionReason::AlreadyInSet(share.id))
+        } else if share.content.len() != set_in_progress.content_length {
+            Some(RejectionReason::ContentLength {
+                set: set_in_progress.content_length,
+                share: share.content.len(),
+            })
+        } else {
+            None
+        }
+    }
     /// Try to add another new share into existing set.
     /// Should be accessible through user interface only for ShareSetState::SetInProgress.
     pub fn try_add_share(&mut self, new: Share) -> Result<(), Error> {
-        if let ShareSetState::SetInProgress(ref mut set_in_progress) = self.state {
-            if new.version != self.version {
-                return Err(Error::ShareVersionDifferent);
-            } // should have same version
-
-            if new.title != self.title {
-                return Err(Error::ShareTitleDifferent);
-            } // ... and same title
-
-            if new.required_shards != self.required_shards {
-                return Err(Error::ShareRequiredShardsDifferent);
-            } // ... and same number of required shards
-
-            if new.nonce != set_in_progress.nonce {
-                return Err(Error::ShareNonceDifferent);
-            } // ... and same nonce
-
-            if new.bits != set_in_progress.bits {
-                return Err(Error::ShareBitsDifferent);
-            } // ... and bits
-
-            if set_in_progress.id_set.contains(&new.id) {
-                return Err(Error::ShareAlreadyInSet);
-            } // ... also should be a new share
-
-            if set_in_progress.content_length != new.content.len() {
-                return Err(Error::ShareContentLengthDifferent);
-            } // ... with same content length
-
-            set_in_progress.id_set.push(new.id);
-            set_in_progress.content_set.push(new.content);
-            if set_in_progress.id_set.len() >= self.required_shards {
-                let set_combined = set_in_progress.combine()?;
-                self.state = ShareSetState::SetCombined(set_combined);
+        trace_span!("try_add_share", id = new.id);
+        if let Some(reason) = self.why_rejected(&new) {
+            self.stats.record_rejected(reason);
+            return Err(reason.into());
+        }
+        // combined set takes no more shares, unless they are kept for the majority vote
+        let set_in_progress = match self.state {
+            ShareSetState::SetInProgress(ref mut a) => a,
+            ShareSetState::SetCombined(ref mut a) if self.error_correction => &mut a.shares,
+            ShareSetState::SetCombined(_) => return Ok(()),
+        };
+        set_in_progress.id_set.push(new.id);
+        set_in_progress.content_set.push(new.content);
+        trace_event!(have = set_in_progress.id_set.len(), "share added");
+        if let Some(ref hook) = self.metrics {
+            hook.report(Metric::ShareProcessed {
+                have: set_in_progress.id_set.len(),
+            });
+        }
+        // failed combining leaves the set as it was, so that the share could be added again,
+        // for example after cancellation; share that leaves the vote without majority is kept
+        let result = self.try_combine();
+        match result {
+            Ok(()) | Err(Error::NoMajority) => self.stats.record_accepted(),
+            Err(_) => {
+                let set_in_progress = match self.state {
+                    ShareSetState::SetInProgress(ref mut a) => a,
+                    ShareSetState::SetCombined(ref mut a) => &mut a.shares,
+                };
+                let _ = set_in_progress.id_set.pop();
+                let _ = set_in_progress.content_set.pop();
+            }
+        }
+        result
+    }
+    /// Apply the owner manifest to the set of private split,
+    /// filling in the title and the number of required shards.
+    /// Manifest must be for the same split, and agree with the data the shares do record.
+    pub fn apply_manifest(&mut self, manifest: Manifest) -> Result<(), Error> {
+        if manifest.fingerprint != self.fingerprint {
+            return Err(Error::ManifestMismatch);
+        }
+        if let Some(ref title) = self.title {
+            if title != &manifest.title {
+                return Err(Error::ManifestMismatch);
+            }
+        }
+        if let Some(required_shards) = self.required_shards {
+            if required_shards != manifest.required_shards {
+                return Err(Error::ManifestMismatch);
+            }
+        }
+        self.manifest = Some(manifest);
+        trace_event!("manifest applied");
+        self.try_combine()
+    }
+    /// Statistics of the shares scanned into the set so far.
+    pub fn scan_stats(&self) -> &ScanStats {
+        &self.stats
+    }
+    /// Report metrics of the following operations on the set to the hook.
+    pub fn set_metrics(&mut self, hook: MetricsHook) {
+        self.metrics = Some(hook);
+    }
+    /// Stop combining and key derivation with [`Error::Cancelled`]
+    /// once the token is cancelled.
+    pub fn set_cancellation(&mut self, token: CancellationToken) {
+        self.cancellation = token;
+    }
+    /// Drop all the shares collected so far, along with the scan statistics and
+    /// the errors kept by [`Extend::extend`], to restart the collection after
+    /// a botched session. The set still takes only the shares of the same split;
+    /// the manifest, metrics hook, and cancellation token are kept.
+    pub fn reset(&mut self) {
+        let _ = self.retain_shares(|_| false);
+        self.stats = ScanStats::default();
+        self.extend_errors.clear();
+    }
+    /// Keep only the shares with numbers the predicate approves, for example to drop
+    /// the shares the user distrusts, without scanning the rest again.
+    ///
+    /// Combined set left with too few shares goes back in progress, and takes
+    /// more shares. Set still having enough shares is combined again; if combining
+    /// fails, for example when cancelled, the set is left in progress with the shares kept.
+    pub fn retain_shares(&mut self, mut keep: impl FnMut(u32) -> bool) -> Result<(), Error> {
+        let mut shares = self.take_shares();
+        let (ids, contents) = shares
+            .id_set
+            .drain(..)
+            .zip(shares.content_set.drain(..))
+            .filter(|(id, _)| keep(*id))
+            .unzip();
+        shares.id_set = ids;
+        shares.content_set = contents;
+        self.state = ShareSetState::SetInProgress(shares);
+        self.try_combine()
+    }
+    /// Take back the share with given number, for example scanned by mistake,
+    /// without scanning the rest again; same as [`ShareSet::retain_shares`]
+    /// keeping all the other shares.
+    ///
+    /// Share not in the set is reported with [`Error::ShareNotInSet`].
+    pub fn remove_share(&mut self, id: u32) -> Result<(), Error> {
+        if !self.collected_ids().contains(&id) {
+            return Err(Error::ShareNotInSet(id));
+        }
+        self.retain_shares(|a| a != id)
+    }
+    /// Shares of the set, leaving the set in progress with no shares.
+    fn take_shares(&mut self) -> SetInProgress {
+        match std::mem::replace(
+            &mut self.state,
+            ShareSetState::SetInProgress(SetInProgress::default()),
+        ) {
+            ShareSetState::SetInProgress(set_in_progress) => set_in_progress,
+            ShareSetState::SetCombined(set_combined) => set_combined.shares,
+        }
+    }
+    /// Choose how the arithmetic in GF(2^n) is done when the set gets combined,
+    /// see [`GfStrategy`]; to be set before the last share is added.
+    pub fn set_gf_strategy(&mut self, strategy: GfStrategy) {
+        self.gf_strategy = strategy;
+    }
+    /// Keep the shares scanned after the set is combined, and combine all
+    /// subsets of the required number of shares, taking the result most of them agree on,
+    /// so that a single damaged share is detected and identified instead of
+    /// silently spoiling the secret; see [`ShareSet::majority_vote`].
+    ///
+    /// At most [`MAX_VOTE_SUBSETS`] subsets are combined; with more, each share
+    /// is in the required number of them. To be set before the last share is added.
+    ///
+    /// If no result has the majority, for example with a single surplus share
+    /// and one of the shares damaged, adding the share fails with [`Error::NoMajority`];
+    /// the share is kept, and the set goes back in progress, to take another share
+    /// or to find the damaged one with [`ShareSet::diagnose`].
+    pub fn set_error_correction(&mut self, enabled: bool) {
+        self.error_correction = enabled;
+    }
+    /// Outcome of the majority vote, if the set was combined from surplus shares
+    /// with error correction on, see [`ShareSet::set_error_correction`].
+    pub fn majority_vote(&self) -> Option<&MajorityVote> {
+        match &self.state {
+            ShareSetState::SetCombined(set_combined) => set_combined.vote.as_ref(),
+            ShareSetState::SetInProgress(_) => None,
+        }
+    }
+    /// Combine the set in progress, if the number of required shards is known and reached.
+    /// With error correction, combined set is combined again with the surplus shares.
+    fn try_combine(&mut self) -> Result<(), Error> {
+        let set_in_progress = match &self.state {
+            ShareSetState::SetInProgress(a) => a,
+            ShareSetState::SetCombined(a) if self.error_correction => &a.shares,
+            ShareSetState::SetCombined(_) => return Ok(()),
+        };
+        if let Some(required_shards) = self.known_required_shards() {
+            if set_in_progress.id_set.len() >= required_shards {
+                let timer = MetricsHook::start(self.metrics.as_ref());
+                let (data, nonce, vote) =
+                    if self.error_correction && set_in_progress.id_set.len() > required_shards {
+                        match set_in_progress.combine_by_majority(
+                            required_shards,
+                            &self.cancellation,
+                            self.gf_strategy,
+                        ) {
+                            Ok((data, nonce, vote)) => (data, nonce, Some(vote)),
+                            Err(Error::NoMajority) => {
+                                let shares = self.take_shares();
+                                self.state = ShareSetState::SetInProgress(shares);
+                                return Err(Error::NoMajority);
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    } else {
+                        let (data, nonce) =
+                            set_in_progress.combine(&self.cancellation, self.gf_strategy)?;
+                        (data, nonce, None)
+                    };
+                if let Some(timer) = timer {
+                    timer.finish(|duration| Metric::Combined {
+                        duration,
+                        shares: set_in_progress.id_set.len(),
+                    });
+                }
+                let shares = self.take_shares();
+                self.state = ShareSetState::SetCombined(SetCombined {
+                    data,
+                    nonce,
+                    shares,
+                    vote,
+                });
             }
         }
         Ok(())
     }
+    /// Title, from the shares or from the manifest.
+    fn known_title(&self) -> Option<&str> {
+        self.title
+            .as_deref()
+            .or(self.manifest.as_ref().map(|a| a.title.as_str()))
+    }
+    /// Number of required shards, from the shares or from the manifest.
+    fn known_required_shards(&self) -> Option<usize> {
+        self.required_shards
+            .or(self.manifest.as_ref().map(|a| a.required_shards))
+    }
     /// Function for user interface to decide on next allowed action
     pub fn next_action(&self) -> NextAction {
-        match &self.state {
-            ShareSetState::SetInProgress(set_in_progress) => NextAction::MoreShares {
+        if self.known_title().is_none() {
+            return NextAction::AskUserForManifest;
+        }
+        match (&self.state, self.known_required_shards()) {
+            (ShareSetState::SetInProgress(set_in_progress), Some(need)) => NextAction::MoreShares {
                 have: set_in_progress.id_set.len(),
-                need: self.required_shards,
+                need,
+                collected: set_in_progress.id_set.clone(),
             },
-            ShareSetState::SetCombined(_) => NextAction::AskUserForPassword,
+            (ShareSetState::SetInProgress(_), None) => NextAction::AskUserForManifest,
+            (ShareSetState::SetCombined(_), _) => NextAction::AskUserForPassword,
         }
     }
-    /// Function to print set title into user interface
+    /// Function to print set title into user interface;
+    /// empty for private set until the manifest is applied
     pub fn title(&self) -> String {
-        self.title.to_owned()
+        self.known_title().unwrap_or_default().to_owned()
+    }
+    /// Get the number of required shards;
+    /// 0 for private set until the manifest is applied
+    pub fn required_shards(&self) -> usize {
+        self.known_required_shards().unwrap_or_default()
+    }
+    /// Format version of the set shares, as recorded in share `"v"` field;
+    /// `None` for legacy shares without version.
+    pub fn version(&self) -> Option<u32> {
+        match self.version.tag() {
+            0 => None,
+            a => Some(a as u32),
+        }
+    }
+    /// Bits setting of the set shares, i.e. n in GF(2^n).
+    pub fn bits(&self) -> u32 {
+        match &self.state {
+            ShareSetState::SetInProgress(set_in_progress) => set_in_progress.bits,
+            ShareSetState::SetCombined(set_combined) => set_combined.shares.bits,
+        }
+    }
+    /// Numbers of the shares collected so far, in the order they were added.
+    pub fn collected_ids(&self) -> &[u32] {
+        match &self.state {
+            ShareSetState::SetInProgress(set_in_progress) => &set_in_progress.id_set,
+            ShareSetState::SetCombined(set_combined) => &set_combined.shares.id_set,
+        }
+    }
+    /// Number of shares still needed for recovery, 0 once the set is combined;
+    /// `None` for private set until the manifest is applied.
+    pub fn missing_count(&self) -> Option<usize> {
+        self.known_required_shards()
+            .map(|a| a.saturating_sub(self.collected_ids().len()))
+    }
+    /// Progress of the set, see [`ShareSetSummary`].
+    pub fn summary(&self) -> ShareSetSummary {
+        ShareSetSummary {
+            title: self.title(),
+            required: self.known_required_shards(),
+            have: self.collected_ids().len(),
+            bits: self.bits(),
+            version: self.version(),
+        }
+    }
+    /// Shares collected so far, in the order they were added, for the user interface
+    /// to list what has been gathered.
+    pub fn collected(&self) -> impl Iterator<Item = CollectedShare> + '_ {
+        let bits = self.bits();
+        self.collected_ids().iter().map(move |id| CollectedShare {
+            id: *id,
+            bits,
+            scans: 1 + self.stats.duplicates.get(id).copied().unwrap_or_default(),
+        })
+    }
+    /// Errors of the shares that [`Extend::extend`] could not add, with the share numbers,
+    /// in the order they occurred; taking them clears the list.
+    pub fn take_extend_errors(&mut self) -> Vec<(u32, Error)> {
+        std::mem::take(&mut self.extend_errors)
+    }
+    /// Combined encrypted secret, once the set is combined.
+    #[cfg(test)]
+    pub(crate) fn encrypted_secret(&self) -> Option<&[u8]> {
+        match &self.state {
+            ShareSetState::SetInProgress(_) => None,
+            ShareSetState::SetCombined(set_combined) => Some(&set_combined.data),
+        }
+    }
+    /// Number of distinct shares collected so far.
+    pub(crate) fn collected_shards(&self) -> usize {
+        self.collected_ids().len()
+    }
+    /// Short fingerprint of the split the set belongs to, for example `3fa0-91c2`.
+    ///
+    /// Fingerprint is derived only from public parameters of the split
+    /// (version, title, number of required shards, bits, and nonce), and never from the secret.
+    /// [`PrintLayout`](crate::PrintLayout) puts it next to every printed share, and
+    /// `banana split` reports it, so that custodians could periodically check
+    /// that their shares still belong to the current set:
+    /// shares from a refreshed split have a new nonce, and therefore a different fingerprint.
+    pub fn fingerprint(&self) -> String {
+        self.fingerprint.to_owned()
+    }
+    /// Non-fatal problems with the set, to be shown to the user before recovery.
+    ///
+    /// Set outside of its validity period is reported here by the system clock,
+    /// and is still recovered; see [`Validity::warnings_at`] for other clocks.
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = key_derivation_warnings(&self.key_derivation);
+        warnings.extend(self.validity.warnings());
+        warnings
+    }
+    /// Validity period of the set, see [`Validity`].
+    pub fn validity(&self) -> Validity {
+        self.validity
+    }
+    /// Random identifier of the split, a uuid, if the shares record it.
+    pub fn set_id(&self) -> Option<&str> {
+        self.set_id.as_deref()
+    }
+    /// Key derivation salt of the set, exactly as it is used on recovery:
+    /// the salt override recorded in V2 shares, if there is one,
+    /// and [`title_salt`](crate::title_salt) of the title otherwise.
+    ///
+    /// Intended for audit tooling reproducing the key derivation step by step.
+    /// Private set needs the manifest to be applied first.
+    pub fn salt(&self) -> Result<Vec<u8>, Error> {
+        match (&self.salt, self.known_title()) {
+            (Some(salt), _) => Ok(salt.to_vec()),
+            (None, Some(title)) => Ok(derivation_salt(None, title)),
+            (None, None) => Err(Error::ManifestNeeded),
+        }
+    }
+    /// Estimated length of the recovered secret, in bytes, before the decryption.
+    ///
+    /// For combined set the length is exact. Before that, it is estimated from
+    /// the share content length and bits, and could be off by the padding size, up to 16 bytes.
+    /// Could be used to warn the user early that the scanned set does not match
+    /// the expected backup, for example a 16 bytes secret when a keystore was expected.
+    pub fn estimated_secret_len(&self) -> RangeInclusive<usize> {
+        match &self.state {
+            ShareSetState::SetInProgress(set_in_progress) => {
+                let total_bits = set_in_progress.content_length * set_in_progress.bits as usize;
+                // at least the padding marker is cut, and at most the full padding,
+                // rounded up to a whole element
+                let max = total_bits.saturating_sub(1) / 8;
+                let min = total_bits
+                    .saturating_sub(MAX_PADDING_BITS + set_in_progress.bits as usize)
+                    .div_ceil(8);
+                min.saturating_sub(TAG_LEN)..=max.saturating_sub(TAG_LEN)
+            }
+            ShareSetState::SetCombined(set_combined) => {
+                let len = set_combined.data.len().saturating_sub(TAG_LEN);
+                len..=len
+            }
+        }
+    }
+    /// Number of sequential key derivation rounds needed for recovery.
+    /// Value above 1 means the set was time-locked at split time,
+    /// and the recovery will take at least that many times longer than usual.
+    pub fn time_lock_rounds(&self) -> u32 {
+        self.key_derivation.rounds
     }
     /// Function to recover the secret from the share set with known passphrase;
     /// `passphrase` is the passphrase generated together with qr set by banana split.
     /// Should be accessible through user interface only for ShareSetState::SetCombined.
     pub fn recover_with_passphrase(&self, passphrase: &str) -> Result<String, Error> {
-        if let ShareSetState::SetCombined(SetCombined { data, nonce }) = &self.state {
-            // hash title into salt
-            let salt = hash_string(&self.title);
-
-            // set up the parameters for scrypt
-            let params = Params::new(15, 8, 1, 32).expect("static checked params"); // default ones are used
-
-            // set up output buffer for scrypt
-            let mut key: Vec<u8> = [0; 32].to_vec(); // allocate here, empty output buffer is rejected
+        let key = self.derive_key(passphrase)?;
+        self.decrypt_with_key(&key)
+    }
+    /// Recover the secret as bytes, same as [`ShareSet::recover_with_passphrase`],
+    /// for binary secrets, for example raw entropy or keystore blobs split by other tools.
+    ///
+    /// Secret is not required to be utf8, and is wiped when dropped.
+    pub fn recover_bytes_with_passphrase(
+        &self,
+        passphrase: &str,
+    ) -> Result<Zeroizing<Vec<u8>>, Error> {
+        let key = self.derive_key(passphrase)?;
+        self.decrypt_bytes_with_key(&key)
+    }
+    /// Derive the key with the set key derivation, once the set is combined.
+    fn derive_key(&self, passphrase: &str) -> Result<Zeroizing<Vec<u8>>, Error> {
+        trace_span!("recover");
+        if let ShareSetState::SetInProgress(_) = self.state {
+            return Err(Error::NotReadyToDecode);
+        }
+        self.derive_set_key(passphrase)
+    }
+    /// Derive the key with the set key derivation, in progress or combined.
+    fn derive_set_key(&self, passphrase: &str) -> Result<Zeroizing<Vec<u8>>, Error> {
+        let salt = self.salt()?;
 
-            // ... and scrypt them
-            scrypt(passphrase.as_bytes(), &salt, &params, &mut key).map_err(Error::ScryptFailed)?;
+        let timer = MetricsHook::start(self.metrics.as_ref());
+        let key = self
+            .key_derivation
+            .derive(passphrase.as_bytes(), &salt, &self.cancellation)?;
+        if let Some(timer) = timer {
+            timer.finish(|duration| Metric::KeyDerived {
+                duration,
+                rounds: self.key_derivation.rounds,
+            });
+        }
+        Ok(key)
+    }
+    /// Find the shares likely damaged, after the recovery failed with [`Error::DecodingFailed`]
+    /// or the majority vote failed with [`Error::NoMajority`], if the set has more shares
+    /// than required: kept with error correction, see [`ShareSet::set_error_correction`],
+    /// or collected before the manifest was applied.
+    ///
+    /// Key is derived once, and the subsets of the required number of shares,
+    /// at most [`MAX_VOTE_SUBSETS`] of them, are combined and decrypted; the shares
+    /// left out of all the subsets that decrypt are reported. Set without surplus shares
+    /// is refused with [`Error::TooFewShares`]. With key commitment, wrong passphrase
+    /// is reported with [`Error::WrongPassphrase`] right away.
+    pub fn diagnose(&self, passphrase: &str) -> Result<Diagnosis, Error> {
+        let shares = self.shares_in_set();
+        let required_shards = self.known_required_shards().ok_or(Error::ManifestNeeded)?;
+        if shares.id_set.len() <= required_shards {
+            return Err(Error::TooFewShares);
+        }
+        let key = self.derive_set_key(passphrase)?;
+        check_key(&key, self.key_commitment.as_ref())?;
+        let nonce = decode_nonce(&shares.nonce)?;
+        let subsets = share_subsets(shares.id_set.len(), required_shards, MAX_VOTE_SUBSETS);
+        let mut decrypted: Vec<&[usize]> = Vec::new();
+        for subset in &subsets {
+            let (data, _) = shares
+                .subset(subset)
+                .combine(&self.cancellation, self.gf_strategy)?;
+            if decrypt_bytes(&key, &nonce, &data).is_ok() {
+                decrypted.push(subset);
+            }
+        }
+        let suspects = if decrypted.is_empty() {
+            Vec::new()
+        } else {
+            shares.left_out(&decrypted)
+        };
+        trace_event!(decrypted = decrypted.len(), "diagnosed");
+        Ok(Diagnosis {
+            subsets: subsets.len(),
+            decrypted: decrypted.len(),
+            suspects,
+        })
+    }
+    /// Decrypt the secret with the key already derived for the set.
+    pub(crate) fn decrypt_with_key(&self, key: &[u8]) -> Result<String, Error> {
+        self.decrypt_bytes_with_key(key).and_then(secret_string)
+    }
+    /// Decrypt the secret bytes with the key already derived for the set.
+    fn decrypt_bytes_with_key(&self, key: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+        if let ShareSetState::SetCombined(SetCombined { data, nonce, .. }) = &self.state {
+            // wrong passphrase is told apart from damaged shares, if the set has the commitment
+            check_key(key, self.key_commitment.as_ref())?;
 
             // set up cipher with key and decrypt secret using nonce
-            let cipher = XSalsa20Poly1305::new(GenericArray::from_slice(&key[..]));
-            match cipher.decrypt(GenericArray::from_slice(&nonce[..]), data.as_ref()) {
-                Ok(a) => match String::from_utf8(a) {
-                    // in case of successful vector-to-string conversion, vector does not get copied:
-                    // https://doc.rust-lang.org/std/string/struct.String.html#method.from_utf8
-                    // string ptr same as the one of former vector,
-                    // string goes into output, no zeroize
-                    Ok(b) => Ok(b),
-                    // in case of conversion error, the vector goes into error;
-                    // should be zeroized
-                    Err(e) => {
-                        let mut cleanup = e.into_bytes();
-                        cleanup.zeroize();
-                        Err(Error::DecodedSecretNotString)
-                    }
-                },
-                Err(_) => Err(Error::DecodingFailed),
-            }
+            decrypt_bytes(key, nonce, data)
         } else {
             Err(Error::NotReadyToDecode)
         }
     }
+    /// Key derivation of the set.
+    pub(crate) fn key_derivation(&self) -> KeyDerivation {
+        self.key_derivation
+    }
+    /// Recover the secret and immediately split it again with new `options`,
+    /// for example for periodic rotation of custodians.
+    ///
+    /// New split keeps the title; the passphrase is kept too, unless `new_passphrase` is given.
+    /// Recovered secret never leaves the function, and is wiped before returning.
+    /// Private split needs the manifest, and could not be produced here.
+    pub fn recover_and_resplit(
+        &self,
+        passphrase: &str,
+        new_passphrase: Option<&str>,
+        options: &EncryptOptions,
+    ) -> Result<Vec<String>, Error> {
+        let title = self.known_title().ok_or(Error::ManifestNeeded)?;
+        self.recover_and_encrypt(
+            passphrase,
+            title,
+            new_passphrase.unwrap_or(passphrase),
+            options,
+        )
+    }
+    /// Recover the secret, and encrypt it with given title and passphrase;
+    /// the secret is wiped before returning.
+    fn recover_and_encrypt(
+        &self,
+        passphrase: &str,
+        title: &str,
+        new_passphrase: &str,
+        options: &EncryptOptions,
+    ) -> Result<Vec<String>, Error> {
+        let secret = Zeroizing::new(self.recover_with_passphrase(passphrase)?);
+        encrypt_with_options(&secret, title, new_passphrase, options)
+    }
+    /// Options for the new split, with key derivation, time-lock, and salt override of the set.
+    fn resplit_options(&self, total_shards: usize, required_shards: usize) -> EncryptOptions {
+        let options = EncryptOptions::new(total_shards, required_shards)
+            .kdf(self.key_derivation.algorithm)
+            .time_lock(self.key_derivation.rounds);
+        match &self.salt {
+            Some(salt) => options.salt_override(salt),
+            None => options,
+        }
+    }
+    /// Change the passphrase: decrypt the secret with the `old` passphrase, and encrypt it
+    /// again with the `new` one into a fresh set of `total_shards` shares, `required_shards`
+    /// of them needed for recovery. Plaintext is never returned to the caller.
+    ///
+    /// Key derivation and time-lock of the set are kept, see [`ShareSet::recover_and_resplit`].
+    pub fn reencrypt_with_new_passphrase(
+        &self,
+        old: &str,
+        new: &str,
+        total_shards: usize,
+        required_shards: usize,
+    ) -> Result<Vec<String>, Error> {
+        let options = self.resplit_options(total_shards, required_shards);
+        self.recover_and_resplit(old, Some(new), &options)
+    }
+    /// Rename the backup: decrypt the secret, and encrypt it again under `new_title`
+    /// into a fresh set of `total_shards` shares.
+    ///
+    /// Title is the key derivation salt, so the whole set is re-encrypted,
+    /// and all the old shares are to be replaced. Passphrase, number of required shards,
+    /// key derivation, and time-lock of the set are kept.
+    pub fn retitle(
+        &self,
+        passphrase: &str,
+        new_title: &str,
+        total_shards: usize,
+    ) -> Result<Vec<String>, Error> {
+        let required_shards = self.known_required_shards().ok_or(Error::ManifestNeeded)?;
+        let options = self.resplit_options(total_shards, required_shards);
+        self.recover_and_encrypt(passphrase, new_title, passphrase, &options)
+    }
+    /// Upgrade the legacy set, i.e. with hexadecimal shares without version,
+    /// into an equivalent set of `total_shards` shares in V1 format.
+    ///
+    /// Title, passphrase, and number of required shards are kept.
+    /// Secret is decrypted and encrypted again internally, and never returned.
+    pub fn upgrade_legacy(
+        &self,
+        passphrase: &str,
+        total_shards: usize,
+    ) -> Result<Vec<String>, Error> {
+        if self.version != Version::Undefined {
+            return Err(Error::NotLegacySet);
+        }
+        let required_shards = self.known_required_shards().ok_or(Error::ManifestNeeded)?;
+        let title = self.known_title().ok_or(Error::ManifestNeeded)?;
+        let options = self.resplit_options(total_shards, required_shards);
+        self.recover_and_encrypt(passphrase, title, passphrase, &options)
+    }
+    /// Migrate the V1 or legacy set into V2 format, with the V2 features chosen in `options`,
+    /// for example checksums, key commitment, and key derivation parameters.
+    ///
+    /// Title and passphrase are kept; number of shares is set by the `options`.
+    /// Shares are produced in V2 format even if no option needs it.
+    /// Secret is decrypted and encrypted again internally, and never returned.
+    pub fn migrate_to_v2(
+        &self,
+        passphrase: &str,
+        options: &EncryptOptions,
+    ) -> Result<Vec<String>, Error> {
+        if self.version == Version::V2 {
+            return Err(Error::NotLegacySet);
+        }
+        let title = self.known_title().ok_or(Error::ManifestNeeded)?;
+        let options = options.clone().force_v2(true);
+        self.recover_and_encrypt(passphrase, title, passphrase, &options)
+    }
+    /// Start recovery with the key derivation done in small steps,
+    /// see [`ChunkedRecovery`].
+    /// Should be accessible through user interface only for ShareSetState::SetCombined.
+    pub fn start_recovery(&self, passphrase: &str) -> Result<ChunkedRecovery, Error> {
+        if let ShareSetState::SetCombined(SetCombined { data, nonce, .. }) = &self.state {
+            let salt = self.salt()?;
+            ChunkedRecovery::new(
+                self.key_derivation,
+                passphrase.as_bytes(),
+                &salt,
+                data.to_owned(),
+                nonce.to_vec(),
+                self.key_commitment,
+                self.cancellation.clone(),
+            )
+        } else {
+            Err(Error::NotReadyToDecode)
+        }
+    }
+    /// Save the set, in progress or combined, so that the collection could be
+    /// resumed later, for example after the app restarts, see [`ShareSet::from_bytes`].
+    ///
+    /// Saved set holds the contents of the collected shares, and should be stored
+    /// as carefully as the shares themselves. Metrics hook, cancellation token,
+    /// scan statistics, and the errors kept by [`Extend::extend`] are not saved.
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let shares = self.shares_in_set();
+        let snapshot = SetSnapshot {
+            format: SNAPSHOT_FORMAT,
+            version: self.version.tag(),
+            fingerprint: self.fingerprint.to_owned(),
+            title: self.title.to_owned(),
+            required_shards: self.required_shards,
+            kdf: self.key_derivation.to_header(),
+            key_commitment: self.key_commitment.map(hex::encode),
+            not_before: self.validity.not_before,
+            not_after: self.validity.not_after,
+            set_key: self.set_key.map(hex::encode),
+            set_id: self.set_id.to_owned(),
+            salt: self.salt.as_ref().map(hex::encode),
+            manifest: self.manifest.to_owned(),
+            error_correction: self.error_correction,
+            bits: shares.bits,
+            nonce: shares.nonce.to_owned(),
+            content_length: shares.content_length,
+            shares: shares
+                .id_set
+                .iter()
+                .zip(shares.content_set.iter())
+                .map(|(id, content)| SnapshotShare {
+                    id: *id,
+                    content: BASE64.encode(content),
+                })
+                .collect(),
+        };
+        Zeroizing::new(serde_json::to_vec(&snapshot).expect("snapshot is serializable"))
+    }
+    /// Restore the set saved with [`ShareSet::to_bytes`].
+    ///
+    /// Shares are added again one by one, same as with [`ShareSet::try_add_share`],
+    /// so the set restored with enough shares is combined again.
+    /// Saved set that is damaged or altered is rejected with [`Error::SetSnapshotInvalid`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let snapshot: SetSnapshot =
+            serde_json::from_slice(bytes).map_err(|_| Error::SetSnapshotInvalid)?;
+        if snapshot.format != SNAPSHOT_FORMAT {
+            return Err(Error::SetSnapshotInvalid);
+        }
+        let version = match snapshot.version {
+            0 => Version::Undefined,
+            1 => Version::V1,
+            2 => Version::V2,
+            _ => return Err(Error::SetSnapshotInvalid),
+        };
+        let max = max_shares(snapshot.bits).ok_or(Error::BitsOutOfRange(snapshot.bits))?;
+        let kdf = json::parse(&snapshot.kdf.to_string()).map_err(|_| Error::SetSnapshotInvalid)?;
+        let key_derivation = KeyDerivation::from_header(&kdf)?;
+        let validity = Validity {
+            not_before: snapshot.not_before,
+            not_after: snapshot.not_after,
+        };
+        validity.check()?;
+        let salt = snapshot
+            .salt
+            .as_deref()
+            .map(|a| hex::decode(a).map_err(|_| Error::SetSnapshotInvalid))
+            .transpose()?;
+        let fingerprint = fingerprint(
+            &version,
+            snapshot.title.as_deref().unwrap_or_default(),
+            snapshot.required_shards.unwrap_or_default(),
+            snapshot.bits,
+            &snapshot.nonce,
+        );
+        if fingerprint != snapshot.fingerprint {
+            return Err(Error::SetSnapshotInvalid);
+        }
+        let mut set = Self {
+            version,
+            title: snapshot.title.to_owned(),
+            required_shards: snapshot.required_shards,
+            manifest: None,
+            metrics: None,
+            cancellation: CancellationToken::default(),
+            gf_strategy: GfStrategy::default(),
+            error_correction: snapshot.error_correction,
+            fingerprint,
+            key_derivation,
+            key_commitment: snapshot_field(snapshot.key_commitment.as_deref())?,
+            validity,
+            set_key: snapshot_field(snapshot.set_key.as_deref())?,
+            set_id: snapshot.set_id.to_owned(),
+            salt,
+            stats: ScanStats::default(),
+            extend_errors: Vec::new(),
+            state: ShareSetState::SetInProgress(SetInProgress {
+                bits: snapshot.bits,
+                id_set: Vec::new(),
+                content_length: snapshot.content_length,
+                content_set: Vec::new(),
+                nonce: snapshot.nonce.to_owned(),
+            }),
+        };
+        if let Some(ref manifest) = snapshot.manifest {
+            set.apply_manifest(manifest.to_owned())?;
+        }
+        for share in snapshot.shares.iter() {
+            if share.id == 0 || share.id > max {
+                return Err(Error::ShareIdOutOfRange(share.id));
+            }
+            let content = BASE64
+                .decode(&share.content)
+                .map_err(|_| Error::SetSnapshotInvalid)?;
+            set.try_add_share(Share {
+                version: set.version,
+                title: set.title.to_owned(),
+                required_shards: set.required_shards,
+                nonce: snapshot.nonce.to_owned(),
+                bits: snapshot.bits,
+                id: share.id,
+                content: Zeroizing::new(content),
+                key_derivation: set.key_derivation,
+                key_commitment: set.key_commitment,
+                checksum: None,
+                tags: Vec::new(),
+                validity: set.validity,
+                set_key: set.set_key,
+                set_id: set.set_id.to_owned(),
+                salt: set.salt.to_owned(),
+            })?;
+        }
+        Ok(set)
+    }
+    /// Shares of the set, in progress or combined.
+    fn shares_in_set(&self) -> &SetInProgress {
+        match &self.state {
+            ShareSetState::SetInProgress(set_in_progress) => set_in_progress,
+            ShareSetState::SetCombined(set_combined) => &set_combined.shares,
+        }
+    }
+}
+
+/// Format of the saved set, see [`ShareSet::to_bytes`].
+const SNAPSHOT_FORMAT: u8 = 1;
+
+/// Saved set, see [`ShareSet::to_bytes`].
+/// Keys and salt are hex encoded, share contents are base64 encoded.
+#[derive(Serialize, Deserialize)]
+struct SetSnapshot {
+    format: u8,
+    version: u8,
+    fingerprint: String,
+    title: Option<String>,
+    required_shards: Option<usize>,
+    kdf: serde_json::Value,
+    key_commitment: Option<String>,
+    not_before: Option<u64>,
+    not_after: Option<u64>,
+    set_key: Option<String>,
+    set_id: Option<String>,
+    salt: Option<String>,
+    manifest: Option<Manifest>,
+    #[serde(default)]
+    error_correction: bool,
+    bits: u32,
+    nonce: String,
+    content_length: usize,
+    shares: Vec<SnapshotShare>,
+}
+
+/// Share of the saved set; content is zeroized once the share is dropped.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct SnapshotShare {
+    id: u32,
+    content: String,
+}
+
+/// Fixed length key field of the saved set, hex encoded.
+fn snapshot_field<const N: usize>(field: Option<&str>) -> Result<Option<[u8; N]>, Error> {
+    field
+        .map(|a| {
+            let mut out = [0; N];
+            hex::decode_to_slice(a, &mut out)
+                .map(|_| out)
+                .map_err(|_| Error::SetSnapshotInvalid)
+        })
+        .transpose()
+}
+
+/// Adds the shares one by one, same as [`ShareSet::try_add_share`], so that
+/// the shares from a scanner pipeline could be collected into the set.
+/// Errors are kept for [`ShareSet::take_extend_errors`].
+impl Extend<Share> for ShareSet {
+    fn extend<T: IntoIterator<Item = Share>>(&mut self, shares: T) {
+        for share in shares {
+            let id = share.id;
+            if let Err(e) = self.try_add_share(share) {
+                self.extend_errors.push((id, e));
+            }
+        }
+    }
+}
+
+/// Tags must be non-empty, and not too long.
+pub(crate) fn check_tag_values(tags: &[String]) -> Result<(), Error> {
+    match tags.iter().find(|a| a.is_empty() || a.len() > MAX_TAG_LEN) {
+        Some(a) => Err(Error::TagInvalid(format!(
+            "tag {a:?} should be from 1 to {MAX_TAG_LEN} bytes long"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Decode the nonce directly into the fixed-size array.
+///
+/// Nonce length is checked here, cipher would panic on nonce of wrong length.
+pub(crate) fn decode_nonce(nonce: &str) -> Result<[u8; NONCE_LEN], Error> {
+    // buffer has room for the base64 decoding slack, so that short and long nonces
+    // are both reported with their decoded length
+    let mut buffer = [0u8; NONCE_LEN + 3];
+    let len = match BASE64.decode_slice(nonce, &mut buffer) {
+        Ok(a) => a,
+        Err(base64::DecodeSliceError::OutputSliceTooSmall) => {
+            return Err(Error::NonceLengthInvalid(base64::decoded_len_estimate(
+                nonce.len(),
+            )))
+        }
+        Err(base64::DecodeSliceError::DecodeError(_)) => return Err(Error::NonceNotBase64),
+    };
+    match buffer[..len].try_into() {
+        Ok(a) => Ok(a),
+        Err(_) => Err(Error::NonceLengthInvalid(len)),
+    }
+}
+
+/// Decrypt the secret with derived key.
+pub(crate) fn decrypt(key: &[u8], nonce: &[u8], data: &[u8]) -> Result<String, Error> {
+    decrypt_bytes(key, nonce, data).and_then(secret_string)
+}
+
+/// Decrypt the secret bytes with derived key.
+pub(crate) fn decrypt_bytes(
+    key: &[u8],
+    nonce: &[u8],
+    data: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    paranoid::cipher_input(key, nonce)?;
+    let cipher = XSalsa20Poly1305::new(GenericArray::from_slice(key));
+    match cipher.decrypt(GenericArray::from_slice(nonce), data) {
+        Ok(a) => {
+            trace_event!("secret decrypted");
+            Ok(Zeroizing::new(a))
+        }
+        Err(_) => {
+            trace_event!("decryption failed");
+            Err(Error::DecodingFailed)
+        }
+    }
+}
+
+/// Decrypted secret as string.
+fn secret_string(mut secret: Zeroizing<Vec<u8>>) -> Result<String, Error> {
+    match String::from_utf8(std::mem::take(&mut *secret)) {
+        // in case of successful vector-to-string conversion, vector does not get copied:
+        // https://doc.rust-lang.org/std/string/struct.String.html#method.from_utf8
+        // string ptr same as the one of former vector,
+        // string goes into output, no zeroize
+        Ok(a) => Ok(a),
+        // in case of conversion error, the vector goes into error;
+        // should be zeroized
+        Err(e) => {
+            let mut cleanup = e.into_bytes();
+            cleanup.zeroize();
+            Err(Error::DecodedSecretNotString)
+        }
+    }
+}
+
+/// Warnings on key derivation parameters.
+fn key_derivation_warnings(key_derivation: &KeyDerivation) -> Vec<Warning> {
+    if key_derivation.is_weak() {
+        vec![Warning::WeakKdf(key_derivation.algorithm)]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Function to calculate set fingerprint from public split parameters.
+/// Variable length fields are length-prefixed, so that different parameter
+/// combinations could not produce the same hashed input.
+fn fingerprint(
+    version: &Version,
+    title: &str,
+    required_shards: usize,
+    bits: u32,
+    nonce: &str,
+) -> String {
+    let mut hasher = Sha512::new();
+    hasher.update(b"banana_split fingerprint");
+    hasher.update([version.tag()]);
+    hasher.update((title.len() as u64).to_be_bytes());
+    hasher.update(title.as_bytes());
+    hasher.update((required_shards as u64).to_be_bytes());
+    hasher.update(bits.to_be_bytes());
+    hasher.update((nonce.len() as u64).to_be_bytes());
+    hasher.update(nonce.as_bytes());
+    let hash = hasher.finalize();
+    let hex = hex::encode(&hash[..FINGERPRINT_LEN]);
+    format!("{}-{}", &hex[..FINGERPRINT_LEN], &hex[FINGERPRINT_LEN..])
+}
+
+/// Subsets of `k` out of `n` shares, as share positions.
+///
+/// All subsets, if there are at most `max` of them; otherwise `n` subsets of
+/// consecutive shares, wrapping around, so that each share is in `k` of them.
+pub(crate) fn share_subsets(n: usize, k: usize, max: usize) -> Vec<Vec<usize>> {
+    if k == 0 || k > n {
+        return Vec::new();
+    }
+    if binomial(n, k) > max {
+        return (0..n)
+            .map(|i| (0..k).map(|j| (i + j) % n).collect())
+            .collect();
+    }
+    let mut out = Vec::new();
+    let mut current: Vec<usize> = (0..k).collect();
+    loop {
+        out.push(current.clone());
+        let Some(i) = (0..k).rev().find(|i| current[*i] < n - k + i) else {
+            return out;
+        };
+        current[i] += 1;
+        for j in i + 1..k {
+            current[j] = current[j - 1] + 1;
+        }
+    }
+}
+
+/// Number of subsets of `k` out of `n`, saturating.
+fn binomial(n: usize, k: usize) -> usize {
+    let mut out: usize = 1;
+    for i in 0..k {
+        out = match out.checked_mul(n - i) {
+            Some(a) => a / (i + 1),
+            None => return usize::MAX,
+        };
+    }
+    out
 }
 
 /// Primitive polynomials in Galois field GF(2^n), for 3 <= n <= 20.
@@ -417,6 +1991,9 @@ fn primitive_polynomial(n: u32) -> u32 {
     PRIMITIVE_POLYNOMIALS[n as usize - 3]
 }
 
+/// Marker of the undefined logarithm, i.e. `log[0]`, in tables filled by [`fill_logs_and_exps`].
+pub(crate) const UNDEFINED_LOG: u32 = u32::MAX;
+
 /// Function to generate a table of logarithms and exponents in GF(2^n) for given n (i.e. bits).
 /// Already checked that n is within the acceptable range.
 /// There are total n exponents and n logarithms generated, with values within the field.
@@ -424,21 +2001,32 @@ fn primitive_polynomial(n: u32) -> u32 {
 /// (this is quite self-evident, but will be needed later on).
 ///
 pub(crate) fn generate_logs_and_exps(n: u32) -> (Vec<Option<u32>>, Vec<u32>) {
-    let size = 2u32.pow(n); // the number of elements in GF(2^n)
+    let size = 2usize.pow(n); // the number of elements in GF(2^n)
+    let mut logs = vec![0; size];
+    let mut exps = vec![0; size];
+    fill_logs_and_exps(n, &mut logs, &mut exps);
+    let logs = logs
+        .into_iter()
+        .map(|a| (a != UNDEFINED_LOG).then_some(a))
+        .collect();
+    (logs, exps)
+}
 
-    let mut logs: Vec<Option<u32>> = Vec::with_capacity(size as usize);
-    for _i in 0..size {
-        logs.push(None)
-    } // 0th element could not be reached during the cycling and is undefined
+/// Same as [`generate_logs_and_exps`], but fills tables provided by the caller,
+/// with length 2^n each; undefined `log[0]` is marked with [`UNDEFINED_LOG`].
+///
+pub(crate) fn fill_logs_and_exps(n: u32, logs: &mut [u32], exps: &mut [u32]) {
+    let size = 2u32.pow(n); // the number of elements in GF(2^n)
 
-    let mut exps: Vec<u32> = Vec::with_capacity(size as usize);
+    // 0th element could not be reached during the cycling and is undefined
+    logs.fill(UNDEFINED_LOG);
 
     let mut x = 1;
     let primitive_polynomial = primitive_polynomial(n);
     for i in 0..size {
-        exps.push(x);
-        if logs[x as usize].is_none() {
-            logs[x as usize] = Some(i)
+        exps[i as usize] = x;
+        if logs[x as usize] == UNDEFINED_LOG {
+            logs[x as usize] = i
         } // x = 1 is encountered twice
         x <<= 1; // left shift
         if x >= size {
@@ -446,49 +2034,114 @@ pub(crate) fn generate_logs_and_exps(n: u32) -> (Vec<Option<u32>>, Vec<u32>) {
             x &= size - 1; // Bitwise AND
         }
     }
-    (logs, exps)
 }
 
 /// Function calculates Lagrange interpolation polynomial in GF(2^n).
-/// x is vector of share identification numbers, and y is vector of certain number components from each share data;
+/// x is vector of share identification numbers, and y are the corresponding number components from each share data;
 /// x and y length are always identical, and do not exceed the maximum number of shares, 2^n-1;
-/// logs and exps are the vectors of pre-calculated logarithms and exponents, with length 2^n;
+/// logs and exps are the tables of pre-calculated logarithms and exponents, with length 2^n,
+/// filled by [`fill_logs_and_exps`];
 ///
 pub(crate) fn lagrange(
     x: &[u32],
-    y: &[u32],
-    logs: &[Option<u32>],
+    y: impl Iterator<Item = u32>,
+    logs: &[u32],
     exps: &[u32],
     n: u32,
 ) -> Result<u32, Error> {
     let mut sum = 0;
     let size = 2u32.pow(n);
-    let len = x.len();
-
-    for i in 0..len {
-        match logs.get(y[i] as usize) {
-            Some(Some(a)) => {
-                let mut product = *a;
-                for j in 0..len {
-                    if i != j {
-                        let p1 = match logs.get(x[j] as usize) {
-                            Some(a) => a.expect(
-                                "x[j] is never zero, it is share number, numbering starts from 1",
-                            ),
-                            None => return Err(Error::LogOutOfRange(x[j])),
-                        };
-                        let p2 = match logs.get((x[i]^x[j]) as usize) {
-                            Some(a) => a.expect("x[i] and x[j] are never equal for non-equal i and j, through Galois field properties"),
-                            None => return Err(Error::LogOutOfRange(x[i]^x[j])),
-                        };
-                        product = ((size - 1) + product + p1 - p2) % (size - 1);
-                    }
+    let log = |a: u32| match logs.get(a as usize) {
+        Some(&UNDEFINED_LOG) => Ok(None),
+        Some(b) => Ok(Some(*b)),
+        None => Err(Error::LogOutOfRange(a)),
+    };
+
+    for (i, (xi, yi)) in x.iter().zip(y).enumerate() {
+        // values of y elements are u8 by decoding, and could in principle exceed 2^n number of elements in logs;
+        // undefined log is the only undefined element (through Galois field properties), i.e. tried to calculate log[0],
+        // zero does not contribute to the sum
+        if let Some(a) = log(yi)? {
+            let mut product = a;
+            for (j, xj) in x.iter().enumerate() {
+                if i != j {
+                    // x[j] is share number, it is checked to be non-zero and within the field when the share is parsed
+                    let p1 = log(*xj)?.ok_or(Error::LogOutOfRange(*xj))?;
+                    // x[i] and x[j] are never equal for non-equal i and j, duplicate shares are not added to the set
+                    let p2 = log(xi ^ xj)?.ok_or(Error::LogOutOfRange(xi ^ xj))?;
+                    product = ((size - 1) + product + p1 - p2) % (size - 1);
                 }
-                sum ^= exps[product as usize]; // product is always positive and below 2^n, exponent is always addressed correctly
             }
-            Some(None) => (), // encountered the only undefined element (through Galois field properties), i.e. tried to calculate log[0]
-            None => return Err(Error::LogOutOfRange(y[i])), // this should not happen, but values of y elements are u8 by decoding, and could in principle exceed 2^n number of elements in logs vector
+            // product is always positive and below 2^n, exponent is always addressed correctly
+            match exps.get(product as usize) {
+                Some(a) => sum ^= a,
+                None => return Err(Error::LogOutOfRange(product)),
+            }
         }
     }
     Ok(sum)
 }
+
+/// Multiplication in GF(2^n), without tables: carry-less multiplication,
+/// reduced by the primitive polynomial. Both values are below 2^n.
+pub(crate) fn gf_mul(mut a: u32, mut b: u32, n: u32) -> u32 {
+    let size = 2u32.pow(n);
+    let primitive_polynomial = primitive_polynomial(n);
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 == 1 {
+            product ^= a;
+        }
+        b >>= 1;
+        a <<= 1;
+        if a >= size {
+            a ^= primitive_polynomial;
+            a &= size - 1;
+        }
+    }
+    product
+}
+
+/// Inverse of non-zero value in GF(2^n), without tables, as `a^(2^n - 2)`.
+fn gf_inv(a: u32, n: u32) -> u32 {
+    let mut exponent = 2u32.pow(n) - 2;
+    let mut base = a;
+    let mut inverse = 1;
+    while exponent != 0 {
+        if exponent & 1 == 1 {
+            inverse = gf_mul(inverse, base, n);
+        }
+        base = gf_mul(base, base, n);
+        exponent >>= 1;
+    }
+    inverse
+}
+
+/// Lagrange basis coefficients at zero for share numbers `x`, in GF(2^n), without tables:
+/// the secret element is the sum of `y[i] * coefficient[i]`, see [`lagrange`].
+///
+/// Coefficients depend only on the share numbers, and are calculated once for all the elements.
+pub(crate) fn lagrange_coefficients(x: &[u32], n: u32) -> Result<Vec<u32>, Error> {
+    let size = 2u32.pow(n);
+    x.iter()
+        .enumerate()
+        .map(|(i, xi)| {
+            let mut numerator = 1;
+            let mut denominator = 1;
+            for (j, xj) in x.iter().enumerate() {
+                if i != j {
+                    // same checks as for the table logarithms of x[j] and x[i] ^ x[j]
+                    if *xj == 0 || *xj >= size {
+                        return Err(Error::LogOutOfRange(*xj));
+                    }
+                    if xi ^ xj == 0 || xi ^ xj >= size {
+                        return Err(Error::LogOutOfRange(xi ^ xj));
+                    }
+                    numerator = gf_mul(numerator, *xj, n);
+                    denominator = gf_mul(denominator, xi ^ xj, n);
+                }
+            }
+            Ok(gf_mul(numerator, gf_inv(denominator, n), n))
+        })
+        .collect()
+}
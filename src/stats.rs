@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::shares::RejectionReason;
+
+/// Statistics of the scanning session, see [`ShareSet::scan_stats`](crate::ShareSet::scan_stats)
+/// and [`ShareSetCollection::scan_stats`](crate::ShareSetCollection::scan_stats),
+/// for the user interface to show hints, for example "share 2 was scanned three times".
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ScanStats {
+    /// Shares accepted into the set.
+    pub accepted: usize,
+    /// Repeated scans of shares already in the set, by share number.
+    /// Share scanned three times has 2 repeated scans.
+    pub duplicates: BTreeMap<u32, usize>,
+    /// Shares rejected as not belonging to the set, by reason, in the order first seen.
+    /// Repeated scans are counted in [`ScanStats::duplicates`] instead.
+    pub rejected: Vec<(RejectionReason, usize)>,
+    /// Scanned payloads that are not shares at all; counted only by
+    /// [`ShareSetCollection::add_payload`](crate::ShareSetCollection::add_payload).
+    pub not_shares: usize,
+    first_share: Option<Instant>,
+}
+
+impl ScanStats {
+    /// Time passed since the first share was accepted.
+    pub fn since_first_share(&self) -> Option<Duration> {
+        self.first_share.map(|a| a.elapsed())
+    }
+    pub(crate) fn record_accepted(&mut self) {
+        self.accepted += 1;
+        if self.first_share.is_none() {
+            self.first_share = Some(Instant::now());
+        }
+    }
+    pub(crate) fn record_rejected(&mut self, reason: RejectionReason) {
+        match reason {
+            RejectionReason::AlreadyInSet(id) => *self.duplicates.entry(id).or_default() += 1,
+            reason => match self.rejected.iter_mut().find(|(a, _)| *a == reason) {
+                Some((_, count)) => *count += 1,
+                None => self.rejected.push((reason, 1)),
+            },
+        }
+    }
+    /// Add up the statistics of another set.
+    pub(crate) fn merge(&mut self, other: &ScanStats) {
+        self.accepted += other.accepted;
+        for (id, count) in &other.duplicates {
+            *self.duplicates.entry(*id).or_default() += count;
+        }
+        for (reason, count) in &other.rejected {
+            match self.rejected.iter_mut().find(|(a, _)| a == reason) {
+                Some((_, total)) => *total += count,
+                None => self.rejected.push((*reason, *count)),
+            }
+        }
+        self.first_share = match (self.first_share, other.first_share) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+}
@@ -0,0 +1,136 @@
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+use crate::error::Error;
+use crate::shares::Share;
+
+/// Marker at the start of embedded data, to tell images carrying shares apart.
+const STEGO_MAGIC: &[u8] = b"BSST";
+
+/// Header length in bytes: marker and payload length.
+const HEADER_LEN: usize = STEGO_MAGIC.len() + 4;
+
+/// Limit on embedded payload size, in bytes, same as for decompressed shares.
+const MAX_EMBEDDED_LEN: usize = 1 << 16;
+
+/// Hide the share payload in the least significant bits of the carrier png image,
+/// for example a family photo, instead of printing an obvious qr code.
+///
+/// Payload is checked to be a share. Color channels carry one bit each,
+/// alpha channel is left untouched, so that transparent areas stay as they are.
+/// Output is a png image with 8 bits per channel; carrier metadata chunks
+/// and animation frames other than the first are dropped.
+///
+/// Lossy recompression, resizing, or editing of the output image destroys the share.
+pub fn embed_in_png(share: &[u8], carrier_image: &[u8]) -> Result<Vec<u8>, Error> {
+    let _ = Share::new(share)?;
+    if share.len() > MAX_EMBEDDED_LEN {
+        return Err(Error::ImageTooSmall(bits_needed(share.len())));
+    }
+    let mut image = Image::decode(carrier_image)?;
+
+    let mut data = STEGO_MAGIC.to_vec();
+    data.extend_from_slice(&(share.len() as u32).to_be_bytes());
+    data.extend_from_slice(share);
+    let needed = bits_needed(share.len());
+    let samples: Vec<usize> = image.color_samples().take(needed).collect();
+    if samples.len() < needed {
+        return Err(Error::ImageTooSmall(needed));
+    }
+    let bits = data
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1));
+    for (sample, bit) in samples.into_iter().zip(bits) {
+        image.pixels[sample] = (image.pixels[sample] & !1) | bit;
+    }
+    image.encode()
+}
+
+/// Extract the share payload hidden in png image by [`embed_in_png`].
+///
+/// Payload is returned as is, to be parsed with [`Share::new`](crate::Share::new).
+pub fn extract_from_png(image: &[u8]) -> Result<Vec<u8>, Error> {
+    let image = Image::decode(image)?;
+    let mut samples = image.color_samples().map(|a| image.pixels[a] & 1);
+    let mut read_byte = || {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | samples.next()?;
+        }
+        Some(byte)
+    };
+    let header = (0..HEADER_LEN)
+        .map(|_| read_byte())
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(Error::NoShareInImage)?;
+    if !header.starts_with(STEGO_MAGIC) {
+        return Err(Error::NoShareInImage);
+    }
+    let mut len = [0; 4];
+    len.copy_from_slice(&header[STEGO_MAGIC.len()..]);
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_EMBEDDED_LEN {
+        return Err(Error::NoShareInImage);
+    }
+    (0..len)
+        .map(|_| read_byte())
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(Error::NoShareInImage)
+}
+
+/// Number of color samples needed to carry the payload of given length.
+fn bits_needed(payload_len: usize) -> usize {
+    (HEADER_LEN + payload_len) * 8
+}
+
+/// Decoded image, 8 bits per channel.
+struct Image {
+    width: u32,
+    height: u32,
+    color_type: ColorType,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn decode(png: &[u8]) -> Result<Self, Error> {
+        let mut decoder = Decoder::new(png);
+        // palette and low bit depth images are expanded, 16 bit channels are stripped
+        decoder.set_transformations(Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|_| Error::ImageInvalid)?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut pixels)
+            .map_err(|_| Error::ImageInvalid)?;
+        pixels.truncate(info.buffer_size());
+        let (color_type, bit_depth) = reader.output_color_type();
+        if bit_depth != BitDepth::Eight {
+            return Err(Error::ImageInvalid);
+        }
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            color_type,
+            pixels,
+        })
+    }
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        let mut encoder = Encoder::new(&mut out, self.width, self.height);
+        encoder.set_color(self.color_type);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|_| Error::ImageInvalid)?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(|_| Error::ImageInvalid)?;
+        writer.finish().map_err(|_| Error::ImageInvalid)?;
+        Ok(out)
+    }
+    /// Positions of color samples in pixel data, alpha channel skipped.
+    fn color_samples(&self) -> impl Iterator<Item = usize> {
+        let channels = self.color_type.samples();
+        let color_channels = match self.color_type {
+            ColorType::GrayscaleAlpha | ColorType::Rgba => channels - 1,
+            _ => channels,
+        };
+        (0..self.pixels.len()).filter(move |a| a % channels < color_channels)
+    }
+}
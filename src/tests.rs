@@ -1,6 +1,13 @@
-use crate::encrypt::encrypt;
+use crate::encrypt::{
+    encrypt, encrypt_bytes, encrypt_bytes_with_options, encrypt_with_manifest,
+    encrypt_with_options, horner, migrate_v1_to_v2, retitle, upgrade_legacy, Distribution,
+    EncryptOptions, Padding,
+};
 use crate::shares::{generate_logs_and_exps, BIT_RANGE};
-use crate::{NextAction, Share, ShareSet};
+use crate::{
+    CollectedShare, Error, KdfAlgorithm, Manifest, NextAction, RecoveryStatus, Share, ShareRequest,
+    ShareResponse, ShareSet, Warning,
+};
 
 const SECRET_SEEDPHRASE: &str =
     "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
@@ -29,7 +36,12 @@ fn alice_recovers_secret1() {
     let share1 = Share::new(hex::decode(SCAN_A1).unwrap()).unwrap();
     let mut share_set = ShareSet::init(share1);
     assert!(
-        share_set.next_action() == NextAction::MoreShares { have: 1, need: 2 },
+        share_set.next_action()
+            == NextAction::MoreShares {
+                have: 1,
+                need: 2,
+                collected: vec![1]
+            },
         "Single share is not enough."
     );
 
@@ -106,7 +118,12 @@ fn alice_recovers_secret5() {
     let share2 = Share::new(hex::decode(SCAN_C2).unwrap()).unwrap();
     share_set.try_add_share(share2).unwrap();
     assert!(
-        share_set.next_action() == NextAction::MoreShares { have: 2, need: 3 },
+        share_set.next_action()
+            == NextAction::MoreShares {
+                have: 2,
+                need: 3,
+                collected: vec![1, 2]
+            },
         "Two shares not enough this time."
     );
     let share3 = Share::new(hex::decode(SCAN_C3).unwrap()).unwrap();
@@ -249,3 +266,3835 @@ fn test_encrypt_decrypt_empty() {
     let alice_secret = share_set.recover_with_passphrase("").unwrap();
     assert_eq!(alice_secret, "", "Unexpected secret!");
 }
+
+#[test]
+fn fingerprint_is_same_within_set() {
+    let share1 = Share::new(hex::decode(SCAN_A1).unwrap()).unwrap();
+    let share2 = Share::new(hex::decode(SCAN_A2).unwrap()).unwrap();
+    let share3 = Share::new(hex::decode(SCAN_A3).unwrap()).unwrap();
+    assert_eq!(share1.fingerprint(), share2.fingerprint());
+    assert_eq!(share1.fingerprint(), share3.fingerprint());
+    assert_eq!(share1.fingerprint().len(), 9);
+
+    let share_set = ShareSet::init(share1);
+    assert_eq!(share_set.fingerprint(), share2.fingerprint());
+
+    let other = Share::new(hex::decode(SCAN_C1).unwrap()).unwrap();
+    assert_ne!(
+        share_set.fingerprint(),
+        other.fingerprint(),
+        "Different sets must have different fingerprints."
+    );
+}
+
+#[test]
+fn fingerprint_changes_after_refresh() {
+    let old = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let new = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let old_share = Share::new(old[0].clone().into_bytes()).unwrap();
+    let new_share = Share::new(new[0].clone().into_bytes()).unwrap();
+    assert_ne!(
+        old_share.fingerprint(),
+        new_share.fingerprint(),
+        "Stale share should be detected by fingerprint."
+    );
+}
+
+#[test]
+fn weighted_distribution() {
+    let distribution = Distribution::new(3)
+        .custodian("Alice", 2)
+        .custodian("Bob", 1)
+        .custodian("Carol", 1);
+    assert_eq!(distribution.total_shards(), Ok(4));
+    let custodians = distribution
+        .encrypt(SECRET_B, "title", PASSPHRASE_B)
+        .unwrap();
+    assert_eq!(custodians.len(), 3);
+    assert_eq!(custodians[0].label, "Alice");
+    assert_eq!(custodians[0].shares.len(), 2);
+    assert_eq!(custodians[1].shares.len(), 1);
+    assert_eq!(custodians[2].shares.len(), 1);
+
+    // Alice and Bob together have enough shares
+    let alice = &custodians[0].shares;
+    let bob = &custodians[1].shares;
+    let mut share_set = ShareSet::init(Share::new(alice[0].clone().into_bytes()).unwrap());
+    share_set
+        .try_add_share(Share::new(alice[1].clone().into_bytes()).unwrap())
+        .unwrap();
+    share_set
+        .try_add_share(Share::new(bob[0].clone().into_bytes()).unwrap())
+        .unwrap();
+    let secret = share_set.recover_with_passphrase(PASSPHRASE_B).unwrap();
+    assert_eq!(secret, SECRET_B);
+
+    // weights adding up past the share limit, or past usize, are refused
+    let crowded = Distribution::new(3)
+        .custodian("Alice", 200)
+        .custodian("Bob", 100);
+    assert_eq!(crowded.total_shards(), Err(Error::TooManyShares(255)));
+    let overflowing = Distribution::new(3)
+        .custodian("Alice", usize::MAX)
+        .custodian("Bob", 1);
+    assert_eq!(overflowing.total_shards(), Err(Error::TooManyShares(255)));
+    assert!(matches!(
+        overflowing.encrypt(SECRET_B, "title", PASSPHRASE_B),
+        Err(Error::TooManyShares(255))
+    ));
+}
+
+#[test]
+fn distribution_rejects_empty_custodian() {
+    let distribution = Distribution::new(2)
+        .custodian("Alice", 2)
+        .custodian("Bob", 0);
+    assert!(distribution
+        .encrypt(SECRET_B, "title", PASSPHRASE_B)
+        .is_err());
+}
+
+#[test]
+fn remote_collection_of_shares() {
+    let shares = encrypt(SECRET_B, "remote", PASSPHRASE_B, 3, 2).unwrap();
+
+    let request = ShareRequest::new("req-1", "remote");
+    let response = ShareResponse {
+        request_id: "req-1".to_string(),
+        payload: shares[0].clone(),
+    };
+    // messages survive the transport
+    let request: ShareRequest =
+        serde_json::from_str(&serde_json::to_string(&request).unwrap()).unwrap();
+    let response: ShareResponse =
+        serde_json::from_str(&serde_json::to_string(&response).unwrap()).unwrap();
+    let mut share_set = ShareSet::init(response.into_share(&request).unwrap());
+    assert_eq!(
+        RecoveryStatus::of(&share_set),
+        RecoveryStatus {
+            title: "remote".to_string(),
+            fingerprint: share_set.fingerprint(),
+            have: 1,
+            need: 2,
+        }
+    );
+
+    let request = ShareRequest::for_set("req-2", &share_set);
+    let wrong_id = ShareResponse {
+        request_id: "req-1".to_string(),
+        payload: shares[1].clone(),
+    };
+    assert!(wrong_id.ingest(&request, &mut share_set).is_err());
+
+    let other_set = encrypt(SECRET_B, "remote", PASSPHRASE_B, 3, 2).unwrap();
+    let stale = ShareResponse {
+        request_id: "req-2".to_string(),
+        payload: other_set[1].clone(),
+    };
+    assert!(stale.ingest(&request, &mut share_set).is_err());
+
+    let response = ShareResponse {
+        request_id: "req-2".to_string(),
+        payload: shares[1].clone(),
+    };
+    let status = response.ingest(&request, &mut share_set).unwrap();
+    assert!(status.is_complete());
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+}
+
+#[cfg(feature = "seal")]
+#[test]
+fn sealed_share_roundtrip() {
+    use base64::Engine;
+
+    use crate::{custodian_keypair, SealedShare};
+
+    let shares = encrypt(SECRET_B, "sealed", PASSPHRASE_B, 3, 2).unwrap();
+    let (secret_key, public_key) = custodian_keypair().unwrap();
+    let sealed = SealedShare::seal(&shares[0], &public_key).unwrap();
+    let transported = SealedShare::from_base64(&sealed.to_base64()).unwrap();
+    assert_eq!(transported, sealed);
+    assert!(!String::from_utf8_lossy(sealed.as_bytes()).contains("sealed"));
+
+    let opened = transported.open(&secret_key).unwrap();
+    assert_eq!(opened, shares[0]);
+
+    let (other_secret_key, _) = custodian_keypair().unwrap();
+    assert!(transported.open(&other_secret_key).is_err());
+
+    // sealed bytes that are not utf-8 are not a share
+    let not_utf8 = crypto_box::PublicKey::from(public_key)
+        .seal(&mut rand::thread_rng(), &[0xff, 0xfe])
+        .unwrap();
+    let sealed =
+        SealedShare::from_base64(&base64::engine::general_purpose::STANDARD.encode(not_utf8));
+    assert_eq!(
+        sealed.unwrap().open(&secret_key),
+        Err(Error::NotShareString)
+    );
+}
+
+#[test]
+fn time_locked_recovery() {
+    let options = EncryptOptions::new(3, 2).time_lock(3);
+    let shares = encrypt_with_options(SECRET_B, "locked", PASSPHRASE_B, &options).unwrap();
+    assert!(
+        shares[0].contains(r#""v":2"#),
+        "Time-lock requires V2 format."
+    );
+
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    assert_eq!(share_set.time_lock_rounds(), 3);
+
+    // share of the same secret without time-lock does not fit
+    let unlocked = encrypt(SECRET_B, "locked", PASSPHRASE_B, 3, 2).unwrap();
+    assert!(share_set
+        .try_add_share(Share::new(unlocked[1].clone().into_bytes()).unwrap())
+        .is_err());
+
+    share_set
+        .try_add_share(Share::new(shares[2].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+}
+
+#[test]
+fn time_lock_needs_rounds() {
+    let options = EncryptOptions::new(3, 2).time_lock(0);
+    assert!(encrypt_with_options(SECRET_B, "locked", PASSPHRASE_B, &options).is_err());
+}
+
+#[test]
+fn v2_share_with_excessive_kdf_is_rejected() {
+    let share =
+        r#"{"v":2,"t":"x","r":2,"d":"8AQ==","n":"","k":{"a":"scrypt","n":40,"r":8,"p":1,"c":1}}"#;
+    assert!(Share::new(share.as_bytes().to_vec()).is_err());
+    let share = r#"{"v":2,"t":"x","r":2,"d":"8AQ==","n":"","k":{"a":"bcrypt"}}"#;
+    assert!(Share::new(share.as_bytes().to_vec()).is_err());
+}
+
+#[test]
+fn v2_share_with_excessive_rounds_or_parallelism_is_rejected() {
+    for k in [
+        r#"{"a":"scrypt","n":10,"r":8,"p":1,"c":4294967295}"#,
+        r#"{"a":"scrypt","n":10,"r":8,"p":16000000,"c":1}"#,
+        r#"{"a":"scrypt","n":10,"r":8,"p":17,"c":1}"#,
+    ] {
+        let share = format!(r#"{{"v":2,"t":"x","r":2,"d":"8AQ==","n":"","k":{k}}}"#);
+        assert!(
+            matches!(
+                Share::new(share.as_str()),
+                Err(Error::KdfParamsNotSupported(_))
+            ),
+            "{k}"
+        );
+    }
+    let options = EncryptOptions::new(3, 2).time_lock(u32::MAX);
+    assert!(matches!(
+        encrypt_with_options(SECRET_B, "locked", PASSPHRASE_B, &options),
+        Err(Error::KdfParamsNotSupported(_))
+    ));
+}
+
+#[test]
+fn pbkdf2_recovery() {
+    let options = EncryptOptions::new(3, 2).kdf(KdfAlgorithm::PBKDF2_DEFAULT);
+    let shares = encrypt_with_options(SECRET_B, "pbkdf2", PASSPHRASE_B, &options).unwrap();
+    assert!(shares[0].contains(r#""a":"pbkdf2-sha512""#));
+
+    let mut share_set = ShareSet::init(Share::new(shares[1].clone().into_bytes()).unwrap());
+    share_set
+        .try_add_share(Share::new(shares[2].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+    assert!(share_set.recover_with_passphrase(PASSPHRASE_A).is_err());
+}
+
+#[test]
+fn pbkdf2_iterations_are_bounded() {
+    let share = r#"{"v":2,"t":"x","r":2,"d":"8AQ==","n":"","k":{"a":"pbkdf2-sha512","i":4294967295,"c":1}}"#;
+    assert!(matches!(
+        Share::new(share),
+        Err(Error::KdfParamsNotSupported(_))
+    ));
+    let options = EncryptOptions::new(3, 2).kdf(KdfAlgorithm::Pbkdf2 {
+        iterations: 100_000_000,
+    });
+    assert!(matches!(
+        encrypt_with_options(SECRET_B, "pbkdf2", PASSPHRASE_B, &options),
+        Err(Error::KdfParamsNotSupported(_))
+    ));
+}
+
+#[test]
+fn weak_kdf_is_refused_or_reported() {
+    let weak = KdfAlgorithm::Scrypt {
+        log_n: 12,
+        r: 8,
+        p: 1,
+    };
+    let options = EncryptOptions::new(3, 2).kdf(weak);
+    assert!(matches!(
+        encrypt_with_options(SECRET_B, "weak", PASSPHRASE_B, &options),
+        Err(Error::KdfParamsTooWeak(_))
+    ));
+
+    let options = options.allow_weak_kdf(true);
+    let shares = encrypt_with_options(SECRET_B, "weak", PASSPHRASE_B, &options).unwrap();
+    let share = Share::new(shares[0].clone().into_bytes()).unwrap();
+    assert_eq!(share.warnings(), vec![Warning::WeakKdf(weak)]);
+    let share_set = ShareSet::init(share);
+    assert_eq!(share_set.warnings(), vec![Warning::WeakKdf(weak)]);
+
+    let default = Share::new(hex::decode(SCAN_A1).unwrap()).unwrap();
+    assert!(default.warnings().is_empty());
+}
+
+#[test]
+fn insecure_kdf_is_rejected() {
+    let insecure = KdfAlgorithm::Pbkdf2 { iterations: 100 };
+    let options = EncryptOptions::new(3, 2).kdf(insecure).allow_weak_kdf(true);
+    assert!(matches!(
+        encrypt_with_options(SECRET_B, "weak", PASSPHRASE_B, &options),
+        Err(Error::KdfParamsTooWeak(_))
+    ));
+
+    let share =
+        r#"{"v":2,"t":"x","r":2,"d":"8AQ==","n":"","k":{"a":"scrypt","n":4,"r":1,"p":1,"c":1}}"#;
+    assert!(matches!(
+        Share::new(share.as_bytes().to_vec()),
+        Err(Error::KdfParamsTooWeak(_))
+    ));
+}
+
+#[test]
+fn selftest_passes() {
+    crate::selftest().unwrap();
+}
+
+#[test]
+fn encrypt_max_shares() {
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 255, 2).unwrap();
+    assert_eq!(shares.len(), 255);
+    let mut share_set = ShareSet::init(Share::new(shares[254].clone().into_bytes()).unwrap());
+    share_set
+        .try_add_share(Share::new(shares[0].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+
+    assert!(matches!(
+        encrypt(SECRET_B, "title", PASSPHRASE_B, 256, 2),
+        Err(Error::TooManyShares(255))
+    ));
+}
+
+#[test]
+fn encrypt_rejects_trivial_threshold() {
+    assert!(encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 1).is_err());
+    assert!(encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 0).is_err());
+}
+
+#[test]
+fn encrypt_with_random_ids() {
+    use base64::Engine;
+
+    let shares = encrypt_with_options(
+        SECRET_B,
+        "title",
+        PASSPHRASE_B,
+        &EncryptOptions::new(5, 3).random_ids(true),
+    )
+    .unwrap();
+    let ids: Vec<u8> = shares
+        .iter()
+        .map(|share| {
+            let data = json::parse(share).unwrap()["d"]
+                .as_str()
+                .unwrap()
+                .to_owned();
+            base64::engine::general_purpose::STANDARD
+                .decode(&data[1..])
+                .unwrap()[0]
+        })
+        .collect();
+    assert!(ids.iter().all(|id| *id != 0));
+    for (i, id) in ids.iter().enumerate() {
+        assert!(!ids[i + 1..].contains(id), "Share ids are distinct.");
+    }
+    // chance for random ids to come out as 1..=5 in order is negligible
+    assert_ne!(ids, vec![1, 2, 3, 4, 5]);
+
+    let mut share_set = ShareSet::init(Share::new(shares[4].clone().into_bytes()).unwrap());
+    for share in [&shares[2], &shares[0]] {
+        share_set
+            .try_add_share(Share::new(share.clone().into_bytes()).unwrap())
+            .unwrap();
+    }
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+}
+
+#[test]
+fn private_split_needs_manifest() {
+    let options = EncryptOptions::new(3, 2).private(true).hide_threshold(true);
+    assert!(matches!(
+        encrypt_with_options(SECRET_B, "family vault", PASSPHRASE_B, &options),
+        Err(Error::ManifestNeeded)
+    ));
+    let (shares, manifest) =
+        encrypt_with_manifest(SECRET_B, "family vault", PASSPHRASE_B, &options).unwrap();
+    for share in shares.iter() {
+        assert!(!share.contains("family vault"));
+        assert!(json::parse(share).unwrap()["r"].is_null());
+    }
+
+    let share = Share::new(shares[0].clone().into_bytes()).unwrap();
+    assert!(share.is_private());
+    assert_eq!(share.title(), "");
+    assert_eq!(share.fingerprint(), manifest.fingerprint);
+
+    let mut share_set = ShareSet::init(share);
+    for share in &shares[1..] {
+        share_set
+            .try_add_share(Share::new(share.clone().into_bytes()).unwrap())
+            .unwrap();
+    }
+    // threshold is unknown, so the set could not be combined yet
+    assert_eq!(share_set.next_action(), NextAction::AskUserForManifest);
+    assert!(matches!(
+        share_set.recover_with_passphrase(PASSPHRASE_B),
+        Err(Error::NotReadyToDecode)
+    ));
+
+    // manifest of another split is rejected
+    let (_, other_manifest) =
+        encrypt_with_manifest(SECRET_B, "family vault", PASSPHRASE_B, &options).unwrap();
+    assert!(matches!(
+        share_set.apply_manifest(other_manifest),
+        Err(Error::ManifestMismatch)
+    ));
+
+    let manifest = Manifest::from_json(&manifest.to_json()).unwrap();
+    share_set.apply_manifest(manifest).unwrap();
+    assert_eq!(share_set.title(), "family vault");
+    assert_eq!(share_set.next_action(), NextAction::AskUserForPassword);
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+}
+
+#[test]
+fn private_split_with_visible_threshold() {
+    let options = EncryptOptions::new(3, 2).private(true);
+    let (shares, manifest) =
+        encrypt_with_manifest(SECRET_B, "family vault", PASSPHRASE_B, &options).unwrap();
+    let mut share_set = ShareSet::init(Share::new(shares[2].clone().into_bytes()).unwrap());
+    share_set
+        .try_add_share(Share::new(shares[0].clone().into_bytes()).unwrap())
+        .unwrap();
+    // shares are combined, but the title for key derivation is still unknown
+    assert_eq!(share_set.next_action(), NextAction::AskUserForManifest);
+    assert!(matches!(
+        share_set.recover_with_passphrase(PASSPHRASE_B),
+        Err(Error::ManifestNeeded)
+    ));
+
+    // manifest must agree with the data recorded in the shares
+    let mut wrong_manifest = manifest.clone();
+    wrong_manifest.required_shards = 3;
+    assert!(matches!(
+        share_set.apply_manifest(wrong_manifest),
+        Err(Error::ManifestMismatch)
+    ));
+    share_set.apply_manifest(manifest).unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+}
+
+#[test]
+fn manifest_of_public_split() {
+    let (shares, manifest) =
+        encrypt_with_manifest(SECRET_B, "title", PASSPHRASE_B, &EncryptOptions::new(3, 2)).unwrap();
+    let share = Share::new(shares[0].clone().into_bytes()).unwrap();
+    assert!(!share.is_private());
+    assert_eq!(manifest.title, "title");
+    assert_eq!(manifest.required_shards, 2);
+    assert_eq!(manifest.fingerprint, share.fingerprint());
+    assert!(Manifest::from_json("{}").is_err());
+}
+
+#[test]
+fn compact_share_json() {
+    // fixed field order, no whitespace
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    assert!(shares[0].starts_with(r#"{"v":1,"t":"title","r":2,"d":""#));
+    assert!(!shares[0].contains(' '));
+
+    let full = encrypt_with_options(
+        SECRET_B,
+        "title",
+        PASSPHRASE_B,
+        &EncryptOptions::new(3, 2).time_lock(2),
+    )
+    .unwrap();
+    let compact = encrypt_with_options(
+        SECRET_B,
+        "title",
+        PASSPHRASE_B,
+        &EncryptOptions::new(3, 2).time_lock(2).compact(true),
+    )
+    .unwrap();
+    assert!(compact[0].ends_with(r#","k":{"c":2}}"#));
+    assert!(compact[0].len() < full[0].len());
+
+    let mut share_set = ShareSet::init(Share::new(compact[0].clone().into_bytes()).unwrap());
+    assert_eq!(share_set.time_lock_rounds(), 2);
+    share_set
+        .try_add_share(Share::new(compact[1].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+
+    // header with only default values is left out
+    let (private, _) = encrypt_with_manifest(
+        SECRET_B,
+        "title",
+        PASSPHRASE_B,
+        &EncryptOptions::new(3, 2).private(true).compact(true),
+    )
+    .unwrap();
+    assert!(private[0].starts_with(r#"{"v":2,"r":2,"d":""#));
+    assert!(json::parse(&private[0]).unwrap()["k"].is_null());
+
+    // non-default scrypt parameters are kept, scrypt algorithm itself is implied
+    let scrypt = encrypt_with_options(
+        SECRET_B,
+        "title",
+        PASSPHRASE_B,
+        &EncryptOptions::new(3, 2)
+            .kdf(KdfAlgorithm::Scrypt {
+                log_n: 15,
+                r: 8,
+                p: 2,
+            })
+            .compact(true),
+    )
+    .unwrap();
+    assert!(scrypt[0].ends_with(r#","k":{"p":2}}"#));
+    let share = Share::new(scrypt[0].clone().into_bytes()).unwrap();
+    assert_eq!(share.fingerprint(), ShareSet::init(share).fingerprint());
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn compressed_share_roundtrip() {
+    let shares = encrypt(SECRET_SEEDPHRASE, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let compressed: Vec<Vec<u8>> = shares.iter().map(|a| crate::compress(a)).collect();
+    assert!(compressed[0].len() < shares[0].len());
+
+    let mut share_set = ShareSet::init(Share::new(compressed[0].clone()).unwrap());
+    // compressed and plain shares of the same set could be mixed
+    share_set
+        .try_add_share(Share::new(shares[1].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_SEEDPHRASE
+    );
+
+    // decompression is bounded
+    let bomb = crate::compress(&" ".repeat(1 << 20));
+    assert!(matches!(Share::new(bomb), Err(Error::DeflateFailed)));
+    let mut damaged = compressed[0].clone();
+    damaged.truncate(10);
+    assert!(Share::new(damaged).is_err());
+}
+
+#[cfg(feature = "stego")]
+#[test]
+fn share_hidden_in_png() {
+    use crate::{embed_in_png, extract_from_png};
+
+    /// Carrier image with a gradient, 8 bits per channel.
+    fn carrier(width: u32, height: u32, color: png::ColorType) -> Vec<u8> {
+        let len = (width * height) as usize * color.samples();
+        let pixels: Vec<u8> = (0..len).map(|a| (a * 7 % 256) as u8).collect();
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&pixels).unwrap();
+        writer.finish().unwrap();
+        out
+    }
+
+    let shares = encrypt(SECRET_SEEDPHRASE, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let image = embed_in_png(shares[0].as_bytes(), &carrier(40, 40, png::ColorType::Rgba)).unwrap();
+    let hidden = extract_from_png(&image).unwrap();
+    assert_eq!(hidden, shares[0].as_bytes());
+
+    // alpha channel is untouched, color channels change at most by one
+    let original = carrier(40, 40, png::ColorType::Rgba);
+    let decode = |a: &[u8]| {
+        let mut reader = png::Decoder::new(a).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let _ = reader.next_frame(&mut pixels).unwrap();
+        pixels
+    };
+    for (i, (a, b)) in decode(&original).iter().zip(decode(&image)).enumerate() {
+        if i % 4 == 3 {
+            assert_eq!(*a, b);
+        } else {
+            assert!(a.abs_diff(b) <= 1);
+        }
+    }
+
+    let image = embed_in_png(
+        shares[1].as_bytes(),
+        &carrier(80, 40, png::ColorType::Grayscale),
+    )
+    .unwrap();
+    let mut share_set = ShareSet::init(Share::new(hidden).unwrap());
+    share_set
+        .try_add_share(Share::new(extract_from_png(&image).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_SEEDPHRASE
+    );
+
+    // carrier must be large enough, and a valid png, and the payload a share
+    assert!(matches!(
+        embed_in_png(shares[0].as_bytes(), &carrier(10, 10, png::ColorType::Rgb)),
+        Err(Error::ImageTooSmall(_))
+    ));
+    assert_eq!(
+        embed_in_png(shares[0].as_bytes(), b"not a png"),
+        Err(Error::ImageInvalid)
+    );
+    assert_eq!(
+        embed_in_png(b"not a share", &carrier(40, 40, png::ColorType::Rgb)),
+        Err(Error::JsonParsing)
+    );
+    assert_eq!(
+        extract_from_png(&carrier(40, 40, png::ColorType::Rgb)),
+        Err(Error::NoShareInImage)
+    );
+}
+
+#[cfg(not(feature = "deflate"))]
+#[test]
+fn compressed_share_needs_feature() {
+    assert!(matches!(
+        Share::new(b"BSZ1\x01\x02".to_vec()),
+        Err(Error::DeflateNotSupported)
+    ));
+}
+
+#[test]
+fn errors_compare_structurally() {
+    assert_eq!(
+        Share::new(b"not a share".to_vec()).unwrap_err(),
+        Error::JsonParsing
+    );
+    let error = Share::new(br#"{"v":3}"#.to_vec()).unwrap_err();
+    assert_eq!(error.clone(), Error::VersionNotSupported("3".to_string()));
+    assert_ne!(error, Error::VersionNotSupported("4".to_string()));
+}
+
+#[test]
+fn capabilities_report() {
+    let capabilities = crate::capabilities();
+    assert_eq!(capabilities.versions, vec![1, 2]);
+    assert!(capabilities.kdfs.contains(&"pbkdf2-sha512"));
+    assert_eq!(capabilities.min_bits, *BIT_RANGE.start());
+    assert_eq!(
+        capabilities.features.contains(&"seal"),
+        cfg!(feature = "seal")
+    );
+    let json = serde_json::to_string(&capabilities).unwrap();
+    assert!(json.contains(r#""ciphers":["xsalsa20poly1305"]"#));
+}
+
+#[test]
+fn version_negotiation() {
+    use crate::{negotiate, Negotiation, Requirement, ShareMeta};
+
+    let v1 = ShareMeta::peek(&hex::decode(SCAN_A1).unwrap()).unwrap();
+    assert_eq!(v1.version, Some(1));
+    assert_eq!(v1.title.as_deref(), Some("Alice tries BananaSplit again"));
+    assert_eq!(v1.required_shards, Some(2));
+    assert_eq!(
+        negotiate(&[v1.clone(), v1.clone()]),
+        Negotiation::Supported { version: Some(1) }
+    );
+
+    let v3 = ShareMeta::peek(br#"{"v":3,"t":"x","r":2,"k":{"a":"balloon"}}"#).unwrap();
+    assert_eq!(
+        negotiate(std::slice::from_ref(&v3)),
+        Negotiation::UpgradeRequired(vec![
+            Requirement::Version(3),
+            Requirement::Kdf("balloon".to_string())
+        ])
+    );
+    assert_eq!(negotiate(&[v1, v3]), Negotiation::Inconsistent);
+
+    // compressed share could not be looked into without the feature
+    #[cfg(not(feature = "deflate"))]
+    {
+        let compressed = ShareMeta::peek(b"BSZ1\x01\x02").unwrap();
+        assert!(compressed.compressed);
+        assert_eq!(
+            negotiate(&[compressed]),
+            Negotiation::UpgradeRequired(vec![Requirement::Feature("deflate")])
+        );
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_records_nothing_sensitive() {
+    use std::fmt::Write;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata};
+
+    struct Visitor<'a>(&'a mut String);
+    impl Visit for Visitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            write!(self.0, "{}={:?} ", field.name(), value).unwrap();
+        }
+    }
+
+    struct Collector(Arc<Mutex<String>>);
+    impl tracing::Subscriber for Collector {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut log = self.0.lock().unwrap();
+            log.push_str(span.metadata().name());
+            log.push(' ');
+            span.record(&mut Visitor(&mut log));
+            Id::from_u64(1)
+        }
+        fn record(&self, _span: &Id, values: &Record<'_>) {
+            values.record(&mut Visitor(&mut self.0.lock().unwrap()));
+        }
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut Visitor(&mut self.0.lock().unwrap()));
+        }
+        fn enter(&self, _span: &Id) {}
+        fn exit(&self, _span: &Id) {}
+    }
+
+    let log = Arc::new(Mutex::new(String::new()));
+    let shares = tracing::subscriber::with_default(Collector(log.clone()), || {
+        let shares = encrypt(SECRET_B, "traced title", PASSPHRASE_B, 3, 2).unwrap();
+        let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+        share_set
+            .try_add_share(Share::new(shares[1].clone().into_bytes()).unwrap())
+            .unwrap();
+        assert_eq!(
+            share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+            SECRET_B
+        );
+        shares
+    });
+    let log = log.lock().unwrap();
+    for expected in [
+        "encrypt",
+        "share parsed",
+        "set combined",
+        "kdf",
+        "secret decrypted",
+    ] {
+        assert!(log.contains(expected), "{expected} is traced");
+    }
+    let nonce = json::parse(&shares[0]).unwrap()["n"].to_string();
+    for sensitive in [SECRET_B, PASSPHRASE_B, "traced title", &nonce] {
+        assert!(!log.contains(sensitive), "{sensitive} is not traced");
+    }
+}
+
+#[test]
+fn metrics_are_reported() {
+    use crate::{Metric, MetricsHook};
+    use std::sync::{Arc, Mutex};
+
+    let metrics = Arc::new(Mutex::new(Vec::new()));
+    let collected = metrics.clone();
+    let hook = MetricsHook::new(move |metric| collected.lock().unwrap().push(metric));
+
+    let shares = encrypt_with_options(
+        SECRET_B,
+        "title",
+        PASSPHRASE_B,
+        &EncryptOptions::new(3, 2).metrics(hook.clone()),
+    )
+    .unwrap();
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    share_set.set_metrics(hook);
+    share_set
+        .try_add_share(Share::new(shares[1].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+
+    let metrics = metrics.lock().unwrap();
+    assert_eq!(metrics.len(), 4);
+    assert!(matches!(metrics[0], Metric::KeyDerived { rounds: 1, .. }));
+    assert_eq!(metrics[1], Metric::ShareProcessed { have: 2 });
+    assert!(matches!(metrics[2], Metric::Combined { shares: 2, .. }));
+    assert!(matches!(metrics[3], Metric::KeyDerived { rounds: 1, .. }));
+}
+
+#[test]
+fn cancellation() {
+    use crate::CancellationToken;
+
+    let token = CancellationToken::new();
+    token.cancel();
+    assert_eq!(
+        encrypt_with_options(
+            SECRET_B,
+            "title",
+            PASSPHRASE_B,
+            &EncryptOptions::new(3, 2).cancellation(token.clone()),
+        ),
+        Err(Error::Cancelled)
+    );
+
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    share_set.set_cancellation(token);
+    assert_eq!(
+        share_set.try_add_share(Share::new(shares[1].clone().into_bytes()).unwrap()),
+        Err(Error::Cancelled)
+    );
+    // cancelled share is not kept in the set, and could be added again
+    assert_eq!(
+        share_set.next_action(),
+        NextAction::MoreShares {
+            have: 1,
+            need: 2,
+            collected: vec![1]
+        }
+    );
+    share_set.set_cancellation(CancellationToken::new());
+    share_set
+        .try_add_share(Share::new(shares[1].clone().into_bytes()).unwrap())
+        .unwrap();
+
+    let token = CancellationToken::new();
+    share_set.set_cancellation(token.clone());
+    token.cancel();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B),
+        Err(Error::Cancelled)
+    );
+}
+
+#[test]
+fn chunked_recovery() {
+    use crate::{CancellationToken, RecoveryStep};
+
+    let options = EncryptOptions::new(3, 2)
+        .kdf(KdfAlgorithm::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        })
+        .time_lock(2)
+        .allow_weak_kdf(true);
+    let shares = encrypt_with_options(SECRET_B, "title", PASSPHRASE_B, &options).unwrap();
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    let token = CancellationToken::new();
+    share_set.set_cancellation(token.clone());
+    share_set
+        .try_add_share(Share::new(shares[2].clone().into_bytes()).unwrap())
+        .unwrap();
+
+    let mut recovery = share_set.start_recovery(PASSPHRASE_B).unwrap();
+    let mut steps = 0;
+    let secret = loop {
+        match recovery.step(500).unwrap() {
+            RecoveryStep::Pending { done, total } => {
+                assert_eq!(total, 2 * 2 * 1024);
+                assert!(done < total);
+            }
+            RecoveryStep::Done(secret) => break secret,
+        }
+        steps += 1;
+    };
+    assert_eq!(secret, SECRET_B);
+    assert_eq!(steps, 8);
+
+    let mut recovery = share_set.start_recovery("wrong").unwrap();
+    assert!(matches!(
+        recovery.step(100).unwrap(),
+        RecoveryStep::Pending { done: 100, .. }
+    ));
+    token.cancel();
+    assert_eq!(recovery.step(100), Err(Error::Cancelled));
+}
+
+#[test]
+fn chunked_key_derivation_matches_default_scrypt() {
+    use crate::chunked::KdfDriver;
+    use crate::kdf::KeyDerivation;
+    use crate::CancellationToken;
+
+    // real share, scrypt with log_n 15
+    let mut share_set = ShareSet::init(Share::new(hex::decode(SCAN_B1).unwrap()).unwrap());
+    share_set
+        .try_add_share(Share::new(hex::decode(SCAN_B3).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(share_set.key_derivation(), KeyDerivation::default());
+    let salt = share_set.salt().unwrap();
+    let expected = KeyDerivation::default()
+        .derive(
+            PASSPHRASE_B.as_bytes(),
+            &salt,
+            &CancellationToken::default(),
+        )
+        .unwrap();
+    let mut driver =
+        KdfDriver::new(KeyDerivation::default(), PASSPHRASE_B.as_bytes(), &salt).unwrap();
+    let key = loop {
+        if let Some(key) = driver.step(10_000) {
+            break key.to_vec();
+        }
+    };
+    assert_eq!(key, expected.to_vec());
+}
+
+#[test]
+fn combine_without_allocations() {
+    use crate::{combine_into, combine_output_len, combine_table_len};
+    use base64::Engine;
+
+    // contents of the first two Alice shares, with ids cut off
+    let mut ids = [0u32; 2];
+    let mut bodies = [[0u8; 128]; 2];
+    let mut lens = [0usize; 2];
+    for (i, scan) in [SCAN_A1, SCAN_A2].iter().enumerate() {
+        let share = json::parse(&String::from_utf8(hex::decode(scan).unwrap()).unwrap()).unwrap();
+        let body = base64::engine::general_purpose::STANDARD
+            .decode(&share["d"].as_str().unwrap()[1..])
+            .unwrap();
+        ids[i] = body[0] as u32;
+        lens[i] = body.len() - 1;
+        bodies[i][..lens[i]].copy_from_slice(&body[1..]);
+    }
+    let contents = [&bodies[0][..lens[0]], &bodies[1][..lens[1]]];
+
+    let mut logs = [0u32; 256];
+    let mut exps = [0u32; 256];
+    let mut out = [0u8; 128];
+    assert_eq!(combine_table_len(8).unwrap(), 256);
+    assert!(combine_output_len(8, lens[0]) <= out.len());
+    let len = combine_into(8, &ids, &contents, &mut logs, &mut exps, &mut out).unwrap();
+
+    // same result as combined by the share set
+    let mut share_set = ShareSet::init(Share::new(hex::decode(SCAN_A1).unwrap()).unwrap());
+    share_set
+        .try_add_share(Share::new(hex::decode(SCAN_A2).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(share_set.encrypted_secret().unwrap(), &out[..len]);
+
+    // buffers too small are reported
+    assert_eq!(
+        combine_into(8, &ids, &contents, &mut logs[..100], &mut exps, &mut out),
+        Err(Error::BufferTooSmall(256))
+    );
+    assert_eq!(
+        combine_into(8, &ids, &contents, &mut logs, &mut exps, &mut out[..10]),
+        Err(Error::BufferTooSmall(combine_output_len(8, lens[0])))
+    );
+}
+
+#[test]
+fn borrowed_share_parsing() {
+    use crate::ShareRef;
+    use std::borrow::Cow;
+
+    let payload = hex::decode(SCAN_A1).unwrap();
+    let share_ref = ShareRef::parse(&payload).unwrap();
+    assert!(matches!(
+        share_ref.title(),
+        Cow::Borrowed("Alice tries BananaSplit again")
+    ));
+    assert_eq!(share_ref.required_shards(), 2);
+    assert_eq!(share_ref.nonce(), "o9DbpBi9r7UWJHOriuDArR4Vrc0VOo3l");
+    assert_eq!(share_ref.bits(), 8);
+    assert_eq!(share_ref.id(), 1);
+    let mut content = [0u8; 128];
+    let len = share_ref.content_into(&mut content).unwrap();
+    assert_eq!(len, 96);
+    assert!(matches!(
+        share_ref.content_into(&mut content[..10]),
+        Err(Error::BufferTooSmall(_))
+    ));
+
+    // escaped title is unescaped, and owned share is the same as parsed directly
+    let payload = hex::decode(SCAN_B1).unwrap();
+    let share_ref = ShareRef::parse(&payload).unwrap();
+    let share = share_ref.to_share().unwrap();
+    assert_eq!(share_ref.title(), share.title());
+    assert!(matches!(share_ref.title(), Cow::Owned(_)));
+
+    // legacy shares without version, with hex body
+    let payload = br#"{"t":"x","r":2,"d":"8019c","n":"AAAA"}"#;
+    let share_ref = ShareRef::parse(payload).unwrap();
+    assert_eq!(share_ref.id(), 1);
+    assert_eq!(share_ref.content_into(&mut content).unwrap(), 1);
+    assert_eq!(content[0], 0x9c);
+
+    // same checks as for owned shares
+    for payload in [
+        &br#"{"v":3,"t":"x","r":2,"d":"8AQID","n":"AAAA"}"#[..],
+        br#"{"v":1,"t":"x","r":"2","d":"8AQID","n":"AAAA"}"#,
+        br#"{"v":1,"t":"x","r":2,"d":"8AAID","n":"AAAA"}"#,
+        br#"{"v":1,"t":"x","r":2,"d":"","n":"AAAA"}"#,
+        br#"{"v":1,"t":"x","r":2,"d":"8AQID","n":"AAAA"} trailing"#,
+        br#"{"v":1,"t":"x"#,
+    ] {
+        assert_eq!(
+            ShareRef::parse(payload).unwrap_err(),
+            Share::new(payload.to_vec()).unwrap_err()
+        );
+    }
+}
+
+#[test]
+fn share_contents_decoded_into_reused_buffer() {
+    use crate::ShareRef;
+    use zeroize::Zeroizing;
+
+    // one wiped buffer is reused for all shares of the set
+    let mut buffer = Zeroizing::new(Vec::new());
+    let mut ids = Vec::new();
+    let mut contents = Vec::new();
+    for scan in [SCAN_A1, SCAN_A2] {
+        let payload = hex::decode(scan).unwrap();
+        let share_ref = ShareRef::parse(&payload).unwrap();
+        buffer.resize(share_ref.body_len(), 0);
+        let len = share_ref.content_into(&mut buffer).unwrap();
+        ids.push(share_ref.id());
+        contents.push(buffer[..len].to_vec());
+    }
+    let contents: Vec<&[u8]> = contents.iter().map(|a| a.as_slice()).collect();
+    let mut logs = vec![0; crate::combine_table_len(8).unwrap()];
+    let mut exps = vec![0; logs.len()];
+    let mut out = vec![0; crate::combine_output_len(8, contents[0].len())];
+    let len = crate::combine_into(8, &ids, &contents, &mut logs, &mut exps, &mut out).unwrap();
+
+    // same result as with owned shares
+    let mut share_set = ShareSet::init(Share::new(hex::decode(SCAN_A1).unwrap()).unwrap());
+    share_set
+        .try_add_share(Share::new(hex::decode(SCAN_A2).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(share_set.encrypted_secret(), Some(&out[..len]));
+}
+
+#[test]
+fn share_with_long_nonce_is_rejected() {
+    let mut share1 =
+        json::parse(&String::from_utf8(hex::decode(SCAN_A1).unwrap()).unwrap()).unwrap();
+    let mut share2 =
+        json::parse(&String::from_utf8(hex::decode(SCAN_A2).unwrap()).unwrap()).unwrap();
+    share1["n"] = "A".repeat(40).into();
+    share2["n"] = "A".repeat(40).into();
+    let mut share_set = ShareSet::init(Share::new(share1.dump().into_bytes()).unwrap());
+    assert!(matches!(
+        share_set.try_add_share(Share::new(share2.dump().into_bytes()).unwrap()),
+        Err(Error::NonceLengthInvalid(30))
+    ));
+}
+
+#[test]
+fn secret_length_is_estimated_before_decryption() {
+    // js-made shares, and shares made by this crate
+    let rust_made: Vec<Vec<u8>> = encrypt("seven bytes of secret", "title", "pass", 3, 2)
+        .unwrap()
+        .into_iter()
+        .map(String::into_bytes)
+        .collect();
+    for (shares, secret_len) in [
+        (
+            vec![hex::decode(SCAN_B1).unwrap(), hex::decode(SCAN_B2).unwrap()],
+            SECRET_B.len(),
+        ),
+        (rust_made, "seven bytes of secret".len()),
+    ] {
+        let mut share_set = ShareSet::init(Share::new(shares[0].clone()).unwrap());
+        let estimate = share_set.estimated_secret_len();
+        assert!(estimate.contains(&secret_len), "{estimate:?}");
+        assert!(estimate.end() - estimate.start() <= 16);
+
+        share_set
+            .try_add_share(Share::new(shares[1].clone()).unwrap())
+            .unwrap();
+        assert_eq!(share_set.estimated_secret_len(), secret_len..=secret_len);
+    }
+}
+
+#[test]
+fn share_density_report() {
+    use crate::ShareDensity;
+
+    let shares = encrypt("short secret", "title", "pass", 3, 2).unwrap();
+    let density = ShareDensity::of(&shares[0]);
+    assert_eq!(density.payload_len, shares[0].len());
+    assert!(density.qr_version.is_some_and(|a| a < 10));
+    assert!(!density.chunking_advised);
+
+    // boundaries of the capacity table
+    assert_eq!(ShareDensity::of_payload(&[0; 17]).qr_version, Some(1));
+    assert_eq!(ShareDensity::of_payload(&[0; 18]).qr_version, Some(2));
+    assert_eq!(ShareDensity::of_payload(&[0; 2953]).qr_version, Some(40));
+    assert_eq!(ShareDensity::of_payload(&[0; 858]).qr_version, Some(20));
+    assert!(!ShareDensity::of_payload(&[0; 858]).chunking_advised);
+    assert!(ShareDensity::of_payload(&[0; 859]).chunking_advised);
+    let too_long = ShareDensity::of_payload(&[0; 2954]);
+    assert_eq!(too_long.qr_version, None);
+    assert!(too_long.chunking_advised);
+}
+
+#[test]
+fn share_is_printed_without_secrets() {
+    let share = Share::new(hex::decode(SCAN_A2).unwrap()).unwrap();
+    assert_eq!(
+        share.to_string(),
+        format!(
+            "V1 share 2, bits 8, set {}, content 96 bytes",
+            share.fingerprint()
+        )
+    );
+    let debug = format!("{share:?}");
+    assert!(debug.contains("<redacted>"));
+    for secret in [
+        "Alice tries BananaSplit again",
+        "o9DbpBi9r7UWJHOriuDArR4Vrc0VOo3l",
+    ] {
+        assert!(!debug.contains(secret));
+        assert!(!share.to_string().contains(secret));
+    }
+}
+
+#[test]
+fn rejection_reason_without_modifying_set() {
+    use crate::RejectionReason;
+
+    let mut share_set = ShareSet::init(Share::new(hex::decode(SCAN_C1).unwrap()).unwrap());
+    let reasons = [
+        (SCAN_A1, Some(RejectionReason::Title)),
+        (SCAN_C1, Some(RejectionReason::AlreadyInSet(1))),
+        (SCAN_C2, None),
+    ];
+    for (scan, reason) in reasons {
+        let share = Share::new(hex::decode(scan).unwrap()).unwrap();
+        assert_eq!(share_set.why_rejected(&share), reason);
+    }
+    assert_eq!(
+        share_set.next_action(),
+        NextAction::MoreShares {
+            have: 1,
+            need: 3,
+            collected: vec![1]
+        }
+    );
+
+    // reason is the same as the error from adding the share
+    let share = Share::new(hex::decode(SCAN_C1).unwrap()).unwrap();
+    let reason = share_set.why_rejected(&share).unwrap();
+    assert_eq!(
+        share_set.try_add_share(share).unwrap_err(),
+        Error::from(reason)
+    );
+
+    let mut share1 =
+        json::parse(&String::from_utf8(hex::decode(SCAN_C1).unwrap()).unwrap()).unwrap();
+    share1["n"] = "AAAA".into();
+    let share = Share::new(share1.dump().into_bytes()).unwrap();
+    assert_eq!(share_set.why_rejected(&share), Some(RejectionReason::Nonce));
+}
+
+#[test]
+fn share_diff() {
+    let share = |scan: &str| Share::new(hex::decode(scan).unwrap()).unwrap();
+
+    assert!(share(SCAN_A1).diff(&share(SCAN_A1)).is_identical());
+
+    // same split, different shares
+    let diff = share(SCAN_A1).diff(&share(SCAN_A2));
+    assert!(diff.is_same_split());
+    assert!(diff.id && diff.content);
+    assert!(!diff.is_damaged_copy());
+
+    // different backups
+    let diff = share(SCAN_A1).diff(&share(SCAN_B1));
+    assert!(diff.title && diff.nonce && diff.content_length);
+    assert!(!diff.is_same_split());
+
+    // damaged copy, with some content bytes changed
+    let mut damaged =
+        json::parse(&String::from_utf8(hex::decode(SCAN_A1).unwrap()).unwrap()).unwrap();
+    let data = damaged["d"].to_string().replacen("RMF", "xyz", 1);
+    damaged["d"] = data.into();
+    let diff = share(SCAN_A1).diff(&Share::new(damaged.dump().into_bytes()).unwrap());
+    assert_eq!(
+        diff,
+        crate::ShareDiff {
+            content: true,
+            ..Default::default()
+        }
+    );
+    assert!(diff.is_damaged_copy());
+}
+
+#[test]
+fn recover_and_resplit_rotates_custodians() {
+    let mut share_set = ShareSet::init(Share::new(hex::decode(SCAN_B1).unwrap()).unwrap());
+    share_set
+        .try_add_share(Share::new(hex::decode(SCAN_B2).unwrap()).unwrap())
+        .unwrap();
+
+    // wrong passphrase gives no new shares
+    assert!(share_set
+        .recover_and_resplit("wrong", None, &EncryptOptions::new(5, 3))
+        .is_err());
+
+    let new_passphrase = "fresh-passphrase-for-rotation";
+    let shares = share_set
+        .recover_and_resplit(
+            PASSPHRASE_B,
+            Some(new_passphrase),
+            &EncryptOptions::new(5, 3),
+        )
+        .unwrap();
+    assert_eq!(shares.len(), 5);
+
+    let mut new_set = ShareSet::init(Share::new(shares[4].clone().into_bytes()).unwrap());
+    for share in &shares[1..3] {
+        new_set
+            .try_add_share(Share::new(share.clone().into_bytes()).unwrap())
+            .unwrap();
+    }
+    assert_eq!(new_set.title(), share_set.title());
+    assert_ne!(new_set.fingerprint(), share_set.fingerprint());
+    assert!(new_set.recover_with_passphrase(PASSPHRASE_B).is_err());
+    assert_eq!(
+        new_set.recover_with_passphrase(new_passphrase).unwrap(),
+        SECRET_B
+    );
+}
+
+#[test]
+fn passphrase_change_keeps_key_derivation() {
+    let options = EncryptOptions::new(3, 2).time_lock(2);
+    let shares = encrypt_with_options("the secret", "title", "old passphrase", &options).unwrap();
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    share_set
+        .try_add_share(Share::new(shares[1].clone().into_bytes()).unwrap())
+        .unwrap();
+
+    let shares = share_set
+        .reencrypt_with_new_passphrase("old passphrase", "new passphrase", 4, 3)
+        .unwrap();
+    assert_eq!(shares.len(), 4);
+    let mut new_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    for share in &shares[1..3] {
+        new_set
+            .try_add_share(Share::new(share.clone().into_bytes()).unwrap())
+            .unwrap();
+    }
+    assert_eq!(new_set.time_lock_rounds(), 2);
+    assert_eq!(
+        new_set.recover_with_passphrase("new passphrase").unwrap(),
+        "the secret"
+    );
+}
+
+#[test]
+fn backup_is_retitled() {
+    let shares = [
+        hex::decode(SCAN_C1).unwrap(),
+        hex::decode(SCAN_C2).unwrap(),
+        hex::decode(SCAN_C3).unwrap(),
+    ];
+    assert!(retitle(&shares, "wrong", "new title").is_err());
+    assert_eq!(
+        retitle(&shares[..2], PASSPHRASE_C, "new title").unwrap_err(),
+        Error::NotReadyToDecode
+    );
+    assert_eq!(
+        retitle(&[] as &[Vec<u8>], PASSPHRASE_C, "new title").unwrap_err(),
+        Error::TooFewShares
+    );
+
+    let new_shares = retitle(&shares, PASSPHRASE_C, "new title").unwrap();
+    assert_eq!(new_shares.len(), 3);
+    let mut share_set = ShareSet::init(Share::new(new_shares[0].clone().into_bytes()).unwrap());
+    assert_eq!(share_set.title(), "new title");
+    assert_eq!(
+        share_set.next_action(),
+        NextAction::MoreShares {
+            have: 1,
+            need: 3,
+            collected: vec![1]
+        }
+    );
+    for share in &new_shares[1..] {
+        share_set
+            .try_add_share(Share::new(share.clone().into_bytes()).unwrap())
+            .unwrap();
+    }
+    let mut old_set = ShareSet::init(Share::new(shares[0].clone()).unwrap());
+    for share in &shares[1..] {
+        old_set
+            .try_add_share(Share::new(share.clone()).unwrap())
+            .unwrap();
+    }
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_C).unwrap(),
+        old_set.recover_with_passphrase(PASSPHRASE_C).unwrap()
+    );
+}
+
+#[test]
+fn legacy_set_is_upgraded() {
+    use base64::Engine;
+
+    // legacy shares are the same as V1, but without version, and with hex body
+    let legacy = |scan: &str| {
+        let mut share =
+            json::parse(&String::from_utf8(hex::decode(scan).unwrap()).unwrap()).unwrap();
+        let data = share["d"].to_string();
+        let body = base64::engine::general_purpose::STANDARD
+            .decode(&data[1..])
+            .unwrap();
+        share["d"] = format!("{}{}", &data[..1], hex::encode(body)).into();
+        let _ = share.remove("v");
+        share.dump().into_bytes()
+    };
+    let shares = [legacy(SCAN_B1), legacy(SCAN_B2), legacy(SCAN_B3)];
+
+    let new_shares = upgrade_legacy(&shares, PASSPHRASE_B).unwrap();
+    assert_eq!(new_shares.len(), 3);
+    assert!(new_shares.iter().all(|a| a.starts_with(r#"{"v":1,"#)));
+    let mut share_set = ShareSet::init(Share::new(new_shares[2].clone().into_bytes()).unwrap());
+    share_set
+        .try_add_share(Share::new(new_shares[0].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+
+    // current sets need no upgrade
+    assert_eq!(
+        upgrade_legacy(
+            &[hex::decode(SCAN_B1).unwrap(), hex::decode(SCAN_B2).unwrap()],
+            PASSPHRASE_B
+        )
+        .unwrap_err(),
+        Error::NotLegacySet
+    );
+}
+
+#[test]
+fn v1_set_is_migrated_with_checksums_and_key_commitment() {
+    let shares = [
+        hex::decode(SCAN_C1).unwrap(),
+        hex::decode(SCAN_C2).unwrap(),
+        hex::decode(SCAN_C3).unwrap(),
+    ];
+    let options = EncryptOptions::new(4, 2)
+        .checksums(true)
+        .key_commitment(true);
+    let new_shares = migrate_v1_to_v2(&shares, PASSPHRASE_C, &options).unwrap();
+    assert_eq!(new_shares.len(), 4);
+    let parsed = json::parse(&new_shares[0]).unwrap();
+    assert_eq!(parsed["v"], 2);
+    assert!(parsed["c"].is_string() && parsed["m"].is_string());
+
+    let mut share_set = ShareSet::init(Share::new(new_shares[1].clone().into_bytes()).unwrap());
+    share_set
+        .try_add_share(Share::new(new_shares[3].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase("wrong").unwrap_err(),
+        Error::WrongPassphrase
+    );
+    assert_eq!(
+        share_set
+            .start_recovery("wrong")
+            .unwrap()
+            .step(u64::MAX)
+            .unwrap_err(),
+        Error::WrongPassphrase
+    );
+    let mut old_set = ShareSet::init(Share::new(shares[0].clone()).unwrap());
+    for share in &shares[1..] {
+        old_set
+            .try_add_share(Share::new(share.clone()).unwrap())
+            .unwrap();
+    }
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_C).unwrap(),
+        old_set.recover_with_passphrase(PASSPHRASE_C).unwrap()
+    );
+
+    // damaged share is detected on parsing
+    let mut damaged = parsed.clone();
+    let data = damaged["d"].to_string();
+    let flipped = if &data[5..6] == "A" { "B" } else { "A" };
+    damaged["d"] = format!("{}{}{}", &data[..5], flipped, &data[6..]).into();
+    assert_eq!(
+        Share::new(damaged.dump().into_bytes()).unwrap_err(),
+        Error::ShareChecksumMismatch
+    );
+
+    // shares with different key commitment are not mixed
+    let mut other = json::parse(&new_shares[2]).unwrap();
+    other["m"] = "AAAAAAAAAAAAAAAAAAAAAA==".into();
+    assert_eq!(
+        share_set_with(&new_shares[0], &other.dump()),
+        Err(Error::ShareKeyCommitmentDifferent)
+    );
+    other["m"] = "AAAA".into();
+    assert_eq!(
+        Share::new(other.dump().into_bytes()).unwrap_err(),
+        Error::KeyCommitmentInvalid
+    );
+
+    // V2 sets need no migration, and plain options still give V2 shares
+    assert_eq!(
+        migrate_v1_to_v2(&new_shares, PASSPHRASE_C, &options).unwrap_err(),
+        Error::NotLegacySet
+    );
+    let plain = migrate_v1_to_v2(&shares, PASSPHRASE_C, &EncryptOptions::new(3, 2)).unwrap();
+    assert_eq!(json::parse(&plain[0]).unwrap()["v"], 2);
+}
+
+fn share_set_with(first: &str, second: &str) -> Result<(), Error> {
+    let mut share_set = ShareSet::init(Share::new(first.as_bytes().to_vec()).unwrap());
+    share_set.try_add_share(Share::new(second.as_bytes().to_vec()).unwrap())
+}
+
+/// Share payload with given content bits flipped, as in a damaged scan.
+fn damaged(share: &str, flips: &[(usize, u8)]) -> Vec<u8> {
+    use base64::Engine;
+
+    let mut parsed = json::parse(share).unwrap();
+    let data = parsed["d"].to_string();
+    let engine = base64::engine::general_purpose::STANDARD;
+    let mut body = engine.decode(&data[1..]).unwrap();
+    for (byte, mask) in flips {
+        // share id takes the first body byte
+        body[1 + byte] ^= mask;
+    }
+    parsed["d"] = format!("{}{}", &data[..1], engine.encode(body)).into();
+    parsed.dump().into_bytes()
+}
+
+#[test]
+fn damaged_share_is_repaired() {
+    use crate::{repair, Repaired};
+
+    let shares = encrypt("repair me", "title", "pass", 3, 2).unwrap();
+    let payloads = [
+        shares[0].clone().into_bytes(),
+        damaged(&shares[2], &[(5, 0x04)]),
+    ];
+    assert!(ShareSet::init(Share::new(payloads[0].clone()).unwrap())
+        .try_add_share(Share::new(payloads[1].clone()).unwrap())
+        .is_ok());
+    assert_eq!(
+        repair(&payloads, "pass", 0).unwrap_err(),
+        Error::RepairFailed
+    );
+    assert_eq!(
+        repair(&payloads, "pass", 1).unwrap(),
+        Repaired {
+            secret: "repair me".to_string(),
+            share: Some(1),
+            flipped_bits: vec![5 * 8 + 2],
+        }
+    );
+    assert_eq!(
+        repair(&payloads[..1], "pass", 1).unwrap_err(),
+        Error::TooFewShares
+    );
+
+    // checksums point at the damaged share, and rule out most candidates
+    let options = EncryptOptions::new(3, 2)
+        .checksums(true)
+        .key_commitment(true);
+    let shares = encrypt_with_options("repair me too", "title", "pass", &options).unwrap();
+    let payloads = [
+        damaged(&shares[0], &[(1, 0x80), (7, 0x01)]),
+        shares[1].clone().into_bytes(),
+    ];
+    assert_eq!(
+        Share::new(payloads[0].clone()).unwrap_err(),
+        Error::ShareChecksumMismatch
+    );
+    let repaired = repair(&payloads, "pass", 2).unwrap();
+    assert_eq!(repaired.secret, "repair me too");
+    assert_eq!(repaired.share, Some(0));
+    assert_eq!(repaired.flipped_bits, vec![8 + 7, 7 * 8]);
+    assert_eq!(
+        repair(&payloads, "wrong", 2).unwrap_err(),
+        Error::WrongPassphrase
+    );
+
+    // undamaged set needs no repair
+    let payloads = [shares[0].clone(), shares[2].clone()];
+    assert_eq!(repair(&payloads, "pass", 1).unwrap().share, None);
+}
+
+#[test]
+#[cfg(feature = "paranoid-checks")]
+fn paranoid_checks_catch_broken_invariants() {
+    use base64::Engine;
+
+    fn violated<T>(result: Result<T, Error>) -> bool {
+        matches!(result, Err(Error::InvariantViolated(_)))
+    }
+
+    // repeated ids reach the combining only through the low level api
+    let mut logs = vec![0; 256];
+    let mut exps = vec![0; 256];
+    let mut out = vec![0; 16];
+    assert!(violated(crate::combine_into(
+        8,
+        &[1, 1],
+        &[&[1, 2], &[3, 4]],
+        &mut logs,
+        &mut exps,
+        &mut out
+    )));
+
+    // uniform share content
+    let mut body = vec![0; 20];
+    body[0] = 1;
+    let data = base64::engine::general_purpose::STANDARD.encode(body);
+    let share = format!(r#"{{"v":1,"t":"x","r":2,"d":"8{data}","n":"AAAA"}}"#);
+    assert!(violated(Share::new(share.into_bytes())));
+
+    // damaged tables
+    let (logs, mut exps) = generate_logs_and_exps(8);
+    let log = |x: usize| logs[x];
+    assert!(crate::paranoid::gf_tables(8, log, &exps).is_ok());
+    exps.swap(3, 4);
+    assert!(violated(crate::paranoid::gf_tables(8, log, &exps)));
+
+    // wrong key and nonce lengths
+    assert!(violated(crate::paranoid::cipher_input(&[0; 31], &[0; 24])));
+    assert!(violated(crate::paranoid::cipher_input(&[0; 32], &[0; 23])));
+}
+
+#[test]
+fn canonical_json_matches_web_app() {
+    use crate::canonical;
+
+    // scans of the shares printed by the reference web app, the constants
+    // at the top of this file, are canonical already
+    for scan in [
+        SCAN_A1, SCAN_A2, SCAN_A3, SCAN_B1, SCAN_B2, SCAN_B3, SCAN_C1, SCAN_C2, SCAN_C3,
+    ] {
+        let payload = hex::decode(scan).unwrap();
+        assert_eq!(canonical(&payload).unwrap().into_bytes(), payload);
+    }
+
+    // field order, whitespace, and unknown fields are normalized
+    let payload = hex::decode(SCAN_B1).unwrap();
+    let parsed = json::parse(std::str::from_utf8(&payload).unwrap()).unwrap();
+    let mut reordered = json::JsonValue::new_object();
+    for key in ["n", "d", "extra", "r", "t", "v"] {
+        reordered[key] = match key {
+            "extra" => "dropped".into(),
+            _ => parsed[key].clone(),
+        };
+    }
+    assert_eq!(
+        canonical(reordered.pretty(2).as_bytes())
+            .unwrap()
+            .into_bytes(),
+        payload
+    );
+
+    // escaping follows QuoteJSONString of the ECMAScript specification,
+    // used by JSON.stringify
+    let title = "a\"b\\c\n\u{1}\u{7f}é/\u{2028}";
+    let shares = encrypt("secret", title, "pass", 2, 2).unwrap();
+    assert!(shares[0]
+        .starts_with("{\"v\":1,\"t\":\"a\\\"b\\\\c\\n\\u0001\u{7f}é/\u{2028}\",\"r\":2,\"d\":\""));
+    assert_eq!(canonical(shares[0].as_bytes()).unwrap(), shares[0]);
+    assert_eq!(
+        Share::new(shares[0].clone().into_bytes()).unwrap().title(),
+        title
+    );
+
+    // V2 fields follow the web app fields
+    let options = EncryptOptions::new(2, 2)
+        .time_lock(2)
+        .checksums(true)
+        .key_commitment(true);
+    let shares = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    assert_eq!(canonical(shares[1].as_bytes()).unwrap(), shares[1]);
+    let keys: Vec<String> = json::parse(&shares[1])
+        .unwrap()
+        .entries()
+        .map(|(key, _)| key.to_string())
+        .collect();
+    assert_eq!(keys, ["v", "t", "r", "d", "n", "k", "c", "m"]);
+}
+
+/// Shares with the title written in the json escaped as given, as another
+/// implementation could have written it.
+fn with_escaped_title(shares: &[String], title: &str, escaped: &str) -> Vec<Share> {
+    let raw = format!("\"t\":\"{title}\"");
+    shares
+        .iter()
+        .map(|a| {
+            assert!(a.contains(&raw));
+            let payload = a.replace(&raw, &format!("\"t\":\"{escaped}\""));
+            Share::new(payload.into_bytes()).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn title_escaping_matches_web_app() {
+    use crate::{title_salt, unescape_title};
+
+    // salt fixtures, SHA-512 of the utf-8 title; the first title is the one
+    // of the web app scans `SCAN_B*`, recovered with this salt in
+    // `alice_recovers_secret4`, the other values are computed by this crate,
+    // and pin the same salt for the escaped and the raw title
+    for (escaped, salt_start) in [
+        (
+            r#"terrible\"truth\\\"escaping"#,
+            "6b1e6e061b7226d4c96be783e04fbcce",
+        ),
+        (
+            r"\u0417\u0430\u0433\u043e\u043b\u043e\u0432\u043e\u043a \ud83d\ude00",
+            "7395638f09baa51abfd4e76f9f8a61b1",
+        ),
+        ("Заголовок 😀", "7395638f09baa51abfd4e76f9f8a61b1"),
+        // lone surrogate is encoded by TextEncoder as U+FFFD
+        (r"a\ud83db", "4d7fdee3b9f28d2289f1db90ee2ccab2"),
+        (r"a\ude00b", "4d7fdee3b9f28d2289f1db90ee2ccab2"),
+    ] {
+        let title = unescape_title(escaped).unwrap();
+        assert_eq!(hex::encode(&title_salt(&title)[..16]), salt_start);
+    }
+    assert_eq!(unescape_title(r"a\/b\u0041").unwrap(), "a/bA");
+    assert_eq!(unescape_title(r"\\ud83d").unwrap(), r"\ud83d");
+    assert_eq!(unescape_title(r"\ud83d\ud83d").unwrap(), "\u{fffd}\u{fffd}");
+    assert_eq!(unescape_title(r"a\x"), Err(Error::JsonParsing));
+    assert_eq!(unescape_title(r#"a"b"#), Err(Error::JsonParsing));
+
+    // escaped unicode title recovers same as the raw one
+    let title = "Заголовок 😀";
+    let shares = encrypt("secret", title, "pass", 2, 2).unwrap();
+    let escaped = r"\u0417\u0430\u0433\u043e\u043b\u043e\u0432\u043e\u043a \ud83d\ude00";
+    let mut share_set = None;
+    for share in with_escaped_title(&shares, title, escaped) {
+        assert_eq!(share.title(), title);
+        match share_set.as_mut() {
+            None => share_set = Some(ShareSet::init(share)),
+            Some(a) => a.try_add_share(share).unwrap(),
+        }
+    }
+    let share_set = share_set.unwrap();
+    assert_eq!(share_set.recover_with_passphrase("pass").unwrap(), "secret");
+
+    // title with escaped lone surrogate recovers with the replaced one
+    let title = "a\u{fffd}b";
+    let shares = encrypt("secret", title, "pass", 2, 2).unwrap();
+    let mut lone = with_escaped_title(&shares, title, r"a\ud83db").into_iter();
+    let mut share_set = ShareSet::init(lone.next().unwrap());
+    share_set.try_add_share(lone.next().unwrap()).unwrap();
+    assert_eq!(share_set.recover_with_passphrase("pass").unwrap(), "secret");
+
+    // same title through the borrowed parsing and metadata
+    let payload = shares[0].replace(title, r"a\ud83db").into_bytes();
+    assert_eq!(crate::ShareRef::parse(&payload).unwrap().title(), title);
+    assert_eq!(
+        crate::ShareMeta::peek(&payload).unwrap().title.as_deref(),
+        Some(title)
+    );
+    assert!(crate::canonical(&payload).unwrap().contains(title));
+}
+
+#[test]
+fn print_layout_lists_share_fields() {
+    use crate::PrintLayout;
+
+    let shares = encrypt("secret", "my title", "pass", 3, 2).unwrap();
+    let layouts = PrintLayout::for_shares(&shares).unwrap();
+    assert_eq!(layouts.len(), 3);
+    let fingerprint = Share::new(shares[0].clone().into_bytes())
+        .unwrap()
+        .fingerprint();
+    for (i, layout) in layouts.iter().enumerate() {
+        assert_eq!(layout.title.as_deref(), Some("my title"));
+        assert_eq!(layout.index, i + 1);
+        assert_eq!(layout.index_text, format!("{} of 3", i + 1));
+        assert_eq!(layout.required_shards, Some(2));
+        assert_eq!(layout.qr_payload, shares[i]);
+        assert_eq!(layout.fingerprint, fingerprint);
+        assert!(layout.instructions.contains("any 2 of them"));
+        assert!(layout.passphrase_placeholder.starts_with("Passphrase:"));
+    }
+
+    // private split does not reveal the title
+    let (shares, _) = encrypt_with_manifest(
+        "secret",
+        "my title",
+        "pass",
+        &EncryptOptions::new(3, 2).private(true).hide_threshold(true),
+    )
+    .unwrap();
+    let layouts = PrintLayout::for_shares(&shares).unwrap();
+    assert_eq!(layouts[0].title, None);
+    assert_eq!(layouts[0].required_shards, None);
+    assert!(layouts[0].instructions.contains("manifest"));
+
+    // shares of different sets are not mixed
+    let other = encrypt("secret", "other title", "pass", 3, 2).unwrap();
+    assert_eq!(
+        PrintLayout::for_shares(&[shares[0].clone(), other[0].clone()]),
+        Err(Error::ShareFingerprintDifferent)
+    );
+    assert_eq!(PrintLayout::for_shares(&[]), Err(Error::TooFewShares));
+}
+
+#[test]
+fn share_typed_from_paper_text() {
+    use crate::{from_paper_text, to_paper_text};
+
+    let shares = encrypt(SECRET_SEEDPHRASE, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let text = to_paper_text(shares[0].as_bytes()).unwrap();
+    assert!(text.starts_with("01 "));
+    assert!(text.lines().last().unwrap().starts_with("END "));
+    for line in text.lines() {
+        assert!(line.len() <= 29);
+        assert!(line
+            .chars()
+            .all(|c| c == ' ' || c.is_ascii_digit() || c.is_ascii_uppercase()));
+    }
+    assert_eq!(from_paper_text(&text).unwrap(), shares[0].as_bytes());
+
+    // typing and recognition mistakes that are tolerated
+    let mut lines: Vec<String> = text
+        .lines()
+        .map(|a| a.to_lowercase().replace('0', "o").replace(' ', "-"))
+        .collect();
+    lines.reverse();
+    let sloppy = format!("Banana split share\n\n{}\n", lines.join("\n\n"));
+    let typed = from_paper_text(&sloppy).unwrap();
+    let mut share_set = ShareSet::init(Share::new(typed).unwrap());
+    let text = to_paper_text(shares[1].as_bytes()).unwrap();
+    share_set
+        .try_add_share(Share::new(from_paper_text(&text).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_SEEDPHRASE
+    );
+
+    // mistyped line is pointed out
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let damaged = lines[2].replace(|c: char| c.is_ascii_uppercase(), "Z");
+    lines[2] = damaged;
+    assert_eq!(
+        from_paper_text(&lines.join("\n")),
+        Err(Error::PaperLineDamaged(3))
+    );
+
+    // lines with swapped numbers are damaged too
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    lines[0] = format!("02{}", &lines[0][2..]);
+    assert_eq!(
+        from_paper_text(&lines.join("\n")),
+        Err(Error::PaperLineDamaged(2))
+    );
+
+    // missing lines are noticed
+    let lines: Vec<&str> = text.lines().filter(|a| !a.starts_with("04")).collect();
+    assert_eq!(
+        from_paper_text(&lines.join("\n")),
+        Err(Error::PaperTextIncomplete)
+    );
+    let lines: Vec<&str> = text.lines().filter(|a| !a.starts_with("END")).collect();
+    assert_eq!(
+        from_paper_text(&lines.join("\n")),
+        Err(Error::PaperTextIncomplete)
+    );
+    assert_eq!(to_paper_text(b"not a share"), Err(Error::JsonParsing));
+}
+
+#[test]
+fn share_tags_are_public_metadata() {
+    let options = EncryptOptions::new(3, 2)
+        .share_tags(0, &["safe-deposit-box"])
+        .share_tags(2, &["office-vault", "floor 3"]);
+    let shares = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    assert!(shares[0].starts_with(r#"{"v":2,"#));
+    assert!(shares[0].ends_with(r#","g":["safe-deposit-box"]}"#));
+    assert_eq!(crate::canonical(shares[2].as_bytes()).unwrap(), shares[2]);
+
+    // tags are read without the passphrase
+    let parsed: Vec<Share> = shares
+        .iter()
+        .map(|a| Share::new(a.clone().into_bytes()).unwrap())
+        .collect();
+    assert_eq!(parsed[0].tags(), ["safe-deposit-box"]);
+    assert!(parsed[1].tags().is_empty());
+    assert_eq!(parsed[2].tags(), ["office-vault", "floor 3"]);
+    assert_eq!(
+        crate::ShareMeta::peek(shares[2].as_bytes()).unwrap().tags,
+        ["office-vault", "floor 3"]
+    );
+
+    // tags differ per share, and do not keep shares out of the set
+    let diff = parsed[0].diff(&parsed[2]);
+    assert!(diff.tags && diff.is_same_split());
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    share_set
+        .try_add_share(Share::new(shares[2].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(share_set.recover_with_passphrase("pass").unwrap(), "secret");
+
+    // limits are checked on both sides
+    for options in [
+        EncryptOptions::new(3, 2).share_tags(3, &["out of range"]),
+        EncryptOptions::new(3, 2).share_tags(0, &[""]),
+        EncryptOptions::new(3, 2).share_tags(0, &[&"x".repeat(33)]),
+        EncryptOptions::new(3, 2).share_tags(0, &["x"; 9]),
+    ] {
+        assert!(matches!(
+            encrypt_with_options("secret", "title", "pass", &options),
+            Err(Error::TagInvalid(_))
+        ));
+    }
+    for tags in [r#""g":"x""#, r#""g":[1]"#, r#""g":[""]"#] {
+        let share = shares[0].replace(r#""g":["safe-deposit-box"]"#, tags);
+        assert!(matches!(
+            Share::new(share.into_bytes()),
+            Err(Error::TagInvalid(_))
+        ));
+    }
+}
+
+#[test]
+fn expired_set_is_recovered_with_warning() {
+    use crate::Validity;
+
+    let expired = Validity {
+        not_before: Some(1_000),
+        not_after: Some(2_000),
+    };
+    let options = EncryptOptions::new(3, 2).validity(expired);
+    let shares = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    assert!(shares[0].starts_with(r#"{"v":2,"#));
+    assert!(shares[0].ends_with(r#","b":1000,"e":2000}"#));
+    assert_eq!(crate::canonical(shares[0].as_bytes()).unwrap(), shares[0]);
+
+    let share = Share::new(shares[0].clone().into_bytes()).unwrap();
+    assert_eq!(share.validity(), expired);
+    assert!(share.warnings().contains(&Warning::Expired(2_000)));
+
+    let mut share_set = ShareSet::init(share);
+    share_set
+        .try_add_share(Share::new(shares[1].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(share_set.warnings(), vec![Warning::Expired(2_000)]);
+    assert_eq!(share_set.recover_with_passphrase("pass").unwrap(), "secret");
+
+    // warnings at other times
+    assert_eq!(expired.warnings_at(500), vec![Warning::NotYetValid(1_000)]);
+    assert!(expired.warnings_at(1_500).is_empty());
+    assert!(Validity::default().warnings_at(u64::MAX).is_empty());
+
+    // set in its validity period has no warnings
+    let current = Validity {
+        not_before: None,
+        not_after: Some(u64::MAX),
+    };
+    let options = EncryptOptions::new(2, 2).validity(current);
+    let other = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    let share = Share::new(other[0].clone().into_bytes()).unwrap();
+    assert!(share.warnings().is_empty());
+
+    // validity is set-level
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    let changed = shares[1].replace(r#""e":2000"#, r#""e":3000"#);
+    assert_eq!(
+        share_set.try_add_share(Share::new(changed.into_bytes()).unwrap()),
+        Err(Error::ShareValidityDifferent)
+    );
+
+    // empty period is refused
+    let empty = Validity {
+        not_before: Some(2),
+        not_after: Some(1),
+    };
+    assert_eq!(
+        encrypt_with_options("s", "t", "p", &EncryptOptions::new(2, 2).validity(empty)),
+        Err(Error::ValidityInvalid)
+    );
+    for field in [r#""e":-1"#, r#""e":"2000""#, r#""e":500"#] {
+        let share = shares[0].replace(r#""e":2000"#, field);
+        assert!(matches!(
+            Share::new(share.into_bytes()),
+            Err(Error::ValidityInvalid)
+        ));
+    }
+}
+
+#[test]
+fn set_mac_tells_sibling_sets_apart() {
+    let options = EncryptOptions::new(3, 2).set_mac(true);
+    let shares = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    let sibling = encrypt_with_options("other", "title", "pass", &options).unwrap();
+    let parsed = json::parse(&shares[0]).unwrap();
+    assert_eq!(parsed["v"], 2);
+    assert!(parsed["i"].is_string() && parsed["h"].is_string());
+    assert_eq!(crate::canonical(shares[0].as_bytes()).unwrap(), shares[0]);
+
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    let sibling_share = Share::new(sibling[1].clone().into_bytes()).unwrap();
+    assert_eq!(
+        share_set.why_rejected(&sibling_share),
+        Some(crate::RejectionReason::SetKey)
+    );
+    assert_eq!(
+        share_set.try_add_share(sibling_share),
+        Err(Error::ShareFromDifferentSet)
+    );
+    share_set
+        .try_add_share(Share::new(shares[1].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(share_set.recover_with_passphrase("pass").unwrap(), "secret");
+
+    // altered set parameters are detected on parsing
+    for (from, to) in [
+        (r#""r":2"#, r#""r":3"#),
+        (r#""t":"title""#, r#""t":"titlf""#),
+    ] {
+        let altered = shares[0].replacen(from, to, 1);
+        assert!(matches!(
+            Share::new(altered.into_bytes()),
+            Err(Error::SetMacMismatch)
+        ));
+    }
+    let mut without_mac = parsed.clone();
+    let _ = without_mac.remove("h");
+    assert!(matches!(
+        Share::new(without_mac.dump().into_bytes()),
+        Err(Error::SetMacMismatch)
+    ));
+}
+
+#[test]
+fn set_id_groups_backups_with_same_title() {
+    use crate::ShareSetCollection;
+
+    let options = EncryptOptions::new(3, 2).set_id(true);
+    let old = encrypt_with_options("old secret", "title", "pass", &options).unwrap();
+    let new = encrypt_with_options("new secret", "title", "pass", &options).unwrap();
+    let set_id = json::parse(&old[0]).unwrap()["u"].to_string();
+    assert_eq!(set_id.len(), 36);
+    assert_eq!(&set_id[14..15], "4");
+    for share in &old {
+        assert!(share.ends_with(&format!(r#","u":"{set_id}"}}"#)));
+    }
+    assert_ne!(json::parse(&new[0]).unwrap()["u"].to_string(), set_id);
+
+    // shares scanned in a mix are grouped by the set identifier
+    let mut collection = ShareSetCollection::new();
+    let mixed = [&old[0], &new[1], &old[2], &new[0]];
+    let indices: Vec<usize> = mixed
+        .iter()
+        .map(|a| {
+            collection
+                .add_share(Share::new(a.as_bytes().to_vec()).unwrap())
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(indices, [0, 1, 0, 1]);
+    assert_eq!(collection.sets()[0].set_id(), Some(set_id.as_str()));
+    assert_eq!(
+        collection
+            .by_set_id(&set_id)
+            .unwrap()
+            .recover_with_passphrase("pass")
+            .unwrap(),
+        "old secret"
+    );
+    assert_eq!(
+        collection.sets()[1]
+            .recover_with_passphrase("pass")
+            .unwrap(),
+        "new secret"
+    );
+
+    // shares without set identifier are grouped by the fingerprint
+    let plain = encrypt("plain", "title", "pass", 2, 2).unwrap();
+    let index = collection
+        .add_share(Share::new(plain[0].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(index, 2);
+    assert_eq!(
+        collection.add_share(Share::new(plain[0].clone().into_bytes()).unwrap()),
+        Err(Error::ShareAlreadyInSet)
+    );
+
+    // shares with different set identifiers never join the same set
+    let mut share_set = ShareSet::init(Share::new(old[0].clone().into_bytes()).unwrap());
+    let foreign = old[1].replace(&set_id, "00000000-0000-4000-8000-000000000000");
+    assert_eq!(
+        share_set.try_add_share(Share::new(foreign.into_bytes()).unwrap()),
+        Err(Error::ShareFromDifferentSet)
+    );
+    let invalid = old[1].replace(&set_id, "not a uuid");
+    assert!(matches!(
+        Share::new(invalid.into_bytes()),
+        Err(Error::SetIdInvalid(_))
+    ));
+}
+
+#[test]
+fn hybrid_nonce_survives_repeating_randomness() {
+    use crate::NonceStrategy;
+    use rand::rngs::mock::StepRng;
+
+    // cloned machine, with the random generator state repeating
+    let nonces: Vec<[u8; 24]> = (0..3)
+        .map(|_| {
+            NonceStrategy::Hybrid
+                .nonce(&mut StepRng::new(7, 0))
+                .unwrap()
+        })
+        .collect();
+    assert_ne!(nonces[0], nonces[1]);
+    assert_ne!(nonces[1], nonces[2]);
+    assert_eq!(NonceStrategy::default(), NonceStrategy::Hybrid);
+    let random = NonceStrategy::Random
+        .nonce(&mut StepRng::new(7, 0))
+        .unwrap();
+    assert_eq!(
+        random,
+        NonceStrategy::Random
+            .nonce(&mut StepRng::new(7, 0))
+            .unwrap()
+    );
+
+    // nonce strategy does not change the format
+    for strategy in [NonceStrategy::Hybrid, NonceStrategy::Random] {
+        let options = EncryptOptions::new(2, 2).nonce_strategy(strategy);
+        let shares = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+        assert!(shares[0].starts_with(r#"{"v":1,"#));
+        let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+        share_set
+            .try_add_share(Share::new(shares[1].clone().into_bytes()).unwrap())
+            .unwrap();
+        assert_eq!(share_set.recover_with_passphrase("pass").unwrap(), "secret");
+    }
+}
+
+#[test]
+fn default_nonce_is_hybrid() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::encrypt_with_rng;
+
+    // same random generator state, with nonce strategy left as is,
+    // so that only the nonce could make the shares differ
+    let options = EncryptOptions::new(2, 2)
+        .kdf(KdfAlgorithm::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        })
+        .allow_weak_kdf(true);
+    let split = || {
+        encrypt_with_rng(
+            "secret",
+            "title",
+            "pass",
+            &options,
+            &mut StdRng::seed_from_u64(1467),
+        )
+        .unwrap()
+    };
+    assert_ne!(split(), split());
+}
+
+#[test]
+fn salt_override_is_honored_at_recovery() {
+    use base64::Engine;
+
+    let salt = [7u8; 32];
+    let options = EncryptOptions::new(3, 2).salt_override(&salt);
+    let shares = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    let encoded = base64::engine::general_purpose::STANDARD.encode(salt);
+    assert!(shares[0].ends_with(&format!(r#","s":"{encoded}"}}"#)));
+    assert_eq!(crate::canonical(shares[0].as_bytes()).unwrap(), shares[0]);
+
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    share_set
+        .try_add_share(Share::new(shares[2].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(share_set.recover_with_passphrase("pass").unwrap(), "secret");
+
+    // the recorded salt is used, and not the title
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    let other_salt = base64::engine::general_purpose::STANDARD.encode([8u8; 32]);
+    let changed: Vec<String> = shares
+        .iter()
+        .map(|a| a.replace(&encoded, &other_salt))
+        .collect();
+    let mut changed_set = ShareSet::init(Share::new(changed[0].clone().into_bytes()).unwrap());
+    changed_set
+        .try_add_share(Share::new(changed[1].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(
+        changed_set.recover_with_passphrase("pass"),
+        Err(Error::DecodingFailed)
+    );
+    assert_eq!(
+        share_set.try_add_share(Share::new(changed[1].clone().into_bytes()).unwrap()),
+        Err(Error::ShareSaltDifferent)
+    );
+
+    // salt override is kept when the passphrase is changed
+    share_set
+        .try_add_share(Share::new(shares[1].clone().into_bytes()).unwrap())
+        .unwrap();
+    let new_shares = share_set
+        .reencrypt_with_new_passphrase("pass", "new pass", 2, 2)
+        .unwrap();
+    assert!(new_shares[0].contains(&encoded));
+
+    // salt length is limited
+    for salt in [&[1u8; 15][..], &[1u8; 65][..]] {
+        let options = EncryptOptions::new(2, 2).salt_override(salt);
+        assert_eq!(
+            encrypt_with_options("secret", "title", "pass", &options),
+            Err(Error::SaltInvalid)
+        );
+    }
+    let short = shares[0].replace(&encoded, "AAAA");
+    assert!(matches!(
+        Share::new(short.into_bytes()),
+        Err(Error::SaltInvalid)
+    ));
+}
+
+#[test]
+fn random_salt_per_split() {
+    use crate::title_salt;
+
+    let options = EncryptOptions::new(3, 2).random_salt(true);
+    let first = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    let second = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    let mut salts = Vec::new();
+    for shares in [&first, &second] {
+        let mut share_set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+        assert_eq!(share_set.version(), Some(2));
+        share_set
+            .try_add_share(Share::new(shares[1].as_str()).unwrap())
+            .unwrap();
+        assert_eq!(share_set.recover_with_passphrase("pass").unwrap(), "secret");
+        let salt = share_set.salt().unwrap();
+        assert_eq!(salt.len(), 32);
+        assert_ne!(salt, title_salt("title"));
+        salts.push(salt);
+    }
+    assert_ne!(salts[0], salts[1]);
+
+    // given salt takes precedence
+    let options = options.salt_override(&[3; 16]);
+    let shares = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    let share_set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+    assert_eq!(share_set.salt().unwrap(), [3; 16]);
+}
+
+#[test]
+fn salt_pipeline_is_reproducible() {
+    use crate::{title_salt, unescape_title};
+
+    // title as printed in the share json, unescaped, and hashed
+    let mut share_set = ShareSet::init(Share::new(hex::decode(SCAN_B1).unwrap()).unwrap());
+    share_set
+        .try_add_share(Share::new(hex::decode(SCAN_B2).unwrap()).unwrap())
+        .unwrap();
+    let title = unescape_title(r#"terrible\"truth\\\"escaping"#).unwrap();
+    assert_eq!(share_set.title(), title);
+    let salt = share_set.salt().unwrap();
+    assert_eq!(salt, title_salt(&title));
+    assert_eq!(hex::encode(&salt[..8]), "6b1e6e061b7226d4");
+
+    // salt override replaces the title salt
+    let options = EncryptOptions::new(2, 2).salt_override(&[3; 16]);
+    let shares = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    let share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    assert_eq!(share_set.salt().unwrap(), [3; 16]);
+
+    // private set has no salt until the manifest is applied
+    let options = EncryptOptions::new(2, 2).private(true);
+    let (shares, manifest) = encrypt_with_manifest("secret", "title", "pass", &options).unwrap();
+    let mut share_set = ShareSet::init(Share::new(shares[0].clone().into_bytes()).unwrap());
+    assert_eq!(share_set.salt(), Err(Error::ManifestNeeded));
+    share_set.apply_manifest(manifest).unwrap();
+    assert_eq!(share_set.salt().unwrap(), title_salt("title"));
+}
+
+#[test]
+fn error_report() {
+    let report = Error::WrongPassphrase.report();
+    assert_eq!(report.name, "WrongPassphrase");
+    assert_eq!(report.code, 66);
+    assert_eq!(report.message, Error::WrongPassphrase.to_string());
+    assert!(report.context.is_empty());
+
+    let report = Error::PaperLineDamaged(3).report();
+    assert_eq!(report.name, "PaperLineDamaged");
+    assert_eq!(report.context["line"], 3);
+    let json = serde_json::to_string(&report).unwrap();
+    assert!(json.contains(r#""context":{"line":3}"#));
+}
+
+#[test]
+fn share_input_forms() {
+    let payload = encrypt("secret", "title", "pass", 3, 2).unwrap().remove(0);
+    let fingerprint = Share::new(payload.as_str()).unwrap().fingerprint();
+    assert_eq!(
+        Share::new(payload.clone()).unwrap().fingerprint(),
+        fingerprint
+    );
+    assert_eq!(
+        Share::new(payload.as_bytes()).unwrap().fingerprint(),
+        fingerprint
+    );
+    assert_eq!(
+        Share::new(payload.into_bytes()).unwrap().fingerprint(),
+        fingerprint
+    );
+    assert!(matches!(
+        Share::new(&[0xff, 0xfe][..]),
+        Err(Error::NotShareString)
+    ));
+}
+
+#[test]
+fn scan_stats() {
+    use crate::{RejectionReason, ShareSetCollection};
+
+    let shares = encrypt("secret", "title", "pass", 4, 3).unwrap();
+    let other = encrypt("other", "title", "pass", 4, 3).unwrap();
+    let mut collection = ShareSetCollection::new();
+    for payload in [&shares[0], &shares[1], &shares[1], &shares[1]] {
+        let _ = collection.add_payload(payload.as_str());
+    }
+    assert!(collection.add_payload("not a share").is_err());
+
+    let stats = collection.sets()[0].scan_stats();
+    assert_eq!(stats.accepted, 2);
+    assert_eq!(stats.duplicates.values().sum::<usize>(), 2);
+    assert_eq!(stats.duplicates.len(), 1);
+    assert!(stats.since_first_share().is_some());
+
+    // share of a different split with the same title is rejected by the set
+    let mut share_set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+    assert!(share_set
+        .try_add_share(Share::new(other[1].as_str()).unwrap())
+        .is_err());
+    assert_eq!(
+        share_set.scan_stats().rejected,
+        vec![(RejectionReason::Nonce, 1)]
+    );
+
+    let total = collection.scan_stats();
+    assert_eq!(total.accepted, 2);
+    assert_eq!(total.not_shares, 1);
+    assert!(total.rejected.is_empty());
+}
+
+#[test]
+fn share_set_accessors() {
+    let options = EncryptOptions::new(4, 3).key_commitment(true);
+    let shares = encrypt_with_options("secret", "title", "pass", &options).unwrap();
+    let mut share_set = ShareSet::init(Share::new(shares[3].as_str()).unwrap());
+    share_set
+        .try_add_share(Share::new(shares[1].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(share_set.required_shards(), 3);
+    assert_eq!(share_set.version(), Some(2));
+    assert_eq!(share_set.bits(), 8);
+    assert_eq!(share_set.collected_ids(), [4, 2]);
+
+    // details stay available once the set is combined
+    share_set
+        .try_add_share(Share::new(shares[0].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(share_set.next_action(), NextAction::AskUserForPassword);
+    assert_eq!(share_set.bits(), 8);
+    assert_eq!(share_set.collected_ids(), [4, 2, 1]);
+
+    let v1 = ShareSet::init(Share::new(hex::decode(SCAN_A1).unwrap()).unwrap());
+    assert_eq!(v1.version(), Some(1));
+}
+
+#[test]
+fn padding_matches_upstream() {
+    // shares made by the reference web app, with the secrets of known length
+    let fixtures = [
+        (SCAN_A1, SECRET_SEEDPHRASE, PASSPHRASE_A),
+        (SCAN_B1, SECRET_B, PASSPHRASE_B),
+        (SCAN_C1, SECRET_SEEDPHRASE, PASSPHRASE_C),
+    ];
+    for (scan, secret, passphrase) in fixtures {
+        let upstream = Share::new(hex::decode(scan).unwrap()).unwrap();
+        let title = upstream.title();
+        let options = EncryptOptions::new(3, 2);
+        let shares = encrypt_with_options(secret, &title, passphrase, &options).unwrap();
+        let share = Share::new(shares[0].as_str()).unwrap();
+        assert_eq!(share.content().len(), upstream.content().len());
+        assert_eq!(share.content().len() % 16, 0);
+    }
+
+    // compact padding is shorter, and is recovered all the same
+    let options = EncryptOptions::new(3, 2).padding(Padding::Compact);
+    let shares = encrypt_with_options(SECRET_B, "title", PASSPHRASE_B, &options).unwrap();
+    let share = Share::new(shares[0].as_str()).unwrap();
+    assert_eq!(share.content().len(), 42);
+    let mut share_set = ShareSet::init(share);
+    share_set
+        .try_add_share(Share::new(shares[1].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+
+    // padding block of custom length
+    let options = EncryptOptions::new(3, 2).padding(Padding::Block(4));
+    let shares = encrypt_with_options(SECRET_B, "title", PASSPHRASE_B, &options).unwrap();
+    let share = Share::new(shares[2].as_str()).unwrap();
+    assert_eq!(share.content().len() % 4, 0);
+    let mut share_set = ShareSet::init(share);
+    share_set
+        .try_add_share(Share::new(shares[0].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(
+        share_set.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+    for block in [0, 17] {
+        let options = EncryptOptions::new(3, 2).padding(Padding::Block(block));
+        assert_eq!(
+            encrypt_with_options(SECRET_B, "title", PASSPHRASE_B, &options).unwrap_err(),
+            Error::PaddingInvalid(block)
+        );
+    }
+}
+
+#[test]
+fn bits_query() {
+    use crate::{max_shares, supported_bits, MAX_BITS, MIN_BITS};
+
+    assert_eq!(supported_bits(), MIN_BITS..=MAX_BITS);
+    assert_eq!(max_shares(8), Some(255));
+    assert_eq!(max_shares(MIN_BITS), Some(7));
+    assert_eq!(max_shares(MAX_BITS), Some(1_048_575));
+    assert_eq!(max_shares(MIN_BITS - 1), None);
+    assert_eq!(max_shares(MAX_BITS + 1), None);
+}
+
+#[test]
+fn qr_decoder_plugged_in() {
+    use crate::ShareSetCollection;
+
+    let shares = encrypt("secret", "title", "pass", 3, 2).unwrap();
+    // photo of the first two printed shares, as a platform decoder would see it
+    let decoder = |image: &[u8]| match image {
+        b"photo" => Ok(vec![
+            shares[0].clone().into_bytes(),
+            shares[1].clone().into_bytes(),
+        ]),
+        b"blank" => Ok(Vec::new()),
+        b"sticker" => Ok(vec![
+            b"https://example.com".to_vec(),
+            shares[2].clone().into_bytes(),
+        ]),
+        _ => Err(Error::QrDecodingFailed("unknown image".to_owned())),
+    };
+    let mut collection = ShareSetCollection::new();
+    let outcomes = collection.add_image(&decoder, b"photo").unwrap();
+    assert_eq!(outcomes, [Ok(0), Ok(0)]);
+    assert_eq!(
+        collection.sets()[0]
+            .recover_with_passphrase("pass")
+            .unwrap(),
+        "secret"
+    );
+    assert_eq!(
+        collection.add_image(&decoder, b"blank"),
+        Err(Error::NoShareInImage)
+    );
+    assert!(matches!(
+        collection.add_image(&decoder, b"other"),
+        Err(Error::QrDecodingFailed(_))
+    ));
+
+    // single share from a scanned photo, other codes next to it skipped
+    assert_eq!(
+        Share::from_qr_image_with(&decoder, b"sticker")
+            .unwrap()
+            .id(),
+        3
+    );
+    assert_eq!(
+        Share::from_qr_image_with(&decoder, b"blank").unwrap_err(),
+        Error::NoShareInImage
+    );
+}
+
+#[test]
+fn share_set_extend() {
+    let payloads = encrypt("secret", "title", "pass", 4, 3).unwrap();
+    let share = |i: usize| Share::new(payloads[i].as_str()).unwrap();
+    let other = Share::new(encrypt("secret", "title", "pass", 4, 3).unwrap()[1].as_str()).unwrap();
+    let mut set = ShareSet::init(share(0));
+    set.extend([share(0), other, share(2)]);
+    assert_eq!(
+        set.collected().collect::<Vec<_>>(),
+        [
+            CollectedShare {
+                id: 1,
+                bits: 8,
+                scans: 2
+            },
+            CollectedShare {
+                id: 3,
+                bits: 8,
+                scans: 1
+            },
+        ]
+    );
+    assert_eq!(
+        set.take_extend_errors(),
+        [
+            (1, Error::ShareAlreadyInSet),
+            (2, Error::ShareNonceDifferent)
+        ]
+    );
+    assert!(set.take_extend_errors().is_empty());
+
+    set.extend((3..4).map(share));
+    assert_eq!(set.next_action(), NextAction::AskUserForPassword);
+    assert_eq!(set.collected().map(|a| a.id).collect::<Vec<_>>(), [1, 3, 4]);
+    assert_eq!(set.recover_with_passphrase("pass").unwrap(), "secret");
+}
+
+#[test]
+fn vault_seed_export() {
+    use crate::vault_seed_payload;
+
+    let secret = format!(
+        "  {}\n",
+        SECRET_SEEDPHRASE.to_uppercase().replace(' ', "  ")
+    );
+    assert_eq!(
+        vault_seed_payload(&secret).unwrap().as_str(),
+        SECRET_SEEDPHRASE
+    );
+    assert_eq!(
+        vault_seed_payload("bottom drive obey lake"),
+        Err(Error::SecretNotSeedPhrase)
+    );
+    assert_eq!(
+        vault_seed_payload(&SECRET_SEEDPHRASE.replace("lake", "l4ke")),
+        Err(Error::SecretNotSeedPhrase)
+    );
+    #[cfg(feature = "qr")]
+    {
+        assert!(crate::vault_seed_qr_svg(SECRET_SEEDPHRASE)
+            .unwrap()
+            .starts_with("<?xml"));
+        assert!(crate::vault_seed_qr_text(SECRET_SEEDPHRASE)
+            .unwrap()
+            .contains('\u{2580}'));
+    }
+}
+
+#[cfg(feature = "polkadot-js")]
+#[test]
+fn polkadot_js_keystore_export() {
+    use base64::{engine::general_purpose, Engine};
+    use crypto_secretbox::aead::{generic_array::GenericArray, Aead, KeyInit};
+    use crypto_secretbox::XSalsa20Poly1305;
+
+    let json =
+        crate::polkadot_js_keystore(SECRET_SEEDPHRASE, "new password", "Alice root", 42).unwrap();
+    let keystore: serde_json::Value = serde_json::from_str(&json).unwrap();
+    // root account of the development seed phrase, as shown by subkey
+    assert_eq!(
+        keystore["address"],
+        "5DfhGyQdFobKM8NsWvEeAKk5EQQgYe9AydgJ7rMB6E1EqRzV"
+    );
+    assert_eq!(keystore["encoding"]["version"], "3");
+    assert_eq!(keystore["meta"]["name"], "Alice root");
+
+    let encoded = general_purpose::STANDARD
+        .decode(keystore["encoded"].as_str().unwrap())
+        .unwrap();
+    assert_eq!(&encoded[32..44], [0, 128, 0, 0, 1, 0, 0, 0, 8, 0, 0, 0]);
+    let mut key = [0u8; 64];
+    scrypt::scrypt(
+        b"new password",
+        &encoded[..32],
+        &scrypt::Params::new(15, 8, 1, 64).unwrap(),
+        &mut key,
+    )
+    .unwrap();
+    let pkcs8 = XSalsa20Poly1305::new(GenericArray::from_slice(&key[..32]))
+        .decrypt(GenericArray::from_slice(&encoded[44..68]), &encoded[68..])
+        .unwrap();
+    assert_eq!(pkcs8.len(), 117);
+    assert_eq!(
+        hex::encode(&pkcs8[85..]),
+        "46ebddef8cd9bb167dc30878d7113b7e168e6f0646beffd77d69d39bad76b47a"
+    );
+
+    assert_eq!(
+        crate::polkadot_js_keystore("bottom drive obey lake", "a", "", 42),
+        Err(Error::SecretNotSeedPhrase)
+    );
+}
+
+#[cfg(feature = "ethereum")]
+#[test]
+fn ethereum_keystore_export() {
+    use aes::cipher::{KeyIvInit, StreamCipher};
+    use sha3::{Digest, Keccak256};
+
+    // first development account of hardhat
+    let private_key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    let json = crate::ethereum_keystore(private_key, "new password").unwrap();
+    let keystore: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(keystore["version"], 3);
+    assert_eq!(
+        keystore["address"],
+        "f39fd6e51aad88f6f4ce6ab8827279cfffb92266"
+    );
+
+    let crypto = &keystore["crypto"];
+    let hex_field = |a: &serde_json::Value| hex::decode(a.as_str().unwrap()).unwrap();
+    let mut key = [0u8; 32];
+    scrypt::scrypt(
+        b"new password",
+        &hex_field(&crypto["kdfparams"]["salt"]),
+        &scrypt::Params::new(18, 8, 1, 32).unwrap(),
+        &mut key,
+    )
+    .unwrap();
+    let mut data = hex_field(&crypto["ciphertext"]);
+    let mac = Keccak256::new()
+        .chain_update(&key[16..])
+        .chain_update(&data)
+        .finalize();
+    assert_eq!(mac.to_vec(), hex_field(&crypto["mac"]));
+    let iv = hex_field(&crypto["cipherparams"]["iv"]);
+    ctr::Ctr128BE::<aes::Aes128>::new(key[..16].into(), iv.as_slice().into())
+        .apply_keystream(&mut data);
+    assert_eq!(hex::encode(data), &private_key[2..]);
+
+    assert_eq!(
+        crate::ethereum_keystore(SECRET_SEEDPHRASE, "a"),
+        Err(Error::SecretNotPrivateKey)
+    );
+}
+
+#[test]
+fn file_split_and_restore() {
+    use crate::encrypt_file;
+
+    let dir = std::env::temp_dir().join(format!("banana-file-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("wallet.json");
+    let content: Vec<u8> = (0..=255).chain([b'{', b'\n', 0]).collect();
+    std::fs::write(&path, &content).unwrap();
+    let shares = encrypt_file(&path, "wallet", "pass", &EncryptOptions::new(3, 2)).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut set = ShareSet::init(Share::new(shares[2].as_str()).unwrap());
+    set.try_add_share(Share::new(shares[0].as_str()).unwrap())
+        .unwrap();
+    let file = set.recover_file("pass").unwrap();
+    assert_eq!(file.name, "wallet.json");
+    assert_eq!(file.content.as_slice(), content);
+    assert_eq!(
+        set.recover_file("wrong").unwrap_err(),
+        Error::DecodingFailed
+    );
+
+    // secret not made from a file
+    let shares = encrypt("secret", "title", "pass", 2, 2).unwrap();
+    let mut set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+    set.try_add_share(Share::new(shares[1].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(set.recover_file("pass").unwrap_err(), Error::NotFileSecret);
+
+    assert!(matches!(
+        encrypt_file(
+            dir.join("missing"),
+            "title",
+            "pass",
+            &EncryptOptions::new(2, 2)
+        ),
+        Err(Error::FileIo(_))
+    ));
+}
+
+#[test]
+fn files_reassembled_from_chunks() {
+    use crate::{encrypt_files, reassemble, FileManifest};
+
+    let dir = std::env::temp_dir().join(format!("banana-files-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let large: Vec<u8> = (0..250u32).map(|a| (a * 7) as u8).collect();
+    std::fs::write(dir.join("large.bin"), &large).unwrap();
+    std::fs::write(dir.join("notes.txt"), "notes").unwrap();
+    let options = EncryptOptions::new(3, 2)
+        .kdf(KdfAlgorithm::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        })
+        .allow_weak_kdf(true);
+    let paths = [dir.join("large.bin"), dir.join("notes.txt")];
+    let split = encrypt_files(&paths, 100, "files", "pass", &options).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        encrypt_files(&paths, 0, "files", "pass", &options).unwrap_err(),
+        Error::ChunkSizeZero
+    );
+
+    let manifest = FileManifest::from_json(&split.manifest.to_json()).unwrap();
+    assert_eq!(manifest.chunks.len(), 4);
+    assert_eq!(manifest.files[0].chunks, [0, 1, 2]);
+    assert_eq!(manifest.chunks[2].size, 50);
+
+    // shares 3 and 1 of every chunk, in any order
+    let shares: Vec<&str> = split
+        .shares
+        .iter()
+        .rev()
+        .flat_map(|a| [a[2].as_str(), a[0].as_str()])
+        .collect();
+    let files = reassemble(&manifest, shares.clone(), "pass").unwrap();
+    assert_eq!(files[0].name, "large.bin");
+    assert_eq!(files[0].content.as_slice(), large);
+    assert_eq!(files[1].content.as_slice(), b"notes");
+
+    let without_chunk = shares[2..].to_vec();
+    assert_eq!(
+        reassemble(&manifest, without_chunk, "pass").unwrap_err(),
+        Error::ChunkMissing(3)
+    );
+    let mut tampered = manifest.clone();
+    tampered.files[1].sha256 = manifest.files[0].sha256.clone();
+    assert_eq!(
+        reassemble(&tampered, shares, "pass").unwrap_err(),
+        Error::FileChecksumMismatch
+    );
+}
+
+#[cfg(feature = "bundle")]
+#[test]
+fn custodian_bundles() {
+    use std::io::Read;
+
+    let bundles = Distribution::new(2)
+        .custodian("alice", 2)
+        .custodian("bob", 1)
+        .bundles(SECRET_B, "title", PASSPHRASE_B)
+        .unwrap();
+    assert_eq!(bundles.len(), 2);
+    assert_eq!(bundles[0].label, "alice");
+    assert_eq!(bundles[0].shares, ["1 of 3", "2 of 3"]);
+    assert_eq!(bundles[1].shares, ["3 of 3"]);
+
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(bundles[1].zip.clone())).unwrap();
+    let mut names: Vec<&str> = zip.file_names().collect();
+    names.sort();
+    let mut expected = vec!["instructions.txt", "share-3-paper.txt", "share-3.txt"];
+    if cfg!(feature = "qr") {
+        expected.push("share-3.svg");
+    }
+    expected.sort();
+    assert_eq!(names, expected);
+    let mut instructions = String::new();
+    let _ = zip
+        .by_name("instructions.txt")
+        .unwrap()
+        .read_to_string(&mut instructions)
+        .unwrap();
+    assert!(instructions.contains("Shares: 3 of 3"));
+    assert!(!instructions.contains(PASSPHRASE_B));
+
+    // bob's share recovers the secret together with one of alice's
+    let mut payload = String::new();
+    let _ = zip
+        .by_name("share-3.txt")
+        .unwrap()
+        .read_to_string(&mut payload)
+        .unwrap();
+    let mut alice = zip::ZipArchive::new(std::io::Cursor::new(bundles[0].zip.clone())).unwrap();
+    let mut other = String::new();
+    let _ = alice
+        .by_name("share-1.txt")
+        .unwrap()
+        .read_to_string(&mut other)
+        .unwrap();
+    let mut set = ShareSet::init(Share::new(payload).unwrap());
+    set.try_add_share(Share::new(other).unwrap()).unwrap();
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+}
+
+#[test]
+fn share_set_reset_and_retain() {
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 4, 2).unwrap();
+    let share = |i: usize| Share::new(shares[i].as_str()).unwrap();
+    let mut set = ShareSet::init(share(0));
+    set.try_add_share(share(1)).unwrap();
+    assert_eq!(set.next_action(), NextAction::AskUserForPassword);
+
+    // share 1 is distrusted, the set takes more shares again
+    set.retain_shares(|id| id != 1).unwrap();
+    assert_eq!(set.collected_ids(), [2]);
+    assert!(matches!(
+        set.next_action(),
+        NextAction::MoreShares { have: 1, .. }
+    ));
+    assert_eq!(
+        set.recover_with_passphrase(PASSPHRASE_B).unwrap_err(),
+        Error::NotReadyToDecode
+    );
+    set.try_add_share(share(3)).unwrap();
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+
+    // combined set keeping enough shares stays combined
+    set.retain_shares(|_| true).unwrap();
+    assert_eq!(set.next_action(), NextAction::AskUserForPassword);
+
+    set.reset();
+    assert!(set.collected_ids().is_empty());
+    assert_eq!(set.scan_stats().accepted, 0);
+    assert_eq!(
+        set.try_add_share(
+            Share::new(encrypt(SECRET_B, "title", PASSPHRASE_B, 2, 2).unwrap()[0].as_str())
+                .unwrap()
+        )
+        .unwrap_err(),
+        Error::ShareNonceDifferent
+    );
+    set.try_add_share(share(0)).unwrap();
+    set.try_add_share(share(2)).unwrap();
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+}
+
+#[test]
+fn recovery_session_flow() {
+    use crate::{RecoverySession, SessionState};
+
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let mut session = RecoverySession::new(2);
+    assert_eq!(
+        session.state(),
+        SessionState::Scanning {
+            have: 0,
+            need: None
+        }
+    );
+    assert_eq!(
+        session.try_passphrase(PASSPHRASE_B).unwrap_err(),
+        Error::SessionStepInvalid
+    );
+    assert_eq!(
+        session.add_payload(shares[0].as_str()).unwrap(),
+        SessionState::Scanning {
+            have: 1,
+            need: Some(2)
+        }
+    );
+    assert_eq!(
+        session.add_payload(shares[0].as_str()).unwrap_err(),
+        Error::ShareAlreadyInSet
+    );
+    let ready = session.add_payload(shares[2].as_str()).unwrap();
+    assert_eq!(ready, SessionState::Ready { attempts_left: 2 });
+    assert_eq!(
+        serde_json::to_string(&ready).unwrap(),
+        r#"{"state":"ready","attempts_left":2}"#
+    );
+    assert_eq!(
+        session.add_payload(shares[1].as_str()).unwrap_err(),
+        Error::SessionStepInvalid
+    );
+
+    assert_eq!(
+        session.try_passphrase("wrong").unwrap(),
+        SessionState::Ready { attempts_left: 1 }
+    );
+    assert!(session.secret().is_none());
+    assert_eq!(
+        session.try_passphrase(PASSPHRASE_B).unwrap(),
+        SessionState::Done
+    );
+    assert_eq!(session.secret(), Some(SECRET_B));
+
+    // attempts are used up
+    let mut session = RecoverySession::new(1);
+    let _ = session.add_payload(shares[1].as_str()).unwrap();
+    let _ = session.add_payload(shares[2].as_str()).unwrap();
+    assert_eq!(
+        session.try_passphrase("wrong").unwrap(),
+        SessionState::Failed
+    );
+    assert_eq!(
+        session.try_passphrase(PASSPHRASE_B).unwrap_err(),
+        Error::SessionStepInvalid
+    );
+}
+
+#[test]
+fn batch_recovery_with_candidates() {
+    use crate::{BatchResult, ShareSetCollection};
+
+    let options = EncryptOptions::new(3, 2)
+        .kdf(KdfAlgorithm::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        })
+        .allow_weak_kdf(true);
+    let first = encrypt_with_options("first", "title", "one", &options).unwrap();
+    let second = encrypt_with_options("second", "title", "one", &options).unwrap();
+    let third = encrypt_with_options("third", "other", "two", &options).unwrap();
+    let incomplete = encrypt_with_options("fourth", "title", "one", &options).unwrap();
+    let mut collection = ShareSetCollection::new();
+    for payload in [
+        &first[0],
+        &second[1],
+        &third[0],
+        &incomplete[0],
+        &first[2],
+        &second[0],
+        &third[1],
+    ] {
+        let _ = collection.add_payload(payload.as_str()).unwrap();
+    }
+
+    let outcomes = collection.recover_all(&["wrong", "two", "one"]);
+    assert_eq!(outcomes.len(), 4);
+    let recovered = |i: usize| match outcomes[i].result {
+        BatchResult::Recovered {
+            passphrase,
+            ref secret,
+        } => (passphrase, secret.as_str()),
+        ref other => panic!("set {i} not recovered: {other:?}"),
+    };
+    assert_eq!(recovered(0), (2, "first"));
+    assert_eq!(recovered(1), (2, "second"));
+    assert_eq!(recovered(2), (1, "third"));
+    assert!(matches!(outcomes[3].result, BatchResult::NotComplete));
+    assert_eq!(outcomes[2].title, "other");
+
+    let outcomes = collection.recover_all(&["wrong"]);
+    assert!(matches!(
+        outcomes[0].result,
+        BatchResult::NoPassphraseMatched
+    ));
+}
+
+#[test]
+fn table_free_gf_matches_tables() {
+    use crate::shares::gf_mul;
+    use crate::{combine_into, combine_into_table_free, GfStrategy};
+
+    for n in *BIT_RANGE.start()..=12 {
+        let (logs, exps) = generate_logs_and_exps(n);
+        let size = 2u32.pow(n);
+        for a in (1..size).step_by(7) {
+            for b in (1..size).step_by(5) {
+                let product = (logs[a as usize].unwrap() + logs[b as usize].unwrap()) % (size - 1);
+                assert_eq!(gf_mul(a, b, n), exps[product as usize]);
+            }
+        }
+    }
+
+    for bits in [3, 8, 13, 20] {
+        let ids = [1, 2, 5];
+        let contents: Vec<Vec<u8>> = (0..3u8)
+            .map(|a| (0..40u8).map(|b| (a * 40 + b) % 8).collect())
+            .collect();
+        let contents: Vec<&[u8]> = contents.iter().map(|a| a.as_slice()).collect();
+        let table_len = 2usize.pow(bits);
+        let (mut logs, mut exps) = (vec![0; table_len], vec![0; table_len]);
+        let mut out = vec![0; 100];
+        let len = combine_into(bits, &ids, &contents, &mut logs, &mut exps, &mut out).unwrap();
+        let mut table_free = vec![0; 100];
+        assert_eq!(
+            combine_into_table_free(bits, &ids, &contents, &mut table_free).unwrap(),
+            len
+        );
+        assert_eq!(out, table_free);
+    }
+
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let mut set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+    set.set_gf_strategy(GfStrategy::TableFree);
+    set.try_add_share(Share::new(shares[1].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+
+    let small = GfStrategy::Auto {
+        memory_budget: 64 * 1024,
+    };
+    assert!(small.uses_tables(8));
+    assert!(!small.uses_tables(14));
+    assert!(GfStrategy::default().uses_tables(20));
+}
+
+#[test]
+fn sanitized_wrapped_payloads() {
+    use base64::Engine;
+
+    use crate::{sanitize_payload, PayloadWrapper, ShareSetCollection};
+
+    let share = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap()[0].clone();
+    let expected = Share::new(share.as_str()).unwrap();
+
+    // plain payload is not touched
+    let sanitized = sanitize_payload(share.as_bytes());
+    assert!(sanitized.wrappers.is_empty());
+    assert_eq!(&sanitized.payload[..], share.as_bytes());
+
+    let encoded = share
+        .bytes()
+        .map(|a| match a {
+            b'{' | b'}' | b'"' | b':' | b',' | b'/' | b'=' => format!("%{a:02X}"),
+            _ => (a as char).to_string(),
+        })
+        .collect::<String>();
+    let data_uri = format!(
+        "data:text/plain;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(&share)
+    );
+    for (payload, wrappers) in [
+        (format!("  {share}\n"), vec![]),
+        (data_uri, vec![PayloadWrapper::DataUri]),
+        (format!("data:,{encoded}"), vec![PayloadWrapper::DataUri]),
+        (encoded.clone(), vec![PayloadWrapper::PercentEncoding]),
+        (
+            format!("bananasplit:{share}"),
+            vec![PayloadWrapper::Prefix("bananasplit:".to_owned())],
+        ),
+        (
+            format!("https://bs.parity.io/#{encoded}"),
+            vec![
+                PayloadWrapper::PercentEncoding,
+                PayloadWrapper::Prefix("https://bs.parity.io/#".to_owned()),
+            ],
+        ),
+    ] {
+        let sanitized = sanitize_payload(payload.as_bytes());
+        assert_eq!(sanitized.wrappers, wrappers);
+        assert_eq!(&sanitized.payload[..], share.as_bytes());
+        assert_eq!(
+            Share::new_sanitized(payload.as_bytes()).unwrap().id(),
+            expected.id()
+        );
+    }
+
+    // text that merely contains braces is not unwrapped
+    let text = "my share: {not json}";
+    assert!(sanitize_payload(text.as_bytes()).wrappers.is_empty());
+
+    // failure after unwrapping reports the wrapper
+    let damaged = format!("bananasplit:{}", share.replace(r#""v":1"#, r#""v":7"#));
+    match Share::new_sanitized(damaged.as_bytes()) {
+        Err(Error::WrappedShareInvalid(wrappers, reason)) => {
+            assert_eq!(wrappers, "prefix \"bananasplit:\"");
+            assert_eq!(*reason, Error::VersionNotSupported("7".to_owned()));
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+    assert_eq!(
+        Share::new_sanitized(b"no share here").unwrap_err(),
+        Share::new(b"no share here".as_slice()).unwrap_err()
+    );
+
+    let mut collection = ShareSetCollection::new();
+    assert_eq!(
+        collection
+            .add_sanitized_payload(format!("bananasplit:{share}").as_bytes())
+            .unwrap(),
+        0
+    );
+    assert!(collection.add_sanitized_payload(b"data:,nothing").is_err());
+    assert_eq!(collection.scan_stats().not_shares, 1);
+}
+
+#[test]
+fn share_from_json_str() {
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let share = Share::from_json_str(&shares[1]).unwrap();
+    assert_eq!(share.id(), 2);
+    let parsed: Share = shares[2].parse().unwrap();
+    assert_eq!(parsed.id(), 3);
+    let mut set = ShareSet::init(share);
+    set.try_add_share(parsed).unwrap();
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+    assert_eq!(
+        "not a share".parse::<Share>().unwrap_err(),
+        Share::new("not a share").unwrap_err()
+    );
+}
+
+#[test]
+fn recover_secret_bytes() {
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let mut set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+    assert_eq!(
+        set.recover_bytes_with_passphrase(PASSPHRASE_B).unwrap_err(),
+        Error::NotReadyToDecode
+    );
+    set.try_add_share(Share::new(shares[2].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(
+        &set.recover_bytes_with_passphrase(PASSPHRASE_B).unwrap()[..],
+        SECRET_B.as_bytes()
+    );
+    assert!(set.recover_bytes_with_passphrase("wrong").is_err());
+}
+
+#[test]
+fn binary_secret_round_trip() {
+    // not utf8, with zero bytes at both ends
+    let secret = [0u8, 0xff, 0xfe, 1, 2, 0x80, 0];
+    let options = EncryptOptions::new(3, 2)
+        .kdf(KdfAlgorithm::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        })
+        .allow_weak_kdf(true);
+    let shares = encrypt_bytes_with_options(&secret, "title", PASSPHRASE_B, &options).unwrap();
+    let mut set = ShareSet::init(Share::new(shares[1].as_str()).unwrap());
+    set.try_add_share(Share::new(shares[2].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(
+        &set.recover_bytes_with_passphrase(PASSPHRASE_B).unwrap()[..],
+        &secret
+    );
+    assert_eq!(
+        set.recover_with_passphrase(PASSPHRASE_B).unwrap_err(),
+        Error::DecodedSecretNotString
+    );
+
+    let shares = encrypt_bytes(SECRET_B.as_bytes(), "title", PASSPHRASE_B, 3, 2).unwrap();
+    let mut set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+    set.try_add_share(Share::new(shares[1].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+
+    assert_eq!(
+        encrypt_bytes_with_options(&secret, "title", PASSPHRASE_B, &options.private(true))
+            .unwrap_err(),
+        Error::ManifestNeeded
+    );
+}
+
+#[test]
+fn argon2id_key_derivation() {
+    use crate::Kdf;
+
+    let algorithm = KdfAlgorithm::Argon2id {
+        memory_kib: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+    assert_eq!(algorithm.name(), "argon2id");
+    let options = EncryptOptions::new(3, 2)
+        .kdf(algorithm)
+        .allow_weak_kdf(true);
+
+    #[cfg(feature = "argon2")]
+    {
+        let shares = encrypt_with_options(SECRET_B, "title", PASSPHRASE_B, &options).unwrap();
+        assert!(shares[0].contains(r#""k":{"a":"argon2id","c":1,"m":1024,"p":1,"t":1}"#));
+        let mut set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+        set.try_add_share(Share::new(shares[1].as_str()).unwrap())
+            .unwrap();
+        assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+
+        // same key step by step
+        let mut recovery = set.start_recovery(PASSPHRASE_B).unwrap();
+        assert_eq!(
+            recovery.step(1).unwrap(),
+            crate::RecoveryStep::Done(SECRET_B.to_owned())
+        );
+
+        let mut key = [0; 32];
+        algorithm
+            .derive_key(b"pass", b"saltsalt", &mut key)
+            .unwrap();
+        assert_ne!(key, [0; 32]);
+
+        // parameters from the share are checked
+        let greedy = shares[0].replace(r#""m":1024"#, r#""m":4194304"#);
+        assert!(matches!(
+            Share::new(greedy.as_str()),
+            Err(Error::KdfParamsNotSupported(_))
+        ));
+        let slow = shares[0].replace(r#""t":1"#, r#""t":4294967295"#);
+        assert!(matches!(
+            Share::new(slow.as_str()),
+            Err(Error::KdfParamsNotSupported(_))
+        ));
+        let weak = EncryptOptions::new(3, 2).kdf(KdfAlgorithm::Argon2id {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        });
+        assert!(matches!(
+            encrypt_with_options(SECRET_B, "title", PASSPHRASE_B, &weak),
+            Err(Error::KdfParamsTooWeak(_))
+        ));
+    }
+    #[cfg(not(feature = "argon2"))]
+    assert_eq!(
+        encrypt_with_options(SECRET_B, "title", PASSPHRASE_B, &options).unwrap_err(),
+        Error::KdfNotSupported("argon2id".to_owned())
+    );
+}
+
+#[cfg(feature = "qr")]
+#[test]
+fn shares_rendered_as_qr_images() {
+    use crate::{encrypt_to_qr_png, encrypt_to_qr_svg, share_qr_png};
+
+    let svgs = encrypt_to_qr_svg(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    assert_eq!(svgs.len(), 3);
+    assert!(svgs.iter().all(|a| a.contains("<svg")));
+
+    let pngs = encrypt_to_qr_png(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    assert_eq!(pngs.len(), 3);
+    let mut reader = png::Decoder::new(&pngs[0][..]).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.width, info.height);
+    assert_eq!(info.width % 8, 0);
+    // quiet zone is light, finder pattern corner is dark
+    let at = |x: u32, y: u32| pixels[(y * info.width + x) as usize];
+    assert_eq!(at(0, 0), 255);
+    assert_eq!(at(31, 31), 255);
+    assert_eq!(at(32, 32), 0);
+    assert_eq!(at(39, 39), 0);
+
+    // payload too long for any qr code
+    assert!(matches!(
+        share_qr_png(&"a".repeat(8000)),
+        Err(Error::QrEncodingFailed(_))
+    ));
+}
+
+#[cfg(all(feature = "qr", feature = "qr-decode"))]
+#[test]
+fn share_decoded_from_qr_image() {
+    use crate::share_qr_png;
+
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let share = Share::from_qr_image(&share_qr_png(&shares[1]).unwrap()).unwrap();
+    assert_eq!(share.id(), 2);
+    let mut set = ShareSet::init(share);
+    set.try_add_share(Share::new(shares[0].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+
+    assert!(matches!(
+        Share::from_qr_image(b"not an image"),
+        Err(Error::QrDecodingFailed(_))
+    ));
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn wasm_bindings_recover() {
+    use crate::{wasm_encrypt, wasm_generate, WasmShare, WasmShareSet};
+
+    // only the successful calls, errors are JS objects and need the wasm runtime
+    let passphrase = wasm_generate(4).unwrap();
+    let shares = wasm_encrypt(SECRET_B, "title", &passphrase, 3, 2).unwrap();
+    let share = WasmShare::new(&shares[2]).unwrap();
+    assert_eq!((share.id(), share.required_shards()), (3, 2));
+    assert_eq!(share.title(), "title");
+    let mut set = WasmShareSet::new(share);
+    assert!(!set.is_complete());
+    set.add_share(WasmShare::new(&shares[0]).unwrap()).unwrap();
+    assert!(set.is_complete());
+    assert_eq!(set.collected_ids(), [3, 1]);
+    assert_eq!(set.recover_with_passphrase(&passphrase).unwrap(), SECRET_B);
+}
+
+#[cfg(feature = "uniffi")]
+#[test]
+fn mobile_bindings_recover() {
+    use crate::{
+        mobile_encrypt, mobile_generate, MobileError, MobileNextAction, MobileShare, MobileShareSet,
+    };
+
+    let passphrase = mobile_generate(4).unwrap();
+    let shares = mobile_encrypt(
+        SECRET_B.to_owned(),
+        "title".to_owned(),
+        passphrase.clone(),
+        3,
+        2,
+    )
+    .unwrap();
+    let share = MobileShare::new(shares[1].clone().into_bytes()).unwrap();
+    assert_eq!((share.id(), share.required_shards()), (2, 2));
+    let set = MobileShareSet::new(share.clone()).unwrap();
+    assert_eq!(
+        set.next_action(),
+        MobileNextAction::MoreShares {
+            have: 1,
+            need: 2,
+            collected: vec![2]
+        }
+    );
+    assert_eq!(
+        set.add_share(share),
+        Err(MobileError::from(Error::ShareAlreadyInSet))
+    );
+    set.add_share(MobileShare::new(shares[0].clone().into_bytes()).unwrap())
+        .unwrap();
+    assert_eq!(set.next_action(), MobileNextAction::AskUserForPassword);
+    assert_eq!(set.recover_with_passphrase(passphrase).unwrap(), SECRET_B);
+    match MobileShare::new(b"not a share".to_vec()) {
+        Err(MobileError::Failed { name, .. }) => assert_eq!(name, "JsonParsing"),
+        Ok(_) => panic!("not a share was parsed"),
+    }
+}
+
+#[test]
+fn injected_rng_is_deterministic() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{encrypt_with_rng, generate_with_rng, NonceStrategy};
+
+    let passphrase = generate_with_rng(4, &mut StdRng::seed_from_u64(7)).unwrap();
+    assert_eq!(
+        passphrase,
+        generate_with_rng(4, &mut StdRng::seed_from_u64(7)).unwrap()
+    );
+    assert_ne!(
+        passphrase,
+        generate_with_rng(4, &mut StdRng::seed_from_u64(8)).unwrap()
+    );
+
+    let options = EncryptOptions::new(3, 2)
+        .kdf(KdfAlgorithm::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        })
+        .allow_weak_kdf(true)
+        .random_ids(true)
+        .random_salt(true)
+        .nonce_strategy(NonceStrategy::Random);
+    let split = |seed| {
+        encrypt_with_rng(
+            SECRET_B,
+            "title",
+            &passphrase,
+            &options,
+            &mut StdRng::seed_from_u64(seed),
+        )
+        .unwrap()
+    };
+    let shares = split(1);
+    assert_eq!(shares, split(1));
+    assert_ne!(shares, split(2));
+    let mut set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+    set.try_add_share(Share::new(shares[2].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(set.recover_with_passphrase(&passphrase).unwrap(), SECRET_B);
+}
+
+#[test]
+fn share_set_saved_and_restored() {
+    let options = EncryptOptions::new(3, 2)
+        .kdf(KdfAlgorithm::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        })
+        .allow_weak_kdf(true)
+        .random_salt(true);
+    let shares = encrypt_with_options(SECRET_B, "title", PASSPHRASE_B, &options).unwrap();
+    let share = |i: usize| Share::new(shares[i].as_str()).unwrap();
+
+    // set in progress is resumed, and takes the rest of the shares
+    let set = ShareSet::init(share(2));
+    let mut restored = ShareSet::from_bytes(&set.to_bytes()).unwrap();
+    assert_eq!(restored.fingerprint(), set.fingerprint());
+    assert_eq!(restored.collected_ids(), [3]);
+    assert_eq!(restored.salt().unwrap(), set.salt().unwrap());
+    assert_eq!(
+        restored.try_add_share(share(2)).unwrap_err(),
+        Error::ShareAlreadyInSet
+    );
+    restored.try_add_share(share(0)).unwrap();
+    assert_eq!(
+        restored.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+
+    // combined set is combined again
+    let restored = ShareSet::from_bytes(&restored.to_bytes()).unwrap();
+    assert_eq!(restored.next_action(), NextAction::AskUserForPassword);
+    assert_eq!(
+        restored.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+
+    // emptied set still takes only the shares of the same split
+    let mut set = ShareSet::init(share(0));
+    set.reset();
+    let mut restored = ShareSet::from_bytes(&set.to_bytes()).unwrap();
+    assert!(restored.collected_ids().is_empty());
+    restored.try_add_share(share(1)).unwrap();
+
+    // altered set is rejected
+    let saved = String::from_utf8(ShareSet::init(share(0)).to_bytes().to_vec()).unwrap();
+    let altered = saved.replace(r#""title":"title""#, r#""title":"other""#);
+    assert_ne!(altered, saved);
+    assert_eq!(
+        ShareSet::from_bytes(altered.as_bytes()).unwrap_err(),
+        Error::SetSnapshotInvalid
+    );
+    assert_eq!(
+        ShareSet::from_bytes(b"not a set").unwrap_err(),
+        Error::SetSnapshotInvalid
+    );
+}
+
+#[test]
+fn private_share_set_saved_with_manifest() {
+    let options = EncryptOptions::new(3, 2).private(true).hide_threshold(true);
+    let (shares, manifest) =
+        encrypt_with_manifest(SECRET_B, "family vault", PASSPHRASE_B, &options).unwrap();
+    let mut set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+    set.apply_manifest(manifest).unwrap();
+    let mut restored = ShareSet::from_bytes(&set.to_bytes()).unwrap();
+    assert_eq!(restored.title(), "family vault");
+    restored
+        .try_add_share(Share::new(shares[1].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(restored.next_action(), NextAction::AskUserForPassword);
+}
+
+#[test]
+fn share_set_saved_encrypted() {
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let set = ShareSet::init(Share::new(shares[1].as_str()).unwrap());
+    let path = std::env::temp_dir().join(format!("banana-saved-{}", std::process::id()));
+    set.save_encrypted(&path, "app password").unwrap();
+    let saved = std::fs::read(&path).unwrap();
+    let mut restored = ShareSet::load_encrypted(&path, "app password").unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // share contents are not in the clear
+    let content = json::parse(std::str::from_utf8(&set.to_bytes()).unwrap()).unwrap()["shares"][0]
+        ["content"]
+        .to_string();
+    assert!(!String::from_utf8_lossy(&saved).contains(&content));
+
+    assert_eq!(restored.collected_ids(), [2]);
+    restored
+        .try_add_share(Share::new(shares[0].as_str()).unwrap())
+        .unwrap();
+    assert_eq!(
+        restored.recover_with_passphrase(PASSPHRASE_B).unwrap(),
+        SECRET_B
+    );
+
+    assert_eq!(
+        ShareSet::from_encrypted_bytes(&saved, "wrong password").unwrap_err(),
+        Error::DecodingFailed
+    );
+    assert!(matches!(
+        ShareSet::load_encrypted(&path, "app password"),
+        Err(Error::FileIo(_))
+    ));
+}
+
+#[test]
+fn share_set_from_shares() {
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 4, 3).unwrap();
+    let share = |i: usize| Share::new(shares[i].as_str()).unwrap();
+    let other =
+        Share::new(encrypt(SECRET_B, "title", PASSPHRASE_B, 4, 3).unwrap()[1].as_str()).unwrap();
+    let mut set =
+        ShareSet::from_shares(vec![share(3), other, share(3), share(0), share(1)]).unwrap();
+    assert_eq!(set.collected_ids(), [4, 1, 2]);
+    assert_eq!(
+        set.take_extend_errors(),
+        [
+            (2, Error::ShareNonceDifferent),
+            (4, Error::ShareAlreadyInSet)
+        ]
+    );
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+
+    assert_eq!(
+        ShareSet::from_shares(Vec::new()).unwrap_err(),
+        Error::TooFewShares
+    );
+}
+
+#[test]
+fn mixed_shares_grouped_with_progress() {
+    use crate::{SetProgress, ShareSetCollection};
+
+    let first = encrypt("first", "wallet", "pass", 3, 2).unwrap();
+    let second = encrypt("second", "wallet", "pass", 5, 3).unwrap();
+    let third = encrypt("third", "savings", "pass", 3, 2).unwrap();
+    let share = |a: &String| Share::new(a.as_str()).unwrap();
+    let mut collection = ShareSetCollection::new();
+    let outcomes = collection.add_shares([
+        share(&second[4]),
+        share(&first[0]),
+        share(&third[1]),
+        share(&second[0]),
+        share(&first[2]),
+        share(&second[4]),
+    ]);
+    assert_eq!(
+        outcomes,
+        [
+            Ok(0),
+            Ok(1),
+            Ok(2),
+            Ok(0),
+            Ok(1),
+            Err(Error::ShareAlreadyInSet)
+        ]
+    );
+    let progress = collection.progress();
+    assert_eq!(progress.len(), 3);
+    assert_eq!(
+        progress[0],
+        SetProgress {
+            index: 0,
+            fingerprint: collection.sets()[0].fingerprint(),
+            title: "wallet".to_owned(),
+            next_action: NextAction::MoreShares {
+                have: 2,
+                need: 3,
+                collected: vec![5, 1]
+            },
+        }
+    );
+    assert_eq!(progress[1].next_action, NextAction::AskUserForPassword);
+    assert_eq!(progress[2].title, "savings");
+    assert_eq!(
+        collection.sets()[1]
+            .recover_with_passphrase("pass")
+            .unwrap(),
+        "first"
+    );
+}
+
+#[test]
+fn share_metadata_accessors() {
+    let share = Share::new(hex::decode(SCAN_B1).unwrap()).unwrap();
+    assert_eq!(share.id(), 1);
+    assert_eq!(share.bits(), 8);
+    assert_eq!(share.version(), Some(1));
+    assert_eq!(share.required_shards(), 2);
+    let other = Share::new(hex::decode(SCAN_B3).unwrap()).unwrap();
+    assert_eq!(share.content_len(), other.content_len());
+    assert_eq!(share.fingerprint(), other.fingerprint());
+
+    let options = EncryptOptions::new(5, 3).checksums(true);
+    let shares = encrypt_with_options(SECRET_B, "title", PASSPHRASE_B, &options).unwrap();
+    let share = Share::new(shares[2].as_str()).unwrap();
+    assert_eq!(share.id(), 3);
+    assert_eq!(share.version(), Some(2));
+    assert_eq!(share.required_shards(), 3);
+}
+
+#[test]
+fn share_set_summary() {
+    use crate::ShareSetSummary;
+
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 5, 3).unwrap();
+    let share = |i: usize| Share::new(shares[i].as_str()).unwrap();
+    let mut set = ShareSet::init(share(3));
+    set.try_add_share(share(0)).unwrap();
+    assert_eq!(set.collected_ids(), [4, 1]);
+    assert_eq!(set.missing_count(), Some(1));
+    assert_eq!(
+        set.summary(),
+        ShareSetSummary {
+            title: "title".to_owned(),
+            required: Some(3),
+            have: 2,
+            bits: 8,
+            version: Some(1),
+        }
+    );
+    assert_eq!(
+        serde_json::to_string(&set.summary()).unwrap(),
+        r#"{"title":"title","required":3,"have":2,"bits":8,"version":1}"#
+    );
+    set.try_add_share(share(4)).unwrap();
+    assert_eq!(set.missing_count(), Some(0));
+    assert_eq!(set.summary().have, 3);
+
+    // private set needs the manifest to tell
+    let options = EncryptOptions::new(3, 2).private(true).hide_threshold(true);
+    let (shares, _) =
+        encrypt_with_manifest(SECRET_B, "family vault", PASSPHRASE_B, &options).unwrap();
+    let set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+    assert_eq!(set.missing_count(), None);
+    assert_eq!(set.summary().required, None);
+    assert_eq!(set.summary().title, "");
+    assert_eq!(set.summary().version, Some(2));
+}
+
+#[test]
+fn share_removed_from_set() {
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 5, 3).unwrap();
+    let share = |i: usize| Share::new(shares[i].as_str()).unwrap();
+    let mut set = ShareSet::from_shares(vec![share(0), share(1)]).unwrap();
+    set.remove_share(1).unwrap();
+    assert_eq!(set.collected_ids(), [2]);
+    assert_eq!(set.remove_share(1).unwrap_err(), Error::ShareNotInSet(1));
+    set.try_add_share(share(0)).unwrap();
+    set.try_add_share(share(4)).unwrap();
+    assert_eq!(set.next_action(), NextAction::AskUserForPassword);
+
+    // combined set goes back in progress
+    set.remove_share(5).unwrap();
+    assert_eq!(set.missing_count(), Some(1));
+    set.try_add_share(share(2)).unwrap();
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+}
+
+/// Share with the last content byte flipped, still parsing as V1 share without checksum.
+fn damaged_share(share: &str) -> Share {
+    use base64::Engine;
+
+    let mut parsed = json::parse(share).unwrap();
+    let data = parsed["d"].to_string();
+    let mut body = base64::engine::general_purpose::STANDARD
+        .decode(&data[1..])
+        .unwrap();
+    *body.last_mut().unwrap() ^= 1;
+    parsed["d"] = format!(
+        "{}{}",
+        &data[..1],
+        base64::engine::general_purpose::STANDARD.encode(body)
+    )
+    .into();
+    Share::new(parsed.dump().as_str()).unwrap()
+}
+
+#[test]
+fn majority_vote_finds_damaged_share() {
+    use crate::MajorityVote;
+
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 5, 2).unwrap();
+    let share = |i: usize| Share::new(shares[i].as_str()).unwrap();
+    let mut set = ShareSet::init(share(0));
+    set.set_error_correction(true);
+    set.try_add_share(damaged_share(&shares[1])).unwrap();
+    assert!(set.majority_vote().is_none());
+    assert!(set.recover_with_passphrase(PASSPHRASE_B).is_err());
+
+    // one surplus share tells that the shares disagree, but not which one is damaged
+    assert_eq!(set.try_add_share(share(2)).unwrap_err(), Error::NoMajority);
+    assert!(set.majority_vote().is_none());
+
+    // two surplus shares outvote the damaged one
+    set.try_add_share(share(3)).unwrap();
+    assert_eq!(set.collected_ids(), [1, 2, 3, 4]);
+    assert_eq!(RecoveryStatus::of(&set).have, 4);
+    assert_eq!(
+        set.majority_vote(),
+        Some(&MajorityVote {
+            subsets: 6,
+            agreeing: 3,
+            suspects: vec![2],
+        })
+    );
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+    assert_eq!(
+        set.try_add_share(share(3)).unwrap_err(),
+        Error::ShareAlreadyInSet
+    );
+
+    // all shares agree
+    set.remove_share(2).unwrap();
+    assert_eq!(set.majority_vote().unwrap().suspects, Vec::<u32>::new());
+    assert_eq!(set.majority_vote().unwrap().agreeing, 3);
+
+    // without error correction, surplus shares are ignored
+    let mut set = ShareSet::from_shares(vec![share(0), share(1), share(2)]).unwrap();
+    assert_eq!(set.collected_ids(), [1, 2]);
+    assert!(set.majority_vote().is_none());
+    set.try_add_share(share(1)).unwrap();
+}
+
+#[test]
+fn majority_vote_without_majority_leaves_set_in_progress() {
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 3, 2).unwrap();
+    let mut set = ShareSet::init(Share::new(shares[0].as_str()).unwrap());
+    set.set_error_correction(true);
+    set.try_add_share(damaged_share(&shares[1])).unwrap();
+    assert_eq!(
+        set.try_add_share(Share::new(shares[2].as_str()).unwrap())
+            .unwrap_err(),
+        Error::NoMajority
+    );
+
+    // no result is taken, and the shares are kept for the diagnosis
+    assert_eq!(set.collected_ids(), [1, 2, 3]);
+    assert!(set.majority_vote().is_none());
+    assert_eq!(
+        set.recover_with_passphrase(PASSPHRASE_B).unwrap_err(),
+        Error::NotReadyToDecode
+    );
+    assert_eq!(set.diagnose(PASSPHRASE_B).unwrap().suspects, vec![2]);
+
+    set.remove_share(2).unwrap();
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+}
+
+#[test]
+fn diagnose_damaged_share() {
+    use crate::Diagnosis;
+
+    let shares = encrypt(SECRET_B, "title", PASSPHRASE_B, 5, 3).unwrap();
+    let share = |i: usize| Share::new(shares[i].as_str()).unwrap();
+    let mut set = ShareSet::init(share(0));
+    set.set_error_correction(true);
+    assert_eq!(set.diagnose(PASSPHRASE_B).unwrap_err(), Error::TooFewShares);
+    set.try_add_share(damaged_share(&shares[2])).unwrap();
+    set.try_add_share(share(3)).unwrap();
+    assert_eq!(set.diagnose(PASSPHRASE_B).unwrap_err(), Error::TooFewShares);
+
+    assert_eq!(
+        set.recover_with_passphrase(PASSPHRASE_B).unwrap_err(),
+        Error::DecodingFailed
+    );
+
+    // one surplus share is not enough for the majority vote, but the passphrase tells
+    assert_eq!(set.try_add_share(share(4)).unwrap_err(), Error::NoMajority);
+    assert_eq!(
+        set.diagnose(PASSPHRASE_B).unwrap(),
+        Diagnosis {
+            subsets: 4,
+            decrypted: 1,
+            suspects: vec![3],
+        }
+    );
+
+    // with the wrong passphrase nothing decrypts
+    let diagnosis = set.diagnose("wrong").unwrap();
+    assert_eq!(diagnosis.decrypted, 0);
+    assert!(diagnosis.suspects.is_empty());
+
+    set.remove_share(3).unwrap();
+    assert_eq!(set.recover_with_passphrase(PASSPHRASE_B).unwrap(), SECRET_B);
+}
+
+#[test]
+fn horner_boundaries() {
+    for n in [*BIT_RANGE.start(), 8] {
+        let (logs, exps) = generate_logs_and_exps(n);
+        let max = 2u32.pow(n) - 1;
+        // highest share number is still within the field
+        assert!(horner(max, &[1, 1], &logs, &exps, n).unwrap() <= max);
+        // share number 0 and numbers outside of the field are errors, not panics
+        assert!(horner(0, &[1, 1], &logs, &exps, n).is_err());
+        assert!(horner(max + 1, &[1, 1], &logs, &exps, n).is_err());
+    }
+    // coefficients outside of small field are errors, not panics
+    let (logs, exps) = generate_logs_and_exps(3);
+    assert!(horner(1, &[1, 255, 1], &logs, &exps, 3).is_err());
+}
+
+/// Mutate a valid payload: flip bytes, truncate, insert garbage, or replace a field,
+/// key derivation header fields included.
+fn mutate_payload(payload: &[u8], rng: &mut rand::rngs::StdRng) -> Vec<u8> {
+    use rand::Rng;
+
+    const FIELDS: [&str; 6] = ["v", "t", "r", "d", "n", "k"];
+    const KDF_FIELDS: [&str; 8] = ["a", "n", "r", "p", "c", "i", "m", "t"];
+    const VALUES: [&str; 14] = [
+        "null",
+        "0",
+        "-1",
+        "1.5",
+        "2",
+        "3",
+        "18446744073709551616",
+        "4294967295",
+        r#""""#,
+        r#""8""#,
+        r#""zzzz""#,
+        "[]",
+        r#"{"a":"scrypt","n":0}"#,
+        r#""argon2id""#,
+    ];
+    let mut mutated = payload.to_vec();
+    match rng.gen_range(0..4) {
+        0 => {
+            for _ in 0..rng.gen_range(1..4) {
+                let i = rng.gen_range(0..mutated.len());
+                mutated[i] = rng.gen();
+            }
+        }
+        1 => mutated.truncate(rng.gen_range(0..mutated.len())),
+        2 => {
+            let i = rng.gen_range(0..mutated.len());
+            let garbage: Vec<u8> = (0..rng.gen_range(1..8)).map(|_| rng.gen()).collect();
+            drop(mutated.splice(i..i, garbage));
+        }
+        _ => {
+            let mut object = json::parse(std::str::from_utf8(payload).unwrap()).unwrap();
+            let field = FIELDS[rng.gen_range(0..FIELDS.len())];
+            let value = json::parse(VALUES[rng.gen_range(0..VALUES.len())]).unwrap();
+            if field == "k" && object["k"].is_object() && rng.gen() {
+                object["k"][KDF_FIELDS[rng.gen_range(0..KDF_FIELDS.len())]] = value;
+            } else {
+                object[field] = value;
+            }
+            mutated = object.dump().into_bytes();
+        }
+    }
+    mutated
+}
+
+#[test]
+fn untrusted_payloads_do_not_panic() {
+    use rand::SeedableRng;
+
+    let v2 = encrypt_with_options(
+        SECRET_B,
+        "v2",
+        PASSPHRASE_B,
+        &EncryptOptions::new(3, 2).time_lock(2),
+    )
+    .unwrap();
+    let seeds = [
+        hex::decode(SCAN_A1).unwrap(),
+        hex::decode(SCAN_B2).unwrap(),
+        hex::decode(SCAN_C3).unwrap(),
+        v2[0].clone().into_bytes(),
+    ];
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1421);
+    for i in 0..5000 {
+        let seed = &seeds[i % seeds.len()];
+        let payload = mutate_payload(seed, &mut rng);
+        let outcome = std::panic::catch_unwind(|| {
+            if let Ok(share_ref) = crate::ShareRef::parse(&payload) {
+                let _ = share_ref.title();
+                let _ = share_ref.content_into(&mut [0; 512]);
+            }
+            if let Ok(share) = Share::new(payload.clone()) {
+                // mutated share as the first one in the set
+                let mut share_set = ShareSet::init(share);
+                let _ = share_set.try_add_share(Share::new(seed.clone()).unwrap());
+                let _ = share_set.next_action();
+
+                // ... and as the second one
+                let mut share_set = ShareSet::init(Share::new(seed.clone()).unwrap());
+                let _ = share_set.try_add_share(Share::new(payload.clone()).unwrap());
+                let _ = share_set.next_action();
+            }
+        });
+        assert!(
+            outcome.is_ok(),
+            "Panic on payload {}",
+            String::from_utf8_lossy(&payload)
+        );
+    }
+}
+
+#[test]
+fn share_with_short_nonce_is_rejected() {
+    let mut share1 =
+        json::parse(&String::from_utf8(hex::decode(SCAN_A1).unwrap()).unwrap()).unwrap();
+    let mut share2 =
+        json::parse(&String::from_utf8(hex::decode(SCAN_A2).unwrap()).unwrap()).unwrap();
+    share1["n"] = "AAAA".into();
+    share2["n"] = "AAAA".into();
+    let mut share_set = ShareSet::init(Share::new(share1.dump().into_bytes()).unwrap());
+    assert!(matches!(
+        share_set.try_add_share(Share::new(share2.dump().into_bytes()).unwrap()),
+        Err(Error::NonceLengthInvalid(3))
+    ));
+    assert!(share_set.recover_with_passphrase(PASSPHRASE_A).is_err());
+}
+
+#[test]
+fn share_with_zero_id_is_rejected() {
+    // bits 8, share id 0
+    let share = r#"{"v":1,"t":"x","r":2,"d":"8AAE=","n":"AAAA"}"#;
+    assert!(matches!(
+        Share::new(share.as_bytes().to_vec()),
+        Err(Error::ShareIdOutOfRange(0))
+    ));
+}
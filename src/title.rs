@@ -0,0 +1,110 @@
+use zeroize::Zeroizing;
+
+use crate::encrypt::hash_string;
+use crate::error::Error;
+
+/// Replacement character, as `TextEncoder` in the web app encodes lone surrogates.
+const REPLACEMENT: &str = "\\ufffd";
+
+/// Title as the reference web app sees it, from the title escaped as in the share json,
+/// without the surrounding quotes.
+///
+/// The web app unescapes the title with `JSON.parse`, and derives the salt from
+/// the utf-8 encoding of the result, see [`title_salt`]. Escapes `\"`, `\\`, `\/`,
+/// the short control character escapes and `\uXXXX`, including surrogate pairs,
+/// are unescaped same way here. `JSON.parse` also accepts lone surrogates that
+/// have no utf-8 encoding; `TextEncoder` encodes them as U+FFFD, and so they are
+/// replaced with U+FFFD here, so that the salt is the same on both implementations.
+pub fn unescape_title(escaped: &str) -> Result<String, Error> {
+    match parse_json(&format!("\"{escaped}\""))? {
+        json::JsonValue::Short(a) => Ok(a.to_string()),
+        json::JsonValue::String(a) => Ok(a),
+        _ => Err(Error::JsonParsing),
+    }
+}
+
+/// Key derivation salt for the unescaped title: SHA-512 of its utf-8 encoding,
+/// as in the reference web app.
+///
+/// This is the second step of the key derivation pipeline, that audit tooling
+/// could reproduce independently:
+///
+/// 1. title, as printed on the shares, is unescaped with [`unescape_title`];
+/// 2. salt is the SHA-512 hash of the unescaped title, 64 bytes, unless V2 shares
+///    record the salt override, see [`ShareSet::salt`](crate::ShareSet::salt);
+/// 3. key is derived from the passphrase utf-8 bytes and the salt with the key
+///    derivation of the set, scrypt with `N = 2^15`, `r = 8`, `p = 1`
+///    and 32 bytes output by default.
+pub fn title_salt(title: &str) -> [u8; 64] {
+    hash_string(title)
+}
+
+/// Key derivation salt of the set: the salt recorded in V2 shares `"s"` field,
+/// if there is one, and the title salt otherwise.
+pub(crate) fn derivation_salt(salt_override: Option<&[u8]>, title: &str) -> Vec<u8> {
+    match salt_override {
+        Some(salt) => salt.to_vec(),
+        None => title_salt(title).to_vec(),
+    }
+}
+
+/// Parse json as `JSON.parse` in the web app would, with lone surrogate escapes
+/// replaced as described in [`unescape_title`].
+pub(crate) fn parse_json(text: &str) -> Result<json::JsonValue, Error> {
+    match json::parse(text) {
+        Ok(a) => Ok(a),
+        // json crate rejects lone surrogates, the web app accepts them
+        Err(_) => match replace_lone_surrogates(text) {
+            Some(replaced) => {
+                let replaced = Zeroizing::new(replaced);
+                json::parse(&replaced).map_err(|_| Error::JsonParsing)
+            }
+            None => Err(Error::JsonParsing),
+        },
+    }
+}
+
+/// Text with the lone surrogate escapes replaced with `�`; `None` if there are none.
+fn replace_lone_surrogates(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut replaced = false;
+    let mut rest = text;
+    while let Some(position) = rest.find('\\') {
+        out.push_str(&rest[..position]);
+        rest = &rest[position..];
+        match code_unit(rest) {
+            // surrogate pair is kept
+            Some(0xd800..=0xdbff) if code_unit(&rest[6..]).is_some_and(is_low) => {
+                out.push_str(&rest[..12]);
+                rest = &rest[12..];
+            }
+            Some(0xd800..=0xdfff) => {
+                out.push_str(REPLACEMENT);
+                rest = &rest[6..];
+                replaced = true;
+            }
+            _ => {
+                // any other escape is kept, together with the escaped character
+                let len = rest[1..].chars().next().map_or(0, char::len_utf8);
+                out.push_str(&rest[..1 + len]);
+                rest = &rest[1 + len..];
+            }
+        }
+    }
+    out.push_str(rest);
+    replaced.then_some(out)
+}
+
+/// Utf-16 code unit of `\uXXXX` escape at the start of the text.
+fn code_unit(text: &str) -> Option<u16> {
+    let hex = text.strip_prefix("\\u")?.get(..4)?;
+    if !hex.bytes().all(|a| a.is_ascii_hexdigit()) {
+        return None;
+    }
+    u16::from_str_radix(hex, 16).ok()
+}
+
+/// Low surrogate, second in the pair.
+fn is_low(unit: u16) -> bool {
+    (0xdc00..=0xdfff).contains(&unit)
+}
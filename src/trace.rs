@@ -0,0 +1,23 @@
+//! Tracing instrumentation, compiled in only with `tracing` feature.
+//!
+//! Spans and events must never carry sensitive data: no secret, passphrase,
+//! key, share content, nonce, or title. Only sizes, counts, versions, and
+//! non-secret parameters are recorded.
+
+/// Enter a debug span until the end of the enclosing block.
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+/// Emit a debug event.
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+pub(crate) use {trace_event, trace_span};
@@ -0,0 +1,71 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Warning};
+
+/// Validity period of the split, recorded in V2 shares `"b"` (not before)
+/// and `"e"` (not after) fields, as unix time in seconds.
+///
+/// Validity is a rotation policy, and not a security boundary: recovery of
+/// the set outside of its validity period is not prevented, but reported with
+/// [`Warning::Expired`] or [`Warning::NotYetValid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Validity {
+    /// Set should not be recovered before this time.
+    pub not_before: Option<u64>,
+    /// Set should be rotated by this time.
+    pub not_after: Option<u64>,
+}
+
+impl Validity {
+    /// Set is valid at any time.
+    pub fn is_unlimited(&self) -> bool {
+        self.not_before.is_none() && self.not_after.is_none()
+    }
+    /// Warnings for the set recovered at `now`, unix time in seconds.
+    pub fn warnings_at(&self, now: u64) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        if let Some(not_before) = self.not_before.filter(|a| now < *a) {
+            warnings.push(Warning::NotYetValid(not_before));
+        }
+        if let Some(not_after) = self.not_after.filter(|a| now > *a) {
+            warnings.push(Warning::Expired(not_after));
+        }
+        warnings
+    }
+    /// Warnings for the set recovered now, by the system clock.
+    pub(crate) fn warnings(&self) -> Vec<Warning> {
+        if self.is_unlimited() {
+            return Vec::new();
+        }
+        // clock before the epoch is as good as no clock, nothing could be told
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(now) => self.warnings_at(now.as_secs()),
+            Err(_) => Vec::new(),
+        }
+    }
+    /// Period must not be empty.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        match (self.not_before, self.not_after) {
+            (Some(not_before), Some(not_after)) if not_before > not_after => {
+                Err(Error::ValidityInvalid)
+            }
+            _ => Ok(()),
+        }
+    }
+    /// Validity from the share `"b"` and `"e"` fields.
+    pub(crate) fn from_fields(
+        not_before: &json::JsonValue,
+        not_after: &json::JsonValue,
+    ) -> Result<Self, Error> {
+        let field = |value: &json::JsonValue| match value {
+            json::JsonValue::Null => Ok(None),
+            a => a.as_u64().map(Some).ok_or(Error::ValidityInvalid),
+        };
+        let validity = Self {
+            not_before: field(not_before)?,
+            not_after: field(not_after)?,
+        };
+        validity.check()?;
+        Ok(validity)
+    }
+}
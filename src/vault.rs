@@ -0,0 +1,61 @@
+use zeroize::Zeroizing;
+
+use crate::error::Error;
+
+/// Word counts of BIP39 seed phrases.
+const SEED_PHRASE_WORDS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// Seed phrase in the recovered secret, as the qr payload for importing the seed
+/// into Polkadot Vault (formerly Parity Signer): words in lowercase, separated by single spaces.
+///
+/// Secret that is not a seed phrase of 12, 15, 18, 21, or 24 words
+/// is refused with [`Error::SecretNotSeedPhrase`].
+pub fn vault_seed_payload(secret: &str) -> Result<Zeroizing<String>, Error> {
+    let words: Vec<String> = secret
+        .split_whitespace()
+        .map(|a| a.to_lowercase())
+        .collect();
+    let words = Zeroizing::new(words);
+    if !SEED_PHRASE_WORDS.contains(&words.len())
+        || words
+            .iter()
+            .any(|a| !a.chars().all(|c| c.is_ascii_lowercase()))
+    {
+        return Err(Error::SecretNotSeedPhrase);
+    }
+    Ok(Zeroizing::new(words.join(" ")))
+}
+
+/// Qr code of [`vault_seed_payload`], as svg image.
+#[cfg(feature = "qr")]
+pub fn vault_seed_qr_svg(secret: &str) -> Result<Zeroizing<String>, Error> {
+    use qrcode::render::svg;
+
+    Ok(Zeroizing::new(
+        seed_qr(secret)?
+            .render::<svg::Color>()
+            .min_dimensions(256, 256)
+            .build(),
+    ))
+}
+
+/// Qr code of [`vault_seed_payload`], as text of unicode half blocks,
+/// for showing on the terminal.
+#[cfg(feature = "qr")]
+pub fn vault_seed_qr_text(secret: &str) -> Result<Zeroizing<String>, Error> {
+    use qrcode::render::unicode::Dense1x2;
+
+    Ok(Zeroizing::new(
+        seed_qr(secret)?
+            .render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build(),
+    ))
+}
+
+#[cfg(feature = "qr")]
+fn seed_qr(secret: &str) -> Result<qrcode::QrCode, Error> {
+    qrcode::QrCode::new(vault_seed_payload(secret)?.as_bytes())
+        .map_err(|e| Error::QrEncodingFailed(e.to_string()))
+}
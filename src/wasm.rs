@@ -0,0 +1,153 @@
+use wasm_bindgen::prelude::*;
+
+// `js` backend of getrandom, so that `rand::thread_rng` works on wasm32-unknown-unknown
+use getrandom as _;
+
+use crate::chunked::{ChunkedRecovery, RecoveryStep};
+use crate::shares::{NextAction, Share, ShareSet};
+
+/// [`crate::encrypt`] for JavaScript: the share payloads of the secret.
+#[wasm_bindgen(js_name = encrypt)]
+pub fn wasm_encrypt(
+    secret: &str,
+    title: &str,
+    passphrase: &str,
+    total_shards: usize,
+    required_shards: usize,
+) -> Result<Vec<String>, JsValue> {
+    Ok(crate::encrypt(
+        secret,
+        title,
+        passphrase,
+        total_shards,
+        required_shards,
+    )?)
+}
+
+/// [`crate::generate`] for JavaScript: random passphrase of `amount` words.
+#[wasm_bindgen(js_name = generate)]
+pub fn wasm_generate(amount: usize) -> Result<String, JsValue> {
+    Ok(crate::generate(amount)?)
+}
+
+/// [`Share`] for JavaScript, exported as `Share`.
+#[wasm_bindgen(js_name = Share)]
+pub struct WasmShare(Share);
+
+#[wasm_bindgen(js_class = Share)]
+impl WasmShare {
+    /// Parse the scanned qr code payload, see [`Share::new`].
+    #[wasm_bindgen(constructor)]
+    pub fn new(payload: &str) -> Result<WasmShare, JsValue> {
+        Ok(Self(Share::new(payload)?))
+    }
+    /// Share number, see [`Share::id`].
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> u32 {
+        self.0.id()
+    }
+    /// Title of the set, see [`Share::title`].
+    #[wasm_bindgen(getter)]
+    pub fn title(&self) -> String {
+        self.0.title()
+    }
+    /// Number of shares needed for recovery, see [`Share::required_shards`].
+    #[wasm_bindgen(getter, js_name = requiredShards)]
+    pub fn required_shards(&self) -> usize {
+        self.0.required_shards()
+    }
+    /// Fingerprint of the set, see [`Share::fingerprint`].
+    #[wasm_bindgen(getter)]
+    pub fn fingerprint(&self) -> String {
+        self.0.fingerprint()
+    }
+}
+
+/// [`ShareSet`] for JavaScript, exported as `ShareSet`.
+#[wasm_bindgen(js_name = ShareSet)]
+pub struct WasmShareSet(ShareSet);
+
+#[wasm_bindgen(js_class = ShareSet)]
+impl WasmShareSet {
+    /// Start the set with its first share; the share object is consumed.
+    #[wasm_bindgen(constructor)]
+    pub fn new(share: WasmShare) -> WasmShareSet {
+        Self(ShareSet::init(share.0))
+    }
+    /// Add the share to the set, see [`ShareSet::try_add_share`]; the share object is consumed.
+    #[wasm_bindgen(js_name = addShare)]
+    pub fn add_share(&mut self, share: WasmShare) -> Result<(), JsValue> {
+        Ok(self.0.try_add_share(share.0)?)
+    }
+    /// Title of the set, see [`ShareSet::title`].
+    #[wasm_bindgen(getter)]
+    pub fn title(&self) -> String {
+        self.0.title()
+    }
+    /// Number of shares needed for recovery, see [`ShareSet::required_shards`].
+    #[wasm_bindgen(getter, js_name = requiredShards)]
+    pub fn required_shards(&self) -> usize {
+        self.0.required_shards()
+    }
+    /// Numbers of the shares collected, see [`ShareSet::collected_ids`].
+    #[wasm_bindgen(getter, js_name = collectedIds)]
+    pub fn collected_ids(&self) -> Vec<u32> {
+        self.0.collected_ids().to_vec()
+    }
+    /// Enough shares are collected, and the passphrase could be tried.
+    #[wasm_bindgen(getter, js_name = isComplete)]
+    pub fn is_complete(&self) -> bool {
+        self.0.next_action() == NextAction::AskUserForPassword
+    }
+    /// Recover the secret, see [`ShareSet::recover_with_passphrase`].
+    #[wasm_bindgen(js_name = recoverWithPassphrase)]
+    pub fn recover_with_passphrase(&self, passphrase: &str) -> Result<String, JsValue> {
+        Ok(self.0.recover_with_passphrase(passphrase)?)
+    }
+    /// Start the recovery in small steps, see [`ShareSet::start_recovery`],
+    /// so that the page does not freeze during the key derivation.
+    #[wasm_bindgen(js_name = startRecovery)]
+    pub fn start_recovery(&self, passphrase: &str) -> Result<WasmRecovery, JsValue> {
+        Ok(WasmRecovery {
+            recovery: self.0.start_recovery(passphrase)?,
+            done: 0,
+            total: 0,
+        })
+    }
+}
+
+/// [`ChunkedRecovery`] for JavaScript, exported as `Recovery`.
+#[wasm_bindgen(js_name = Recovery)]
+pub struct WasmRecovery {
+    recovery: ChunkedRecovery,
+    done: u64,
+    total: u64,
+}
+
+#[wasm_bindgen(js_class = Recovery)]
+impl WasmRecovery {
+    /// Do up to `units` units of work, see [`ChunkedRecovery::step`];
+    /// the secret once it is recovered, `undefined` while the key derivation runs.
+    pub fn step(&mut self, units: u32) -> Result<Option<String>, JsValue> {
+        match self.recovery.step(units as u64)? {
+            RecoveryStep::Pending { done, total } => {
+                self.done = done;
+                self.total = total;
+                Ok(None)
+            }
+            RecoveryStep::Done(secret) => {
+                self.done = self.total;
+                Ok(Some(secret))
+            }
+        }
+    }
+    /// Share of the key derivation done, from 0 to 1, for the progress bar.
+    #[wasm_bindgen(getter)]
+    pub fn progress(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.done as f64 / self.total as f64
+        }
+    }
+}